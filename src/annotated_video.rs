@@ -0,0 +1,260 @@
+use crate::audio_processor::stream_start_times;
+use crate::frame_analyzer::{DetectedObject, FrameResult};
+use crate::palette::label_color;
+use crate::video_processor::{decode_frames, DecodedFrame, ExtractionOptions};
+use anyhow::Result;
+use ffmpeg_next::format::Pixel;
+use ffmpeg_next::software::scaling;
+use ffmpeg_next::{codec, encoder, format, frame, Error, Packet, Rational};
+use image::{Rgb, RgbImage};
+use std::path::Path;
+
+// Video encoders in order of preference; MPEG-4 Part 2 is in every ffmpeg build
+const VIDEO_ENCODERS: [&str; 3] = ["libx264", "h264", "mpeg4"];
+const BOX_THICKNESS: u32 = 3;
+// A decoded frame is the analyzed one when their timestamps are this close (seconds)
+const SAME_FRAME_TOLERANCE: f64 = 0.001;
+// Output timestamps are in milliseconds
+const TIME_BASE: Rational = Rational(1, 1000);
+
+// What frames that weren't analyzed (with sampled extraction) show
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnanalyzedFrames {
+    // The boxes of the last analyzed frame, until the next one
+    #[default]
+    HoldLast,
+    // No boxes
+    Blank,
+}
+
+// Re-encodes `source` into `out_path` with every frame's detection boxes drawn on it,
+// each label in its palette color (palette::label_color). Result timestamps are on the
+// results.json clock and boxes on the full frame (BboxCoordinates::FullFrame). Audio
+// isn't copied. The source is decoded as `decoding` says (rotation, decoder options,
+// tolerated corrupt packets), so the frames match the ones that were analyzed; its
+// sampling, window and crop are ignored since every frame is rendered whole.
+pub fn render_annotated_video(
+    source: &Path,
+    results: &[FrameResult],
+    out_path: &Path,
+    unanalyzed: UnanalyzedFrames,
+    decoding: &ExtractionOptions,
+) -> Result<()> {
+    let mut results: Vec<&FrameResult> = results.iter().collect();
+    results.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+
+    // Decoded frames are on the container clock
+    let video_start = stream_start_times(source)
+        .map_err(|e| anyhow::anyhow!("Failed to read stream start times: {}", e))?
+        .video;
+
+    let options = ExtractionOptions {
+        max_decode_errors: decoding.max_decode_errors,
+        ignore_rotation: decoding.ignore_rotation,
+        decoder_options: decoding.decoder_options.clone(),
+        ..ExtractionOptions::default()
+    };
+    let mut output: Option<AnnotatedOutput> = None;
+    let mut next_result = 0;
+    decode_frames(source, &options, |frame| {
+        let timestamp = frame.timestamp - video_start;
+        while next_result < results.len()
+            && results[next_result].timestamp <= timestamp + SAME_FRAME_TOLERANCE
+        {
+            next_result += 1;
+        }
+        let objects: &[DetectedObject] = match next_result.checked_sub(1).map(|i| results[i]) {
+            Some(result)
+                if unanalyzed == UnanalyzedFrames::HoldLast
+                    || timestamp - result.timestamp <= SAME_FRAME_TOLERANCE =>
+            {
+                &result.objects
+            }
+            _ => &[],
+        };
+
+        if output.is_none() {
+            output = Some(AnnotatedOutput::open(out_path, &frame)?);
+        }
+        if let Some(output) = output.as_mut() {
+            output.push(frame, objects)?;
+        }
+        Ok(true)
+    })
+    .map_err(|e| anyhow::anyhow!("Failed to render {:?}: {}", out_path, e))?;
+
+    output
+        .ok_or_else(|| anyhow::anyhow!("No frames decoded from {:?}", source))?
+        .finish()
+        .map_err(|e| anyhow::anyhow!("Failed to render {:?}: {}", out_path, e))
+}
+
+fn draw_boxes(image: &mut RgbImage, objects: &[DetectedObject]) {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return;
+    }
+    let clamp = |value: f32, max: u32| (value.max(0.0) as u32).min(max - 1);
+    for object in objects {
+        let color = Rgb(label_color(&object.label));
+        let [x1, y1, x2, y2] = object.bbox;
+        let (x1, x2) = (clamp(x1, width), clamp(x2, width));
+        let (y1, y2) = (clamp(y1, height), clamp(y2, height));
+        for t in 0..BOX_THICKNESS {
+            // Inset each ring so the line stays inside the box
+            let (left, right) = (x1.saturating_add(t).min(x2), x2.saturating_sub(t).max(x1));
+            let (top, bottom) = (y1.saturating_add(t).min(y2), y2.saturating_sub(t).max(y1));
+            for x in left..=right {
+                image.put_pixel(x, top, color);
+                image.put_pixel(x, bottom, color);
+            }
+            for y in top..=bottom {
+                image.put_pixel(left, y, color);
+                image.put_pixel(right, y, color);
+            }
+        }
+    }
+}
+
+struct AnnotatedOutput {
+    octx: format::context::Output,
+    encoder: encoder::Video,
+    scaler: scaling::Context,
+    // Size of the decoded frames the scaler takes; the output size never changes
+    input_size: (u32, u32),
+    last_pts: Option<i64>,
+    first_timestamp: f64,
+}
+
+impl AnnotatedOutput {
+    fn open(path: &Path, first: &DecodedFrame) -> Result<Self, Error> {
+        let codec = VIDEO_ENCODERS
+            .iter()
+            .find_map(|name| encoder::find_by_name(name))
+            .ok_or(Error::EncoderNotFound)?;
+        let mut octx = format::output(&path)?;
+        let global_header = octx
+            .format()
+            .flags()
+            .contains(format::flag::Flags::GLOBAL_HEADER);
+
+        let mut ost = octx.add_stream(codec)?;
+        let mut encoder = codec::context::Context::new_with_codec(codec)
+            .encoder()
+            .video()?;
+        // 4:2:0 needs even dimensions
+        let (width, height) = (first.width & !1, first.height & !1);
+        encoder.set_width(width.max(2));
+        encoder.set_height(height.max(2));
+        encoder.set_format(Pixel::YUV420P);
+        encoder.set_time_base(TIME_BASE);
+        if global_header {
+            encoder.set_flags(codec::flag::Flags::GLOBAL_HEADER);
+        }
+        ost.set_time_base(TIME_BASE);
+
+        let encoder = encoder.open_as(codec)?;
+        ost.set_parameters(&encoder);
+        octx.write_header()?;
+
+        let scaler = Self::scaler(&encoder, first.width, first.height)?;
+        Ok(Self {
+            octx,
+            encoder,
+            scaler,
+            input_size: (first.width, first.height),
+            last_pts: None,
+            first_timestamp: first.timestamp,
+        })
+    }
+
+    fn scaler(
+        encoder: &encoder::Video,
+        width: u32,
+        height: u32,
+    ) -> Result<scaling::Context, Error> {
+        scaling::Context::get(
+            Pixel::RGB24,
+            width,
+            height,
+            encoder.format(),
+            encoder.width(),
+            encoder.height(),
+            scaling::Flags::BILINEAR,
+        )
+    }
+
+    fn push(&mut self, frame: DecodedFrame, objects: &[DetectedObject]) -> Result<(), Error> {
+        let (width, height) = (frame.width, frame.height);
+        let mut image = RgbImage::from_raw(width, height, frame.rgb).ok_or(Error::InvalidData)?;
+        draw_boxes(&mut image, objects);
+
+        // Resolution changes mid-stream are scaled to the first frame's size
+        if self.input_size != (width, height) {
+            self.scaler = Self::scaler(&self.encoder, width, height)?;
+            self.input_size = (width, height);
+        }
+        let mut rgb = frame::Video::new(Pixel::RGB24, width, height);
+        let stride = rgb.stride(0);
+        let row = width as usize * 3;
+        for (y, source) in image.as_raw().chunks_exact(row).enumerate() {
+            rgb.data_mut(0)[y * stride..y * stride + row].copy_from_slice(source);
+        }
+        let mut yuv = frame::Video::empty();
+        self.scaler.run(&rgb, &mut yuv)?;
+
+        // Timestamps must increase even where the source's don't
+        let pts = ((frame.timestamp - self.first_timestamp) * 1000.0).round() as i64;
+        let pts = self.last_pts.map_or(pts, |last| pts.max(last + 1));
+        self.last_pts = Some(pts);
+        yuv.set_pts(Some(pts));
+        self.encoder.send_frame(&yuv)?;
+        self.write_packets()
+    }
+
+    fn write_packets(&mut self) -> Result<(), Error> {
+        let stream_time_base = self.octx.stream(0).unwrap().time_base();
+        let mut packet = Packet::empty();
+        while self.encoder.receive_packet(&mut packet).is_ok() {
+            packet.set_stream(0);
+            packet.rescale_ts(TIME_BASE, stream_time_base);
+            packet.write_interleaved(&mut self.octx)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), Error> {
+        self.encoder.send_eof()?;
+        self.write_packets()?;
+        self.octx.write_trailer()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures;
+    use crate::video_processor::display_dimensions;
+
+    #[test]
+    fn annotated_frames_keep_the_orientation_they_were_analyzed_in() {
+        let Some(video) = test_fixtures::rotated_clip() else {
+            return;
+        };
+        if display_dimensions(&video, false).unwrap() == (64, 48) {
+            eprintln!("skipping: this ffmpeg doesn't write rotation tags");
+            return;
+        }
+        let dir = test_fixtures::scratch_dir("annotated-rotation");
+        for (ignore_rotation, size) in [(false, (48, 64)), (true, (64, 48))] {
+            let out_path = dir.join(format!("annotated-{}.mp4", ignore_rotation));
+            let decoding = ExtractionOptions {
+                ignore_rotation,
+                ..ExtractionOptions::default()
+            };
+            render_annotated_video(&video, &[], &out_path, UnanalyzedFrames::Blank, &decoding)
+                .unwrap();
+            assert_eq!(display_dimensions(&out_path, false).unwrap(), size);
+        }
+    }
+}
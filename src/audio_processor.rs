@@ -1,5 +1,21 @@
-use ffmpeg_next::{format, media, Error};
-use std::path::Path;
+use crate::transcription_backend::{MockTranscriptionBackend, TranscriptionBackend};
+use crate::video_log::{video_eprintln, video_println};
+use crate::video_processor::open_decoder;
+use ffmpeg_next::{
+    codec, encoder, filter, format, frame, media, ChannelLayout, Error, Packet, Rational,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// Audio encoders in order of preference, with the container extension each one writes.
+// Minimal ffmpeg builds often lack the native AAC encoder.
+const AUDIO_ENCODERS: [(&str, &str); 5] = [
+    ("aac", "aac"),
+    ("libfdk_aac", "aac"),
+    ("libmp3lame", "mp3"),
+    ("libopus", "opus"),
+    ("libvorbis", "ogg"),
+];
 
 #[derive(Debug, Clone)]
 pub struct AudioResult {
@@ -8,52 +24,530 @@ pub struct AudioResult {
     pub text: String,
 }
 
-pub fn extract_audio(video_path: &Path, audio_path: &Path) -> Result<(), Error> {
+// Target timestamps for sampling one frame per transcribed segment
+pub fn segment_midpoints(audio_results: &[AudioResult]) -> Vec<f64> {
+    audio_results
+        .iter()
+        .map(|segment| (segment.start_time + segment.end_time) / 2.0)
+        .collect()
+}
+
+// Container start times (seconds) of the best video and audio streams; streams
+// without a start time count as starting at zero
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StreamStartTimes {
+    pub video: f64,
+    pub audio: f64,
+}
+
+pub fn stream_start_times(video_path: &Path) -> Result<StreamStartTimes, Error> {
+    ffmpeg_next::init()?;
+
+    let ictx = format::input(&video_path)?;
+    let start_of = |kind: media::Type| {
+        ictx.streams()
+            .best(kind)
+            .filter(|stream| stream.start_time() != ffmpeg_next::ffi::AV_NOPTS_VALUE)
+            .map(|stream| stream.start_time() as f64 * f64::from(stream.time_base()))
+            .unwrap_or(0.0)
+    };
+
+    Ok(StreamStartTimes {
+        video: start_of(media::Type::Video),
+        audio: start_of(media::Type::Audio),
+    })
+}
+
+// Lengths (seconds) of the best video and audio streams, where the container records
+// them
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StreamDurations {
+    pub video: Option<f64>,
+    pub audio: Option<f64>,
+}
+
+pub fn stream_durations(video_path: &Path) -> Result<StreamDurations, Error> {
+    ffmpeg_next::init()?;
+
+    let ictx = format::input(&video_path)?;
+    let duration_of = |kind: media::Type| {
+        ictx.streams()
+            .best(kind)
+            .filter(|stream| stream.duration() != ffmpeg_next::ffi::AV_NOPTS_VALUE)
+            .map(|stream| stream.duration() as f64 * f64::from(stream.time_base()))
+            .filter(|duration| *duration > 0.0)
+    };
+
+    Ok(StreamDurations {
+        video: duration_of(media::Type::Video),
+        audio: duration_of(media::Type::Audio),
+    })
+}
+
+// First available encoder from AUDIO_ENCODERS, with its output extension
+pub fn select_audio_encoder() -> anyhow::Result<(ffmpeg_next::Codec, &'static str)> {
+    ffmpeg_next::init()?;
+
+    AUDIO_ENCODERS
+        .iter()
+        .find_map(|(name, extension)| {
+            ffmpeg_next::encoder::find_by_name(name).map(|codec| (codec, *extension))
+        })
+        .ok_or_else(|| {
+            let names: Vec<&str> = AUDIO_ENCODERS.iter().map(|(name, _)| *name).collect();
+            anyhow::anyhow!(
+                "No usable audio encoder: this ffmpeg build has none of {}",
+                names.join(", ")
+            )
+        })
+}
+
+// One encoded copy of the extracted audio
+#[derive(Debug, Clone)]
+pub struct AudioOutputSpec {
+    // ffmpeg encoder name, e.g. "pcm_s16le" for WAV or "aac"; the container follows the
+    // path's extension
+    pub encoder: String,
+    pub path: PathBuf,
+    // None keeps the source's sample rate / channel count
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+}
+
+impl AudioOutputSpec {
+    pub fn new(encoder: &str, path: &Path) -> Self {
+        Self {
+            encoder: encoder.to_string(),
+            path: path.to_path_buf(),
+            sample_rate: None,
+            channels: None,
+        }
+    }
+
+    // 16-bit PCM WAV next to `path`
+    pub fn pcm_wav(path: &Path, sample_rate: u32, channels: u16) -> Self {
+        Self {
+            sample_rate: Some(sample_rate),
+            channels: Some(channels),
+            ..Self::new("pcm_s16le", &path.with_extension("wav"))
+        }
+    }
+
+    // 16 kHz mono 16-bit PCM, the input speech models expect
+    pub fn speech_wav(path: &Path) -> Self {
+        Self::pcm_wav(path, 16_000, 1)
+    }
+}
+
+// Returns the path actually written, which has a different extension than `audio_path`
+// when a fallback encoder was used
+pub fn extract_audio(video_path: &Path, audio_path: &Path) -> anyhow::Result<PathBuf> {
+    extract_audio_range(video_path, audio_path, None, None, &HashMap::new())
+}
+
+// Encodes only the audio inside [start, end] seconds with the first available encoder
+// from AUDIO_ENCODERS; the output starts at time zero. `decoder_options` are passed to
+// the audio decoder (see open_decoder).
+pub fn extract_audio_range(
+    video_path: &Path,
+    audio_path: &Path,
+    start: Option<f64>,
+    end: Option<f64>,
+    decoder_options: &HashMap<String, String>,
+) -> anyhow::Result<PathBuf> {
+    let spec = archival_audio_spec(audio_path)?;
+    extract_audio_outputs(
+        video_path,
+        std::slice::from_ref(&spec),
+        start,
+        end,
+        decoder_options,
+    )?;
+    Ok(spec.path)
+}
+
+// The spec extract_audio_range writes: AAC, or the first fallback encoder available
+pub fn archival_audio_spec(audio_path: &Path) -> anyhow::Result<AudioOutputSpec> {
+    let (codec, extension) = select_audio_encoder()?;
+    if codec.name() != "aac" {
+        video_println!(
+            "Note: AAC encoder unavailable, using {} for extracted audio",
+            codec.name()
+        );
+    }
+    Ok(AudioOutputSpec::new(
+        codec.name(),
+        &audio_path.with_extension(extension),
+    ))
+}
+
+// Decodes the audio inside [start, end] seconds once and encodes it into every output,
+// each through its own resampler and encoder
+pub fn extract_audio_outputs(
+    video_path: &Path,
+    outputs: &[AudioOutputSpec],
+    start: Option<f64>,
+    end: Option<f64>,
+    decoder_options: &HashMap<String, String>,
+) -> anyhow::Result<()> {
     ffmpeg_next::init()?;
 
     let mut ictx = format::input(&video_path)?;
-    let audio_stream = ictx
-        .streams()
-        .best(media::Type::Audio)
-        .ok_or(Error::StreamNotFound)?;
+    let (stream_index, time_base, parameters) = {
+        let stream = ictx
+            .streams()
+            .best(media::Type::Audio)
+            .ok_or(Error::StreamNotFound)?;
+        (stream.index(), stream.time_base(), stream.parameters())
+    };
+    let mut decoder = open_decoder(parameters, decoder_options)?.audio()?;
+    // Some containers leave the layout unset; the resamplers need one
+    let layout = if decoder.channel_layout().is_empty() {
+        ChannelLayout::default(decoder.channels() as i32)
+    } else {
+        decoder.channel_layout()
+    };
 
-    let mut octx = format::output(&audio_path)?;
-    let mut ost = octx.add_stream(ffmpeg_next::encoder::find_by_name("aac"))?;
-    ost.set_parameters(audio_stream.parameters());
+    let mut encoders = outputs
+        .iter()
+        .map(|spec| {
+            AudioOutput::open(spec, &decoder, layout, time_base)
+                .map_err(|e| anyhow::anyhow!("Failed to set up {:?}: {}", spec.path, e))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
 
-    let mut encoder = ost.codec().encoder().audio()?;
-    encoder.set_bit_rate(audio_stream.bit_rate());
-    encoder.set_sample_rate(audio_stream.sample_rate());
-    encoder.set_channels(audio_stream.channels());
-    encoder.open_as(ffmpeg_next::encoder::find_by_name("aac"))?;
+    if let Some(start) = start {
+        let position = (start * ffmpeg_next::ffi::AV_TIME_BASE as f64) as i64;
+        ictx.seek(position, ..position)?;
+    }
+
+    let mut decoded = frame::Audio::empty();
+    let mut fan_out = |decoder: &mut ffmpeg_next::decoder::Audio,
+                       encoders: &mut [AudioOutput]|
+     -> Result<(), Error> {
+        while decoder.receive_frame(&mut decoded).is_ok() {
+            if decoded.channel_layout().is_empty() {
+                decoded.set_channel_layout(layout);
+            }
+            let timestamp = decoded.timestamp();
+            decoded.set_pts(timestamp);
+            for output in encoders.iter_mut() {
+                output.push(&decoded)?;
+            }
+        }
+        Ok(())
+    };
 
     for (stream, packet) in ictx.packets() {
-        if stream.index() == audio_stream.index() {
-            packet.write_interleaved(&mut octx)?;
+        if stream.index() != stream_index {
+            continue;
+        }
+        let timestamp = packet.pts().unwrap_or(0) as f64 * f64::from(time_base);
+        if start.is_some_and(|start| timestamp < start) {
+            continue;
         }
+        if end.is_some_and(|end| timestamp > end) {
+            break;
+        }
+        decoder.send_packet(&packet)?;
+        fan_out(&mut decoder, &mut encoders)?;
     }
+    decoder.send_eof()?;
+    fan_out(&mut decoder, &mut encoders)?;
 
-    octx.write_trailer()?;
+    for output in encoders {
+        output.finish()?;
+    }
     Ok(())
 }
 
-pub fn transcribe_audio(audio_path: &Path) -> Result<Vec<AudioResult>, Box<dyn std::error::Error>> {
-    // Pseudo-code for speech recognition (e.g., Whisper integration)
-    // In real implementation, you would call an external service or library
-    println!("Transcribing audio from: {:?}", audio_path);
-
-    let transcription = vec![
-        AudioResult {
-            start_time: 0.0,
-            end_time: 5.0,
-            text: "Hello, this is a sample transcription".to_string(),
-        },
-        AudioResult {
-            start_time: 5.0,
-            end_time: 10.0,
-            text: "This demonstrates audio processing capabilities".to_string(),
-        },
-    ];
-
-    Ok(transcription)
+// The sample rate and channel layout `codec` will take in place of `rate` Hz with
+// `channels` channels: the nearest rate it lists (the higher one on a tie) and its widest
+// layout with at most that many channels. The resampler in front of the encoder does the
+// conversion. Encoders that list no rates or layouts take any.
+fn supported_audio_format(
+    codec: &ffmpeg_next::codec::Audio,
+    rate: i32,
+    channels: i32,
+) -> (i32, ChannelLayout) {
+    let rate = codec
+        .rates()
+        .and_then(|rates| {
+            rates.min_by_key(|supported| ((supported - rate).abs(), std::cmp::Reverse(*supported)))
+        })
+        .unwrap_or(rate);
+    let layout = codec
+        .channel_layouts()
+        .map(|layouts| layouts.best(channels))
+        .unwrap_or_else(|| ChannelLayout::default(channels));
+    (rate, layout)
+}
+
+struct AudioOutput {
+    octx: format::context::Output,
+    encoder: encoder::Audio,
+    filter: filter::Graph,
+    // Samples sent to the encoder so far, which is the next pts in its 1/rate time base
+    next_pts: i64,
+    encoder_time_base: Rational,
+}
+
+impl AudioOutput {
+    fn open(
+        spec: &AudioOutputSpec,
+        decoder: &ffmpeg_next::decoder::Audio,
+        layout: ChannelLayout,
+        in_time_base: Rational,
+    ) -> Result<Self, Error> {
+        let codec = encoder::find_by_name(&spec.encoder)
+            .ok_or(Error::EncoderNotFound)?
+            .audio()?;
+        let mut octx = format::output(&spec.path)?;
+        let global_header = octx
+            .format()
+            .flags()
+            .contains(format::flag::Flags::GLOBAL_HEADER);
+
+        let mut ost = octx.add_stream(codec)?;
+        let mut encoder = codec::context::Context::from_parameters(ost.parameters())?
+            .encoder()
+            .audio()?;
+
+        let channels = spec.channels.unwrap_or(layout.channels() as u16) as i32;
+        let requested_rate = spec.sample_rate.unwrap_or(decoder.rate()) as i32;
+        let (rate, channel_layout) = supported_audio_format(&codec, requested_rate, channels);
+        if rate != requested_rate || channel_layout.channels() != channels {
+            video_eprintln!(
+                "Warning: {} can't encode {} Hz / {} channels; {:?} gets {} Hz / {} channels",
+                spec.encoder,
+                requested_rate,
+                channels,
+                spec.path.file_name().unwrap_or_default(),
+                rate,
+                channel_layout.channels()
+            );
+        }
+        let sample_format = codec
+            .formats()
+            .and_then(|mut formats| formats.next())
+            .ok_or(Error::InvalidData)?;
+
+        if global_header {
+            encoder.set_flags(codec::flag::Flags::GLOBAL_HEADER);
+        }
+        encoder.set_rate(rate);
+        encoder.set_channel_layout(channel_layout);
+        encoder.set_channels(channel_layout.channels());
+        encoder.set_format(sample_format);
+        encoder.set_bit_rate(decoder.bit_rate());
+        encoder.set_time_base((1, rate));
+        ost.set_time_base((1, rate));
+
+        let encoder = encoder.open_as(codec)?;
+        ost.set_parameters(&encoder);
+
+        // abuffersink converts to whatever the encoder takes, so the graph itself is empty
+        let mut filter = filter::Graph::new();
+        let args = format!(
+            "time_base={}:sample_rate={}:sample_fmt={}:channel_layout=0x{:x}",
+            in_time_base,
+            decoder.rate(),
+            decoder.format().name(),
+            layout.bits()
+        );
+        filter.add(
+            &filter::find("abuffer").ok_or(Error::FilterNotFound)?,
+            "in",
+            &args,
+        )?;
+        filter.add(
+            &filter::find("abuffersink").ok_or(Error::FilterNotFound)?,
+            "out",
+            "",
+        )?;
+        {
+            let mut out = filter.get("out").unwrap();
+            out.set_sample_format(encoder.format());
+            out.set_channel_layout(encoder.channel_layout());
+            out.set_sample_rate(encoder.rate());
+        }
+        filter.output("in", 0)?.input("out", 0)?.parse("anull")?;
+        filter.validate()?;
+        // Fixed-frame-size encoders (AAC: 1024 samples) need exactly that many per frame
+        if !codec
+            .capabilities()
+            .contains(codec::capabilities::Capabilities::VARIABLE_FRAME_SIZE)
+        {
+            filter
+                .get("out")
+                .unwrap()
+                .sink()
+                .set_frame_size(encoder.frame_size());
+        }
+
+        octx.write_header()?;
+
+        Ok(Self {
+            octx,
+            encoder,
+            filter,
+            next_pts: 0,
+            encoder_time_base: Rational(1, rate),
+        })
+    }
+
+    fn push(&mut self, frame: &frame::Audio) -> Result<(), Error> {
+        // The source takes ownership of the frame's buffers, and every output needs them
+        self.filter
+            .get("in")
+            .unwrap()
+            .source()
+            .add(&frame.clone())?;
+        self.drain_filter()
+    }
+
+    fn drain_filter(&mut self) -> Result<(), Error> {
+        let mut filtered = frame::Audio::empty();
+        while self
+            .filter
+            .get("out")
+            .unwrap()
+            .sink()
+            .frame(&mut filtered)
+            .is_ok()
+        {
+            filtered.set_pts(Some(self.next_pts));
+            self.next_pts += filtered.samples() as i64;
+            self.encoder.send_frame(&filtered)?;
+            self.write_packets()?;
+        }
+        Ok(())
+    }
+
+    fn write_packets(&mut self) -> Result<(), Error> {
+        let stream_time_base = self.octx.stream(0).unwrap().time_base();
+        let mut packet = Packet::empty();
+        while self.encoder.receive_packet(&mut packet).is_ok() {
+            packet.set_stream(0);
+            packet.rescale_ts(self.encoder_time_base, stream_time_base);
+            packet.write_interleaved(&mut self.octx)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), Error> {
+        self.filter.get("in").unwrap().source().flush()?;
+        self.drain_filter()?;
+        self.encoder.send_eof()?;
+        self.write_packets()?;
+        self.octx.write_trailer()
+    }
+}
+
+pub fn transcribe_audio(audio_path: &Path) -> anyhow::Result<Vec<AudioResult>> {
+    transcribe_audio_with(&MockTranscriptionBackend::new(), audio_path)
+}
+
+pub fn transcribe_audio_with(
+    backend: &dyn TranscriptionBackend,
+    audio_path: &Path,
+) -> anyhow::Result<Vec<AudioResult>> {
+    backend.transcribe(audio_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures;
+
+    // Three seconds of a 440 Hz tone at 48 kHz stereo, with video
+    fn tone_fixture() -> Option<PathBuf> {
+        test_fixtures::generate(
+            "tone.mkv",
+            &[
+                "-f",
+                "lavfi",
+                "-i",
+                "testsrc=size=64x48:rate=10:duration=3",
+                "-f",
+                "lavfi",
+                "-i",
+                "sine=frequency=440:sample_rate=48000:duration=3",
+                "-ac",
+                "2",
+                "-c:a",
+                "pcm_s16le",
+            ],
+        )
+    }
+
+    // (sample rate, channels, duration in seconds) of a file's audio stream
+    fn audio_format(path: &Path) -> (u32, u16, f64) {
+        let ictx = format::input(&path).unwrap();
+        let stream = ictx.streams().best(media::Type::Audio).unwrap();
+        let decoder = codec::context::Context::from_parameters(stream.parameters())
+            .unwrap()
+            .decoder()
+            .audio()
+            .unwrap();
+        let duration = ictx.duration() as f64 / f64::from(ffmpeg_next::ffi::AV_TIME_BASE);
+        (decoder.rate(), decoder.channels(), duration)
+    }
+
+    #[test]
+    fn one_decode_pass_writes_wav_and_archival_audio() {
+        let Some(video) = tone_fixture() else { return };
+        let dir = test_fixtures::scratch_dir("audio-outputs");
+        let wav = AudioOutputSpec::speech_wav(&dir.join("speech"));
+        let archival = archival_audio_spec(&dir.join("audio.aac")).unwrap();
+
+        extract_audio_outputs(
+            &video,
+            &[wav.clone(), archival.clone()],
+            None,
+            None,
+            &HashMap::new(),
+        )
+        .unwrap();
+
+        let (rate, channels, duration) = audio_format(&wav.path);
+        assert_eq!((rate, channels), (16_000, 1));
+        assert!((duration - 3.0).abs() < 0.2, "{}", duration);
+        let (rate, _, duration) = audio_format(&archival.path);
+        assert_eq!(rate, 48_000);
+        assert!((duration - 3.0).abs() < 0.2, "{}", duration);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn sample_rate_above_what_aac_lists_is_resampled_to_the_nearest() {
+        // 192 kHz stereo; AAC goes up to 96 kHz
+        let Some(video) = test_fixtures::generate(
+            "tone_192k.mkv",
+            &[
+                "-f",
+                "lavfi",
+                "-i",
+                "sine=frequency=440:sample_rate=192000:duration=2",
+                "-ac",
+                "2",
+                "-c:a",
+                "pcm_s16le",
+            ],
+        ) else {
+            return;
+        };
+        let dir = test_fixtures::scratch_dir("audio-high-rate");
+        let archival = archival_audio_spec(&dir.join("audio.aac")).unwrap();
+        if archival.encoder != "aac" {
+            return;
+        }
+
+        extract_audio_outputs(&video, &[archival.clone()], None, None, &HashMap::new()).unwrap();
+
+        let (rate, channels, duration) = audio_format(&archival.path);
+        assert_eq!((rate, channels), (96_000, 2));
+        assert!((duration - 2.0).abs() < 0.2, "{}", duration);
+        let _ = std::fs::remove_dir_all(dir);
+    }
 }
@@ -1,18 +1,375 @@
-use crate::audio_processor::{extract_audio, transcribe_audio, AudioResult};
+use crate::annotated_video::{render_annotated_video, UnanalyzedFrames};
+use crate::audio_processor::{
+    archival_audio_spec, extract_audio_outputs, segment_midpoints, stream_durations,
+    stream_start_times, transcribe_audio_with, AudioOutputSpec, AudioResult,
+};
+use crate::clip_extractor::{extract_clips, sanitize, ClipConfig, ClipTrigger};
+use crate::config::{MLConfig, OutputConfig, ProcessingConfig};
+use crate::coverage::{format_coverage, label_coverage, CoverageAccumulator};
+use crate::dedupe::{find_duplicates, Duplicate};
 use crate::frame_analyzer::{FrameAnalyzer, FrameResult};
-use crate::synchronizer::{synchronize_results, SynchronizedResult};
-use crate::video_processor::extract_frames;
+use crate::manifest::Manifest;
+use crate::ml_backend::{load_class_names, FrameAnalysis};
+use crate::output_writer::{
+    BboxFormat, CueTiming, JsonWriter, OutputRegistry, OutputWriter, SrtWriter, TxtWriter,
+    VideoInfo, YoloClasses, YoloWriter,
+};
+use crate::palette::{write_palette, PALETTE_FILE};
+use crate::postprocess::{postprocess_frame, FrameAudit, PostprocessConfig};
+use crate::preprocess::PreprocessStep;
+use crate::progress_events::{emit, ProgressEvent};
+use crate::progress_file::ProgressFile;
+use crate::subtitle_processor::extract_subtitles;
+use crate::synchronizer::{
+    attach_datetimes, duration_mismatch, rebase_timestamps, shift_audio_results, synchronize_frame,
+    synchronize_results, SyncStrategy, SynchronizedResult,
+};
+use crate::taxonomy::Taxonomy;
+use crate::timeline::{
+    combined_timeline, timeline_csv, timeline_json, undated_videos, TIMELINE_CSV, TIMELINE_JSON,
+};
+use crate::tracker::{interpolate_tracks, smooth_confidences, track_detections};
+use crate::transcription_backend::{create_transcription_backend, TranscriptionBackend};
+use crate::video_log::{video_eprintln, video_println, with_video_log};
+use crate::video_processor::{
+    analysis_input, creation_time, decode_frames, display_dimensions, extract_frames_with_options,
+    frame_path, has_video_stream, is_variable_frame_rate, motion_profile, video_duration,
+    CropRegion, DecodeSummary, DecodedFrame, ExtractionOptions, FrameBitDepth, FrameNaming,
+    FrameQuality, FrameSampling, FrameWriter, HdrMode, ImageLimits, PixelRect,
+};
 use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
 use std::time::Instant;
 
+// Queue size for the shared inference queue when frame_queue_capacity is unset
+const DEFAULT_FAN_IN_QUEUE: usize = 32;
+// Decoded frames one decoding video holds at a time for Concurrency::Auto: the codec's
+// reference and reordering buffers (up to 16 for H.264/HEVC) plus the frames being
+// converted and handed over
+const FRAMES_PER_DECODER: u64 = 18;
+// Share of the available memory Concurrency::Auto plans for; the rest is left for the
+// model, the transcriber and the results
+const AUTO_MEMORY_SHARE: f64 = 0.5;
+// Frames kept in the analysis cache with cache_detections
+const DETECTION_CACHE_CAPACITY: usize = 4096;
+// Under the output directory; see ReviewAction
+const NEEDS_REVIEW_DIR: &str = "needs_review";
+// Under the output directory; see OutputBucketing::ByDominantLabel
+const PENDING_DIR: &str = ".pending";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    #[default]
+    Name,
+    SizeDesc,
+    SizeAsc,
+    MTime,
+}
+
+// What happens to videos flagged by review_threshold besides being listed in the summary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReviewAction {
+    #[default]
+    List,
+    // Link the input video into needs_review/ in the output directory
+    Symlink,
+    // Move the input video there
+    Move,
+}
+
+// How many videos are decoded at once (with video_workers; the watcher also takes at
+// most this many new files per round)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Concurrency {
+    // Estimated from the memory available when the batch starts and its largest frame:
+    //   frame = width * height * 3 bytes (6 for 16-bit frames)
+    //   budget = available * AUTO_MEMORY_SHARE - queue capacity * frame
+    //   videos = budget / (frame * FRAMES_PER_DECODER), between 1 and the CPU count
+    Auto,
+    Fixed(usize),
+}
+
+impl Default for Concurrency {
+    fn default() -> Self {
+        Self::Fixed(4)
+    }
+}
+
+impl std::fmt::Display for Concurrency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Auto => f.write_str("auto"),
+            Self::Fixed(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+// How per-video output directories are grouped under output_dir
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputBucketing {
+    // output_dir/<video>/
+    #[default]
+    None,
+    // output_dir/<YYYY-MM-DD>/<video>/ from the container creation_time (UTC), or
+    // output_dir/undated/<video>/
+    ByDate,
+    // output_dir/<label>/<video>/ for the label with the most detections, or
+    // output_dir/unlabeled/<video>/ for videos without any (and failed ones). The label
+    // is only known after analysis, so the video is written to output_dir/.pending/
+    // first and moved when it's done.
+    ByDominantLabel,
+}
+
+// Live progress reporting besides the console output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProgressFormat {
+    #[default]
+    Text,
+    // Also write one JSON event per line to stderr (see progress_events) for a parent
+    // process to follow
+    Jsonl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PipelineMode {
+    #[default]
+    FrameFirst,
+    // Transcribe first, then analyze one frame at each segment's midpoint
+    AudioAligned,
+}
+
+// Where the timed text fed to the synchronizer comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TranscriptSource {
+    #[default]
+    Audio,
+    Subtitles,
+    // Use the subtitle stream when it yields text, otherwise transcribe the audio
+    PreferSubtitles,
+}
+
+// Stops analyzing a video's remaining frames once met
+#[derive(Debug, Clone, PartialEq)]
+pub enum EarlyStop {
+    // This label has passed its postprocess threshold in this many frames
+    LabelCount { label: String, frames: usize },
+    // Any detection at or above this confidence
+    ConfidenceAbove(f32),
+}
+
+// Progress toward the configured EarlyStop for one video
+#[derive(Debug, Default)]
+struct EarlyStopCounter {
+    hits: usize,
+}
+
+// What is left to sample of a video. The chunk_duration windows of a video share one,
+// so max_frames_per_video, frames_per_video and early_stop apply to the whole video
+// rather than to each chunk.
+#[derive(Debug, Default)]
+struct FrameBudget {
+    // Frames max_frames_per_video still allows
+    max_frames: Option<usize>,
+    // This window's share of frames_per_video, when it picks the frames
+    frames: Option<usize>,
+    early_stop: EarlyStopCounter,
+}
+
+impl FrameBudget {
+    fn new(config: &BatchConfig) -> Self {
+        Self {
+            max_frames: config.max_frames_per_video,
+            ..Self::default()
+        }
+    }
+}
+
+// Coordinate space of reported boxes when frames are cropped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BboxCoordinates {
+    CropLocal,
+    #[default]
+    FullFrame,
+}
+
 #[derive(Debug)]
 pub struct BatchConfig {
     pub input_dir: PathBuf,
     pub output_dir: PathBuf,
+    pub bucketing: OutputBucketing,
     pub video_extensions: Vec<String>,
-    pub max_concurrent: usize,
+    // Audio files to transcribe alongside the videos; empty means videos only
+    pub audio_extensions: Vec<String>,
+    pub max_concurrent: Concurrency,
+    // Worker threads are named "<prefix>-decode-N" / "<prefix>-infer-N" for debuggers and
+    // panic messages
+    pub thread_name_prefix: String,
+    pub sort_order: SortOrder,
+    // File name -> priority; higher runs first. Videos not listed fall back to a
+    // `<video file name>.priority` sidecar holding an integer, then to 0.
+    pub priorities: HashMap<String, i32>,
+    pub pipeline_mode: PipelineMode,
+    pub max_frames_per_video: Option<usize>,
+    // In frame-first mode, analyze one frame per this many seconds instead of every frame
+    pub frame_interval: Option<f64>,
+    // Frame-first mode: up to this many evenly spaced frames per keyframe interval;
+    // takes precedence over frame_interval
+    pub frames_per_gop: Option<usize>,
+    // Frame-first mode: this many evenly spaced frames per video, whatever its length;
+    // takes precedence over frame_interval (FrameSampling::FixedCount)
+    pub frames_per_video: Option<usize>,
+    // Add interpolated detections for tracked objects every this many seconds between
+    // analyzed frames, for smoother visualization of sparse sampling
+    pub interpolate_interval: Option<f64>,
+    // Only this region of each frame is saved and analyzed
+    pub crop: Option<CropRegion>,
+    // HDR input is tone-mapped to SDR by default; see HdrMode
+    pub hdr_mode: HdrMode,
+    // Keep more than 8 bits per component in saved frames of high bit depth sources
+    pub frame_bit_depth: FrameBitDepth,
+    // Corrupt packets skipped per video before it fails, like a media player would
+    pub max_decode_errors: usize,
+    // Frames are turned upright using the stream's rotation tag unless this is set
+    pub ignore_rotation: bool,
+    // ffmpeg decoder options (AVDictionary) for both video and audio decoding
+    pub decoder_options: HashMap<String, String>,
+    pub frame_naming: FrameNaming,
+    // Videos and frames larger than this are rejected instead of decoded
+    pub image_limits: ImageLimits,
+    // Applied in order to the copy of each frame the model sees
+    pub preprocessing: Vec<PreprocessStep>,
+    // Also apply preprocessing to the saved frames
+    pub preprocess_saved_frames: bool,
+    // Frames that look the same as one analyzed earlier in the batch (same size and
+    // dHash) reuse its detections instead of running the model again, e.g. for intros
+    // and outros shared by many videos
+    pub cache_detections: bool,
+    // Add sharpness/brightness to each frame; frames below min_sharpness are flagged blurry
+    pub quality_metrics: bool,
+    pub min_sharpness: Option<f32>,
+    pub bbox_coordinates: BboxCoordinates,
+    // Decode and analyze concurrently through a queue of at most this many frames
+    pub frame_queue_capacity: Option<usize>,
+    // Decode up to max_concurrent videos at once, feeding this many inference workers
+    // through one shared frame queue; None processes videos one after another
+    pub video_workers: Option<usize>,
+    // With video_workers, load transcripts on a separate pool of this many threads while
+    // frames are analyzed, instead of one video after another once analysis is done.
+    // Each pool is sized for its own workload, e.g. many workers for a remote
+    // transcription API next to a couple of GPU inference workers.
+    pub audio_workers: Option<usize>,
+    // Postprocess, synchronize and append each frame to results.json as soon as it's
+    // analyzed instead of holding the video's results, so memory stays flat however long
+    // the video is; the transcript is loaded first. Only the json format is written, and
+    // features that need every frame at once (tracking, clips, exports, ...) are
+    // rejected. Label coverage and taxonomy rollups are left out of the summary.
+    pub low_memory: bool,
+    // Process only one of each set of duplicate videos; the others get a copy of its results
+    pub dedupe: bool,
+    // Skip videos whose peak motion_profile score is below this
+    pub min_activity: Option<f32>,
+    // Decimal places written to results.json; f32 confidences otherwise print widening noise
+    pub confidence_decimals: usize,
+    pub timestamp_decimals: usize,
+    // save_frames / save_audio keep extracted frames / audio in the output directory;
+    // otherwise they go to a scratch directory that is removed once the video is processed
+    pub output: OutputConfig,
+    // Adds supercategories to results.json and category rollups to the summary
+    pub taxonomy: Option<Taxonomy>,
+    // Label -> name shown in results.txt and the summary; stored labels are unchanged
+    // (see taxonomy::load_display_aliases)
+    pub display_aliases: HashMap<String, String>,
+    // Only process this window (seconds) of each video
+    pub start_time: Option<f64>,
+    pub end_time: Option<f64>,
+    // Process videos longer than this many seconds in windows of this length, one after
+    // another, each written to <video>/chunk_NNN/ and listed in <video>/chunks.json.
+    // A chunk's frames and analyses are released before the next one starts, but the
+    // results of every chunk are kept for the batch (e.g. dominant-label bucketing)
+    // unless low_memory is set too.
+    pub chunk_duration: Option<f64>,
+    // ML backends to try in order; the first one that loads is used
+    pub ml_backends: Vec<String>,
+    pub ml_model_path: Option<PathBuf>,
+    // Labels for the model's class indices, one per line (see load_class_names)
+    pub class_names_path: Option<PathBuf>,
+    // Debug aid: write the raw output tensors of each video's first frames here, as
+    // <dir>/<video>/<frame>.tensor.json (onnx backend only)
+    pub tensor_dump_dir: Option<PathBuf>,
+    pub transcription_backend: String,
+    // Model file for the transcription backend, e.g. a ggml model for whisper
+    pub transcription_model_path: Option<PathBuf>,
+    pub transcript_source: TranscriptSource,
+    // Also write a 16 kHz mono WAV next to the extracted audio and transcribe that.
+    // Backends that name their own format (TranscriptionBackend::audio_format) always
+    // get a WAV in it.
+    pub speech_wav: bool,
+    // Extra attempts at transcribing a video's audio after the first fails. A video
+    // whose transcription still fails succeeds without audio results (see
+    // VideoProcessingResult::transcription_error); audio-only inputs and the
+    // audio-aligned pipeline, which need the transcript, fail instead.
+    pub transcription_retries: usize,
+    // Seconds added to transcript times after stream start offsets are corrected;
+    // positive values move text later
+    pub audio_offset: f64,
+    pub sync_strategy: SyncStrategy,
+    // Keep transcript segments no analyzed frame falls inside as text-only entries
+    pub include_unmatched_audio: bool,
+    // Names of the output writers run for each video: json, csv, srt, txt, or any
+    // registered with BatchProcessor::register_output_writer
+    pub output_formats: Vec<String>,
+    // How long each cue in results.srt lasts
+    pub subtitle_timing: CueTiming,
+    // Box layout in results.json; the normalized formats divide by the frame size
+    pub bbox_format: BboxFormat,
+    // Class names for the `yolo` output format, which is only available when set
+    pub yolo_classes: Option<YoloClasses>,
+    // Also write detections into this SQLite database (requires the `sqlite` feature)
+    pub sqlite_path: Option<PathBuf>,
+    // Write what postprocessing dropped from each frame to <video>/audit.jsonl
+    pub audit_log: bool,
+    // Also write each video's messages (stages, warnings, failures) to <video>/log.txt
+    pub per_video_logs: bool,
+    // Skip the rest of a video once this is met; the result records why
+    pub early_stop: Option<EarlyStop>,
+    // Successful videos with fewer detections than this are flagged for review; a model
+    // or config problem tends to look like an empty scene
+    pub review_threshold: Option<usize>,
+    pub review_action: ReviewAction,
+    // fsync each video's output files, the summaries and the manifest before moving on,
+    // so a crash or power loss can't take back a video already reported done. Costs a
+    // disk round trip per file, which is noticeable on slow or network storage.
+    pub durable_writes: bool,
+    // Cut stream-copied clips around detections of these labels into <video>/clips
+    pub clips: Option<ClipConfig>,
+    // Re-encode each video into <video>/annotated.mp4 with its detection boxes drawn on;
+    // the value picks what frames between analyzed ones show
+    pub annotated_video: Option<UnanalyzedFrames>,
+    // Add an absolute `datetime` (container creation_time + timestamp) to each result;
+    // videos without creation_time metadata get none
+    pub wall_clock: bool,
+    // Write combined_timeline.json/.csv: every video's detection intervals merged and
+    // ordered by wall-clock time (needs wall_clock), e.g. for a multi-camera wall
+    pub combined_timeline: bool,
+    // Seconds a label may go undetected without splitting its timeline interval (see
+    // timeline::object_intervals)
+    pub max_bridge_gap: f64,
+    // Write every detection of the batch to this Parquet file (requires the `parquet`
+    // feature); see parquet_export for the schema
+    pub parquet_path: Option<PathBuf>,
+    pub postprocess: PostprocessConfig,
+    // Stop the batch at the first failed video instead of continuing (for CI gates)
+    pub fail_fast: bool,
+    // Keep progress.json in the output directory up to date for headless monitoring
+    pub progress_file: bool,
+    pub progress_format: ProgressFormat,
+    // Write palette.json with the label -> color map of every label detected
+    pub export_palette: bool,
 }
 
 impl Default for BatchConfig {
@@ -20,6 +377,7 @@ impl Default for BatchConfig {
         Self {
             input_dir: PathBuf::from("input_videos"),
             output_dir: PathBuf::from("output_results"),
+            bucketing: OutputBucketing::None,
             video_extensions: vec![
                 "mp4".to_string(),
                 "avi".to_string(),
@@ -28,20 +386,395 @@ impl Default for BatchConfig {
                 "wmv".to_string(),
                 "flv".to_string(),
             ],
-            max_concurrent: 4,
+            audio_extensions: Vec::new(),
+            max_concurrent: Concurrency::Fixed(4),
+            thread_name_prefix: "batch".to_string(),
+            sort_order: SortOrder::Name,
+            priorities: HashMap::new(),
+            pipeline_mode: PipelineMode::FrameFirst,
+            max_frames_per_video: None,
+            frame_interval: None,
+            frames_per_gop: None,
+            frames_per_video: None,
+            interpolate_interval: None,
+            crop: None,
+            hdr_mode: HdrMode::ToneMap,
+            frame_bit_depth: FrameBitDepth::Eight,
+            max_decode_errors: 0,
+            ignore_rotation: false,
+            decoder_options: HashMap::new(),
+            frame_naming: FrameNaming::IndexOnly,
+            image_limits: ImageLimits::default(),
+            preprocessing: Vec::new(),
+            preprocess_saved_frames: false,
+            cache_detections: false,
+            quality_metrics: false,
+            min_sharpness: None,
+            bbox_coordinates: BboxCoordinates::FullFrame,
+            frame_queue_capacity: None,
+            video_workers: None,
+            audio_workers: None,
+            low_memory: false,
+            dedupe: false,
+            min_activity: None,
+            confidence_decimals: 3,
+            timestamp_decimals: 3,
+            output: OutputConfig::default(),
+            taxonomy: None,
+            display_aliases: HashMap::new(),
+            start_time: None,
+            end_time: None,
+            chunk_duration: None,
+            ml_backends: vec!["mock".to_string()],
+            ml_model_path: None,
+            class_names_path: None,
+            tensor_dump_dir: None,
+            transcription_backend: "mock".to_string(),
+            transcription_model_path: None,
+            transcript_source: TranscriptSource::Audio,
+            speech_wav: false,
+            transcription_retries: 1,
+            audio_offset: 0.0,
+            sync_strategy: SyncStrategy::default(),
+            include_unmatched_audio: false,
+            output_formats: vec!["json".to_string()],
+            subtitle_timing: CueTiming::ExtendToNext,
+            bbox_format: BboxFormat::Xyxy,
+            yolo_classes: None,
+            sqlite_path: None,
+            parquet_path: None,
+            wall_clock: false,
+            combined_timeline: false,
+            max_bridge_gap: 0.0,
+            clips: None,
+            annotated_video: None,
+            audit_log: false,
+            per_video_logs: false,
+            early_stop: None,
+            review_threshold: None,
+            review_action: ReviewAction::List,
+            durable_writes: false,
+            postprocess: PostprocessConfig::default(),
+            fail_fast: false,
+            progress_file: false,
+            progress_format: ProgressFormat::Text,
+            export_palette: false,
+        }
+    }
+}
+
+// Broad cause of a failed video, for the failure rollup in the batch summaries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureCategory {
+    // The input lacks a stream the pipeline needs (video, audio or subtitles)
+    NoStream,
+    Decode,
+    Io,
+    Model,
+    Transcription,
+    // Processing code panicked; see WorkerPanic
+    Panic,
+    Other,
+}
+
+impl FailureCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureCategory::NoStream => "no_stream",
+            FailureCategory::Decode => "decode",
+            FailureCategory::Io => "io",
+            FailureCategory::Model => "model",
+            FailureCategory::Transcription => "transcription",
+            FailureCategory::Panic => "panic",
+            FailureCategory::Other => "other",
+        }
+    }
+
+    // The underlying ffmpeg / io error decides when there is one, so a missing stream or
+    // a full disk isn't blamed on whichever step ran into it
+    pub fn of(error: &anyhow::Error) -> Self {
+        if error.downcast_ref::<WorkerPanic>().is_some() {
+            return FailureCategory::Panic;
+        }
+        let ffmpeg_error = error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<ffmpeg_next::Error>());
+        if matches!(ffmpeg_error, Some(ffmpeg_next::Error::StreamNotFound)) {
+            return FailureCategory::NoStream;
+        }
+        if error
+            .chain()
+            .any(|cause| cause.downcast_ref::<std::io::Error>().is_some())
+        {
+            return FailureCategory::Io;
+        }
+        match error.downcast_ref::<FailedStep>() {
+            Some(FailedStep::FrameWrite) => FailureCategory::Io,
+            Some(FailedStep::FrameProcessing) => FailureCategory::Model,
+            Some(FailedStep::Transcription) => FailureCategory::Transcription,
+            Some(_) => FailureCategory::Decode,
+            None if ffmpeg_error.is_some() => FailureCategory::Decode,
+            None => FailureCategory::Other,
         }
     }
 }
 
+impl std::fmt::Display for FailureCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Attached as context to errors from each stage of processing a video, so failures can
+// be categorized without matching on messages
+#[derive(Debug, Clone, Copy)]
+enum FailedStep {
+    FrameExtraction,
+    FrameWrite,
+    FrameProcessing,
+    AudioExtraction,
+    SubtitleExtraction,
+    Transcription,
+}
+
+impl std::fmt::Display for FailedStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            FailedStep::FrameExtraction => "Frame extraction failed",
+            FailedStep::FrameWrite => "Failed to write frame",
+            FailedStep::FrameProcessing => "Frame processing failed",
+            FailedStep::AudioExtraction => "Audio extraction failed",
+            FailedStep::SubtitleExtraction => "Subtitle extraction failed",
+            FailedStep::Transcription => "Transcription failed",
+        };
+        f.write_str(message)
+    }
+}
+
+// Returned when fail_fast stops the batch at a failed video
+#[derive(Debug)]
+pub struct BatchAborted {
+    pub video_path: PathBuf,
+}
+
+impl std::fmt::Display for BatchAborted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Aborting batch (fail-fast): {:?} failed",
+            self.video_path
+        )
+    }
+}
+
+impl std::error::Error for BatchAborted {}
+
+// A panic caught while processing a video, so one bad video or backend bug fails that
+// video instead of taking down the batch
+#[derive(Debug)]
+pub struct WorkerPanic(pub String);
+
+impl std::fmt::Display for WorkerPanic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Panicked: {}", self.0)
+    }
+}
+
+impl std::error::Error for WorkerPanic {}
+
+fn catch_panic<T>(work: impl FnOnce() -> Result<T>) -> Result<T> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(work))
+        .unwrap_or_else(|payload| Err(WorkerPanic(panic_message(payload.as_ref())).into()))
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
 #[derive(Debug)]
 pub struct VideoProcessingResult {
     pub video_path: PathBuf,
     pub processing_time: std::time::Duration,
     pub frame_count: usize,
     pub audio_segments: usize,
+    // Detections the model made; interpolated ones don't count
+    pub detections: usize,
     pub synchronized_results: Vec<SynchronizedResult>,
     pub success: bool,
     pub error_message: Option<String>,
+    // Frame extraction stopped at max_frames_per_video
+    pub truncated: bool,
+    // Corrupt packets skipped while decoding (see max_decode_errors)
+    pub skipped_packets: usize,
+    // Times the frame size changed mid-stream (see DecodeSummary)
+    pub resolution_changes: usize,
+    // Failed because the output location is full or read-only; the batch can't continue
+    pub output_unwritable: bool,
+    // Input had no video stream and was only transcribed
+    pub audio_only: bool,
+    // Why analysis stopped before the end of the video, if it did (see early_stop)
+    pub early_stop_reason: Option<String>,
+    // Transcription failed (after transcription_retries) and the video has no audio
+    // results
+    pub transcription_error: Option<String>,
+    // Set for failed videos
+    pub failure_category: Option<FailureCategory>,
+    // Fraction of the processed duration each label was detected in (see
+    // coverage::label_coverage); empty for audio-only inputs
+    pub label_coverage: BTreeMap<String, f64>,
+    // Where the video's output was written, bucket included; set once the video is done
+    // (see move_to_bucket)
+    pub output_dir: PathBuf,
+}
+
+struct VideoAnalysis {
+    frame_results: Vec<FrameResult>,
+    audio_results: Vec<AudioResult>,
+    truncated: bool,
+    skipped_packets: usize,
+    resolution_changes: usize,
+    early_stop_reason: Option<String>,
+    transcription_error: Option<String>,
+    // Empty unless audit_log is set
+    audits: Vec<FrameAudit>,
+    // Set by process_video_low_memory, which has already written the results; its
+    // frame_results are empty
+    streamed: Option<StreamedResults>,
+}
+
+// Per-video counts reported in VideoProcessingResult
+#[derive(Debug, Default)]
+struct ResultTotals {
+    frame_count: usize,
+    audio_segments: usize,
+    detections: usize,
+}
+
+impl ResultTotals {
+    fn push(&mut self, result: &SynchronizedResult) {
+        self.frame_count += usize::from(result.is_analyzed_frame());
+        self.audio_segments += usize::from(result.audio_text.is_some());
+        self.detections += result
+            .video_objects
+            .iter()
+            .filter(|object| !object.interpolated)
+            .count();
+    }
+}
+
+// What complete_video needs from results that were written as they were made
+#[derive(Debug, Default)]
+struct StreamedResults {
+    totals: ResultTotals,
+    coverage: CoverageAccumulator,
+}
+
+impl StreamedResults {
+    fn push(&mut self, result: &SynchronizedResult) {
+        self.totals.push(result);
+        self.coverage.push(result);
+    }
+}
+
+struct FrameAnalysisOutput {
+    analyses: Vec<FrameAnalysis>,
+    // Parallel to analyses
+    qualities: Vec<Option<FrameQuality>>,
+    // Backend that produced the analyses
+    model: String,
+    frame_count: usize,
+    truncated: bool,
+    skipped_packets: usize,
+    resolution_changes: usize,
+    early_stop_reason: Option<String>,
+}
+
+// How analyses of one video become FrameResults; see postprocessed_result
+struct FrameContext {
+    // Crop region to shift boxes by (see full_frame_rect)
+    rect: Option<PixelRect>,
+    // See output_frame_size
+    frame_size: Option<(u32, u32)>,
+    // Backend that produced the analyses
+    model: String,
+}
+
+impl FrameContext {
+    fn to_full_frame(&self, analysis: &mut FrameAnalysis) {
+        if let Some(rect) = &self.rect {
+            for detection in &mut analysis.detections {
+                detection.bbox = rect.to_full_frame(detection.bbox);
+            }
+        }
+    }
+}
+
+struct VideoPaths {
+    name: String,
+    output_dir: PathBuf,
+    scratch_dir: PathBuf,
+    frames_dir: PathBuf,
+    audio_path: PathBuf,
+    // Window of the video processed: start_time/end_time, or one chunk of it (see
+    // chunk_duration)
+    start: Option<f64>,
+    end: Option<f64>,
+    // See is_audio_input; probed once per input
+    audio_only: bool,
+}
+
+// Per-video bookkeeping for the fan-in scheduler
+#[derive(Default)]
+struct FanInState {
+    start_time: Option<Instant>,
+    audio_results: Option<Vec<AudioResult>>,
+    // Loaded by an audio worker (see BatchConfig::audio_workers)
+    transcript: Option<Result<(Vec<AudioResult>, Option<String>)>>,
+    decoded: Option<Result<(usize, DecodeSummary)>>,
+    analyses: Vec<(usize, FrameAnalysis, Option<FrameQuality>)>,
+    analysis_error: Option<anyhow::Error>,
+    early_stop: EarlyStopCounter,
+    early_stop_reason: Option<String>,
+    // Frames sent to the inference workers, and how many of them they are done with
+    queued: usize,
+    analyzed: usize,
+    // An audio worker is loading the transcript
+    transcribing: bool,
+    // Taken for completion; the video is done or being finished
+    completed: bool,
+}
+
+impl FanInState {
+    // Takes the state once the video is decoded, every queued frame is analyzed and no
+    // audio worker is still on it. Only the first caller to find it ready gets it.
+    fn take_if_ready(&mut self) -> Option<FanInState> {
+        if self.completed
+            || self.decoded.is_none()
+            || self.analyzed < self.queued
+            || self.transcribing
+        {
+            return None;
+        }
+        let state = std::mem::take(self);
+        self.completed = true;
+        Some(state)
+    }
+}
+
+// Running totals of a batch, updated as each video finishes
+struct BatchReport {
+    manifest: Manifest,
+    progress: Option<ProgressFile>,
+    results: Vec<VideoProcessingResult>,
+    successful: usize,
+    failed: usize,
 }
 
 #[derive(Debug)]
@@ -51,15 +784,113 @@ pub struct BatchResults {
     pub failed: usize,
     pub total_processing_time: std::time::Duration,
     pub results: Vec<VideoProcessingResult>,
+    pub skipped: SkippedVideos,
+}
+
+// Videos left out by the pre-pass filters
+#[derive(Debug, Default)]
+pub struct SkippedVideos {
+    pub duplicates: Vec<Duplicate>,
+    // Peak activity below min_activity
+    pub inactive: Vec<PathBuf>,
+}
+
+impl SkippedVideos {
+    pub fn len(&self) -> usize {
+        self.duplicates.len() + self.inactive.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 pub struct BatchProcessor {
     config: BatchConfig,
+    output_writers: OutputRegistry,
 }
 
 impl BatchProcessor {
     pub fn new(config: BatchConfig) -> Self {
-        Self { config }
+        let mut output_writers = OutputRegistry::with_builtins(json_writer(&config));
+        if !config.display_aliases.is_empty() {
+            output_writers.register(
+                "txt",
+                TxtWriter {
+                    display_aliases: config.display_aliases.clone(),
+                },
+            );
+        }
+        if config.subtitle_timing != CueTiming::default() {
+            output_writers.register(
+                "srt",
+                SrtWriter {
+                    timing: config.subtitle_timing,
+                },
+            );
+        }
+        if let Some(classes) = &config.yolo_classes {
+            output_writers.register(
+                "yolo",
+                YoloWriter {
+                    classes: classes.clone(),
+                },
+            );
+        }
+        Self {
+            config,
+            output_writers,
+        }
+    }
+
+    // Makes `name` available in output_formats, replacing a built-in of the same name
+    pub fn register_output_writer(&mut self, name: &str, writer: impl OutputWriter + 'static) {
+        self.output_writers.register(name, writer);
+    }
+
+    // What this processor will run with, in the config file's terms (see print-config):
+    // the settings config::ProcessingConfig has a counterpart for, plus the `.priority`
+    // sidecars of the videos now in input_dir. Settings the batch doesn't use
+    // (skip_existing, use_gpu) are kept from `base`, the file it was configured from.
+    pub fn effective_config(&self, base: ProcessingConfig) -> ProcessingConfig {
+        let config = &self.config;
+        let mut priorities = config.priorities.clone();
+        // No input directory yet just means no sidecars
+        for video_path in self.find_video_files().unwrap_or_default() {
+            let Some(name) = video_path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let priority = self.priority(&video_path);
+            if priority != 0 {
+                priorities.entry(name.to_string()).or_insert(priority);
+            }
+        }
+
+        ProcessingConfig {
+            batch: crate::config::BatchConfig {
+                input_directory: config.input_dir.clone(),
+                output_directory: config.output_dir.clone(),
+                video_extensions: config.video_extensions.clone(),
+                max_concurrent_videos: match config.max_concurrent {
+                    Concurrency::Auto => 0,
+                    Concurrency::Fixed(videos) => videos,
+                },
+                skip_existing: base.batch.skip_existing,
+                priorities,
+            },
+            ml_models: MLConfig {
+                video_model_path: config.ml_model_path.clone(),
+                audio_model_path: config.transcription_model_path.clone(),
+                class_names_path: config.class_names_path.clone(),
+                confidence_threshold: config.postprocess.confidence_threshold,
+                per_label_thresholds: config.postprocess.per_label_thresholds.clone(),
+                use_gpu: base.ml_models.use_gpu,
+            },
+            output: OutputConfig {
+                output_format: config.output_formats.join(","),
+                ..config.output.clone()
+            },
+        }
     }
 
     pub fn find_video_files(&self) -> Result<Vec<PathBuf>> {
@@ -76,230 +907,2399 @@ impl BatchProcessor {
             let entry = entry?;
             let path = entry.path();
 
-            if path.is_file() {
-                if let Some(extension) = path.extension() {
-                    let ext = extension.to_string_lossy().to_lowercase();
-                    if self.config.video_extensions.contains(&ext) {
-                        video_files.push(path);
-                    }
+            if path.is_file() && self.is_video_file(&path) {
+                // ffmpeg-next converts paths with to_str().unwrap(), so opening a
+                // non-UTF-8 path would panic instead of failing the video
+                if path.to_str().is_none() {
+                    eprintln!("Warning: Skipping {:?}: path is not valid UTF-8", path);
+                    continue;
                 }
+                video_files.push(path);
             }
         }
 
+        // Name order first so ties in size/mtime stay deterministic
         video_files.sort();
+        match self.config.sort_order {
+            SortOrder::Name => {}
+            SortOrder::SizeDesc => {
+                video_files.sort_by_cached_key(|p| std::cmp::Reverse(Self::file_size(p)))
+            }
+            SortOrder::SizeAsc => video_files.sort_by_cached_key(|p| Self::file_size(p)),
+            SortOrder::MTime => video_files.sort_by_cached_key(|p| {
+                fs::metadata(p)
+                    .and_then(|m| m.modified())
+                    .unwrap_or(std::time::SystemTime::UNIX_EPOCH)
+            }),
+        }
+        // Stable, so equal priorities keep the order above; workers take videos in
+        // this order, so urgent ones start on the first free worker
+        video_files.sort_by_cached_key(|p| std::cmp::Reverse(self.priority(p)));
         Ok(video_files)
     }
 
-    pub fn process_single_video(
+    fn priority(&self, video_path: &Path) -> i32 {
+        let file_name = video_path.file_name().unwrap();
+        if let Some(priority) = file_name
+            .to_str()
+            .and_then(|name| self.config.priorities.get(name))
+        {
+            return *priority;
+        }
+
+        let mut sidecar_name = file_name.to_os_string();
+        sidecar_name.push(".priority");
+        let sidecar = video_path.with_file_name(sidecar_name);
+        match fs::read_to_string(&sidecar) {
+            Ok(content) => content.trim().parse().unwrap_or_else(|_| {
+                eprintln!("Warning: Ignoring invalid priority in {:?}", sidecar);
+                0
+            }),
+            Err(_) => 0,
+        }
+    }
+
+    pub fn is_video_file(&self, path: &Path) -> bool {
+        path.extension().is_some_and(|extension| {
+            let ext = extension.to_string_lossy().to_lowercase();
+            self.config.video_extensions.contains(&ext)
+                || self.config.audio_extensions.contains(&ext)
+        })
+    }
+
+    // Audio extensions are trusted; anything else is audio-only if it has no video stream
+    fn is_audio_input(&self, path: &Path) -> bool {
+        let audio_extension = path.extension().is_some_and(|extension| {
+            let ext = extension.to_string_lossy().to_lowercase();
+            self.config.audio_extensions.contains(&ext)
+        });
+        audio_extension || matches!(has_video_stream(path), Ok(false))
+    }
+
+    pub fn config(&self) -> &BatchConfig {
+        &self.config
+    }
+
+    fn file_size(path: &Path) -> u64 {
+        fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+    }
+
+    // Directories are named after the file stem as is (spaces, non-ASCII and all); only
+    // the name used in messages goes through a lossy conversion
+    fn video_paths(&self, video_path: &Path) -> VideoPaths {
+        let stem = video_path.file_stem().unwrap();
+        let name = stem.to_string_lossy().into_owned();
+
+        // Create output directories for this video
+        let output_dir = match self.config.bucketing {
+            OutputBucketing::None => self.config.output_dir.join(stem),
+            OutputBucketing::ByDate => self
+                .config
+                .output_dir
+                .join(date_bucket(video_path))
+                .join(stem),
+            OutputBucketing::ByDominantLabel => self.config.output_dir.join(PENDING_DIR).join(stem),
+        };
+        // Videos in different directories, or with different extensions, can share a stem
+        let mut scratch_name = std::ffi::OsString::from(format!(
+            "video-audio-processor-{}-{:016x}-",
+            std::process::id(),
+            path_hash(video_path)
+        ));
+        scratch_name.push(stem);
+        let scratch_dir = std::env::temp_dir().join(scratch_name);
+        let frames_dir = if self.config.output.save_frames {
+            output_dir.join("frames")
+        } else {
+            scratch_dir.join("frames")
+        };
+        let audio_path = if self.config.output.save_audio {
+            output_dir.join("audio.aac")
+        } else {
+            scratch_dir.join("audio.aac")
+        };
+
+        VideoPaths {
+            name,
+            output_dir,
+            scratch_dir,
+            frames_dir,
+            audio_path,
+            start: self.config.start_time,
+            end: self.config.end_time,
+            audio_only: self.is_audio_input(video_path),
+        }
+    }
+
+    fn process_single_video(
+        &self,
+        video_path: &Path,
+        paths: VideoPaths,
+        analyzer: &FrameAnalyzer,
+        transcriber: &dyn TranscriptionBackend,
+    ) -> VideoProcessingResult {
+        let mut result = with_video_log(self.video_log_dir(&paths), || {
+            video_println!("Processing video: {}", paths.name);
+            match self.chunk_windows(video_path, &paths) {
+                Some(chunks) => {
+                    self.process_chunks(video_path, &paths, &chunks, analyzer, transcriber)
+                }
+                None => {
+                    let mut budget = FrameBudget::new(&self.config);
+                    self.process_window(video_path, &paths, analyzer, transcriber, &mut budget)
+                }
+            }
+        });
+        self.move_to_bucket(&paths, &mut result);
+        result
+    }
+
+    // Processes the part of the video `paths` covers and saves its results there
+    fn process_window(
         &self,
         video_path: &Path,
+        paths: &VideoPaths,
         analyzer: &FrameAnalyzer,
+        transcriber: &dyn TranscriptionBackend,
+        budget: &mut FrameBudget,
     ) -> VideoProcessingResult {
         let start_time = Instant::now();
-        let video_name = video_path.file_stem().unwrap().to_string_lossy();
+        let outcome = catch_panic(|| {
+            if self.config.low_memory && !paths.audio_only {
+                self.process_video_low_memory(video_path, paths, analyzer, transcriber, budget)
+            } else {
+                self.process_video_internal(video_path, paths, analyzer, transcriber, budget)
+            }
+        });
+        self.complete_video(video_path, paths, start_time, outcome)
+    }
 
-        // Create output directories for this video
-        let video_output_dir = self.config.output_dir.join(&*video_name);
-        let frames_dir = video_output_dir.join("frames");
-        let audio_path = video_output_dir.join("audio.aac");
+    // The chunk_duration windows `paths` is split into, or None when the video fits in
+    // one (or its duration is unknown)
+    fn chunk_windows(&self, video_path: &Path, paths: &VideoPaths) -> Option<Vec<VideoPaths>> {
+        let chunk_duration = self.config.chunk_duration?;
+        if paths.audio_only {
+            return None;
+        }
+        let duration = video_duration(video_path).ok().flatten()?;
+        let start = paths.start.unwrap_or(0.0).max(0.0);
+        let end = paths.end.map_or(duration, |end| end.min(duration));
+        if end - start <= chunk_duration {
+            return None;
+        }
 
-        println!("Processing video: {}", video_name);
+        let count = ((end - start) / chunk_duration).ceil() as usize;
+        let chunks = (0..count)
+            .map(|index| {
+                let chunk_start = start + index as f64 * chunk_duration;
+                let chunk_end = (chunk_start + chunk_duration).min(end);
+                let dir_name = chunk_dir_name(index);
+                let output_dir = paths.output_dir.join(&dir_name);
+                let scratch_dir = paths.scratch_dir.join(&dir_name);
+                let kept_or_scratch = |keep: bool| {
+                    if keep {
+                        output_dir.clone()
+                    } else {
+                        scratch_dir.clone()
+                    }
+                };
+                VideoPaths {
+                    name: format!("{} [{}/{}]", paths.name, index + 1, count),
+                    frames_dir: kept_or_scratch(self.config.output.save_frames).join("frames"),
+                    audio_path: kept_or_scratch(self.config.output.save_audio).join("audio.aac"),
+                    output_dir,
+                    scratch_dir,
+                    start: Some(chunk_start),
+                    end: Some(chunk_end),
+                    audio_only: paths.audio_only,
+                }
+            })
+            .collect();
+        Some(chunks)
+    }
 
-        match self.process_video_internal(video_path, &frames_dir, &audio_path, analyzer) {
-            Ok((frame_results, audio_results)) => {
-                let synchronized_results = synchronize_results(frame_results, audio_results);
-                let processing_time = start_time.elapsed();
+    // Runs each chunk as a video of its own and stitches the results back together.
+    // Timestamps are on the whole video's clock already; frame indices count from the
+    // start of each chunk, matching its frames/ directory. The video fails if any chunk
+    // did, but later chunks still run unless the output location is unwritable. The
+    // chunks share one FrameBudget: once max_frames_per_video or early_stop is met, the
+    // remaining chunks aren't processed, and a chunk whose share of frames_per_video is
+    // zero is skipped.
+    fn process_chunks(
+        &self,
+        video_path: &Path,
+        paths: &VideoPaths,
+        chunks: &[VideoPaths],
+        analyzer: &FrameAnalyzer,
+        transcriber: &dyn TranscriptionBackend,
+    ) -> VideoProcessingResult {
+        let start_time = Instant::now();
+        let mut merged = VideoProcessingResult {
+            video_path: video_path.to_path_buf(),
+            processing_time: std::time::Duration::ZERO,
+            frame_count: 0,
+            audio_segments: 0,
+            detections: 0,
+            synchronized_results: Vec::new(),
+            success: true,
+            error_message: None,
+            truncated: false,
+            skipped_packets: 0,
+            resolution_changes: 0,
+            output_unwritable: false,
+            audio_only: false,
+            early_stop_reason: None,
+            transcription_error: None,
+            failure_category: None,
+            label_coverage: BTreeMap::new(),
+            output_dir: PathBuf::new(),
+        };
+        let mut index = Vec::new();
+        let mut budget = FrameBudget::new(&self.config);
+        let shares = self.frame_shares(chunks);
+        // Seconds each label was covered for, and the seconds of the chunks measured
+        let mut covered: BTreeMap<String, f64> = BTreeMap::new();
+        let mut measured = 0.0;
+
+        for (i, chunk) in chunks.iter().enumerate() {
+            if budget.max_frames == Some(0) {
+                video_println!(
+                    "Note: max_frames_per_video reached; skipping chunks {}-{}",
+                    i + 1,
+                    chunks.len()
+                );
+                merged.truncated = true;
+                break;
+            }
+            if let Some(shares) = &shares {
+                if shares[i] == 0 {
+                    video_println!(
+                        "Chunk {}/{}: no frames to sample; skipped",
+                        i + 1,
+                        chunks.len()
+                    );
+                    continue;
+                }
+                budget.frames = Some(shares[i]);
+            }
+            video_println!(
+                "Chunk {}/{}: {:.1}s - {:.1}s",
+                i + 1,
+                chunks.len(),
+                chunk.start.unwrap_or(0.0),
+                chunk.end.unwrap_or(0.0)
+            );
+            let result = self.process_window(video_path, chunk, analyzer, transcriber, &mut budget);
+            budget.max_frames = budget
+                .max_frames
+                .map(|left| left.saturating_sub(result.frame_count));
+            index.push(ChunkSummary {
+                index: i,
+                dir: chunk_dir_name(i),
+                start: chunk.start.unwrap_or(0.0),
+                end: chunk.end.unwrap_or(0.0),
+                success: result.success,
+                frame_count: result.frame_count,
+                detections: result.detections,
+                error: result.error_message.clone(),
+            });
+
+            if !result.success && merged.success {
+                merged.success = false;
+                merged.error_message = result
+                    .error_message
+                    .map(|message| format!("chunk {}: {}", i + 1, message));
+                merged.failure_category = result.failure_category;
+            }
+            // Each chunk's coverage is a share of its own window
+            if result.success {
+                let length = chunk.end.unwrap_or(0.0) - chunk.start.unwrap_or(0.0);
+                measured += length;
+                for (label, fraction) in &result.label_coverage {
+                    *covered.entry(label.clone()).or_insert(0.0) += fraction * length;
+                }
+            }
+            merged.output_unwritable |= result.output_unwritable;
+            merged.frame_count += result.frame_count;
+            merged.audio_segments += result.audio_segments;
+            merged.detections += result.detections;
+            merged
+                .synchronized_results
+                .extend(result.synchronized_results);
+            merged.truncated |= result.truncated;
+            merged.skipped_packets += result.skipped_packets;
+            merged.resolution_changes += result.resolution_changes;
+            merged.transcription_error = merged.transcription_error.or(result.transcription_error);
+            if merged.output_unwritable {
+                break;
+            }
+            // early_stop is met for the video, not the chunk
+            if let Some(reason) = result.early_stop_reason {
+                merged.early_stop_reason = Some(format!("{} (chunk {})", reason, i + 1));
+                break;
+            }
+        }
+        Self::remove_scratch_dir(paths);
+        if measured > 0.0 {
+            merged.label_coverage = covered
+                .into_iter()
+                .map(|(label, seconds)| (label, seconds / measured))
+                .collect();
+        }
+
+        if let Err(e) = write_chunk_index(&paths.output_dir, &index) {
+            video_eprintln!(
+                "Warning: Failed to write the chunk index for {}: {}",
+                paths.name,
+                e
+            );
+        }
+        merged.processing_time = start_time.elapsed();
+        merged
+    }
+
+    // frames_per_video split over the chunks in proportion to their length, adding up to
+    // the whole count; None unless frames_per_video picks the frames
+    fn frame_shares(&self, chunks: &[VideoPaths]) -> Option<Vec<usize>> {
+        let FrameSampling::FixedCount(count) = self.frame_first_sampling() else {
+            return None;
+        };
+        if self.config.pipeline_mode != PipelineMode::FrameFirst {
+            return None;
+        }
+        let start = chunks.first()?.start.unwrap_or(0.0);
+        let length = chunks.last()?.end.unwrap_or(0.0) - start;
+        // Frames due before `time`, rounded the same way for both ends of a chunk
+        let due = |time: Option<f64>| {
+            (count as f64 * (time.unwrap_or(0.0) - start) / length).round() as usize
+        };
+        Some(
+            chunks
+                .iter()
+                .map(|chunk| due(chunk.end) - due(chunk.start))
+                .collect(),
+        )
+    }
+
+    // Where with_video_log captures a video's messages, if per_video_logs is set
+    fn video_log_dir<'a>(&self, paths: &'a VideoPaths) -> Option<&'a Path> {
+        self.config
+            .per_video_logs
+            .then_some(paths.output_dir.as_path())
+    }
+
+    // Moves a video's output out of .pending/ once its dominant label is known. A
+    // previous run's output for the same video is replaced, as its files would be
+    // without bucketing. Records where the output ended up in result.output_dir.
+    fn move_to_bucket(&self, paths: &VideoPaths, result: &mut VideoProcessingResult) {
+        result.output_dir = paths.output_dir.clone();
+        if self.config.bucketing != OutputBucketing::ByDominantLabel || !paths.output_dir.exists() {
+            return;
+        }
+        let target = self
+            .config
+            .output_dir
+            .join(label_bucket(&result.synchronized_results))
+            .join(paths.output_dir.file_name().unwrap());
+        let moved = (|| {
+            if target.exists() {
+                fs::remove_dir_all(&target)?;
+            }
+            fs::create_dir_all(target.parent().unwrap())?;
+            fs::rename(&paths.output_dir, &target)
+        })();
+        match moved {
+            Ok(()) => {
+                result.output_dir = target;
+                // Fails while other videos are still pending, which is fine
+                let _ = fs::remove_dir(self.config.output_dir.join(PENDING_DIR));
+            }
+            Err(e) => eprintln!(
+                "Warning: Failed to move {:?} to {:?}: {}",
+                paths.output_dir, target, e
+            ),
+        }
+    }
+
+    // Synchronizes and saves an analyzed video, or records why it failed
+    fn complete_video(
+        &self,
+        video_path: &Path,
+        paths: &VideoPaths,
+        start_time: Instant,
+        outcome: Result<VideoAnalysis>,
+    ) -> VideoProcessingResult {
+        let video_name = &paths.name;
+        let audio_only = paths.audio_only;
+        Self::remove_scratch_dir(paths);
 
-                // Save results to JSON file
-                if let Err(e) = self.save_results(&video_output_dir, &synchronized_results) {
-                    eprintln!("Warning: Failed to save results for {}: {}", video_name, e);
+        match outcome {
+            Ok(mut analysis) => {
+                if let Some((video_length, audio_length)) = stream_durations(video_path)
+                    .ok()
+                    .and_then(duration_mismatch)
+                {
+                    video_eprintln!(
+                        "Warning: {}: the video stream is {:.1}s long but the audio stream {:.1}s",
+                        video_name,
+                        video_length,
+                        audio_length
+                    );
                 }
+                // Coverage of a video cut short would be measured against the whole video
+                let cut_short = analysis.truncated || analysis.early_stop_reason.is_some();
+                let duration = if cut_short {
+                    None
+                } else {
+                    self.processed_duration(video_path, paths)
+                };
+
+                let (synchronized_results, streamed, processing_time) =
+                    match analysis.streamed.take() {
+                        Some(streamed) => (Vec::new(), Some(streamed), start_time.elapsed()),
+                        None => match self.save_video(
+                            video_path,
+                            paths,
+                            start_time,
+                            &mut analysis,
+                            duration,
+                        ) {
+                            Ok((synchronized_results, processing_time)) => {
+                                (synchronized_results, None, processing_time)
+                            }
+                            Err(e) => {
+                                video_eprintln!("Failed to save results for {}: {}", video_name, e);
+                                return Self::failed_result(
+                                    video_path,
+                                    start_time.elapsed(),
+                                    e.context("Failed to save results"),
+                                );
+                            }
+                        },
+                    };
+                let (totals, label_coverage) = match streamed {
+                    // Synchronized and written frame by frame already
+                    Some(streamed) => (streamed.totals, streamed.coverage.finish(duration)),
+                    None => {
+                        let mut totals = ResultTotals::default();
+                        for result in &synchronized_results {
+                            totals.push(result);
+                        }
+                        (totals, label_coverage(&synchronized_results, duration))
+                    }
+                };
 
                 VideoProcessingResult {
                     video_path: video_path.to_path_buf(),
                     processing_time,
-                    frame_count: synchronized_results.len(),
-                    audio_segments: synchronized_results
-                        .iter()
-                        .filter(|r| r.audio_text.is_some())
-                        .count(),
+                    frame_count: totals.frame_count,
+                    audio_segments: totals.audio_segments,
+                    detections: totals.detections,
                     synchronized_results,
                     success: true,
                     error_message: None,
+                    truncated: analysis.truncated,
+                    skipped_packets: analysis.skipped_packets,
+                    resolution_changes: analysis.resolution_changes,
+                    output_unwritable: false,
+                    audio_only,
+                    early_stop_reason: analysis.early_stop_reason,
+                    transcription_error: analysis.transcription_error,
+                    failure_category: None,
+                    label_coverage,
+                    output_dir: PathBuf::new(),
                 }
             }
             Err(e) => {
                 let processing_time = start_time.elapsed();
-                eprintln!("Failed to process {}: {}", video_name, e);
+                video_eprintln!("Failed to process {}: {:#}", video_name, e);
 
                 VideoProcessingResult {
-                    video_path: video_path.to_path_buf(),
-                    processing_time,
-                    frame_count: 0,
-                    audio_segments: 0,
-                    synchronized_results: Vec::new(),
-                    success: false,
-                    error_message: Some(e.to_string()),
+                    audio_only,
+                    ..Self::failed_result(video_path, processing_time, e)
                 }
             }
         }
     }
 
-    fn process_video_internal(
+    // Synchronizes the analyzed frames with the transcript and writes every output;
+    // returns the results and the processing time up to synchronization. An Err is
+    // from saving the results, the other outputs only warn.
+    fn save_video(
         &self,
         video_path: &Path,
-        frames_dir: &Path,
-        audio_path: &Path,
-        analyzer: &FrameAnalyzer,
-    ) -> Result<(Vec<FrameResult>, Vec<AudioResult>)> {
-        // Create directories
-        fs::create_dir_all(frames_dir)?;
-        fs::create_dir_all(audio_path.parent().unwrap())?;
+        paths: &VideoPaths,
+        start_time: Instant,
+        analysis: &mut VideoAnalysis,
+        duration: Option<f64>,
+    ) -> Result<(Vec<SynchronizedResult>, std::time::Duration)> {
+        let video_name = &paths.name;
+        let audio_only = paths.audio_only;
+        let frame_results = std::mem::take(&mut analysis.frame_results);
+        let annotated_frames = self
+            .config
+            .annotated_video
+            .filter(|_| !audio_only)
+            .map(|unanalyzed| (frame_results.clone(), unanalyzed));
+        let mut synchronized_results = synchronize_results(
+            frame_results,
+            analysis.audio_results.clone(),
+            self.config.sync_strategy,
+            self.config.include_unmatched_audio,
+        );
+        self.stage_completed(video_path, "synchronization");
+        let processing_time = start_time.elapsed();
 
-        // Extract frames
-        let timestamps = extract_frames(video_path, frames_dir)
-            .map_err(|e| anyhow::anyhow!("Frame extraction failed: {}", e))?;
+        if self.config.wall_clock {
+            if let Ok(Some(creation_time)) = creation_time(video_path) {
+                attach_datetimes(&mut synchronized_results, creation_time);
+            }
+        }
+        let video = VideoInfo {
+            duration,
+            frame_size: (!audio_only)
+                .then(|| self.output_frame_size(video_path))
+                .flatten(),
+        };
 
-        // Process frames - updated to use new analyzer
-        let mut frame_results = Vec::new();
-        for (i, ts) in timestamps.into_iter().enumerate() {
-            let frame_path = frames_dir.join(format!("frame_{:04}.png", i));
-            if frame_path.exists() {
-                let analysis = analyzer
-                    .process_frame(&frame_path, ts)
-                    .map_err(|e| anyhow::anyhow!("Frame processing failed: {}", e))?;
-                frame_results.push(analysis.into());
+        self.save_results(&paths.output_dir, &synchronized_results, &video)?;
+        self.stage_completed(video_path, "output");
+
+        if self.config.audit_log {
+            if let Err(e) = write_audit_log(&paths.output_dir, &analysis.audits) {
+                video_eprintln!(
+                    "Warning: Failed to write audit log for {}: {}",
+                    video_name,
+                    e
+                );
             }
         }
 
-        // Extract and process audio
-        extract_audio(video_path, audio_path)
-            .map_err(|e| anyhow::anyhow!("Audio extraction failed: {}", e))?;
+        if let Some(clips) = &self.config.clips {
+            self.extract_event_clips(video_path, paths, &synchronized_results, clips);
+        }
+
+        if let Some((frame_results, unanalyzed)) = annotated_frames {
+            self.render_annotated(video_path, paths, frame_results, unanalyzed);
+        }
 
-        let audio_results = transcribe_audio(audio_path)?;
+        #[cfg(feature = "sqlite")]
+        if let Some(db_path) = &self.config.sqlite_path {
+            if let Err(e) = crate::sqlite_export::export_video(
+                db_path,
+                video_path,
+                &synchronized_results,
+                &analysis.audio_results,
+            ) {
+                video_eprintln!("Warning: Failed to export {} to SQLite: {}", video_name, e);
+            }
+        }
 
-        Ok((frame_results, audio_results))
+        Ok((synchronized_results, processing_time))
     }
 
-    fn save_results(&self, output_dir: &Path, results: &[SynchronizedResult]) -> Result<()> {
-        use std::io::Write;
+    fn stage_completed(&self, video_path: &Path, stage: &'static str) {
+        if self.config.progress_format == ProgressFormat::Jsonl {
+            emit(&ProgressEvent::StageCompleted {
+                video: &video_path.file_name().unwrap().to_string_lossy(),
+                stage,
+            });
+        }
+    }
 
-        let results_file = output_dir.join("results.json");
-        let mut file = fs::File::create(results_file)?;
-
-        // Simple JSON serialization (in production, use serde)
-        writeln!(file, "[")?;
-        for (i, result) in results.iter().enumerate() {
-            writeln!(file, "  {{")?;
-            writeln!(file, "    \"timestamp\": {},", result.timestamp)?;
-            writeln!(file, "    \"video_objects\": [")?;
-            for (j, (label, conf, bbox)) in result.video_objects.iter().enumerate() {
-                writeln!(file, "      {{")?;
-                writeln!(file, "        \"label\": \"{}\",", label)?;
-                writeln!(file, "        \"confidence\": {},", conf)?;
-                writeln!(
-                    file,
-                    "        \"bbox\": [{}, {}, {}, {}]",
-                    bbox[0], bbox[1], bbox[2], bbox[3]
-                )?;
-                writeln!(
-                    file,
-                    "      }}{}",
-                    if j < result.video_objects.len() - 1 {
-                        ","
-                    } else {
-                        ""
-                    }
-                )?;
-            }
-            writeln!(file, "    ],")?;
-            if let Some(text) = &result.audio_text {
-                writeln!(
-                    file,
-                    "    \"audio_text\": \"{}\"",
-                    text.replace('"', "\\\"")
-                )?;
-            } else {
-                writeln!(file, "    \"audio_text\": null")?;
+    // Intermediate files are only needed until analysis and transcription are done
+    fn remove_scratch_dir(paths: &VideoPaths) {
+        if paths.scratch_dir.exists() {
+            if let Err(e) = fs::remove_dir_all(&paths.scratch_dir) {
+                video_eprintln!("Warning: Failed to remove {:?}: {}", paths.scratch_dir, e);
             }
-            writeln!(file, "  }}{}", if i < results.len() - 1 { "," } else { "" })?;
         }
-        writeln!(file, "]")?;
+    }
 
-        Ok(())
+    // max_concurrent resolved for these videos; see Concurrency::Auto
+    pub fn concurrency_for(&self, video_files: &[PathBuf]) -> usize {
+        let Concurrency::Fixed(n) = self.config.max_concurrent else {
+            return self.estimate_concurrency(video_files);
+        };
+        n.max(1)
     }
 
-    pub fn process_batch(&self) -> Result<BatchResults> {
-        let start_time = Instant::now();
+    fn estimate_concurrency(&self, video_files: &[PathBuf]) -> usize {
+        let largest = video_files
+            .iter()
+            .filter_map(|path| self.frame_dimensions(path).ok())
+            .max_by_key(|(width, height)| *width as u64 * *height as u64);
+        // Nothing to size against; stay conservative
+        let Some((width, height)) = largest else {
+            return 1;
+        };
 
-        // Create output directory
-        fs::create_dir_all(&self.config.output_dir)?;
+        let mut system = sysinfo::System::new();
+        system.refresh_memory();
+        let available = system.available_memory();
+
+        let sixteen_bit = self.config.frame_bit_depth != FrameBitDepth::Eight
+            || self.config.hdr_mode == HdrMode::Rgb16;
+        let frame_bytes = width as u64 * height as u64 * if sixteen_bit { 6 } else { 3 };
+        let queue = self
+            .config
+            .frame_queue_capacity
+            .unwrap_or(DEFAULT_FAN_IN_QUEUE) as u64;
+        let budget =
+            ((available as f64 * AUTO_MEMORY_SHARE) as u64).saturating_sub(queue * frame_bytes);
+        let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let videos = ((budget / (frame_bytes * FRAMES_PER_DECODER).max(1)) as usize).clamp(1, cpus);
+        println!(
+            "Auto concurrency: {} video(s) at once ({}x{} frames, {} MiB available)",
+            videos,
+            width,
+            height,
+            available / (1024 * 1024)
+        );
+        videos
+    }
+
+    // Size of the extracted frames: the coded size, turned upright unless ignore_rotation
+    fn frame_dimensions(&self, video_path: &Path) -> Result<(u32, u32), ffmpeg_next::Error> {
+        display_dimensions(video_path, self.config.ignore_rotation)
+    }
+
+    // Length of the part of the video that was processed: the container duration cut to
+    // the start/end window
+    fn processed_duration(&self, video_path: &Path, paths: &VideoPaths) -> Option<f64> {
+        let duration = video_duration(video_path).ok().flatten()?;
+        let end = paths.end.map_or(duration, |end| end.min(duration));
+        let start = paths.start.unwrap_or(0.0).max(0.0);
+        Some(end - start).filter(|d| *d > 0.0)
+    }
+
+    // Clips are a convenience on top of the results, so failures only warn
+    fn extract_event_clips(
+        &self,
+        video_path: &Path,
+        paths: &VideoPaths,
+        results: &[SynchronizedResult],
+        clips: &ClipConfig,
+    ) {
+        let triggers: Vec<ClipTrigger> = results
+            .iter()
+            .flat_map(|result| {
+                result
+                    .video_objects
+                    .iter()
+                    .filter(|o| {
+                        !o.interpolated
+                            && o.confidence >= clips.min_confidence
+                            && clips.labels.contains(&o.label)
+                    })
+                    .map(|o| ClipTrigger {
+                        label: o.label.clone(),
+                        timestamp: result.timestamp,
+                    })
+            })
+            .collect();
+
+        match extract_clips(
+            video_path,
+            &triggers,
+            clips.pre_seconds,
+            clips.post_seconds,
+            &paths.output_dir.join("clips"),
+        ) {
+            Ok(written) if !written.is_empty() => {
+                video_println!("   Saved {} clip(s) for {}", written.len(), paths.name)
+            }
+            Ok(_) => {}
+            Err(e) => video_eprintln!("Warning: Clip extraction failed for {}: {}", paths.name, e),
+        }
+    }
+
+    // Like clips, the annotated video is a convenience and failures only warn
+    fn render_annotated(
+        &self,
+        video_path: &Path,
+        paths: &VideoPaths,
+        mut frame_results: Vec<FrameResult>,
+        unanalyzed: UnanalyzedFrames,
+    ) {
+        // The renderer draws on the full frame
+        if let (Some(crop), BboxCoordinates::CropLocal) =
+            (&self.config.crop, self.config.bbox_coordinates)
+        {
+            let Ok((width, height)) = self.frame_dimensions(video_path) else {
+                video_eprintln!(
+                    "Warning: Skipping the annotated video for {}: unknown frame size",
+                    paths.name
+                );
+                return;
+            };
+            let rect = crop.resolve(width, height);
+            for object in frame_results.iter_mut().flat_map(|r| &mut r.objects) {
+                object.bbox = rect.to_full_frame(object.bbox);
+            }
+        }
+
+        let decoding = ExtractionOptions {
+            max_decode_errors: self.config.max_decode_errors,
+            ignore_rotation: self.config.ignore_rotation,
+            decoder_options: self.config.decoder_options.clone(),
+            ..ExtractionOptions::default()
+        };
+        let out_path = paths.output_dir.join("annotated.mp4");
+        match render_annotated_video(video_path, &frame_results, &out_path, unanalyzed, &decoding) {
+            Ok(()) => video_println!("   Saved annotated video for {}", paths.name),
+            Err(e) => video_eprintln!(
+                "Warning: Annotated video failed for {}: {:#}",
+                paths.name,
+                e
+            ),
+        }
+    }
+
+    fn failed_result(
+        video_path: &Path,
+        processing_time: std::time::Duration,
+        error: anyhow::Error,
+    ) -> VideoProcessingResult {
+        let output_unwritable = error.chain().any(|cause| {
+            cause
+                .downcast_ref::<std::io::Error>()
+                .is_some_and(is_storage_error)
+        });
+
+        VideoProcessingResult {
+            video_path: video_path.to_path_buf(),
+            processing_time,
+            frame_count: 0,
+            audio_segments: 0,
+            detections: 0,
+            synchronized_results: Vec::new(),
+            success: false,
+            error_message: Some(format!("{:#}", error)),
+            truncated: false,
+            skipped_packets: 0,
+            resolution_changes: 0,
+            output_unwritable,
+            audio_only: false,
+            early_stop_reason: None,
+            transcription_error: None,
+            failure_category: Some(FailureCategory::of(&error)),
+            label_coverage: BTreeMap::new(),
+            output_dir: PathBuf::new(),
+        }
+    }
+
+    // Catch a full or read-only output disk before spending time on any video
+    pub fn check_output_writable(&self) -> Result<()> {
+        use std::io::Write;
+
+        let probe_path = self.config.output_dir.join(".write_check");
+        let probe = || -> std::io::Result<()> {
+            fs::create_dir_all(&self.config.output_dir)?;
+            let mut file = fs::File::create(&probe_path)?;
+            file.write_all(b"ok")?;
+            file.sync_all()?;
+            fs::remove_file(&probe_path)
+        };
+
+        probe().map_err(|e| {
+            anyhow::anyhow!(
+                "Output directory {:?} is not writable: {}",
+                self.config.output_dir,
+                e
+            )
+        })
+    }
+
+    fn process_video_internal(
+        &self,
+        video_path: &Path,
+        paths: &VideoPaths,
+        analyzer: &FrameAnalyzer,
+        transcriber: &dyn TranscriptionBackend,
+        budget: &mut FrameBudget,
+    ) -> Result<VideoAnalysis> {
+        if paths.audio_only {
+            return self.process_audio_only(video_path, paths, transcriber);
+        }
+
+        let (options, audio_results) =
+            self.prepare_extraction(video_path, paths, transcriber, budget)?;
+
+        let frames_dir = &paths.frames_dir;
+        let counter = &mut budget.early_stop;
+        let output = match self.config.frame_queue_capacity {
+            Some(capacity) => self.analyze_pipelined(
+                video_path, frames_dir, &options, analyzer, capacity, counter,
+            )?,
+            None => self.analyze_from_disk(video_path, frames_dir, &options, analyzer, counter)?,
+        };
+
+        let transcript = audio_results.map(|audio_results| (audio_results, None));
+        self.finish_analysis(video_path, paths, output, transcript, transcriber)
+    }
+
+    // No frames to extract: each transcript segment becomes a result with no objects
+    fn process_audio_only(
+        &self,
+        audio_path: &Path,
+        paths: &VideoPaths,
+        transcriber: &dyn TranscriptionBackend,
+    ) -> Result<VideoAnalysis> {
+        fs::create_dir_all(paths.audio_path.parent().unwrap())?;
+        let audio_results = self.load_transcript(audio_path, paths, transcriber)?;
+
+        let frame_results = audio_results
+            .iter()
+            .map(|segment| FrameResult {
+                timestamp: segment.start_time,
+                objects: Vec::new(),
+                quality: None,
+                interpolated: false,
+            })
+            .collect();
+
+        Ok(VideoAnalysis {
+            frame_results,
+            audio_results,
+            truncated: false,
+            skipped_packets: 0,
+            resolution_changes: 0,
+            early_stop_reason: None,
+            transcription_error: None,
+            audits: Vec::new(),
+            streamed: None,
+        })
+    }
+
+    // Sampling for PipelineMode::FrameFirst; the first of frames_per_gop,
+    // frames_per_video and frame_interval that is set wins
+    fn frame_first_sampling(&self) -> FrameSampling {
+        match (
+            self.config.frames_per_gop,
+            self.config.frames_per_video,
+            self.config.frame_interval,
+        ) {
+            (Some(per_gop), _, _) => FrameSampling::PerGop(per_gop),
+            (None, Some(count), _) => FrameSampling::FixedCount(count),
+            (None, None, Some(interval)) => FrameSampling::Interval(interval),
+            (None, None, None) => FrameSampling::All,
+        }
+    }
+
+    // Recorded with every detection (DetectedObject::extraction_method)
+    fn extraction_method(&self) -> &'static str {
+        match self.config.pipeline_mode {
+            PipelineMode::FrameFirst => self.frame_first_sampling().method_name(),
+            PipelineMode::AudioAligned => "audio_aligned",
+        }
+    }
+
+    // Creates the working directories and decides which frames to extract
+    fn prepare_extraction(
+        &self,
+        video_path: &Path,
+        paths: &VideoPaths,
+        transcriber: &dyn TranscriptionBackend,
+        budget: &FrameBudget,
+    ) -> Result<(ExtractionOptions, Option<Vec<AudioResult>>)> {
+        // Create directories
+        fs::create_dir_all(&paths.frames_dir)?;
+        fs::create_dir_all(paths.audio_path.parent().unwrap())?;
+
+        // Audio-aligned mode needs the transcript before it knows which frames to extract
+        let (sampling, audio_results) = match self.config.pipeline_mode {
+            PipelineMode::FrameFirst => {
+                let sampling = budget
+                    .frames
+                    .map_or_else(|| self.frame_first_sampling(), FrameSampling::FixedCount);
+                if matches!(sampling, FrameSampling::Interval(_))
+                    && is_variable_frame_rate(video_path).unwrap_or(false)
+                {
+                    video_println!(
+                        "Note: {:?} has a variable frame rate; sampling by timestamp",
+                        video_path.file_name().unwrap()
+                    );
+                }
+                (sampling, None)
+            }
+            PipelineMode::AudioAligned => {
+                let audio_results = self.load_transcript(video_path, paths, transcriber)?;
+                (
+                    FrameSampling::Timestamps(segment_midpoints(&audio_results)),
+                    Some(audio_results),
+                )
+            }
+        };
+
+        let options = ExtractionOptions {
+            sampling,
+            max_frames: budget.max_frames,
+            start: paths.start,
+            end: paths.end,
+            crop: self.config.crop,
+            quality_metrics: self.config.quality_metrics,
+            hdr: self.config.hdr_mode,
+            bit_depth: self.config.frame_bit_depth,
+            max_decode_errors: self.config.max_decode_errors,
+            ignore_rotation: self.config.ignore_rotation,
+            decoder_options: self.config.decoder_options.clone(),
+            frame_naming: self.config.frame_naming,
+            image_limits: self.config.image_limits,
+            reference_keyframes: false,
+        };
+        Ok((options, audio_results))
+    }
+
+    // Postprocesses analyzed frames and attaches the transcript
+    fn finish_analysis(
+        &self,
+        video_path: &Path,
+        paths: &VideoPaths,
+        output: FrameAnalysisOutput,
+        transcript: Option<(Vec<AudioResult>, Option<String>)>,
+        transcriber: &dyn TranscriptionBackend,
+    ) -> Result<VideoAnalysis> {
+        let FrameAnalysisOutput {
+            mut analyses,
+            qualities,
+            model,
+            frame_count,
+            truncated,
+            skipped_packets,
+            resolution_changes,
+            early_stop_reason,
+        } = output;
+        self.stage_completed(video_path, "analysis");
+        self.note_cut_short(
+            video_path,
+            frame_count,
+            analyses.len(),
+            truncated,
+            early_stop_reason.as_deref(),
+        );
+
+        // A video that decodes nothing is a problem worth investigating, not an empty success
+        if frame_count == 0 {
+            return Err(anyhow::anyhow!("no frames decoded").context(FailedStep::FrameExtraction));
+        }
+
+        let context = self.frame_context(video_path, model)?;
+        for analysis in &mut analyses {
+            context.to_full_frame(analysis);
+        }
+
+        // Tracks and smoothing work on the model's confidences; postprocess_frame
+        // calibrates them before any threshold
+        let needs_tracks = self.config.postprocess.smoothing_alpha.is_some()
+            || self.config.interpolate_interval.is_some();
+        let tracks = if needs_tracks {
+            track_detections(&analyses, self.config.postprocess.track_iou_threshold)
+        } else {
+            Vec::new()
+        };
+
+        // Smoothing has to see the unthresholded confidences, so it runs before postprocessing
+        if let Some(alpha) = self.config.postprocess.smoothing_alpha {
+            smooth_confidences(&mut analyses, &tracks, alpha);
+        }
+        let first_and_last = analyses
+            .first()
+            .zip(analyses.last())
+            .map(|(first, last)| (first.timestamp, last.timestamp));
+
+        let mut audits = Vec::new();
+        let mut frame_results: Vec<FrameResult> = analyses
+            .into_iter()
+            .zip(qualities)
+            .enumerate()
+            .map(|(frame_index, (analysis, quality))| {
+                let audits = self.config.audit_log.then_some(&mut audits);
+                self.postprocessed_result(&context, analysis, frame_index, quality, audits)
+            })
+            .collect();
+
+        if let (Some(step), Some((first, last))) = (
+            self.config.interpolate_interval.filter(|step| *step > 0.0),
+            first_and_last,
+        ) {
+            let targets: Vec<f64> = (0..)
+                .map(|i| first + i as f64 * step)
+                .take_while(|t| *t <= last)
+                .collect();
+            interpolate_tracks(&mut frame_results, &tracks, &targets, &context.model);
+            // Interpolated between the model's confidences
+            let calibration = &self.config.postprocess.calibration;
+            if !calibration.is_identity() {
+                for object in frame_results
+                    .iter_mut()
+                    .flat_map(|r| &mut r.objects)
+                    .filter(|o| o.interpolated)
+                {
+                    object.raw_confidence = Some(object.confidence);
+                    object.confidence = calibration.apply(object.confidence);
+                }
+            }
+        }
+
+        // Extract and process audio
+        let (mut audio_results, transcription_error) = match transcript {
+            Some(transcript) => transcript,
+            None => self.load_transcript_or_skip(video_path, paths, transcriber)?,
+        };
+
+        // Both sides are on the container clock now; make the first video frame time zero
+        let video_start = video_start(video_path)?;
+        rebase_timestamps(&mut frame_results, &mut audio_results, video_start);
+        for audit in &mut audits {
+            audit.timestamp -= video_start;
+        }
+
+        Ok(VideoAnalysis {
+            frame_results,
+            audio_results,
+            truncated,
+            skipped_packets,
+            resolution_changes,
+            early_stop_reason,
+            transcription_error,
+            audits,
+            streamed: None,
+        })
+    }
+
+    fn note_cut_short(
+        &self,
+        video_path: &Path,
+        frame_count: usize,
+        analyzed: usize,
+        truncated: bool,
+        early_stop_reason: Option<&str>,
+    ) {
+        if truncated {
+            video_println!(
+                "Note: {:?} truncated after {} frames (max_frames_per_video)",
+                video_path.file_name().unwrap(),
+                frame_count
+            );
+        }
+        if let Some(reason) = early_stop_reason {
+            video_println!(
+                "Note: {:?} stopped early after {} frames: {}",
+                video_path.file_name().unwrap(),
+                analyzed,
+                reason
+            );
+        }
+    }
+
+    fn frame_context(&self, video_path: &Path, model: String) -> Result<FrameContext> {
+        Ok(FrameContext {
+            rect: self.full_frame_rect(video_path)?,
+            frame_size: self.output_frame_size(video_path),
+            model,
+        })
+    }
+
+    // Postprocesses one analysis, already on the full frame (FrameContext::to_full_frame),
+    // into its FrameResult; `audits` gets the frame's audit. Both finish_analysis and
+    // process_video_low_memory go through here.
+    fn postprocessed_result(
+        &self,
+        context: &FrameContext,
+        analysis: FrameAnalysis,
+        frame_index: usize,
+        quality: Option<FrameQuality>,
+        audits: Option<&mut Vec<FrameAudit>>,
+    ) -> FrameResult {
+        let postprocess = &self.config.postprocess;
+        let postprocessed = match audits {
+            Some(audits) => {
+                let mut audit = FrameAudit {
+                    frame_index,
+                    ..FrameAudit::default()
+                };
+                let postprocessed =
+                    postprocess_frame(analysis, postprocess, context.frame_size, Some(&mut audit));
+                audits.push(audit);
+                postprocessed
+            }
+            None => postprocess_frame(analysis, postprocess, context.frame_size, None),
+        };
+        FrameResult {
+            quality,
+            ..self.frame_result(postprocessed, frame_index, &context.model)
+        }
+    }
+
+    // The crop region on the full frame when the model saw crop-local pixels and boxes
+    // have to be shifted back
+    fn full_frame_rect(&self, video_path: &Path) -> Result<Option<PixelRect>> {
+        let (Some(crop), BboxCoordinates::FullFrame) =
+            (&self.config.crop, self.config.bbox_coordinates)
+        else {
+            return Ok(None);
+        };
+        let (width, height) = self
+            .frame_dimensions(video_path)
+            .map_err(|e| anyhow::anyhow!("Failed to read video dimensions: {}", e))?;
+        Ok(Some(crop.resolve(width, height)))
+    }
+
+    // Size of the frame boxes are reported on: the crop with BboxCoordinates::CropLocal,
+    // the full frame otherwise
+    fn output_frame_size(&self, video_path: &Path) -> Option<(u32, u32)> {
+        let (width, height) = self.frame_dimensions(video_path).ok()?;
+        Some(match (&self.config.crop, self.config.bbox_coordinates) {
+            (Some(crop), BboxCoordinates::CropLocal) => {
+                let rect = crop.resolve(width, height);
+                (rect.width, rect.height)
+            }
+            _ => (width, height),
+        })
+    }
+
+    // low_memory counterpart of process_video_internal: the transcript is loaded up
+    // front, then each frame is postprocessed, synchronized and appended to results.json
+    // as soon as it's analyzed. Only the counts and coverage are kept for complete_video.
+    fn process_video_low_memory(
+        &self,
+        video_path: &Path,
+        paths: &VideoPaths,
+        analyzer: &FrameAnalyzer,
+        transcriber: &dyn TranscriptionBackend,
+        budget: &mut FrameBudget,
+    ) -> Result<VideoAnalysis> {
+        let (options, audio_results) =
+            self.prepare_extraction(video_path, paths, transcriber, budget)?;
+        let (mut audio_results, transcription_error) = match audio_results {
+            Some(audio_results) => (audio_results, None),
+            None => self.load_transcript_or_skip(video_path, paths, transcriber)?,
+        };
+
+        // Frames are rebased one at a time below; make the first video frame time zero
+        let video_start = video_start(video_path)?;
+        rebase_timestamps(&mut [], &mut audio_results, video_start);
+        let creation_time = if self.config.wall_clock {
+            creation_time(video_path).ok().flatten()
+        } else {
+            None
+        };
+
+        let context = self.frame_context(video_path, analyzer.backend_name().to_string())?;
+        let json = json_writer(&self.config);
+        let video = VideoInfo {
+            duration: None,
+            frame_size: context.frame_size,
+        };
+        let mut stream = json
+            .stream(&paths.output_dir, &video)
+            .map_err(|e| e.context("Failed to save results"))?;
+
+        let mut writer = FrameWriter::new(self.config.frame_bit_depth);
+        let counter = &mut budget.early_stop;
+        let mut early_stop_reason = None;
+        let mut streamed = StreamedResults::default();
+        let mut failure = None;
+        let decoded = decode_frames(video_path, &options, |frame| {
+            let pushed = (|| -> Result<()> {
+                let frame_path = frame_path(
+                    &paths.frames_dir,
+                    frame.index,
+                    frame.timestamp,
+                    options.frame_naming,
+                );
+                let analysis_path = writer
+                    .write(&frame, &frame_path)
+                    .map_err(|e| anyhow::Error::new(e).context(FailedStep::FrameWrite))?;
+                let mut analysis = analyzer
+                    .process_frame(&analysis_path, frame.timestamp)
+                    .map_err(|e| e.context(FailedStep::FrameProcessing))?;
+                early_stop_reason = self.check_early_stop(counter, &analysis);
+
+                context.to_full_frame(&mut analysis);
+                let frame_index = streamed.totals.frame_count;
+                let mut frame_result =
+                    self.postprocessed_result(&context, analysis, frame_index, frame.quality, None);
+                rebase_timestamps(
+                    std::slice::from_mut(&mut frame_result),
+                    &mut [],
+                    video_start,
+                );
+
+                let mut result = synchronize_frame(frame_result, &audio_results);
+                if let Some(creation_time) = creation_time {
+                    attach_datetimes(std::slice::from_mut(&mut result), creation_time);
+                }
+                streamed.push(&result);
+                stream
+                    .push(&result)
+                    .map_err(|e| e.context("Failed to save results"))
+            })();
+            match pushed {
+                Ok(()) => Ok(early_stop_reason.is_none()),
+                Err(e) => {
+                    failure = Some(e);
+                    Ok(false)
+                }
+            }
+        });
+
+        if let Some(e) = failure {
+            return Err(e);
+        }
+        let summary =
+            decoded.map_err(|e| anyhow::Error::new(e).context(FailedStep::FrameExtraction))?;
+        let frame_count = streamed.totals.frame_count;
+        if frame_count == 0 {
+            return Err(anyhow::anyhow!("no frames decoded").context(FailedStep::FrameExtraction));
+        }
+        // Each frame was synchronized as it was analyzed
+        self.stage_completed(video_path, "analysis");
+        self.stage_completed(video_path, "synchronization");
+        self.note_cut_short(
+            video_path,
+            frame_count,
+            frame_count,
+            summary.truncated,
+            early_stop_reason.as_deref(),
+        );
+
+        stream
+            .finish()
+            .map_err(|e| e.context("Failed to save results"))?;
+        if self.config.durable_writes {
+            sync_output_files(&paths.output_dir)
+                .map_err(|e| anyhow::Error::new(e).context("Failed to sync results to disk"))?;
+        }
+        self.stage_completed(video_path, "output");
+
+        Ok(VideoAnalysis {
+            frame_results: Vec::new(),
+            audio_results,
+            truncated: summary.truncated,
+            skipped_packets: summary.skipped_packets,
+            resolution_changes: summary.resolution_changes,
+            early_stop_reason,
+            transcription_error,
+            audits: Vec::new(),
+            streamed: Some(streamed),
+        })
+    }
+
+    // Result for a postprocess_frame output, keeping the model's confidences where the
+    // calibration changed them
+    fn frame_result(
+        &self,
+        (analysis, raw_confidences): (FrameAnalysis, Vec<Option<f32>>),
+        frame_index: usize,
+        model: &str,
+    ) -> FrameResult {
+        let mut result =
+            FrameResult::from_analysis(analysis, frame_index, model, self.extraction_method());
+        for (object, raw_confidence) in result.objects.iter_mut().zip(raw_confidences) {
+            object.raw_confidence = raw_confidence;
+        }
+        result
+    }
+
+    // Counts the frame toward early_stop and returns the stop reason once it is met
+    fn check_early_stop(
+        &self,
+        counter: &mut EarlyStopCounter,
+        analysis: &FrameAnalysis,
+    ) -> Option<String> {
+        match self.config.early_stop.as_ref()? {
+            EarlyStop::LabelCount { label, frames } => {
+                let postprocess = &self.config.postprocess;
+                let threshold = postprocess.threshold_for(label);
+                if analysis.detections.iter().any(|d| {
+                    &d.label == label && postprocess.calibration.apply(d.confidence) >= threshold
+                }) {
+                    counter.hits += 1;
+                }
+                (counter.hits >= *frames)
+                    .then(|| format!("{} detected in {} frames", label, counter.hits))
+            }
+            EarlyStop::ConfidenceAbove(threshold) => {
+                let calibration = &self.config.postprocess.calibration;
+                analysis
+                    .detections
+                    .iter()
+                    .map(|d| (&d.label, calibration.apply(d.confidence)))
+                    .find(|(_, confidence)| confidence >= threshold)
+                    .map(|(label, confidence)| {
+                        format!("{} detected with confidence {:.2}", label, confidence)
+                    })
+            }
+        }
+    }
+
+    // Extracts every frame to disk first, then analyzes them in order
+    fn analyze_from_disk(
+        &self,
+        video_path: &Path,
+        frames_dir: &Path,
+        options: &ExtractionOptions,
+        analyzer: &FrameAnalyzer,
+        counter: &mut EarlyStopCounter,
+    ) -> Result<FrameAnalysisOutput> {
+        let extracted = extract_frames_with_options(video_path, frames_dir, options)
+            .map_err(|e| anyhow::Error::new(e).context(FailedStep::FrameExtraction))?;
+        let frame_count = extracted.timestamps.len();
+
+        // Process frames - updated to use new analyzer
+        let mut analyses = Vec::new();
+        let mut qualities = Vec::new();
+        let mut early_stop_reason = None;
+        for (i, (ts, quality)) in extracted
+            .timestamps
+            .into_iter()
+            .zip(extracted.qualities)
+            .enumerate()
+        {
+            let frame_path = frame_path(frames_dir, i, ts, options.frame_naming);
+            if frame_path.exists() {
+                let analysis = analyzer
+                    .process_frame(&analysis_input(&frame_path), ts)
+                    .map_err(|e| e.context(FailedStep::FrameProcessing))?;
+                early_stop_reason = self.check_early_stop(counter, &analysis);
+                analyses.push(analysis);
+                qualities.push(quality);
+                if early_stop_reason.is_some() {
+                    break;
+                }
+            }
+        }
+
+        Ok(FrameAnalysisOutput {
+            analyses,
+            qualities,
+            model: analyzer.backend_name().to_string(),
+            frame_count,
+            truncated: extracted.truncated,
+            skipped_packets: extracted.skipped_packets,
+            resolution_changes: extracted.resolution_changes,
+            early_stop_reason,
+        })
+    }
+
+    // Decodes on a separate thread into a bounded queue, so decoding blocks instead of
+    // piling up frames in memory when analysis is the slower side
+    fn analyze_pipelined(
+        &self,
+        video_path: &Path,
+        frames_dir: &Path,
+        options: &ExtractionOptions,
+        analyzer: &FrameAnalyzer,
+        capacity: usize,
+        counter: &mut EarlyStopCounter,
+    ) -> Result<FrameAnalysisOutput> {
+        let (sender, receiver) = std::sync::mpsc::sync_channel(capacity.max(1));
+        let stopped = AtomicBool::new(false);
+
+        std::thread::scope(|scope| {
+            let stopped = &stopped;
+            let decoder = std::thread::Builder::new()
+                .name(format!("{}-decode", self.config.thread_name_prefix))
+                .spawn_scoped(scope, move || {
+                    // Stops decoding as soon as analysis has met early_stop, instead of
+                    // filling the queue with frames nobody will look at
+                    decode_frames(video_path, options, |frame| {
+                        Ok(!stopped.load(Ordering::SeqCst) && sender.send(frame).is_ok())
+                    })
+                })?;
+
+            let mut analyses = Vec::new();
+            let mut qualities = Vec::new();
+            let mut frame_count = 0;
+            let mut early_stop_reason = None;
+            let consumed = (|| -> Result<()> {
+                let mut writer = FrameWriter::new(self.config.frame_bit_depth);
+                for frame in receiver.iter() {
+                    frame_count += 1;
+                    let frame_path = frame_path(
+                        frames_dir,
+                        frame.index,
+                        frame.timestamp,
+                        options.frame_naming,
+                    );
+                    let analysis_path = writer
+                        .write(&frame, &frame_path)
+                        .map_err(|e| anyhow::Error::new(e).context(FailedStep::FrameWrite))?;
+                    let analysis = analyzer
+                        .process_frame(&analysis_path, frame.timestamp)
+                        .map_err(|e| e.context(FailedStep::FrameProcessing))?;
+                    early_stop_reason = self.check_early_stop(counter, &analysis);
+                    analyses.push(analysis);
+                    qualities.push(frame.quality);
+                    if early_stop_reason.is_some() {
+                        stopped.store(true, Ordering::SeqCst);
+                        break;
+                    }
+                }
+                Ok(())
+            })();
+
+            // Dropping the receiver unblocks the decoder if analysis stopped early
+            drop(receiver);
+            let decoded = decoder
+                .join()
+                .map_err(|payload| WorkerPanic(panic_message(payload.as_ref())))?;
+
+            consumed?;
+            let summary =
+                decoded.map_err(|e| anyhow::Error::new(e).context(FailedStep::FrameExtraction))?;
+            Ok(FrameAnalysisOutput {
+                analyses,
+                qualities,
+                model: analyzer.backend_name().to_string(),
+                frame_count,
+                truncated: summary.truncated,
+                skipped_packets: summary.skipped_packets,
+                resolution_changes: summary.resolution_changes,
+                early_stop_reason,
+            })
+        })
+    }
+
+    fn report_started(&self, report: &mut BatchReport, video_path: &Path) {
+        if let Some(progress) = report.progress.as_mut() {
+            progress.start_video(&video_path.file_name().unwrap().to_string_lossy());
+        }
+    }
+
+    // The JSON-lines video events are emitted where the work happens rather than where
+    // it's reported, so fan-in workers send them as their videos start and finish
+    fn emit_video_started(&self, index: usize, total: usize, video_path: &Path) {
+        if self.config.progress_format == ProgressFormat::Jsonl {
+            emit(&ProgressEvent::VideoStarted {
+                video: &video_path.file_name().unwrap().to_string_lossy(),
+                index: index + 1,
+                total,
+            });
+        }
+    }
+
+    fn emit_video_finished(&self, video_path: &Path, result: &VideoProcessingResult) {
+        if self.config.progress_format == ProgressFormat::Jsonl {
+            emit(&ProgressEvent::VideoFinished {
+                video: &video_path.file_name().unwrap().to_string_lossy(),
+                success: result.success,
+                frames: result.frame_count,
+                processing_secs: result.processing_time.as_secs_f64(),
+                error: result.error_message.as_deref(),
+            });
+        }
+    }
+
+    // Records a finished video. Errors when the batch has to stop: the output can't be
+    // written, or a video failed under fail_fast.
+    fn report_finished(
+        &self,
+        report: &mut BatchReport,
+        video_path: &Path,
+        result: VideoProcessingResult,
+        skipped: &SkippedVideos,
+        start_time: Instant,
+    ) -> Result<()> {
+        if result.success {
+            report.successful += 1;
+            report.manifest.record(video_path)?;
+            println!(
+                "✓ Success - {} frames, {} audio segments, {:.2}s",
+                result.frame_count,
+                result.audio_segments,
+                result.processing_time.as_secs_f64()
+            );
+        } else {
+            report.failed += 1;
+            println!(
+                "✗ Failed - {}",
+                result
+                    .error_message
+                    .as_ref()
+                    .unwrap_or(&"Unknown error".to_string())
+            );
+        }
+
+        if let Some(progress) = report.progress.as_mut() {
+            progress.finish_video(result.success);
+        }
+
+        let output_unwritable = result.output_unwritable;
+        let video_failed = !result.success;
+        report.results.push(result);
+
+        // Rewritten after every video so a batch that dies still leaves a summary
+        let partial_summary =
+            self.generate_batch_summary(&report.results, skipped, start_time.elapsed(), false);
+
+        // The batch ends here; pollers of progress.json shouldn't wait for more videos
+        if output_unwritable || (video_failed && self.config.fail_fast) {
+            if let Some(progress) = report.progress.as_mut() {
+                progress.finish();
+            }
+        }
+
+        if output_unwritable {
+            // No warning: the summary likely failed for the same reason
+            return Err(anyhow::anyhow!(
+                "Aborting batch: output directory {:?} is full or not writable",
+                self.config.output_dir
+            ));
+        }
+
+        if let Err(e) = partial_summary {
+            eprintln!("Warning: Failed to update batch summary: {}", e);
+        }
+
+        if video_failed && self.config.fail_fast {
+            return Err(BatchAborted {
+                video_path: video_path.to_path_buf(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    // Fan-in scheduling: several decoder threads push frames from different videos into
+    // one bounded queue drained by a fixed pool of inference workers, so the model stays
+    // busy while individual videos are seeking or waiting on I/O. With audio_workers, a
+    // second pool loads the transcripts alongside. A video is completed by whichever
+    // worker finishes its last piece of work, and `on_finished` is called on this thread
+    // as each one is done. An error from it stops the workers and is returned.
+    // `on_started` is called from the decoder thread that takes a video on.
+    fn process_videos_fan_in(
+        &self,
+        video_files: &[PathBuf],
+        paths: Vec<VideoPaths>,
+        analyzer: &FrameAnalyzer,
+        transcriber: &dyn TranscriptionBackend,
+        inference_workers: usize,
+        on_started: &(dyn Fn(usize) + Sync),
+        on_finished: &mut dyn FnMut(usize, VideoProcessingResult) -> Result<()>,
+    ) -> Result<()> {
+        let states: Vec<Mutex<FanInState>> = video_files
+            .iter()
+            .map(|_| Mutex::new(FanInState::default()))
+            .collect();
+        let next_video = AtomicUsize::new(0);
+        let next_audio = AtomicUsize::new(0);
+        let stop = AtomicBool::new(false);
+
+        let capacity = self
+            .config
+            .frame_queue_capacity
+            .unwrap_or(DEFAULT_FAN_IN_QUEUE);
+        let (sender, receiver) = mpsc::sync_channel::<(usize, DecodedFrame)>(capacity.max(1));
+        let receiver = Mutex::new(receiver);
+        let (finished, finished_receiver) = mpsc::channel::<(usize, VideoProcessingResult)>();
+
+        let (paths, states, next_video, next_audio, stop, receiver) =
+            (&paths, &states, &next_video, &next_audio, &stop, &receiver);
+        // Runs in the worker that made the video ready; a stopped batch only cleans up
+        let complete =
+            move |i: usize,
+                  state: FanInState,
+                  finished: &mpsc::Sender<(usize, VideoProcessingResult)>| {
+                if stop.load(Ordering::SeqCst) {
+                    Self::remove_scratch_dir(&paths[i]);
+                    return;
+                }
+                let result = self.complete_fan_in_video(
+                    &video_files[i],
+                    &paths[i],
+                    state,
+                    analyzer,
+                    transcriber,
+                );
+                self.emit_video_finished(&video_files[i], &result);
+                let _ = finished.send((i, result));
+            };
+        let complete = &complete;
+
+        let mut aborted = None;
+        std::thread::scope(|scope| {
+            for n in 0..self.config.audio_workers.unwrap_or(0) {
+                let finished = finished.clone();
+                let name = format!("{}-audio-{}", self.config.thread_name_prefix, n);
+                let transcribe_videos = move || loop {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let i = next_audio.fetch_add(1, Ordering::SeqCst);
+                    if i >= video_files.len() {
+                        break;
+                    }
+                    // Videos completed already were transcribed by the worker that did it
+                    {
+                        let mut state = states[i].lock().unwrap();
+                        if state.completed {
+                            continue;
+                        }
+                        state.transcribing = true;
+                    }
+                    let transcript = with_video_log(self.video_log_dir(&paths[i]), || {
+                        catch_panic(|| {
+                            fs::create_dir_all(paths[i].audio_path.parent().unwrap())?;
+                            self.load_transcript_or_skip(&video_files[i], &paths[i], transcriber)
+                        })
+                    });
+                    let ready = {
+                        let mut state = states[i].lock().unwrap();
+                        state.transcript = Some(transcript);
+                        state.transcribing = false;
+                        state.take_if_ready()
+                    };
+                    if let Some(state) = ready {
+                        complete(i, state, &finished);
+                    }
+                };
+                let spawned = std::thread::Builder::new()
+                    .name(name)
+                    .spawn_scoped(scope, transcribe_videos);
+                if let Err(e) = spawned {
+                    eprintln!("Warning: Failed to start audio thread: {}", e);
+                }
+            }
+
+            for n in 0..self.concurrency_for(video_files) {
+                let sender = sender.clone();
+                let finished = finished.clone();
+                let name = format!("{}-decode-{}", self.config.thread_name_prefix, n);
+                let decode_videos = move || loop {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let i = next_video.fetch_add(1, Ordering::SeqCst);
+                    if i >= video_files.len() {
+                        break;
+                    }
+                    let video_path = &video_files[i];
+                    on_started(i);
+                    let ready = with_video_log(self.video_log_dir(&paths[i]), || {
+                        video_println!(
+                            "\n[{}/{}] Decoding: {:?}",
+                            i + 1,
+                            video_files.len(),
+                            video_path.file_name().unwrap()
+                        );
+                        states[i].lock().unwrap().start_time = Some(Instant::now());
+
+                        let decoded = catch_panic(|| {
+                            let (options, audio_results) = self.prepare_extraction(
+                                video_path,
+                                &paths[i],
+                                transcriber,
+                                &FrameBudget::new(&self.config),
+                            )?;
+                            states[i].lock().unwrap().audio_results = audio_results;
+                            let mut frame_count = 0;
+                            let summary = decode_frames(video_path, &options, |frame| {
+                                // Stops decoding once a worker has met early_stop, or the
+                                // batch is aborting
+                                {
+                                    let mut state = states[i].lock().unwrap();
+                                    if state.early_stop_reason.is_some()
+                                        || stop.load(Ordering::SeqCst)
+                                    {
+                                        return Ok(false);
+                                    }
+                                    state.queued += 1;
+                                }
+                                frame_count += 1;
+                                if sender.send((i, frame)).is_err() {
+                                    states[i].lock().unwrap().queued -= 1;
+                                    return Ok(false);
+                                }
+                                Ok(true)
+                            })
+                            .map_err(|e| {
+                                anyhow::Error::new(e).context(FailedStep::FrameExtraction)
+                            })?;
+                            Ok((frame_count, summary))
+                        });
+                        let mut state = states[i].lock().unwrap();
+                        state.decoded = Some(decoded);
+                        state.take_if_ready()
+                    });
+                    if let Some(state) = ready {
+                        complete(i, state, &finished);
+                    }
+                };
+                let spawned = std::thread::Builder::new()
+                    .name(name)
+                    .spawn_scoped(scope, decode_videos);
+                if let Err(e) = spawned {
+                    eprintln!("Warning: Failed to start decoder thread: {}", e);
+                }
+            }
+            // The workers stop once every decoder has dropped its sender
+            drop(sender);
+
+            for n in 0..inference_workers.max(1) {
+                let finished = finished.clone();
+                let name = format!("{}-infer-{}", self.config.thread_name_prefix, n);
+                let analyze_frames = move || {
+                    // One writer per worker, so its buffers are reused without locking
+                    let mut writer = FrameWriter::new(self.config.frame_bit_depth);
+                    loop {
+                        let next = receiver.lock().unwrap().recv();
+                        let Ok((i, frame)) = next else {
+                            break;
+                        };
+                        // Frames already queued when the video stopped early, or the
+                        // batch is aborting, are dropped
+                        let dropped = stop.load(Ordering::SeqCst)
+                            || states[i].lock().unwrap().early_stop_reason.is_some();
+                        let analysis = (!dropped).then(|| {
+                            let frame_path = frame_path(
+                                &paths[i].frames_dir,
+                                frame.index,
+                                frame.timestamp,
+                                self.config.frame_naming,
+                            );
+                            with_video_log(self.video_log_dir(&paths[i]), || {
+                                catch_panic(|| {
+                                    let analysis_path =
+                                        writer.write(&frame, &frame_path).map_err(|e| {
+                                            anyhow::Error::new(e).context(FailedStep::FrameWrite)
+                                        })?;
+                                    analyzer
+                                        .process_frame(&analysis_path, frame.timestamp)
+                                        .map_err(|e| e.context(FailedStep::FrameProcessing))
+                                })
+                            })
+                        });
+
+                        let ready = {
+                            let mut state = states[i].lock().unwrap();
+                            match analysis {
+                                Some(Ok(analysis)) => {
+                                    if state.early_stop_reason.is_none() {
+                                        state.early_stop_reason =
+                                            self.check_early_stop(&mut state.early_stop, &analysis);
+                                    }
+                                    state.analyses.push((frame.index, analysis, frame.quality))
+                                }
+                                Some(Err(e)) => {
+                                    if state.analysis_error.is_none() {
+                                        state.analysis_error = Some(e);
+                                    }
+                                }
+                                None => {}
+                            }
+                            state.analyzed += 1;
+                            state.take_if_ready()
+                        };
+                        if let Some(state) = ready {
+                            complete(i, state, &finished);
+                        }
+                    }
+                };
+                let spawned = std::thread::Builder::new()
+                    .name(name)
+                    .spawn_scoped(scope, analyze_frames);
+                if let Err(e) = spawned {
+                    eprintln!("Warning: Failed to start inference thread: {}", e);
+                }
+            }
+            drop(finished);
+
+            // Ends once every worker is done
+            for (i, result) in finished_receiver {
+                if aborted.is_some() {
+                    continue;
+                }
+                if let Err(e) = on_finished(i, result) {
+                    stop.store(true, Ordering::SeqCst);
+                    aborted = Some(e);
+                }
+            }
+        });
+        if let Some(e) = aborted {
+            return Err(e);
+        }
+
+        // Videos left incomplete because a worker pool couldn't start
+        for (i, state) in states.iter().enumerate() {
+            let mut state = state.lock().unwrap();
+            if state.completed {
+                continue;
+            }
+            let state = std::mem::take(&mut *state);
+            if state.start_time.is_none() {
+                on_started(i);
+            }
+            let result = self.complete_fan_in_video(
+                &video_files[i],
+                &paths[i],
+                state,
+                analyzer,
+                transcriber,
+            );
+            self.emit_video_finished(&video_files[i], &result);
+            on_finished(i, result)?;
+        }
+        Ok(())
+    }
+
+    // Joins a fan-in video's analyzed frames with its transcript and writes its output
+    fn complete_fan_in_video(
+        &self,
+        video_path: &Path,
+        paths: &VideoPaths,
+        mut state: FanInState,
+        analyzer: &FrameAnalyzer,
+        transcriber: &dyn TranscriptionBackend,
+    ) -> VideoProcessingResult {
+        let start_time = state.start_time.unwrap_or_else(Instant::now);
+
+        let mut result = with_video_log(self.video_log_dir(paths), || {
+            let outcome = match (state.decoded, state.analysis_error) {
+                (Some(Err(e)), _) | (_, Some(e)) => Err(e),
+                (None, None) => Err(anyhow::anyhow!("Video was never decoded")),
+                (Some(Ok((frame_count, summary))), None) => catch_panic(|| {
+                    // Videos no audio worker got to are transcribed here
+                    let transcript = match (state.audio_results, state.transcript) {
+                        (Some(audio_results), _) => Some((audio_results, None)),
+                        (None, Some(transcript)) => Some(transcript?),
+                        (None, None) => None,
+                    };
+                    // Workers finish frames out of order
+                    state.analyses.sort_by_key(|(index, _, _)| *index);
+                    let (analyses, qualities) =
+                        state.analyses.into_iter().map(|(_, a, q)| (a, q)).unzip();
+                    self.finish_analysis(
+                        video_path,
+                        paths,
+                        FrameAnalysisOutput {
+                            analyses,
+                            qualities,
+                            model: analyzer.backend_name().to_string(),
+                            frame_count,
+                            truncated: summary.truncated,
+                            skipped_packets: summary.skipped_packets,
+                            resolution_changes: summary.resolution_changes,
+                            early_stop_reason: state.early_stop_reason,
+                        },
+                        transcript,
+                        transcriber,
+                    )
+                }),
+            };
+
+            self.complete_video(video_path, paths, start_time, outcome)
+        });
+        self.move_to_bucket(paths, &mut result);
+        result
+    }
+
+    // A failed transcription costs a video its audio results, not the video: the frames
+    // are still worth keeping. Returns the error message alongside the empty transcript.
+    fn load_transcript_or_skip(
+        &self,
+        video_path: &Path,
+        paths: &VideoPaths,
+        transcriber: &dyn TranscriptionBackend,
+    ) -> Result<(Vec<AudioResult>, Option<String>)> {
+        match self.load_transcript(video_path, paths, transcriber) {
+            Err(e)
+                if matches!(
+                    e.downcast_ref::<FailedStep>(),
+                    Some(FailedStep::Transcription)
+                ) =>
+            {
+                video_eprintln!(
+                    "Warning: Continuing {:?} without audio results: {:#}",
+                    video_path.file_name().unwrap(),
+                    e
+                );
+                Ok((Vec::new(), Some(format!("{:#}", e))))
+            }
+            result => result.map(|segments| (segments, None)),
+        }
+    }
+
+    fn load_transcript(
+        &self,
+        video_path: &Path,
+        paths: &VideoPaths,
+        transcriber: &dyn TranscriptionBackend,
+    ) -> Result<Vec<AudioResult>> {
+        let subtitles = || -> Result<Vec<AudioResult>> {
+            let mut segments = extract_subtitles(video_path)
+                .map_err(|e| anyhow::Error::new(e).context(FailedStep::SubtitleExtraction))?;
+            segments.retain(|s| {
+                !matches!(paths.start, Some(start) if s.end_time < start)
+                    && !matches!(paths.end, Some(end) if s.start_time > end)
+            });
+            Ok(segments)
+        };
+
+        let mut segments = match self.config.transcript_source {
+            TranscriptSource::Audio => {
+                self.extract_and_transcribe(video_path, paths, transcriber)?
+            }
+            TranscriptSource::Subtitles => subtitles()?,
+            TranscriptSource::PreferSubtitles => match subtitles() {
+                Ok(segments) if !segments.is_empty() => segments,
+                _ => self.extract_and_transcribe(video_path, paths, transcriber)?,
+            },
+        };
+
+        shift_audio_results(&mut segments, self.config.audio_offset);
+        self.stage_completed(video_path, "transcription");
+        Ok(segments)
+    }
+
+    fn extract_and_transcribe(
+        &self,
+        video_path: &Path,
+        paths: &VideoPaths,
+        transcriber: &dyn TranscriptionBackend,
+    ) -> Result<Vec<AudioResult>> {
+        let audio_path = &paths.audio_path;
+        // Both copies come from one decode of the source. The archival copy is skipped
+        // when it would be neither kept nor transcribed.
+        let transcription_copy = match transcriber.audio_format() {
+            Some((sample_rate, channels)) => {
+                Some(AudioOutputSpec::pcm_wav(audio_path, sample_rate, channels))
+            }
+            None => self
+                .config
+                .speech_wav
+                .then(|| AudioOutputSpec::speech_wav(audio_path)),
+        };
+        let mut outputs = Vec::new();
+        if self.config.output.save_audio || transcription_copy.is_none() {
+            outputs.push(archival_audio_spec(audio_path)?);
+        }
+        outputs.extend(transcription_copy);
+        extract_audio_outputs(
+            video_path,
+            &outputs,
+            paths.start,
+            paths.end,
+            &self.config.decoder_options,
+        )
+        .map_err(|e| e.context(FailedStep::AudioExtraction))?;
+        let audio_path = &outputs.last().unwrap().path;
+
+        let mut attempt = 0;
+        let mut segments = loop {
+            match transcribe_audio_with(transcriber, audio_path) {
+                Ok(segments) => break segments,
+                Err(e) if attempt < self.config.transcription_retries => {
+                    attempt += 1;
+                    video_eprintln!(
+                        "Warning: Transcription failed, retrying ({} of {}): {:#}",
+                        attempt,
+                        self.config.transcription_retries,
+                        e
+                    );
+                }
+                Err(e) => return Err(e.context(FailedStep::Transcription)),
+            }
+        };
+
+        // Transcript times count from the start of the extracted clip; move them onto the
+        // container clock that frame timestamps (and subtitles) use
+        let audio_start = stream_start_times(video_path)
+            .map_err(|e| anyhow::anyhow!("Failed to read stream start times: {}", e))?
+            .audio;
+        let clip_start = match paths.start {
+            Some(start) => start.max(audio_start),
+            None => audio_start,
+        };
+        shift_audio_results(&mut segments, clip_start);
+        Ok(segments)
+    }
+
+    // Runs every configured output writer; a video whose results weren't all written
+    // hasn't succeeded
+    fn save_results(
+        &self,
+        output_dir: &Path,
+        results: &[SynchronizedResult],
+        video: &VideoInfo,
+    ) -> Result<()> {
+        for format in &self.config.output_formats {
+            let writer = self
+                .output_writers
+                .get(format)
+                .ok_or_else(|| anyhow::anyhow!("Unknown output format: {}", format))?;
+            writer
+                .write_video(output_dir, results, video)
+                .map_err(|e| e.context(format!("Failed to write {} output", format)))?;
+            let simplified = writer.simplified(results);
+            if !simplified.is_empty() {
+                video_eprintln!(
+                    "Warning: The {} output of {:?} leaves out or simplifies: {}",
+                    format,
+                    output_dir.file_name().unwrap_or_default(),
+                    simplified.join(", ")
+                );
+            }
+        }
+        if self.config.durable_writes {
+            sync_output_files(output_dir)
+                .map_err(|e| anyhow::Error::new(e).context("Failed to sync results to disk"))?;
+        }
+        Ok(())
+    }
+
+    pub fn process_batch(&self) -> Result<BatchResults> {
+        let start_time = Instant::now();
+
+        // Create output directory
+        self.check_output_writable()?;
+        self.validate_config()?;
 
         // Find all video files
         let video_files = self.find_video_files()?;
         println!("Found {} video files to process", video_files.len());
+        let (video_files, skipped) = self.filter_inputs(video_files, &[])?;
+
+        if video_files.is_empty() {
+            return Ok(BatchResults {
+                total_videos: 0,
+                successful: 0,
+                failed: 0,
+                total_processing_time: start_time.elapsed(),
+                results: Vec::new(),
+                skipped,
+            });
+        }
+
+        let (analyzer, transcriber) = self.load_backends()?;
+        self.process_videos(
+            &video_files,
+            skipped,
+            Vec::new(),
+            &analyzer,
+            transcriber.as_ref(),
+            start_time,
+        )
+    }
+
+    // Rejects settings that can't work together, before any video is touched
+    pub fn validate_config(&self) -> Result<()> {
+        for format in &self.config.output_formats {
+            if self.output_writers.get(format).is_none() {
+                return Err(anyhow::anyhow!("Unknown output format: {}", format));
+            }
+            if format == "yolo" && !self.config.output.save_frames {
+                return Err(anyhow::anyhow!("The yolo output format needs save_frames"));
+            }
+        }
+        if self.config.audio_workers.is_some() {
+            if self.config.video_workers.is_none() {
+                return Err(anyhow::anyhow!("audio_workers needs video_workers"));
+            }
+            // The transcript is needed before decoding there, and loaded by the decoder
+            if self.config.pipeline_mode == PipelineMode::AudioAligned {
+                return Err(anyhow::anyhow!(
+                    "audio_workers can't be combined with the audio-aligned pipeline"
+                ));
+            }
+        }
+        if let Some(crop) = &self.config.crop {
+            crop.validate()?;
+        }
+        if self.config.combined_timeline && !self.config.wall_clock {
+            return Err(anyhow::anyhow!("The combined timeline needs wall_clock"));
+        }
+        if let Some(chunk_duration) = self.config.chunk_duration {
+            if chunk_duration <= 0.0 {
+                return Err(anyhow::anyhow!("chunk_duration must be positive"));
+            }
+            // Fan-in schedules whole videos; the others would each cover the whole video
+            // but only get one chunk's results
+            let unsupported = [
+                (self.config.video_workers.is_some(), "video_workers"),
+                (self.config.annotated_video.is_some(), "annotated_video"),
+                (self.config.sqlite_path.is_some(), "sqlite_path"),
+            ];
+            if let Some((_, feature)) = unsupported.iter().find(|(set, _)| *set) {
+                return Err(anyhow::anyhow!(
+                    "chunk_duration can't be combined with {}",
+                    feature
+                ));
+            }
+        }
+        if self.config.low_memory {
+            let config = &self.config;
+            let postprocess = &config.postprocess;
+            let unsupported = [
+                (
+                    config.output_formats.iter().any(|format| format != "json"),
+                    "output formats other than json",
+                ),
+                (config.video_workers.is_some(), "video_workers"),
+                (
+                    postprocess.smoothing_alpha.is_some() || config.interpolate_interval.is_some(),
+                    "tracking (smoothing or interpolation)",
+                ),
+                (
+                    config.sync_strategy == SyncStrategy::Distributed,
+                    "the distributed sync strategy",
+                ),
+                (config.include_unmatched_audio, "include_unmatched_audio"),
+                (config.audit_log, "audit_log"),
+                (config.clips.is_some(), "clips"),
+                (config.annotated_video.is_some(), "annotated_video"),
+                (config.sqlite_path.is_some(), "sqlite_path"),
+                (config.parquet_path.is_some(), "parquet_path"),
+                (config.export_palette, "export_palette"),
+                (config.combined_timeline, "combined_timeline"),
+                (
+                    config.bucketing == OutputBucketing::ByDominantLabel,
+                    "bucketing by label",
+                ),
+            ];
+            if let Some((_, feature)) = unsupported.iter().find(|(set, _)| *set) {
+                return Err(anyhow::anyhow!(
+                    "low_memory can't be combined with {}",
+                    feature
+                ));
+            }
+        }
+        Ok(())
+    }
 
-        if video_files.is_empty() {
-            return Ok(BatchResults {
-                total_videos: 0,
-                successful: 0,
-                failed: 0,
-                total_processing_time: start_time.elapsed(),
-                results: Vec::new(),
+    // Drops duplicates (dedupe) and inactive videos (min_activity) from `video_files`.
+    // Files in `known`, e.g. ones processed earlier, count as originals for dedupe.
+    pub fn filter_inputs(
+        &self,
+        video_files: Vec<PathBuf>,
+        known: &[PathBuf],
+    ) -> Result<(Vec<PathBuf>, SkippedVideos)> {
+        let mut skipped = SkippedVideos::default();
+        let mut video_files = if self.config.dedupe {
+            // Known files go first, so they stay the originals
+            let candidates: Vec<PathBuf> = known.iter().chain(&video_files).cloned().collect();
+            let (_, mut duplicates) = find_duplicates(&candidates)?;
+            duplicates.retain(|duplicate| !known.contains(&duplicate.path));
+            for duplicate in &duplicates {
+                println!(
+                    "Skipping {:?}: duplicate of {:?} ({})",
+                    duplicate.path.file_name().unwrap(),
+                    duplicate.original.file_name().unwrap(),
+                    duplicate.kind
+                );
+            }
+            let unique = video_files
+                .into_iter()
+                .filter(|file| !duplicates.iter().any(|d| &d.path == file))
+                .collect();
+            skipped.duplicates = duplicates;
+            unique
+        } else {
+            video_files
+        };
+
+        if let Some(min_activity) = self.config.min_activity {
+            video_files.retain(|video_path| {
+                let peak = match motion_profile(video_path) {
+                    Ok(profile) => profile.iter().map(|(_, score)| *score).fold(0.0, f32::max),
+                    // Let the main pass report unreadable files
+                    Err(_) => return true,
+                };
+                if peak >= min_activity {
+                    return true;
+                }
+                println!(
+                    "Skipping {:?}: no activity (peak {:.3})",
+                    video_path.file_name().unwrap(),
+                    peak
+                );
+                skipped.inactive.push(video_path.clone());
+                false
             });
         }
+        Ok((video_files, skipped))
+    }
+
+    // Loads the ML model and transcription backend once, to be shared by all videos
+    pub fn load_backends(&self) -> Result<(FrameAnalyzer, Box<dyn TranscriptionBackend>)> {
+        #[cfg(not(feature = "sqlite"))]
+        if self.config.sqlite_path.is_some() {
+            eprintln!("Warning: sqlite_path is set but the `sqlite` feature is not enabled");
+        }
+        #[cfg(not(feature = "parquet"))]
+        if self.config.parquet_path.is_some() {
+            eprintln!("Warning: parquet_path is set but the `parquet` feature is not enabled");
+        }
+        #[cfg(not(feature = "onnx"))]
+        if self.config.tensor_dump_dir.is_some() {
+            eprintln!("Warning: tensor_dump_dir is set but the `onnx` feature is not enabled");
+        }
 
         // Load ML model once for all videos - updated
         println!("Loading ML model...");
-        let mut analyzer = FrameAnalyzer::new("mock")
-            .map_err(|e| anyhow::anyhow!("Failed to create ML analyzer: {}", e))?;
-        analyzer
-            .load_model(None)
-            .map_err(|e| anyhow::anyhow!("Failed to load ML model: {}", e))?;
+        let backends: Vec<&str> = self.config.ml_backends.iter().map(String::as_str).collect();
+        let class_names = match &self.config.class_names_path {
+            Some(path) => load_class_names(path)?,
+            None => Vec::new(),
+        };
+        let mut analyzer = FrameAnalyzer::new_with_fallback(
+            &backends,
+            self.config.ml_model_path.as_deref(),
+            &class_names,
+            self.config.tensor_dump_dir.as_deref(),
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to load ML model: {:#}", e))?;
+        analyzer.set_image_limits(self.config.image_limits);
+        analyzer.set_preprocessing(
+            self.config.preprocessing.clone(),
+            self.config.preprocess_saved_frames,
+        );
+        if self.config.cache_detections {
+            analyzer.enable_cache(DETECTION_CACHE_CAPACITY);
+        }
 
         println!("Using ML backend: {}", analyzer.backend_name());
 
-        // Process videos
-        let mut results = Vec::new();
-        let mut successful = 0;
-        let mut failed = 0;
+        let mut transcriber = create_transcription_backend(&self.config.transcription_backend)
+            .map_err(|e| anyhow::anyhow!("Failed to create transcription backend: {}", e))?;
+        transcriber
+            .load_model(self.config.transcription_model_path.as_deref())
+            .map_err(|e| anyhow::anyhow!("Failed to load transcription model: {}", e))?;
+
+        println!(
+            "Using transcription backend: {}",
+            transcriber.backend_name()
+        );
+
+        Ok((analyzer, transcriber))
+    }
+
+    // `earlier` are results of videos processed before (by the watcher), included in the
+    // summary and returned along with the new ones
+    pub fn process_videos(
+        &self,
+        video_files: &[PathBuf],
+        skipped: SkippedVideos,
+        earlier: Vec<VideoProcessingResult>,
+        analyzer: &FrameAnalyzer,
+        transcriber: &dyn TranscriptionBackend,
+        start_time: Instant,
+    ) -> Result<BatchResults> {
+        let mut manifest = Manifest::load(&self.config.output_dir)?;
+        manifest.set_durable(self.config.durable_writes);
+        let progress = self
+            .config
+            .progress_file
+            .then(|| ProgressFile::new(&self.config.output_dir, video_files.len(), start_time));
+        let jsonl = self.config.progress_format == ProgressFormat::Jsonl;
+        if jsonl {
+            emit(&ProgressEvent::BatchStarted {
+                total: video_files.len(),
+            });
+        }
+        let mut report = BatchReport {
+            manifest,
+            progress,
+            results: earlier,
+            successful: 0,
+            failed: 0,
+        };
+
+        // In fan-in mode the videos are reported as they finish. Audio-only inputs have
+        // no frames to fan in and are processed one by one afterwards.
+        let mut paths: Vec<Option<VideoPaths>> = video_files
+            .iter()
+            .map(|path| Some(self.video_paths(path)))
+            .collect();
+        if let Some(workers) = self.config.video_workers {
+            let indices: Vec<usize> = (0..video_files.len())
+                .filter(|&i| paths[i].as_ref().is_some_and(|p| !p.audio_only))
+                .collect();
+            let videos: Vec<PathBuf> = indices.iter().map(|&i| video_files[i].clone()).collect();
+            let video_paths = indices.iter().map(|&i| paths[i].take().unwrap()).collect();
+            self.process_videos_fan_in(
+                &videos,
+                video_paths,
+                analyzer,
+                transcriber,
+                workers,
+                &|i| self.emit_video_started(indices[i], video_files.len(), &videos[i]),
+                &mut |i, result| {
+                    self.report_started(&mut report, &videos[i]);
+                    self.report_finished(&mut report, &videos[i], result, &skipped, start_time)
+                },
+            )?;
+        }
 
         for (i, video_path) in video_files.iter().enumerate() {
+            // Taken by fan-in
+            let Some(video_paths) = paths[i].take() else {
+                continue;
+            };
+            self.report_started(&mut report, video_path);
+            self.emit_video_started(i, video_files.len(), video_path);
             println!(
                 "\n[{}/{}] Processing: {:?}",
                 i + 1,
                 video_files.len(),
                 video_path.file_name().unwrap()
             );
+            let result = self.process_single_video(video_path, video_paths, analyzer, transcriber);
+            self.emit_video_finished(video_path, &result);
+            self.report_finished(&mut report, video_path, result, &skipped, start_time)?;
+        }
 
-            let result = self.process_single_video(video_path, &analyzer);
-
-            if result.success {
-                successful += 1;
-                println!(
-                    "✓ Success - {} frames, {} audio segments, {:.2}s",
-                    result.frame_count,
-                    result.audio_segments,
-                    result.processing_time.as_secs_f64()
-                );
-            } else {
-                failed += 1;
-                println!(
-                    "✗ Failed - {}",
-                    result
-                        .error_message
-                        .as_ref()
-                        .unwrap_or(&"Unknown error".to_string())
-                );
-            }
+        let BatchReport {
+            mut progress,
+            mut results,
+            successful,
+            failed,
+            ..
+        } = report;
+        // Fan-in reports videos as they finish; everything after lists them in input order
+        results.sort_by_key(|result| {
+            video_files
+                .iter()
+                .position(|path| *path == result.video_path)
+        });
 
-            results.push(result);
+        for duplicate in &skipped.duplicates {
+            self.alias_duplicate_output(duplicate, &results)?;
         }
 
         let total_processing_time = start_time.elapsed();
 
         // Generate batch summary
-        self.generate_batch_summary(&results, total_processing_time)?;
+        self.generate_batch_summary(&results, &skipped, total_processing_time, true)?;
+        self.collect_for_review(&results)?;
+        if self.config.export_palette {
+            let labels: BTreeSet<&str> = results
+                .iter()
+                .flat_map(|r| &r.synchronized_results)
+                .flat_map(|s| &s.video_objects)
+                .map(|object| object.label.as_str())
+                .collect();
+            write_palette(&self.config.output_dir.join(PALETTE_FILE), labels)?;
+        }
+        if self.config.combined_timeline {
+            self.write_combined_timeline(&results)?;
+        }
+        #[cfg(feature = "parquet")]
+        if let Some(parquet_path) = &self.config.parquet_path {
+            crate::parquet_export::export_batch(parquet_path, &results)?;
+        }
+        if let Some(progress) = progress.as_mut() {
+            progress.finish();
+        }
+        if jsonl {
+            emit(&ProgressEvent::BatchFinished {
+                successful,
+                failed,
+                elapsed_secs: start_time.elapsed().as_secs_f64(),
+            });
+        }
 
         Ok(BatchResults {
             total_videos: video_files.len(),
@@ -307,65 +3307,951 @@ impl BatchProcessor {
             failed,
             total_processing_time,
             results,
+            skipped,
         })
     }
 
+    // Merges the intervals of every successful video into one wall-clock timeline
+    fn write_combined_timeline(&self, results: &[VideoProcessingResult]) -> Result<()> {
+        let names: Vec<String> = results
+            .iter()
+            .map(|r| {
+                r.video_path
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        let videos = || {
+            results
+                .iter()
+                .zip(&names)
+                .filter(|(result, _)| result.success)
+                .map(|(result, name)| (name.as_str(), result.synchronized_results.as_slice()))
+        };
+
+        let undated = undated_videos(videos());
+        if !undated.is_empty() {
+            eprintln!(
+                "Warning: Left out of the combined timeline (no creation_time): {}",
+                undated.join(", ")
+            );
+        }
+        let entries = combined_timeline(videos(), self.config.max_bridge_gap);
+        write_atomically(
+            &self.config.output_dir.join(TIMELINE_JSON),
+            &timeline_json(&entries)?,
+            self.config.durable_writes,
+        )?;
+        write_atomically(
+            &self.config.output_dir.join(TIMELINE_CSV),
+            &timeline_csv(&entries),
+            self.config.durable_writes,
+        )
+    }
+
+    // Successful video inputs with fewer detections than review_threshold
+    fn needs_review<'a>(
+        &self,
+        results: &'a [VideoProcessingResult],
+    ) -> Vec<&'a VideoProcessingResult> {
+        let Some(threshold) = self.config.review_threshold else {
+            return Vec::new();
+        };
+        results
+            .iter()
+            .filter(|r| r.success && !r.audio_only && r.detections < threshold)
+            .collect()
+    }
+
+    // Links or moves flagged videos into needs_review/. A video that can't be handled is
+    // only warned about; it's still listed in the summary.
+    fn collect_for_review(&self, results: &[VideoProcessingResult]) -> Result<()> {
+        if self.config.review_action == ReviewAction::List {
+            return Ok(());
+        }
+        let flagged = self.needs_review(results);
+        if flagged.is_empty() {
+            return Ok(());
+        }
+
+        let review_dir = self.config.output_dir.join(NEEDS_REVIEW_DIR);
+        fs::create_dir_all(&review_dir)?;
+        for result in flagged {
+            let video_path = &result.video_path;
+            let target = review_dir.join(video_path.file_name().unwrap());
+            if target.symlink_metadata().is_ok() {
+                eprintln!("Warning: {:?} already exists; not replacing it", target);
+                continue;
+            }
+            let outcome = match self.config.review_action {
+                ReviewAction::List => Ok(()),
+                ReviewAction::Symlink => {
+                    fs::canonicalize(video_path).and_then(|source| symlink_file(&source, &target))
+                }
+                // Falls back to copy and delete across filesystems
+                ReviewAction::Move => fs::rename(video_path, &target).or_else(|_| {
+                    fs::copy(video_path, &target)?;
+                    fs::remove_file(video_path)
+                }),
+            };
+            if let Err(e) = outcome {
+                eprintln!("Warning: Failed to flag {:?} for review: {}", video_path, e);
+            }
+        }
+        Ok(())
+    }
+
+    // Gives a skipped duplicate its own output directory with a copy of the original's
+    // results and a note pointing at the original
+    fn alias_duplicate_output(
+        &self,
+        duplicate: &Duplicate,
+        results: &[VideoProcessingResult],
+    ) -> Result<()> {
+        let Some(original) = results
+            .iter()
+            .find(|r| r.video_path == duplicate.original && r.success)
+        else {
+            return Ok(());
+        };
+
+        // The alias goes next to the original, in the same bucket
+        let original_dir = &original.output_dir;
+        let alias_dir = original_dir.with_file_name(duplicate.path.file_stem().unwrap());
+        fs::create_dir_all(&alias_dir)?;
+        // Every result file the output writers produced; subdirectories hold frames/clips
+        for entry in fs::read_dir(original_dir)? {
+            let path = entry?.path();
+            if path.is_file() {
+                fs::copy(&path, alias_dir.join(path.file_name().unwrap()))?;
+            }
+        }
+        fs::write(
+            alias_dir.join("duplicate_of.txt"),
+            format!("{}\n", duplicate.original.display()),
+        )?;
+        Ok(())
+    }
+
     fn generate_batch_summary(
         &self,
         results: &[VideoProcessingResult],
+        skipped: &SkippedVideos,
         total_time: std::time::Duration,
+        complete: bool,
     ) -> Result<()> {
-        use std::io::Write;
+        use std::fmt::Write;
 
-        let summary_file = self.config.output_dir.join("batch_summary.txt");
-        let mut file = fs::File::create(summary_file)?;
-
-        writeln!(file, "=== Batch Processing Summary ===")?;
-        writeln!(file, "Total videos processed: {}", results.len())?;
+        let mut summary = String::new();
+        writeln!(summary, "=== Batch Processing Summary ===")?;
+        if !complete {
+            writeln!(summary, "(in progress; covers the videos finished so far)")?;
+        }
+        writeln!(summary, "Total videos processed: {}", results.len())?;
         writeln!(
-            file,
+            summary,
             "Successful: {}",
             results.iter().filter(|r| r.success).count()
         )?;
         writeln!(
-            file,
+            summary,
             "Failed: {}",
             results.iter().filter(|r| !r.success).count()
         )?;
+        let failures = failures_by_category(results);
+        if !failures.is_empty() {
+            let rollup: Vec<String> = failures
+                .iter()
+                .map(|(category, count)| format!("{} {}", category, count))
+                .collect();
+            writeln!(summary, "Failures by category: {}", rollup.join(", "))?;
+        }
+        let audio_only = results.iter().filter(|r| r.audio_only).count();
+        if audio_only > 0 {
+            writeln!(
+                summary,
+                "Audio-only files: {} ({} successful)",
+                audio_only,
+                results.iter().filter(|r| r.audio_only && r.success).count()
+            )?;
+        }
         writeln!(
-            file,
+            summary,
             "Total processing time: {:.2}s",
             total_time.as_secs_f64()
         )?;
-        writeln!(
-            file,
-            "Average time per video: {:.2}s",
-            total_time.as_secs_f64() / results.len() as f64
-        )?;
-        writeln!(file)?;
+        if !results.is_empty() {
+            writeln!(
+                summary,
+                "Average time per video: {:.2}s",
+                total_time.as_secs_f64() / results.len() as f64
+            )?;
+        }
+        writeln!(summary)?;
 
-        writeln!(file, "=== Individual Results ===")?;
+        writeln!(summary, "=== Individual Results ===")?;
         for result in results {
-            writeln!(file, "Video: {:?}", result.video_path.file_name().unwrap())?;
             writeln!(
-                file,
+                summary,
+                "Video: {:?}",
+                result.video_path.file_name().unwrap()
+            )?;
+            writeln!(
+                summary,
                 "  Status: {}",
                 if result.success { "SUCCESS" } else { "FAILED" }
             )?;
             writeln!(
-                file,
+                summary,
                 "  Processing time: {:.2}s",
                 result.processing_time.as_secs_f64()
             )?;
-            if result.success {
-                writeln!(file, "  Frames processed: {}", result.frame_count)?;
-                writeln!(file, "  Audio segments: {}", result.audio_segments)?;
+            if result.success && result.audio_only {
+                writeln!(summary, "  Audio only: {} segments", result.audio_segments)?;
+            } else if result.success {
+                writeln!(summary, "  Frames processed: {}", result.frame_count)?;
+                writeln!(summary, "  Audio segments: {}", result.audio_segments)?;
+                if result.truncated {
+                    writeln!(summary, "  Truncated: frame cap reached")?;
+                }
+                if result.skipped_packets > 0 {
+                    writeln!(
+                        summary,
+                        "  Corrupt packets skipped: {}",
+                        result.skipped_packets
+                    )?;
+                }
+                if result.resolution_changes > 0 {
+                    writeln!(
+                        summary,
+                        "  Resolution changes: {}",
+                        result.resolution_changes
+                    )?;
+                }
+                if let Some(reason) = &result.early_stop_reason {
+                    writeln!(summary, "  Stopped early: {}", reason)?;
+                }
+                if let Some(error) = &result.transcription_error {
+                    writeln!(summary, "  Audio missing: {}", error)?;
+                }
+                if !result.label_coverage.is_empty() {
+                    writeln!(
+                        summary,
+                        "  Coverage: {}",
+                        format_coverage(&result.label_coverage, &self.config.display_aliases)
+                    )?;
+                }
+                if let Some(taxonomy) = &self.config.taxonomy {
+                    for rollup in taxonomy.rollup(&result.synchronized_results) {
+                        writeln!(summary, "  {}", rollup)?;
+                    }
+                }
             } else if let Some(error) = &result.error_message {
-                writeln!(file, "  Error: {}", error)?;
+                writeln!(summary, "  Error: {}", error)?;
+                if let Some(category) = result.failure_category {
+                    writeln!(summary, "  Failure category: {}", category)?;
+                }
             }
-            writeln!(file)?;
+            writeln!(summary)?;
         }
 
-        Ok(())
+        if let Some(threshold) = self.config.review_threshold {
+            let flagged = self.needs_review(results);
+            if !flagged.is_empty() {
+                writeln!(
+                    summary,
+                    "=== Needs review (fewer than {} detections) ===",
+                    threshold
+                )?;
+                for result in flagged {
+                    writeln!(
+                        summary,
+                        "{:?}: {} detections",
+                        result.video_path.file_name().unwrap(),
+                        result.detections
+                    )?;
+                }
+                writeln!(summary)?;
+            }
+        }
+
+        if !skipped.inactive.is_empty() {
+            writeln!(summary, "=== No activity (not processed) ===")?;
+            for video_path in &skipped.inactive {
+                writeln!(summary, "{:?}", video_path.file_name().unwrap())?;
+            }
+            writeln!(summary)?;
+        }
+
+        if !skipped.duplicates.is_empty() {
+            writeln!(summary, "=== Duplicates (not processed) ===")?;
+            for duplicate in &skipped.duplicates {
+                writeln!(
+                    summary,
+                    "{:?} -> {:?} ({})",
+                    duplicate.path.file_name().unwrap(),
+                    duplicate.original.file_name().unwrap(),
+                    duplicate.kind
+                )?;
+            }
+        }
+
+        write_atomically(
+            &self.config.output_dir.join("batch_summary.txt"),
+            &summary,
+            self.config.durable_writes,
+        )?;
+        self.write_summary_json(results, skipped, total_time, complete)
+    }
+
+    // Machine-readable counterpart of batch_summary.txt
+    fn write_summary_json(
+        &self,
+        results: &[VideoProcessingResult],
+        skipped: &SkippedVideos,
+        total_time: std::time::Duration,
+        complete: bool,
+    ) -> Result<()> {
+        let file_name = |path: &Path| path.file_name().unwrap().to_string_lossy().into_owned();
+        let summary = BatchSummary {
+            complete,
+            total_videos: results.len(),
+            successful: results.iter().filter(|r| r.success).count(),
+            failed: results.iter().filter(|r| !r.success).count(),
+            total_processing_time_secs: total_time.as_secs_f64(),
+            failures_by_category: failures_by_category(results),
+            videos: results
+                .iter()
+                .map(|result| VideoSummary {
+                    video: file_name(&result.video_path),
+                    success: result.success,
+                    processing_time_secs: result.processing_time.as_secs_f64(),
+                    frame_count: result.frame_count,
+                    audio_segments: result.audio_segments,
+                    audio_only: result.audio_only,
+                    truncated: result.truncated,
+                    skipped_packets: result.skipped_packets,
+                    resolution_changes: result.resolution_changes,
+                    early_stop_reason: result.early_stop_reason.as_deref(),
+                    transcription_error: result.transcription_error.as_deref(),
+                    error: result.error_message.as_deref(),
+                    failure_category: result.failure_category,
+                    label_coverage: &result.label_coverage,
+                    detections: result.detections,
+                })
+                .collect(),
+            needs_review: self
+                .needs_review(results)
+                .into_iter()
+                .map(|r| file_name(&r.video_path))
+                .collect(),
+            inactive: skipped.inactive.iter().map(|p| file_name(p)).collect(),
+            duplicates: skipped
+                .duplicates
+                .iter()
+                .map(|d| (file_name(&d.path), file_name(&d.original)))
+                .collect(),
+        };
+
+        write_atomically(
+            &self.config.output_dir.join("batch_summary.json"),
+            &serde_json::to_string_pretty(&summary)?,
+            self.config.durable_writes,
+        )
+    }
+}
+
+fn json_writer(config: &BatchConfig) -> JsonWriter {
+    JsonWriter {
+        taxonomy: config.taxonomy.clone(),
+        confidence_decimals: config.confidence_decimals,
+        timestamp_decimals: config.timestamp_decimals,
+        min_sharpness: config.min_sharpness,
+        bbox_format: config.bbox_format,
+    }
+}
+
+fn date_bucket(video_path: &Path) -> String {
+    match creation_time(video_path) {
+        Ok(Some(time)) => time.format("%Y-%m-%d").to_string(),
+        _ => "undated".to_string(),
+    }
+}
+
+// The label detected most often, the alphabetically first on a tie; interpolated boxes
+// don't count
+fn label_bucket(results: &[SynchronizedResult]) -> String {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for object in results
+        .iter()
+        .flat_map(|r| &r.video_objects)
+        .filter(|o| !o.interpolated)
+    {
+        *counts.entry(object.label.as_str()).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(a.0)))
+        .map_or_else(|| "unlabeled".to_string(), |(label, _)| sanitize(label))
+}
+
+#[cfg(unix)]
+fn symlink_file(source: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(source, link)
+}
+
+#[cfg(windows)]
+fn symlink_file(source: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(source, link)
+}
+
+// Written to a temp file and renamed so a crash mid-write leaves the previous version.
+// `durable` also syncs the data before the rename and the rename itself after.
+fn write_atomically(path: &Path, contents: &str, durable: bool) -> Result<()> {
+    use std::io::Write;
+
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(contents.as_bytes())?;
+    if durable {
+        file.sync_all()?;
+    }
+    drop(file);
+    fs::rename(&temp_path, path)?;
+    if durable {
+        if let Some(parent) = path.parent() {
+            sync_directory(parent)?;
+        }
+    }
+    Ok(())
+}
+
+// Output writers only report success, so everything directly in the video's output
+// directory is synced (frames and clips live in subdirectories and are left alone)
+fn sync_output_files(output_dir: &Path) -> std::io::Result<()> {
+    for entry in fs::read_dir(output_dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            // Windows only flushes handles opened for writing
+            fs::OpenOptions::new()
+                .write(true)
+                .open(entry.path())?
+                .sync_all()?;
+        }
+    }
+    sync_directory(output_dir)
+}
+
+// Makes newly created or renamed entries survive a crash. Windows can't open
+// directories as files, and NTFS journals the metadata anyway.
+#[cfg(unix)]
+fn sync_directory(dir: &Path) -> std::io::Result<()> {
+    fs::File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_directory(_dir: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct BatchSummary<'a> {
+    // False while the batch is still running (or if it died before finishing)
+    complete: bool,
+    total_videos: usize,
+    successful: usize,
+    failed: usize,
+    total_processing_time_secs: f64,
+    failures_by_category: BTreeMap<&'static str, usize>,
+    videos: Vec<VideoSummary<'a>>,
+    // Below review_threshold; empty when it isn't set
+    needs_review: Vec<String>,
+    // Skipped by the activity check / as duplicates (duplicate -> original)
+    inactive: Vec<String>,
+    duplicates: BTreeMap<String, String>,
+}
+
+#[derive(Serialize)]
+struct VideoSummary<'a> {
+    video: String,
+    success: bool,
+    processing_time_secs: f64,
+    frame_count: usize,
+    audio_segments: usize,
+    audio_only: bool,
+    truncated: bool,
+    skipped_packets: usize,
+    resolution_changes: usize,
+    early_stop_reason: Option<&'a str>,
+    transcription_error: Option<&'a str>,
+    error: Option<&'a str>,
+    failure_category: Option<FailureCategory>,
+    label_coverage: &'a BTreeMap<String, f64>,
+    detections: usize,
+}
+
+// Failed videos per category, keyed by name so the rollup reads alphabetically
+fn failures_by_category(results: &[VideoProcessingResult]) -> BTreeMap<&'static str, usize> {
+    let mut counts = BTreeMap::new();
+    for category in results.iter().filter_map(|r| r.failure_category) {
+        *counts.entry(category.as_str()).or_insert(0) += 1;
+    }
+    counts
+}
+
+// One entry of <video>/chunks.json (see chunk_duration)
+#[derive(Serialize)]
+struct ChunkSummary {
+    index: usize,
+    // Relative to the video's output directory
+    dir: String,
+    start: f64,
+    end: f64,
+    success: bool,
+    frame_count: usize,
+    detections: usize,
+    error: Option<String>,
+}
+
+fn chunk_dir_name(index: usize) -> String {
+    format!("chunk_{:03}", index)
+}
+
+fn write_chunk_index(output_dir: &Path, chunks: &[ChunkSummary]) -> Result<()> {
+    fs::create_dir_all(output_dir)?;
+    fs::write(
+        output_dir.join("chunks.json"),
+        serde_json::to_string_pretty(chunks)?,
+    )?;
+    Ok(())
+}
+
+// One JSON object per analyzed frame, including frames where nothing was dropped
+// Container time of the first video frame, which results are rebased to
+fn video_start(video_path: &Path) -> Result<f64> {
+    let start_times = stream_start_times(video_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read stream start times: {}", e))?;
+    Ok(start_times.video)
+}
+
+fn write_audit_log(output_dir: &Path, audits: &[FrameAudit]) -> Result<()> {
+    use std::io::Write;
+
+    fs::create_dir_all(output_dir)?;
+    let mut file = std::io::BufWriter::new(fs::File::create(output_dir.join("audit.jsonl"))?);
+    for audit in audits {
+        writeln!(file, "{}", serde_json::to_string(audit)?)?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+// Stable within a run, which is all scratch directory names need
+fn path_hash(path: &Path) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    path.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Out of space, quota exceeded, read-only filesystem or no permission
+fn is_storage_error(error: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+
+    matches!(
+        error.kind(),
+        ErrorKind::PermissionDenied
+            | ErrorKind::StorageFull
+            | ErrorKind::QuotaExceeded
+            | ErrorKind::ReadOnlyFilesystem
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ml_backend::MLBackend;
+    use crate::test_fixtures;
+
+    // Config for a batch over copies of test_fixtures::av_clip named `inputs`, or None
+    // when ffmpeg isn't available to generate the clip
+    fn batch_config(name: &str, inputs: &[&str]) -> Option<BatchConfig> {
+        let clip = test_fixtures::av_clip()?;
+        let dir = test_fixtures::scratch_dir(name);
+        let input_dir = dir.join("input");
+        fs::create_dir_all(&input_dir).unwrap();
+        for input in inputs {
+            fs::copy(&clip, input_dir.join(input)).unwrap();
+        }
+        Some(BatchConfig {
+            input_dir,
+            output_dir: dir.join("output"),
+            ..BatchConfig::default()
+        })
+    }
+
+    // Runs the batch_config batch; returns the results and output_dir
+    fn run_batch(
+        name: &str,
+        inputs: &[&str],
+        configure: impl FnOnce(&mut BatchConfig),
+    ) -> Option<(BatchResults, PathBuf)> {
+        let mut config = batch_config(name, inputs)?;
+        configure(&mut config);
+        let output_dir = config.output_dir.clone();
+        let results = BatchProcessor::new(config).process_batch().unwrap();
+        Some((results, output_dir))
+    }
+
+    // Stands in for a backend with a bug
+    struct PanickingBackend;
+
+    impl MLBackend for PanickingBackend {
+        fn load_model(&mut self, _model_path: Option<&Path>) -> Result<()> {
+            Ok(())
+        }
+
+        fn process_frame(&self, _frame_path: &Path, _timestamp: f64) -> Result<FrameAnalysis> {
+            panic!("backend bug")
+        }
+
+        fn backend_name(&self) -> &'static str {
+            "Panicking Backend"
+        }
+    }
+
+    #[test]
+    fn storage_errors_are_recognized_by_kind() {
+        use std::io::{Error, ErrorKind};
+
+        for kind in [
+            ErrorKind::PermissionDenied,
+            ErrorKind::StorageFull,
+            ErrorKind::QuotaExceeded,
+            ErrorKind::ReadOnlyFilesystem,
+        ] {
+            assert!(is_storage_error(&Error::from(kind)), "{:?}", kind);
+        }
+        assert!(!is_storage_error(&Error::from(ErrorKind::NotFound)));
+    }
+
+    #[test]
+    fn videos_sharing_a_stem_get_separate_scratch_dirs() {
+        let processor = BatchProcessor::new(BatchConfig::default());
+        let first = processor.video_paths(Path::new("a/x.mp4"));
+        let second = processor.video_paths(Path::new("b/x.mov"));
+        assert_ne!(first.scratch_dir, second.scratch_dir);
+        assert_ne!(first.frames_dir, second.frames_dir);
+        assert_ne!(first.audio_path, second.audio_path);
+    }
+
+    #[test]
+    fn names_with_spaces_and_non_ascii_are_kept_end_to_end() {
+        let names = ["street scene.mkv", "Zürich – été.mkv"];
+        let Some((results, output_dir)) = run_batch("unicode-names", &names, |_| {}) else {
+            return;
+        };
+        assert_eq!((results.successful, results.failed), (2, 0));
+        for stem in ["street scene", "Zürich – été"] {
+            let results_json = output_dir.join(stem).join("results.json");
+            assert!(results_json.exists(), "{:?}", results_json);
+        }
+    }
+
+    #[test]
+    fn duplicate_is_aliased_inside_the_originals_date_bucket() {
+        let Some((results, output_dir)) = run_batch("date-buckets", &["a.mkv", "b.mkv"], |c| {
+            c.dedupe = true;
+            c.bucketing = OutputBucketing::ByDate;
+        }) else {
+            return;
+        };
+        assert_eq!(results.successful, 1);
+        let original = &results.results[0];
+        let bucket = output_dir.join(date_bucket(&original.video_path));
+        assert_eq!(original.output_dir, bucket.join("a"));
+        assert!(bucket.join("a").join("results.json").exists());
+        assert!(bucket.join("b").join("duplicate_of.txt").exists());
+    }
+
+    #[test]
+    fn a_batch_without_videos_succeeds_with_an_empty_summary() {
+        let dir = test_fixtures::scratch_dir("zero-videos");
+        fs::create_dir_all(dir.join("input")).unwrap();
+        let processor = BatchProcessor::new(BatchConfig {
+            input_dir: dir.join("input"),
+            output_dir: dir.join("output"),
+            ..BatchConfig::default()
+        });
+        let results = processor.process_batch().unwrap();
+        assert_eq!(
+            (results.total_videos, results.successful, results.failed),
+            (0, 0, 0)
+        );
+
+        processor
+            .generate_batch_summary(
+                &results.results,
+                &results.skipped,
+                std::time::Duration::ZERO,
+                true,
+            )
+            .unwrap();
+        let summary = fs::read_to_string(dir.join("output").join("batch_summary.txt")).unwrap();
+        assert!(summary.contains("Total videos processed: 0"), "{}", summary);
+        assert!(!summary.contains("Average"), "{}", summary);
+    }
+
+    #[test]
+    fn frame_limits_apply_to_a_chunked_video_as_a_whole() {
+        // Four half-second chunks of the two-second clip
+        let Some((results, _)) = run_batch("chunked-frame-cap", &["a.mkv"], |config| {
+            config.chunk_duration = Some(0.5);
+            config.max_frames_per_video = Some(7);
+        }) else {
+            return;
+        };
+        let capped = &results.results[0];
+        assert!(capped.success, "{:?}", capped.error_message);
+        assert_eq!(capped.frame_count, 7);
+        assert!(capped.truncated);
+
+        let Some((results, _)) = run_batch("chunked-frame-count", &["a.mkv"], |config| {
+            config.chunk_duration = Some(0.5);
+            config.frames_per_video = Some(6);
+        }) else {
+            return;
+        };
+        let counted = &results.results[0];
+        assert!(counted.success, "{:?}", counted.error_message);
+        assert_eq!(counted.frame_count, 6);
+    }
+
+    #[test]
+    fn panicking_backend_fails_each_video_without_aborting_the_batch() {
+        let Some(config) = batch_config("panicking-backend", &["a.mkv", "b.mkv"]) else {
+            return;
+        };
+        let processor = BatchProcessor::new(config);
+        processor.check_output_writable().unwrap();
+        let videos = processor.find_video_files().unwrap();
+        let analyzer = FrameAnalyzer::with_backend(Box::new(PanickingBackend));
+        let transcriber = create_transcription_backend("mock").unwrap();
+
+        let results = processor
+            .process_videos(
+                &videos,
+                SkippedVideos::default(),
+                Vec::new(),
+                &analyzer,
+                transcriber.as_ref(),
+                Instant::now(),
+            )
+            .unwrap();
+        assert_eq!((results.successful, results.failed), (0, 2));
+        for result in &results.results {
+            assert_eq!(result.failure_category, Some(FailureCategory::Panic));
+            let message = result.error_message.as_deref().unwrap();
+            assert!(message.contains("backend bug"), "{}", message);
+        }
+    }
+
+    // Fails every frame of video "a" and is slow on the others, so "a" fails while the
+    // rest of the batch is still being analyzed
+    struct FailingFirstVideoBackend;
+
+    impl MLBackend for FailingFirstVideoBackend {
+        fn load_model(&mut self, _model_path: Option<&Path>) -> Result<()> {
+            Ok(())
+        }
+
+        fn process_frame(&self, frame_path: &Path, timestamp: f64) -> Result<FrameAnalysis> {
+            let scratch_dir = frame_path.parent().and_then(Path::parent).unwrap();
+            if scratch_dir.to_string_lossy().ends_with("-a") {
+                return Err(anyhow::anyhow!("unreadable frame"));
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            Ok(FrameAnalysis {
+                timestamp,
+                detections: Vec::new(),
+            })
+        }
+
+        fn backend_name(&self) -> &'static str {
+            "Failing First Video Backend"
+        }
+    }
+
+    #[derive(Default)]
+    struct CountingTranscriber {
+        calls: AtomicUsize,
+    }
+
+    impl TranscriptionBackend for CountingTranscriber {
+        fn transcribe(&self, _audio_path: &Path) -> Result<Vec<AudioResult>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(Vec::new())
+        }
+
+        fn backend_name(&self) -> &'static str {
+            "Counting Transcriber"
+        }
+    }
+
+    #[test]
+    fn audio_workers_stop_transcribing_once_fail_fast_stops_the_batch() {
+        let inputs = ["a.mkv", "b.mkv", "c.mkv", "d.mkv", "e.mkv", "f.mkv"];
+        let Some(mut config) = batch_config("fail-fast-audio-workers", &inputs) else {
+            return;
+        };
+        config.fail_fast = true;
+        config.max_concurrent = Concurrency::Fixed(1);
+        config.video_workers = Some(1);
+        config.audio_workers = Some(1);
+        let processor = BatchProcessor::new(config);
+        let videos = processor.find_video_files().unwrap();
+        let analyzer = FrameAnalyzer::with_backend(Box::new(FailingFirstVideoBackend));
+        let transcriber = CountingTranscriber::default();
+
+        let outcome = processor.process_videos(
+            &videos,
+            SkippedVideos::default(),
+            Vec::new(),
+            &analyzer,
+            &transcriber,
+            Instant::now(),
+        );
+        let error = outcome.err().unwrap();
+        assert!(
+            error.downcast_ref::<BatchAborted>().is_some(),
+            "{:#}",
+            error
+        );
+        // "a", and at most the video the audio worker had taken before the stop
+        let calls = transcriber.calls.load(Ordering::SeqCst);
+        assert!(
+            calls <= 2,
+            "{} of {} videos transcribed",
+            calls,
+            inputs.len()
+        );
+    }
+
+    // Replaces the allocator of the whole test binary, so it is only built with
+    // `cargo test --features heap-profile -- --ignored --test-threads=1`
+    #[cfg(feature = "heap-profile")]
+    mod peak_heap {
+        use super::*;
+        use std::alloc::{GlobalAlloc, Layout, System};
+        use std::cell::Cell;
+
+        // Counts the heap use of each thread
+        struct CountingAllocator;
+
+        thread_local! {
+            static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+            static PEAK_BYTES: Cell<isize> = const { Cell::new(0) };
+        }
+
+        fn track(delta: isize) {
+            let _ = LIVE_BYTES.try_with(|live| {
+                live.set(live.get() + delta);
+                let _ = PEAK_BYTES.try_with(|peak| peak.set(peak.get().max(live.get())));
+            });
+        }
+
+        unsafe impl GlobalAlloc for CountingAllocator {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                let ptr = System.alloc(layout);
+                if !ptr.is_null() {
+                    track(layout.size() as isize);
+                }
+                ptr
+            }
+
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                System.dealloc(ptr, layout);
+                track(-(layout.size() as isize));
+            }
+        }
+
+        #[global_allocator]
+        static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+        // Highest heap use on this thread while `work` runs, above what was in use before
+        fn peak_heap_growth(work: impl FnOnce()) -> isize {
+            let before = LIVE_BYTES.with(Cell::get);
+            PEAK_BYTES.with(|peak| peak.set(before));
+            work();
+            PEAK_BYTES.with(Cell::get) - before
+        }
+
+        #[test]
+        #[ignore = "needs --features heap-profile and --test-threads=1"]
+        fn low_memory_peak_heap_does_not_grow_with_video_length() {
+            // Two seconds of audio either way, so only the number of frames differs
+            let clip = |name: &str, seconds: u32| {
+                let video = format!("testsrc=size=64x48:rate=25:duration={}", seconds);
+                test_fixtures::generate(
+                    name,
+                    &[
+                        "-f",
+                        "lavfi",
+                        "-i",
+                        &video,
+                        "-f",
+                        "lavfi",
+                        "-i",
+                        "sine=frequency=440:sample_rate=16000:duration=2",
+                        "-c:v",
+                        "mpeg4",
+                        "-c:a",
+                        "pcm_s16le",
+                    ],
+                )
+            };
+            let (Some(short), Some(long)) = (clip("frames_50.mkv", 2), clip("frames_750.mkv", 30))
+            else {
+                return;
+            };
+            let processor = BatchProcessor::new(BatchConfig {
+                output_dir: test_fixtures::scratch_dir("low-memory-peak"),
+                low_memory: true,
+                ..BatchConfig::default()
+            });
+            let analyzer = FrameAnalyzer::new("mock").unwrap();
+            let transcriber = create_transcription_backend("mock").unwrap();
+            let peak = |video: &Path| {
+                peak_heap_growth(|| {
+                    let paths = processor.video_paths(video);
+                    let mut budget = FrameBudget::new(&processor.config);
+                    let result = processor.process_window(
+                        video,
+                        &paths,
+                        &analyzer,
+                        transcriber.as_ref(),
+                        &mut budget,
+                    );
+                    assert!(result.success, "{:?}", result.error_message);
+                    assert!(result.synchronized_results.is_empty());
+                })
+            };
+
+            // The first run also pays for one-off setup
+            peak(&short);
+            let short_peak = peak(&short);
+            let long_peak = peak(&long);
+            // 15 times the frames; keeping even 50 bytes per frame would cross this
+            assert!(
+                long_peak < short_peak + 32 * 1024,
+                "{} bytes for 50 frames, {} for 750",
+                short_peak,
+                long_peak
+            );
+        }
     }
 }
@@ -0,0 +1,207 @@
+use crate::frame_analyzer::FrameAnalyzer;
+use anyhow::Result;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// Side length of the generated frame used when no sample directory is given
+const SYNTHETIC_FRAME_SIZE: u32 = 640;
+
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+    pub backend: String,
+    pub model_path: Option<PathBuf>,
+    // PNG/JPEG frames to cycle through; a synthetic frame is used when None
+    pub frames_dir: Option<PathBuf>,
+    pub iterations: usize,
+    // Untimed runs first, so lazy initialization and caches don't skew the numbers
+    pub warmup: usize,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self {
+            backend: "mock".to_string(),
+            model_path: None,
+            frames_dir: None,
+            iterations: 100,
+            warmup: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchReport {
+    pub backend: String,
+    pub sample_frames: usize,
+    pub iterations: usize,
+    pub warmup: usize,
+    pub mean_ms: f64,
+    pub median_ms: f64,
+    pub p95_ms: f64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+    // Sequential frames per second, from the mean latency
+    pub throughput_fps: f64,
+    // ONNX input preparation alone; None without the `onnx` feature
+    pub preprocessing: Option<PreprocessTiming>,
+}
+
+// Mean time to letterbox and normalize one sample frame into the ONNX input tensor, with
+// the backend's reused buffer and with a freshly allocated one per frame (as before
+// buffers were reused)
+#[derive(Debug, Clone, Serialize)]
+pub struct PreprocessTiming {
+    pub reused_buffer_ms: f64,
+    pub fresh_buffer_ms: f64,
+}
+
+impl BenchReport {
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "Backend: {}\n\
+             Frames: {} sample(s), {} iterations after {} warmup\n\
+             Latency: mean {:.2}ms, median {:.2}ms, p95 {:.2}ms (min {:.2}ms, max {:.2}ms)\n\
+             Throughput: {:.1} frames/s",
+            self.backend,
+            self.sample_frames,
+            self.iterations,
+            self.warmup,
+            self.mean_ms,
+            self.median_ms,
+            self.p95_ms,
+            self.min_ms,
+            self.max_ms,
+            self.throughput_fps
+        );
+        if let Some(p) = &self.preprocessing {
+            summary.push_str(&format!(
+                "\nPreprocessing: {:.2}ms with a reused buffer, {:.2}ms with a fresh one",
+                p.reused_buffer_ms, p.fresh_buffer_ms
+            ));
+        }
+        summary
+    }
+}
+
+pub fn run_benchmark(config: &BenchConfig) -> Result<BenchReport> {
+    if config.iterations == 0 {
+        return Err(anyhow::anyhow!("Benchmark needs at least one iteration"));
+    }
+
+    let mut analyzer = FrameAnalyzer::new(&config.backend)?;
+    analyzer.load_model(config.model_path.as_deref())?;
+
+    let scratch_dir =
+        std::env::temp_dir().join(format!("video-audio-bench-{}", std::process::id()));
+    let frames = match &config.frames_dir {
+        Some(dir) => sample_frames(dir)?,
+        None => vec![synthetic_frame(&scratch_dir)?],
+    };
+
+    for i in 0..config.warmup {
+        analyzer.process_frame(&frames[i % frames.len()], 0.0)?;
+    }
+
+    let mut latencies = Vec::with_capacity(config.iterations);
+    for i in 0..config.iterations {
+        let frame = &frames[i % frames.len()];
+        let started = Instant::now();
+        analyzer.process_frame(frame, i as f64)?;
+        latencies.push(started.elapsed());
+    }
+
+    let preprocessing = time_preprocessing(&frames, config.iterations)?;
+
+    if scratch_dir.exists() {
+        let _ = std::fs::remove_dir_all(&scratch_dir);
+    }
+
+    latencies.sort();
+    let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+    let mean_ms = latencies.iter().map(|d| ms(*d)).sum::<f64>() / latencies.len() as f64;
+
+    Ok(BenchReport {
+        backend: analyzer.backend_name().to_string(),
+        sample_frames: frames.len(),
+        iterations: config.iterations,
+        warmup: config.warmup,
+        mean_ms,
+        median_ms: ms(percentile(&latencies, 0.5)),
+        p95_ms: ms(percentile(&latencies, 0.95)),
+        min_ms: ms(latencies[0]),
+        max_ms: ms(latencies[latencies.len() - 1]),
+        throughput_fps: if mean_ms > 0.0 { 1000.0 / mean_ms } else { 0.0 },
+        preprocessing,
+    })
+}
+
+#[cfg(feature = "onnx")]
+fn time_preprocessing(frames: &[PathBuf], iterations: usize) -> Result<Option<PreprocessTiming>> {
+    use crate::ml_backend::ONNXBackend;
+
+    let images = frames
+        .iter()
+        .map(|frame| Ok(image::open(frame)?.to_rgb8()))
+        .collect::<Result<Vec<_>>>()?;
+    let mean_ms = |run: &mut dyn FnMut(&image::RgbImage) -> Result<()>| -> Result<f64> {
+        let started = Instant::now();
+        for i in 0..iterations {
+            run(&images[i % images.len()])?;
+        }
+        Ok(started.elapsed().as_secs_f64() * 1000.0 / iterations as f64)
+    };
+
+    let mut buffer = Vec::new();
+    let reused_buffer_ms = mean_ms(&mut |image| {
+        ONNXBackend::preprocess_into(image, &mut buffer)?;
+        Ok(())
+    })?;
+    let fresh_buffer_ms = mean_ms(&mut |image| {
+        ONNXBackend::preprocess_into(image, &mut Vec::new())?;
+        Ok(())
+    })?;
+    Ok(Some(PreprocessTiming {
+        reused_buffer_ms,
+        fresh_buffer_ms,
+    }))
+}
+
+#[cfg(not(feature = "onnx"))]
+fn time_preprocessing(_frames: &[PathBuf], _iterations: usize) -> Result<Option<PreprocessTiming>> {
+    Ok(None)
+}
+
+// Nearest-rank percentile of sorted samples
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let rank = (p * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn sample_frames(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut frames: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension().is_some_and(|ext| {
+                let ext = ext.to_string_lossy().to_lowercase();
+                ext == "png" || ext == "jpg" || ext == "jpeg"
+            })
+        })
+        .collect();
+    frames.sort();
+
+    if frames.is_empty() {
+        return Err(anyhow::anyhow!("No PNG/JPEG frames found in {:?}", dir));
+    }
+    Ok(frames)
+}
+
+fn synthetic_frame(dir: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join("synthetic.png");
+    let image = image::RgbImage::from_fn(SYNTHETIC_FRAME_SIZE, SYNTHETIC_FRAME_SIZE, |x, y| {
+        image::Rgb([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8])
+    });
+    image.save(&path)?;
+    Ok(path)
+}
@@ -0,0 +1,188 @@
+use crate::audio_processor::stream_start_times;
+use anyhow::Result;
+use ffmpeg_next::{codec, encoder, format, media, Rational};
+use std::path::{Path, PathBuf};
+
+// A detection worth cutting a clip around. Timestamps are on the results.json clock
+// (seconds from the first video frame).
+#[derive(Debug, Clone)]
+pub struct ClipTrigger {
+    pub label: String,
+    pub timestamp: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClipConfig {
+    // Detections of these labels trigger a clip
+    pub labels: Vec<String>,
+    pub min_confidence: f32,
+    // Seconds kept before and after each trigger
+    pub pre_seconds: f64,
+    pub post_seconds: f64,
+}
+
+impl Default for ClipConfig {
+    fn default() -> Self {
+        Self {
+            labels: Vec::new(),
+            min_confidence: 0.5,
+            pre_seconds: 5.0,
+            post_seconds: 5.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ClipWindow {
+    label: String,
+    trigger_time: f64,
+    start: f64,
+    end: f64,
+}
+
+// Cuts `[trigger - pre, trigger + post]` around each trigger into `out_dir` as
+// clip_{label}_{time}.mp4. Overlapping windows are merged and named after their first
+// trigger. Packets are stream-copied, so clips start on the keyframe at or before the
+// window and cost no re-encoding.
+pub fn extract_clips(
+    video_path: &Path,
+    triggers: &[ClipTrigger],
+    pre: f64,
+    post: f64,
+    out_dir: &Path,
+) -> Result<Vec<PathBuf>> {
+    let windows = merge_windows(triggers, pre, post);
+    if windows.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    std::fs::create_dir_all(out_dir)?;
+    // Results are rebased to the first video frame; seeking needs container time
+    let video_start = stream_start_times(video_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read stream start times: {}", e))?
+        .video;
+
+    let mut clips = Vec::new();
+    for window in windows {
+        let clip_path = out_dir.join(format!(
+            "clip_{}_{:.1}.mp4",
+            sanitize(&window.label),
+            window.trigger_time
+        ));
+        copy_range(
+            video_path,
+            &clip_path,
+            window.start + video_start,
+            window.end + video_start,
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to write {:?}: {}", clip_path, e))?;
+        clips.push(clip_path);
+    }
+    Ok(clips)
+}
+
+fn merge_windows(triggers: &[ClipTrigger], pre: f64, post: f64) -> Vec<ClipWindow> {
+    let mut sorted: Vec<&ClipTrigger> = triggers.iter().collect();
+    sorted.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+
+    let mut windows: Vec<ClipWindow> = Vec::new();
+    for trigger in sorted {
+        let start = (trigger.timestamp - pre).max(0.0);
+        let end = trigger.timestamp + post;
+        match windows.last_mut() {
+            Some(last) if start <= last.end => last.end = last.end.max(end),
+            _ => windows.push(ClipWindow {
+                label: trigger.label.clone(),
+                trigger_time: trigger.timestamp,
+                start,
+                end,
+            }),
+        }
+    }
+    windows
+}
+
+// Labels come from the model and may contain path separators or spaces
+pub fn sanitize(label: &str) -> String {
+    label
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+// Remuxes audio and video packets between `start` and `end` (container seconds)
+fn copy_range(
+    video_path: &Path,
+    clip_path: &Path,
+    start: f64,
+    end: f64,
+) -> Result<(), ffmpeg_next::Error> {
+    ffmpeg_next::init()?;
+
+    let mut ictx = format::input(&video_path)?;
+    let mut octx = format::output(&clip_path)?;
+
+    let video_stream_index = ictx
+        .streams()
+        .best(media::Type::Video)
+        .ok_or(ffmpeg_next::Error::StreamNotFound)?
+        .index();
+
+    let mut stream_mapping: Vec<Option<usize>> = vec![None; ictx.nb_streams() as usize];
+    let mut time_bases = vec![Rational(0, 1); ictx.nb_streams() as usize];
+    let mut ost_index = 0;
+    for (ist_index, ist) in ictx.streams().enumerate() {
+        let medium = ist.parameters().medium();
+        if medium != media::Type::Audio && medium != media::Type::Video {
+            continue;
+        }
+        stream_mapping[ist_index] = Some(ost_index);
+        time_bases[ist_index] = ist.time_base();
+        ost_index += 1;
+
+        let mut ost = octx.add_stream(encoder::find(codec::Id::None))?;
+        ost.set_parameters(ist.parameters());
+        // Codec tags don't always carry over between containers (e.g. MKV -> MP4)
+        unsafe {
+            (*ost.parameters().as_mut_ptr()).codec_tag = 0;
+        }
+    }
+    octx.write_header()?;
+
+    let position = (start * ffmpeg_next::ffi::AV_TIME_BASE as f64) as i64;
+    ictx.seek(position, ..position)?;
+
+    // Clip time zero is the first packet written
+    let mut origin: Option<f64> = None;
+    for (stream, mut packet) in ictx.packets() {
+        let ist_index = stream.index();
+        let Some(ost_index) = stream_mapping[ist_index] else {
+            continue;
+        };
+        let time_base = time_bases[ist_index];
+        let Some(ts) = packet.dts().or(packet.pts()) else {
+            continue;
+        };
+        let seconds = ts as f64 * f64::from(time_base);
+        if seconds > end {
+            if ist_index == video_stream_index {
+                break;
+            }
+            continue;
+        }
+
+        let origin = *origin.get_or_insert(seconds);
+        let shift = (origin / f64::from(time_base)).round() as i64;
+        packet.set_pts(packet.pts().map(|pts| pts - shift));
+        packet.set_dts(packet.dts().map(|dts| dts - shift));
+
+        let ost_time_base = octx.stream(ost_index).unwrap().time_base();
+        packet.rescale_ts(time_base, ost_time_base);
+        packet.set_position(-1);
+        packet.set_stream(ost_index);
+        packet.write_interleaved(&mut octx)?;
+    }
+
+    octx.write_trailer()?;
+    Ok(())
+}
@@ -1,31 +1,41 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ProcessingConfig {
     pub batch: BatchConfig,
     pub ml_models: MLConfig,
     pub output: OutputConfig,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BatchConfig {
     pub input_directory: PathBuf,
     pub output_directory: PathBuf,
     pub video_extensions: Vec<String>,
+    // 0 sizes it from the available memory, like --max-concurrent auto
     pub max_concurrent_videos: usize,
     pub skip_existing: bool,
+    // File name -> priority, higher first (see --priority)
+    #[serde(default)]
+    pub priorities: HashMap<String, i32>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MLConfig {
     pub video_model_path: Option<PathBuf>,
     pub audio_model_path: Option<PathBuf>,
+    // Newline-delimited labels for the video model's class indices
+    #[serde(default)]
+    pub class_names_path: Option<PathBuf>,
     pub confidence_threshold: f32,
+    #[serde(default)]
+    pub per_label_thresholds: HashMap<String, f32>,
     pub use_gpu: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OutputConfig {
     pub save_frames: bool,
     pub save_audio: bool,
@@ -50,19 +60,28 @@ impl Default for ProcessingConfig {
                 ],
                 max_concurrent_videos: 4,
                 skip_existing: true,
+                priorities: HashMap::new(),
             },
             ml_models: MLConfig {
                 video_model_path: None,
                 audio_model_path: None,
+                class_names_path: None,
                 confidence_threshold: 0.5,
+                per_label_thresholds: HashMap::new(),
                 use_gpu: true,
             },
-            output: OutputConfig {
-                save_frames: false,
-                save_audio: false,
-                output_format: "json".to_string(),
-                include_timestamps: true,
-            },
+            output: OutputConfig::default(),
+        }
+    }
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            save_frames: false,
+            save_audio: false,
+            output_format: "json".to_string(),
+            include_timestamps: true,
         }
     }
 }
@@ -75,8 +94,47 @@ impl ProcessingConfig {
     }
 
     pub fn save_to_file(&self, path: &std::path::Path) -> anyhow::Result<()> {
-        let content = toml::to_string_pretty(self)?;
-        std::fs::write(path, content)?;
+        std::fs::write(path, self.to_toml()?)?;
         Ok(())
     }
+
+    // Every field, defaults included, in the layout load_from_file reads back
+    pub fn to_toml(&self) -> anyhow::Result<String> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn printed_config_loads_back_unchanged() {
+        let mut config = ProcessingConfig::default();
+        config.batch.input_directory = PathBuf::from("/media/in box");
+        config.batch.video_extensions = vec!["mp4".to_string()];
+        config.batch.max_concurrent_videos = 0;
+        config
+            .batch
+            .priorities
+            .insert("urgent \"clip\".mp4".to_string(), 5);
+        config.ml_models.class_names_path = Some(PathBuf::from("coco.names"));
+        config.ml_models.confidence_threshold = 0.35;
+        config
+            .ml_models
+            .per_label_thresholds
+            .insert("person".to_string(), 0.3);
+        config.ml_models.use_gpu = false;
+        config.output.output_format = "json,csv".to_string();
+        assert_ne!(config, ProcessingConfig::default());
+
+        let path = std::env::temp_dir().join(format!(
+            "video-audio-processor-config-{}.toml",
+            std::process::id()
+        ));
+        std::fs::write(&path, config.to_toml().unwrap()).unwrap();
+        let loaded = ProcessingConfig::load_from_file(&path);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(loaded.unwrap(), config);
+    }
 }
@@ -0,0 +1,108 @@
+use crate::synchronizer::SynchronizedResult;
+use crate::taxonomy::display_label;
+use std::collections::{BTreeMap, HashMap};
+
+// Fraction (0.0 - 1.0) of `duration` seconds during which each label was detected. Every
+// analyzed frame stands for the time until the next one, and the last frame for the
+// average gap between frames, so sparse sampling still gives a time share rather than a
+// frame count. With no usable duration the analyzed span is used instead.
+pub fn label_coverage(
+    results: &[SynchronizedResult],
+    duration: Option<f64>,
+) -> BTreeMap<String, f64> {
+    let mut frames: Vec<&SynchronizedResult> =
+        results.iter().filter(|r| r.is_analyzed_frame()).collect();
+    frames.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+
+    let mut coverage = CoverageAccumulator::default();
+    for frame in frames {
+        coverage.push(frame);
+    }
+    coverage.finish(duration)
+}
+
+// label_coverage one result at a time, for results that are written as they're made
+// instead of collected. Frames have to arrive in timestamp order.
+#[derive(Debug, Default)]
+pub struct CoverageAccumulator {
+    covered: BTreeMap<String, f64>,
+    span: f64,
+    frames: usize,
+    first: Option<f64>,
+    // The latest frame's time and labels; its weight is known once the next one arrives
+    pending: Option<(f64, Vec<String>)>,
+}
+
+impl CoverageAccumulator {
+    pub fn push(&mut self, result: &SynchronizedResult) {
+        if !result.is_analyzed_frame() {
+            return;
+        }
+        let mut labels: Vec<String> = result
+            .video_objects
+            .iter()
+            .filter(|o| !o.interpolated)
+            .map(|o| o.label.clone())
+            .collect();
+        labels.sort_unstable();
+        labels.dedup();
+
+        if let Some((timestamp, labels)) = self.pending.take() {
+            self.cover(labels, result.timestamp - timestamp);
+        }
+        self.first.get_or_insert(result.timestamp);
+        self.frames += 1;
+        self.pending = Some((result.timestamp, labels));
+    }
+
+    fn cover(&mut self, labels: Vec<String>, weight: f64) {
+        self.span += weight;
+        for label in labels {
+            *self.covered.entry(label).or_insert(0.0) += weight;
+        }
+    }
+
+    pub fn finish(mut self, duration: Option<f64>) -> BTreeMap<String, f64> {
+        let Some((last, labels)) = self.pending.take() else {
+            return BTreeMap::new();
+        };
+        let average_gap = match self.first {
+            Some(first) if self.frames > 1 => (last - first) / (self.frames - 1) as f64,
+            _ => 0.0,
+        };
+        self.cover(labels, average_gap);
+
+        let total = duration.filter(|d| *d > 0.0).unwrap_or(self.span);
+        self.covered
+            .into_iter()
+            .map(|(label, seconds)| {
+                let fraction = if total > 0.0 {
+                    (seconds / total).min(1.0)
+                } else {
+                    1.0
+                };
+                (label, fraction)
+            })
+            .collect()
+    }
+}
+
+// "person 72%, car 10%", most covered first, labels shown under their display alias
+pub fn format_coverage(
+    coverage: &BTreeMap<String, f64>,
+    aliases: &HashMap<String, String>,
+) -> String {
+    let mut entries: Vec<(&String, &f64)> = coverage.iter().collect();
+    entries.sort_by(|a, b| b.1.total_cmp(a.1));
+    entries
+        .iter()
+        .map(|(label, fraction)| {
+            format!(
+                "{} {:.0}%",
+                display_label(aliases, label),
+                *fraction * 100.0
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,249 @@
+use crate::video_processor::{decode_frames, ExtractionOptions, FrameSampling};
+use anyhow::Result;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::hash::Hasher;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+// Positions (as fractions of the duration) of the frames that make up a fingerprint
+const SAMPLE_POINTS: [f64; 3] = [0.1, 0.5, 0.9];
+// Max differing bits per 64-bit frame hash for two frames to count as the same
+const MAX_HASH_DISTANCE: u32 = 6;
+// Re-encodes and remuxes can shift the reported duration slightly
+const DURATION_TOLERANCE: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKind {
+    ExactBytes,
+    Perceptual,
+}
+
+impl fmt::Display for DuplicateKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DuplicateKind::ExactBytes => write!(f, "identical bytes"),
+            DuplicateKind::Perceptual => write!(f, "matching frames"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Duplicate {
+    pub path: PathBuf,
+    // The file that gets processed in its place
+    pub original: PathBuf,
+    pub kind: DuplicateKind,
+}
+
+struct Fingerprint {
+    duration: f64,
+    frame_hashes: Vec<u64>,
+}
+
+// Splits `files` into the ones to process and duplicates of those. Byte-identical files
+// are found first by size + content hash; the rest are compared by duration and a
+// difference hash of a few sampled frames. The first file of each group (in input
+// order) is kept as the original.
+pub fn find_duplicates(files: &[PathBuf]) -> Result<(Vec<PathBuf>, Vec<Duplicate>)> {
+    let mut duplicates = Vec::new();
+
+    // Exact duplicates: only files sharing a size are worth hashing
+    let sizes = files
+        .iter()
+        .map(|file| Ok(fs::metadata(file)?.len()))
+        .collect::<Result<Vec<u64>>>()?;
+    let mut size_counts: HashMap<u64, usize> = HashMap::new();
+    for size in &sizes {
+        *size_counts.entry(*size).or_default() += 1;
+    }
+    let mut by_content: HashMap<(u64, u64), &PathBuf> = HashMap::new();
+    for (file, &size) in files.iter().zip(&sizes) {
+        if size_counts[&size] < 2 {
+            continue;
+        }
+        let key = (size, content_hash(file)?);
+        match by_content.get(&key) {
+            Some(original) if files_equal(original, file)? => duplicates.push(Duplicate {
+                path: file.clone(),
+                original: (*original).clone(),
+                kind: DuplicateKind::ExactBytes,
+            }),
+            Some(_) => {}
+            None => {
+                by_content.insert(key, file);
+            }
+        }
+    }
+
+    // Perceptual duplicates among what's left; unreadable files are left to the main pass
+    let mut fingerprinted: Vec<(&PathBuf, Fingerprint)> = Vec::new();
+    for file in files {
+        if duplicates.iter().any(|d| &d.path == file) {
+            continue;
+        }
+        let fingerprint = match fingerprint(file) {
+            Ok(fingerprint) => fingerprint,
+            Err(e) => {
+                eprintln!("Warning: Could not fingerprint {:?}: {}", file, e);
+                continue;
+            }
+        };
+
+        match fingerprinted
+            .iter()
+            .find(|(_, other)| fingerprints_match(other, &fingerprint))
+        {
+            Some((original, _)) => duplicates.push(Duplicate {
+                path: file.clone(),
+                original: (*original).clone(),
+                kind: DuplicateKind::Perceptual,
+            }),
+            None => fingerprinted.push((file, fingerprint)),
+        }
+    }
+
+    let unique = files
+        .iter()
+        .filter(|file| !duplicates.iter().any(|d| &d.path == *file))
+        .cloned()
+        .collect();
+    Ok((unique, duplicates))
+}
+
+fn content_hash(path: &Path) -> Result<u64> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = DefaultHasher::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.write(&buffer[..read]);
+    }
+    Ok(hasher.finish())
+}
+
+// DefaultHasher isn't collision resistant, so equal hashes are confirmed byte by byte.
+// The files are compared a chunk at a time, stopping at the first difference.
+fn files_equal(a: &Path, b: &Path) -> Result<bool> {
+    let (mut a, mut b) = (fs::File::open(a)?, fs::File::open(b)?);
+    let mut chunk_a = vec![0u8; 64 * 1024];
+    let mut chunk_b = vec![0u8; 64 * 1024];
+    loop {
+        let read = read_chunk(&mut a, &mut chunk_a)?;
+        if read_chunk(&mut b, &mut chunk_b)? != read || chunk_a[..read] != chunk_b[..read] {
+            return Ok(false);
+        }
+        if read == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+// Reads until `buffer` is full or the file ends; returns the number of bytes read
+fn read_chunk(file: &mut impl Read, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match file.read(&mut buffer[filled..])? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+    Ok(filled)
+}
+
+fn fingerprint(path: &Path) -> Result<Fingerprint> {
+    ffmpeg_next::init()?;
+    let duration = ffmpeg_next::format::input(&path)?.duration() as f64
+        / ffmpeg_next::ffi::AV_TIME_BASE as f64;
+
+    // Each sample seeks to the keyframe before it, so only that GOP is decoded
+    let mut frame_hashes = Vec::new();
+    for point in SAMPLE_POINTS {
+        let target = point * duration.max(0.0);
+        let options = ExtractionOptions {
+            sampling: FrameSampling::Timestamps(vec![target]),
+            start: Some(target),
+            max_frames: Some(1),
+            ..Default::default()
+        };
+        decode_frames(path, &options, |frame| {
+            let image = image::RgbImage::from_raw(frame.width, frame.height, frame.rgb);
+            frame_hashes.push(image.as_ref().map_or(0, difference_hash));
+            Ok(false)
+        })?;
+    }
+
+    Ok(Fingerprint {
+        duration,
+        frame_hashes,
+    })
+}
+
+// dHash: shrink to 9x8 grayscale and record whether each pixel is brighter than its
+// right-hand neighbour. Robust to scaling, re-encoding and small color shifts.
+pub fn difference_hash(image: &image::RgbImage) -> u64 {
+    let gray = image::imageops::grayscale(image);
+    let small = image::imageops::resize(&gray, 9, 8, image::imageops::FilterType::Triangle);
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+fn fingerprints_match(a: &Fingerprint, b: &Fingerprint) -> bool {
+    !a.frame_hashes.is_empty()
+        && a.frame_hashes.len() == b.frame_hashes.len()
+        && (a.duration - b.duration).abs() <= DURATION_TOLERANCE
+        && a.frame_hashes
+            .iter()
+            .zip(&b.frame_hashes)
+            .all(|(x, y)| (x ^ y).count_ones() <= MAX_HASH_DISTANCE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str, contents: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "video-audio-processor-dedupe-{}-{}",
+            std::process::id(),
+            name
+        ));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn files_equal_compares_across_chunks() {
+        let contents: Vec<u8> = (0..200_000).map(|i| (i % 251) as u8).collect();
+        let mut changed_at_end = contents.clone();
+        *changed_at_end.last_mut().unwrap() ^= 1;
+
+        let original = temp_file("original", &contents);
+        let same = temp_file("same", &contents);
+        let different = temp_file("different", &changed_at_end);
+        let shorter = temp_file("shorter", &contents[..150_000]);
+
+        assert!(files_equal(&original, &same).unwrap());
+        assert!(!files_equal(&original, &different).unwrap());
+        assert!(!files_equal(&original, &shorter).unwrap());
+        assert!(!files_equal(&shorter, &original).unwrap());
+
+        for path in [original, same, different, shorter] {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
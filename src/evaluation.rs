@@ -0,0 +1,319 @@
+use crate::frame_analyzer::{DetectedObject, FrameResult};
+use crate::ground_truth::GroundTruthBox;
+use crate::postprocess::iou;
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+// Annotations are matched to the nearest produced frame within this many seconds
+const TIMESTAMP_TOLERANCE: f64 = 0.02;
+
+#[derive(Debug, Clone, Default)]
+pub struct LabelMetrics {
+    pub ground_truth: usize,
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub average_precision: f64,
+}
+
+impl LabelMetrics {
+    pub fn precision(&self) -> f64 {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_positives,
+        )
+    }
+
+    pub fn recall(&self) -> f64 {
+        ratio(self.true_positives, self.ground_truth)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EvaluationReport {
+    pub per_label: BTreeMap<String, LabelMetrics>,
+    // Annotated timestamps with no produced frame close enough to compare against
+    pub unmatched_timestamps: usize,
+}
+
+impl EvaluationReport {
+    // Totals over all labels
+    pub fn overall(&self) -> LabelMetrics {
+        let mut overall = LabelMetrics::default();
+        for metrics in self.per_label.values() {
+            overall.ground_truth += metrics.ground_truth;
+            overall.true_positives += metrics.true_positives;
+            overall.false_positives += metrics.false_positives;
+        }
+        overall.average_precision = self.mean_average_precision();
+        overall
+    }
+
+    // Mean of the per-label APs, over labels that appear in the ground truth
+    pub fn mean_average_precision(&self) -> f64 {
+        let aps: Vec<f64> = self
+            .per_label
+            .values()
+            .filter(|m| m.ground_truth > 0)
+            .map(|m| m.average_precision)
+            .collect();
+        if aps.is_empty() {
+            0.0
+        } else {
+            aps.iter().sum::<f64>() / aps.len() as f64
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        let mut lines = vec![format!(
+            "{:<16} {:>6} {:>6} {:>6} {:>9} {:>7} {:>7}",
+            "label", "gt", "tp", "fp", "precision", "recall", "AP"
+        )];
+        let overall = self.overall();
+        let rows = self
+            .per_label
+            .iter()
+            .map(|(label, m)| (label.as_str(), m))
+            .chain(std::iter::once(("overall (mAP)", &overall)));
+        for (label, m) in rows {
+            lines.push(format!(
+                "{:<16} {:>6} {:>6} {:>6} {:>9.3} {:>7.3} {:>7.3}",
+                label,
+                m.ground_truth,
+                m.true_positives,
+                m.false_positives,
+                m.precision(),
+                m.recall(),
+                m.average_precision
+            ));
+        }
+        if self.unmatched_timestamps > 0 {
+            lines.push(format!(
+                "{} annotated timestamps had no matching frame",
+                self.unmatched_timestamps
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+// Scores detections against ground truth, considering only frames that have
+// annotations. Within a frame, detections are matched greedily in confidence order to
+// the unmatched same-label box they overlap most (IoU >= iou_threshold). `results`
+// must be in timestamp order, as results.json is; only one frame is held at a time.
+pub fn evaluate(
+    results: impl IntoIterator<Item = Result<FrameResult>>,
+    ground_truth: &[GroundTruthBox],
+    iou_threshold: f32,
+) -> Result<EvaluationReport> {
+    let mut report = EvaluationReport::default();
+    for gt in ground_truth {
+        report
+            .per_label
+            .entry(gt.label.clone())
+            .or_default()
+            .ground_truth += 1;
+    }
+
+    let mut pending: Vec<&GroundTruthBox> = ground_truth.iter().collect();
+    pending.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+    let mut pending = pending.into_iter().peekable();
+    let mut unmatched = Vec::new();
+    // (confidence, is true positive) for every scored detection, per label
+    let mut scored: BTreeMap<String, Vec<(f32, bool)>> = BTreeMap::new();
+
+    // Each annotation goes to the nearer of the frames just before and after it (the
+    // earlier one on a tie), so a frame's annotations are complete once the next
+    // frame has been read
+    let mut previous: Option<(FrameResult, Vec<&GroundTruthBox>)> = None;
+    for result in results {
+        let result = result?;
+        let mut annotations = Vec::new();
+        while let Some(gt) = pending.next_if(|gt| gt.timestamp <= result.timestamp) {
+            let to_next = result.timestamp - gt.timestamp;
+            match previous.as_mut() {
+                Some((frame, gts)) if gt.timestamp - frame.timestamp <= to_next => {
+                    assign(gt, gt.timestamp - frame.timestamp, gts, &mut unmatched)
+                }
+                _ => assign(gt, to_next, &mut annotations, &mut unmatched),
+            }
+        }
+        if let Some((frame, gts)) = previous.replace((result, annotations)) {
+            score_frame(&frame, &gts, iou_threshold, &mut scored);
+        }
+    }
+    for gt in pending {
+        match previous.as_mut() {
+            Some((frame, gts)) => assign(gt, gt.timestamp - frame.timestamp, gts, &mut unmatched),
+            None => unmatched.push(gt.timestamp),
+        }
+    }
+    if let Some((frame, gts)) = previous {
+        score_frame(&frame, &gts, iou_threshold, &mut scored);
+    }
+    unmatched.sort_by(|a, b| a.total_cmp(b));
+    unmatched.dedup();
+    report.unmatched_timestamps = unmatched.len();
+
+    for (label, mut detections) in scored {
+        let metrics = report.per_label.entry(label).or_default();
+        detections.sort_by(|a, b| b.0.total_cmp(&a.0));
+        metrics.true_positives = detections.iter().filter(|(_, tp)| *tp).count();
+        metrics.false_positives = detections.len() - metrics.true_positives;
+        metrics.average_precision = average_precision(&detections, metrics.ground_truth);
+    }
+
+    Ok(report)
+}
+
+// Adds `gt` to a frame `distance` seconds away, or to `unmatched` if that's too far
+fn assign<'a>(
+    gt: &'a GroundTruthBox,
+    distance: f64,
+    frame: &mut Vec<&'a GroundTruthBox>,
+    unmatched: &mut Vec<f64>,
+) {
+    if distance <= TIMESTAMP_TOLERANCE {
+        frame.push(gt);
+    } else {
+        unmatched.push(gt.timestamp);
+    }
+}
+
+fn score_frame(
+    result: &FrameResult,
+    gts: &[&GroundTruthBox],
+    iou_threshold: f32,
+    scored: &mut BTreeMap<String, Vec<(f32, bool)>>,
+) {
+    if gts.is_empty() {
+        return;
+    }
+    let mut detections: Vec<&DetectedObject> = result
+        .objects
+        .iter()
+        .filter(|object| !object.interpolated)
+        .collect();
+    detections.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+    let mut claimed = vec![false; gts.len()];
+    for object in detections {
+        let best = gts
+            .iter()
+            .enumerate()
+            .filter(|(g, gt)| !claimed[*g] && gt.label == object.label)
+            .map(|(g, gt)| (g, iou(&gt.bbox, &object.bbox)))
+            .filter(|(_, overlap)| *overlap >= iou_threshold)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((g, _)) = best {
+            claimed[g] = true;
+        }
+        scored
+            .entry(object.label.clone())
+            .or_default()
+            .push((object.confidence, best.is_some()));
+    }
+}
+
+// Area under the precision/recall curve with precision made monotonically decreasing
+// (all-point interpolation, as in Pascal VOC 2010+). `detections` must be sorted by
+// descending confidence.
+fn average_precision(detections: &[(f32, bool)], ground_truth: usize) -> f64 {
+    if ground_truth == 0 {
+        return 0.0;
+    }
+
+    let mut points = Vec::with_capacity(detections.len());
+    let mut true_positives = 0;
+    for (i, (_, tp)) in detections.iter().enumerate() {
+        if *tp {
+            true_positives += 1;
+        }
+        points.push((
+            ratio(true_positives, ground_truth),
+            ratio(true_positives, i + 1),
+        ));
+    }
+
+    // Each point takes the best precision reachable at the same or higher recall
+    let mut best_precision = 0.0f64;
+    for point in points.iter_mut().rev() {
+        best_precision = best_precision.max(point.1);
+        point.1 = best_precision;
+    }
+
+    let mut area = 0.0;
+    let mut previous_recall = 0.0;
+    for (recall, precision) in points {
+        area += (recall - previous_recall) * precision;
+        previous_recall = recall;
+    }
+    area
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(timestamp: f64, objects: &[(&str, f32, [f32; 4])]) -> Result<FrameResult> {
+        Ok(FrameResult {
+            timestamp,
+            objects: objects
+                .iter()
+                .map(|(label, confidence, bbox)| (label.to_string(), *confidence, *bbox).into())
+                .collect(),
+            quality: None,
+            interpolated: false,
+        })
+    }
+
+    fn annotation(timestamp: f64, bbox: [f32; 4]) -> GroundTruthBox {
+        GroundTruthBox {
+            timestamp,
+            label: "person".to_string(),
+            bbox,
+        }
+    }
+
+    #[test]
+    fn annotations_are_scored_on_the_nearest_streamed_frame() {
+        let person = [10.0, 10.0, 50.0, 90.0];
+        let results = vec![
+            // Not annotated, so its detection isn't scored
+            frame(0.0, &[("person", 0.9, person)]),
+            frame(
+                0.5,
+                &[
+                    ("person", 0.8, person),
+                    ("person", 0.4, [60.0, 0.0, 90.0, 40.0]),
+                ],
+            ),
+            frame(1.0, &[]),
+        ];
+        let ground_truth = vec![
+            annotation(2.0, person),
+            annotation(0.51, person),
+            // Too far from every frame
+            annotation(0.75, person),
+        ];
+
+        let report = evaluate(results, &ground_truth, 0.5).unwrap();
+        let person = &report.per_label["person"];
+        assert_eq!(person.ground_truth, 3);
+        assert_eq!((person.true_positives, person.false_positives), (1, 1));
+        assert_eq!(report.unmatched_timestamps, 2);
+    }
+
+    #[test]
+    fn stream_errors_are_returned() {
+        let results = vec![frame(0.0, &[]), Err(anyhow::anyhow!("truncated file"))];
+        assert!(evaluate(results, &[], 0.5).is_err());
+    }
+}
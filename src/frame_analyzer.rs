@@ -1,15 +1,123 @@
-use crate::ml_backend::{create_ml_backend, FrameAnalysis, MLBackend};
+use crate::dedupe::difference_hash;
+use crate::ml_backend::{create_ml_backend, try_create_ml_backend, FrameAnalysis, MLBackend};
+use crate::preprocess::{apply_preprocessing, PreprocessStep};
+use crate::video_processor::{FrameQuality, ImageLimits};
 use anyhow::Result;
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::Mutex;
 
 pub struct FrameAnalyzer {
     backend: Box<dyn MLBackend>,
+    // Checked here so no backend ever opens an oversized frame
+    image_limits: ImageLimits,
+    preprocessing: Vec<PreprocessStep>,
+    // Write preprocessed pixels over the frame file instead of a temporary analysis copy
+    preprocess_in_place: bool,
+    cache: Option<Mutex<AnalysisCache>>,
+}
+
+// Frame size, dHash (see dedupe::difference_hash) and a hash of the exact pixels. The
+// dHash alone matches frames of a static camera in which something small moved.
+type CacheKey = (u32, u32, u64, u64);
+
+// Analyses by CacheKey, so a frame identical to one analyzed before, in any video, reuses
+// its detections instead of running the model again. The least recently used entry is
+// dropped once `capacity` is reached.
+struct AnalysisCache {
+    capacity: usize,
+    // Analysis and the tick it was last used at
+    entries: HashMap<CacheKey, (FrameAnalysis, u64)>,
+    tick: u64,
+}
+
+impl AnalysisCache {
+    fn get(&mut self, key: CacheKey) -> Option<FrameAnalysis> {
+        self.tick += 1;
+        let (analysis, last_used) = self.entries.get_mut(&key)?;
+        *last_used = self.tick;
+        Some(analysis.clone())
+    }
+
+    fn insert(&mut self, key: CacheKey, analysis: FrameAnalysis) {
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.tick += 1;
+        self.entries.insert(key, (analysis, self.tick));
+    }
 }
 
 impl FrameAnalyzer {
     pub fn new(backend_type: &str) -> Result<Self> {
         let backend = create_ml_backend(backend_type)?;
-        Ok(Self { backend })
+        Ok(Self {
+            backend,
+            image_limits: ImageLimits::default(),
+            preprocessing: Vec::new(),
+            preprocess_in_place: false,
+            cache: None,
+        })
+    }
+
+    // For backends built in code rather than by name, e.g. a TemplateMLBackend
+    pub fn with_backend(backend: Box<dyn MLBackend>) -> Self {
+        Self {
+            backend,
+            image_limits: ImageLimits::default(),
+            preprocessing: Vec::new(),
+            preprocess_in_place: false,
+            cache: None,
+        }
+    }
+
+    // Tries each backend in order until one is created and loads its model, e.g.
+    // ["onnx", "candle", "mock"]. Ending the chain with "mock" guarantees success, since
+    // the mock backend needs no model file.
+    pub fn new_with_fallback(
+        backend_types: &[&str],
+        model_path: Option<&Path>,
+        class_names: &[String],
+        tensor_dump_dir: Option<&Path>,
+    ) -> Result<Self> {
+        let mut last_error = None;
+
+        for backend_type in backend_types {
+            let created = try_create_ml_backend(backend_type, class_names, tensor_dump_dir);
+            let attempt = created.and_then(|backend| {
+                let mut analyzer = Self {
+                    backend,
+                    image_limits: ImageLimits::default(),
+                    preprocessing: Vec::new(),
+                    preprocess_in_place: false,
+                    cache: None,
+                };
+                analyzer.load_model(model_path)?;
+                Ok(analyzer)
+            });
+
+            match attempt {
+                Ok(analyzer) => {
+                    println!("Selected ML backend: {}", analyzer.backend_name());
+                    return Ok(analyzer);
+                }
+                Err(e) => {
+                    eprintln!("Warning: ML backend '{}' failed: {}", backend_type, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error
+            .unwrap_or_else(|| anyhow::anyhow!("No ML backends configured"))
+            .context("All ML backends in the fallback chain failed"))
     }
 
     pub fn load_model(&mut self, model_path: Option<&Path>) -> Result<()> {
@@ -17,8 +125,87 @@ impl FrameAnalyzer {
         self.backend.load_model(model_path)
     }
 
+    pub fn set_image_limits(&mut self, image_limits: ImageLimits) {
+        self.image_limits = image_limits;
+    }
+
+    pub fn set_preprocessing(&mut self, steps: Vec<PreprocessStep>, in_place: bool) {
+        self.preprocessing = steps;
+        self.preprocess_in_place = in_place;
+    }
+
+    // Reuse analyses of identical frames; see AnalysisCache
+    pub fn enable_cache(&mut self, capacity: usize) {
+        self.cache = Some(Mutex::new(AnalysisCache {
+            capacity: capacity.max(1),
+            entries: HashMap::new(),
+            tick: 0,
+        }));
+    }
+
     pub fn process_frame(&self, frame_path: &Path, timestamp: f64) -> Result<FrameAnalysis> {
-        self.backend.process_frame(frame_path, timestamp)
+        self.image_limits.check_file(frame_path)?;
+        let Some(cache) = &self.cache else {
+            return self.analyze(frame_path, timestamp);
+        };
+
+        let image = image::open(frame_path)?;
+        let eight_bit = is_eight_bit(&image);
+        let frame = image.to_rgb8();
+        let key = (
+            frame.width(),
+            frame.height(),
+            difference_hash(&frame),
+            pixel_hash(&frame),
+        );
+        if let Some(cached) = cache.lock().unwrap().get(key) {
+            // Saved frames are preprocessed either way
+            if self.preprocess_in_place && eight_bit && !self.preprocessing.is_empty() {
+                apply_preprocessing(frame, &self.preprocessing).save(frame_path)?;
+            }
+            return Ok(FrameAnalysis {
+                timestamp,
+                ..cached
+            });
+        }
+        let analysis = if self.preprocessing.is_empty() {
+            self.backend.process_frame(frame_path, timestamp)?
+        } else {
+            self.analyze_preprocessed(frame_path, timestamp, frame, eight_bit)?
+        };
+        cache.lock().unwrap().insert(key, analysis.clone());
+        Ok(analysis)
+    }
+
+    fn analyze(&self, frame_path: &Path, timestamp: f64) -> Result<FrameAnalysis> {
+        if self.preprocessing.is_empty() {
+            return self.backend.process_frame(frame_path, timestamp);
+        }
+        let image = image::open(frame_path)?;
+        self.analyze_preprocessed(frame_path, timestamp, image.to_rgb8(), is_eight_bit(&image))
+    }
+
+    // Backends read frames from disk, so the preprocessed copy has to be written out.
+    // `frame` is the decoded frame file.
+    fn analyze_preprocessed(
+        &self,
+        frame_path: &Path,
+        timestamp: f64,
+        frame: image::RgbImage,
+        eight_bit: bool,
+    ) -> Result<FrameAnalysis> {
+        let processed = apply_preprocessing(frame, &self.preprocessing);
+        // A 16-bit frame (see FrameBitDepth) keeps its samples; it gets a temporary copy
+        if self.preprocess_in_place && eight_bit {
+            processed.save(frame_path)?;
+            return self.backend.process_frame(frame_path, timestamp);
+        }
+
+        let analysis_path = frame_path.with_extension("preprocessed.png");
+        processed.save(&analysis_path)?;
+        let analysis = self.backend.process_frame(&analysis_path, timestamp);
+        let _ = std::fs::remove_file(&analysis_path);
+        analysis
     }
 
     pub fn backend_name(&self) -> &str {
@@ -26,6 +213,23 @@ impl FrameAnalyzer {
     }
 }
 
+fn pixel_hash(frame: &image::RgbImage) -> u64 {
+    use std::hash::Hasher;
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    hasher.write(frame.as_raw());
+    hasher.finish()
+}
+
+// Preprocessing works on 8-bit RGB, so only such frames may be overwritten in place
+fn is_eight_bit(image: &image::DynamicImage) -> bool {
+    use image::ColorType;
+    matches!(
+        image.color(),
+        ColorType::L8 | ColorType::La8 | ColorType::Rgb8 | ColorType::Rgba8
+    )
+}
+
 // Legacy compatibility functions
 pub fn load_model() -> Result<FrameAnalyzer> {
     let mut analyzer = FrameAnalyzer::new("mock")?;
@@ -41,22 +245,173 @@ pub fn process_frame(
     analyzer.process_frame(frame_path, timestamp)
 }
 
+// A detection together with where it came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedObject {
+    pub label: String,
+    pub confidence: f32,
+    // The model's own confidence when a calibration mapped it to `confidence`
+    pub raw_confidence: Option<f32>,
+    // [x1, y1, x2, y2] in pixels of the frame the boxes are reported on (see
+    // BboxCoordinates); output_writer::BboxFormat only changes how they're written
+    pub bbox: [f32; 4],
+    // Position of the source frame among the video's analyzed frames
+    pub frame_index: usize,
+    // Name of the ML backend that produced the detection
+    pub model: String,
+    // How the source frame was picked (FrameSampling::method_name, or "audio_aligned");
+    // "interpolated" for detections synthesized between frames
+    pub extraction_method: String,
+    pub class_id: Option<u32>,
+    // Synthesized between a track's real detections (see tracker::interpolate_tracks);
+    // not something the model saw, so metrics skip it
+    pub interpolated: bool,
+}
+
+impl From<(String, f32, [f32; 4])> for DetectedObject {
+    fn from((label, confidence, bbox): (String, f32, [f32; 4])) -> Self {
+        Self {
+            label,
+            confidence,
+            raw_confidence: None,
+            bbox,
+            frame_index: 0,
+            model: String::new(),
+            extraction_method: String::new(),
+            class_id: None,
+            interpolated: false,
+        }
+    }
+}
+
+impl From<DetectedObject> for (String, f32, [f32; 4]) {
+    fn from(object: DetectedObject) -> Self {
+        (object.label, object.confidence, object.bbox)
+    }
+}
+
 // Legacy types for compatibility
 #[derive(Debug, Clone)]
 pub struct FrameResult {
     pub timestamp: f64,
-    pub objects: Vec<(String, f32, [f32; 4])>,
+    pub objects: Vec<DetectedObject>,
+    pub quality: Option<FrameQuality>,
+    // Added by tracker::interpolate_tracks at a time no frame was analyzed at; holds
+    // only interpolated detections
+    pub interpolated: bool,
 }
 
-impl From<FrameAnalysis> for FrameResult {
-    fn from(analysis: FrameAnalysis) -> Self {
+impl FrameResult {
+    pub fn from_analysis(
+        analysis: FrameAnalysis,
+        frame_index: usize,
+        model: &str,
+        extraction_method: &str,
+    ) -> Self {
         Self {
             timestamp: analysis.timestamp,
             objects: analysis
                 .detections
                 .into_iter()
-                .map(|d| (d.label, d.confidence, d.bbox))
+                .map(|d| DetectedObject {
+                    label: d.label,
+                    confidence: d.confidence,
+                    raw_confidence: None,
+                    bbox: d.bbox,
+                    frame_index,
+                    model: model.to_string(),
+                    extraction_method: extraction_method.to_string(),
+                    class_id: None,
+                    interpolated: false,
+                })
                 .collect(),
+            quality: None,
+            interpolated: false,
+        }
+    }
+}
+
+// Without provenance: frame index 0, no model name and no extraction method
+impl From<FrameAnalysis> for FrameResult {
+    fn from(analysis: FrameAnalysis) -> Self {
+        Self::from_analysis(analysis, 0, "", "")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn in_place_preprocessing_keeps_sixteen_bit_frames() {
+        let dir = test_fixtures::scratch_dir("in-place-sixteen-bit");
+        let path = dir.join("frame_0000.png");
+        image::ImageBuffer::<image::Rgb<u16>, _>::from_raw(2, 1, vec![1000u16; 6])
+            .unwrap()
+            .save(&path)
+            .unwrap();
+
+        let mut analyzer = FrameAnalyzer::new("mock").unwrap();
+        analyzer.set_preprocessing(vec![PreprocessStep::Gamma(2.0)], true);
+        analyzer.process_frame(&path, 0.0).unwrap();
+
+        assert_eq!(image::open(&path).unwrap().color(), image::ColorType::Rgb16);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    // Counts the frames it's asked to analyze
+    struct CountingBackend {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl MLBackend for CountingBackend {
+        fn load_model(&mut self, _model_path: Option<&Path>) -> Result<()> {
+            Ok(())
+        }
+
+        fn process_frame(&self, _frame_path: &Path, timestamp: f64) -> Result<FrameAnalysis> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(FrameAnalysis {
+                timestamp,
+                detections: Vec::new(),
+            })
+        }
+
+        fn backend_name(&self) -> &'static str {
+            "Counting Backend"
+        }
+    }
+
+    #[test]
+    fn a_repeated_frame_is_analyzed_once() {
+        let dir = test_fixtures::scratch_dir("analysis-cache");
+        let frame =
+            image::RgbImage::from_fn(32, 32, |x, y| image::Rgb([x as u8 * 8, y as u8 * 8, 0]));
+        let mut moved = frame.clone();
+        moved.put_pixel(16, 16, image::Rgb([255, 255, 255]));
+        let paths = [
+            dir.join("frame_0000.png"),
+            dir.join("frame_0001.png"),
+            dir.join("frame_0002.png"),
+        ];
+        frame.save(&paths[0]).unwrap();
+        frame.save(&paths[1]).unwrap();
+        moved.save(&paths[2]).unwrap();
+
+        let calls = Arc::default();
+        let mut analyzer = FrameAnalyzer::with_backend(Box::new(CountingBackend {
+            calls: Arc::clone(&calls),
+        }));
+        analyzer.enable_cache(16);
+        for (i, path) in paths.iter().enumerate() {
+            let analysis = analyzer.process_frame(path, i as f64).unwrap();
+            assert_eq!(analysis.timestamp, i as f64);
         }
+        // The second frame reuses the first one's analysis; the third differs by a pixel
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        let _ = std::fs::remove_dir_all(dir);
     }
 }
@@ -0,0 +1,66 @@
+use anyhow::Result;
+use serde::Deserialize;
+use std::path::Path;
+
+// One labeled box. CSV files use the header `timestamp,label,x1,y1,x2,y2`; JSON files
+// hold an array of `{"timestamp": 1.0, "label": "car", "bbox": [x1, y1, x2, y2]}`.
+// Timestamps are seconds on the same clock as results.json, boxes are in pixels.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GroundTruthBox {
+    pub timestamp: f64,
+    pub label: String,
+    pub bbox: [f32; 4],
+}
+
+pub fn load_ground_truth(path: &Path) -> Result<Vec<GroundTruthBox>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {:?}: {}", path, e))?;
+
+    let is_csv = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    if is_csv {
+        parse_csv(&content).map_err(|e| anyhow::anyhow!("Failed to parse {:?}: {}", path, e))
+    } else {
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse {:?}: {}", path, e))
+    }
+}
+
+fn parse_csv(content: &str) -> Result<Vec<GroundTruthBox>> {
+    let mut boxes = Vec::new();
+
+    for (line_number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (line_number == 0 && line.starts_with("timestamp")) {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if fields.len() != 6 {
+            return Err(anyhow::anyhow!(
+                "line {}: expected 6 fields, found {}",
+                line_number + 1,
+                fields.len()
+            ));
+        }
+
+        let number = |field: &str| -> Result<f64> {
+            field.parse::<f64>().map_err(|_| {
+                anyhow::anyhow!("line {}: invalid number '{}'", line_number + 1, field)
+            })
+        };
+        boxes.push(GroundTruthBox {
+            timestamp: number(fields[0])?,
+            label: fields[1].to_string(),
+            bbox: [
+                number(fields[2])? as f32,
+                number(fields[3])? as f32,
+                number(fields[4])? as f32,
+                number(fields[5])? as f32,
+            ],
+        });
+    }
+
+    Ok(boxes)
+}
@@ -1,47 +1,392 @@
+mod annotated_video;
 mod audio_processor;
 mod batch_processor;
+mod benchmark;
+mod clip_extractor;
+mod config;
+mod coverage;
+mod dedupe;
+mod evaluation;
 mod frame_analyzer;
+mod ground_truth;
+mod manifest;
 mod ml_backend;
+mod output_writer;
+mod palette;
+#[cfg(feature = "parquet")]
+mod parquet_export;
+mod postprocess;
+mod preprocess;
+mod progress_events;
+mod progress_file;
+mod results_diff;
+mod results_loader;
+#[cfg(feature = "sqlite")]
+mod sqlite_export;
+mod subtitle_processor;
 mod synchronizer;
+mod taxonomy;
+#[cfg(test)]
+mod test_fixtures;
+mod timeline;
+mod tonemap;
+mod tracker;
+mod transcription_backend;
+mod video_log;
 mod video_processor;
+mod watcher;
 
-use anyhow::Result;
-use std::path::Path;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
 
-use audio_processor::{extract_audio, transcribe_audio};
+use audio_processor::{extract_audio_range, transcribe_audio};
 use frame_analyzer::FrameAnalyzer;
 use std::env;
-use synchronizer::{print_results, synchronize_results};
-use video_processor::extract_frames;
+use synchronizer::{print_results, synchronize_results, SyncStrategy};
+use video_processor::{extract_frames_with_options, ExtractionOptions};
 
-fn main() -> Result<()> {
+// Process exit codes, so scripts can tell "nothing to do" and "some videos failed"
+// apart from a crash:
+//   0  success, including a batch whose inputs were all filtered out
+//   1  any other error
+//   2  unknown command, invalid option value or configuration
+//   3  batch found no input videos
+//   4  batch ran but at least one video failed (including --fail-fast aborts)
+const EXIT_ERROR: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_NO_INPUTS: i32 = 3;
+const EXIT_PARTIAL_FAILURE: i32 = 4;
+
+// Attached to errors (directly or as context) to pick their exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExitStatus {
+    Usage,
+    NoInputs,
+    PartialFailure,
+}
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExitStatus::Usage => write!(f, "invalid arguments or configuration"),
+            ExitStatus::NoInputs => write!(f, "no input videos found"),
+            ExitStatus::PartialFailure => write!(f, "some videos failed"),
+        }
+    }
+}
+
+impl std::error::Error for ExitStatus {}
+
+fn exit_code(error: &anyhow::Error) -> i32 {
+    if error
+        .downcast_ref::<batch_processor::BatchAborted>()
+        .is_some()
+    {
+        return EXIT_PARTIAL_FAILURE;
+    }
+    match error.downcast_ref::<ExitStatus>() {
+        Some(ExitStatus::Usage) => EXIT_USAGE,
+        Some(ExitStatus::NoInputs) => EXIT_NO_INPUTS,
+        Some(ExitStatus::PartialFailure) => EXIT_PARTIAL_FAILURE,
+        None => EXIT_ERROR,
+    }
+}
+
+fn main() {
     let args: Vec<String> = env::args().collect();
 
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {:#}", e);
+        std::process::exit(exit_code(&e));
+    }
+}
+
+fn run(args: &[String]) -> Result<()> {
     if args.len() > 1 && args[1] == "batch" {
-        run_batch_processing()
+        run_batch_processing(&args[2..])
+    } else if args.len() > 1 && args[1] == "watch" {
+        run_watch(&args[2..])
+    } else if args.len() > 1 && args[1] == "bench" {
+        run_benchmark(&args[2..])
+    } else if args.len() > 1 && args[1] == "print-config" {
+        run_print_config(&args[2..])
     } else if args.len() > 1 && args[1] == "single" {
-        run_single_video_processing()
+        run_single_video_processing(&args[2..])
+    } else if args.len() > 3 && args[1] == "diff" {
+        run_results_diff(Path::new(&args[2]), Path::new(&args[3]))
+    } else if args.len() > 3 && args[1] == "evaluate" {
+        run_evaluation(Path::new(&args[2]), Path::new(&args[3]))
     } else {
         println!("Usage:");
-        println!("  {} single    - Process single video (input.mp4)", args[0]);
+        println!(
+            "  {} single [video] - Process one video (default input.mp4; - reads stdin)",
+            args[0]
+        );
         println!("  {} batch     - Process multiple videos in batch", args[0]);
         println!(
-            "  {} batch --config - Show batch configuration options",
+            "  {} watch     - Keep processing new videos as they appear",
             args[0]
         );
+        println!(
+            "  {} bench     - Measure ML backend latency and throughput",
+            args[0]
+        );
+        println!(
+            "  {} help batch - Show batch input and output layout",
+            args[0]
+        );
+        println!(
+            "  {} print-config [batch options] [--output <file>] - Print the configuration",
+            args[0]
+        );
+        println!("                   batch would run with (defaults, --config file, flags and");
+        println!("                   .priority sidecars) as TOML");
+        println!(
+            "  {} diff <before.json> <after.json> - Compare two results files",
+            args[0]
+        );
+        println!(
+            "  {} evaluate <results.json> <ground_truth.csv|json> - Score detections",
+            args[0]
+        );
+        println!("Options:");
+        println!("  --config <file>  Settings from this TOML file (see print-config); flags");
+        println!("                   override them. Per-label confidence thresholds override");
+        println!("                   the global one (default 0.5)");
+        println!("  --ml-backends <a,b> ML backends to try in order, e.g. onnx,candle,mock;");
+        println!("                   the first that loads is used (default mock)");
+        println!("  --transcriber <name> Transcription backend: mock (default) or whisper");
+        println!("  --transcription-model <file> Model for --transcriber, e.g. a ggml file");
+        println!("  --transcript <s> Timed text from audio (default), subtitles (the video's");
+        println!("                   subtitle stream), or prefer-subtitles (audio as fallback)");
+        println!("  --start <time>   Only process from this time (seconds or HH:MM:SS)");
+        println!("  --end <time>     Stop processing after this time");
+        println!("  --chunk <time>   Process longer videos in windows of this length, each");
+        println!("                   saved to chunk_NNN/ and listed in chunks.json");
+        println!("  --taxonomy <file> JSON map of label -> supercategory for rollups");
+        println!("  --display-aliases <file> JSON map of label -> name shown in results.txt,");
+        println!("                   printed results and the summary (results.json unchanged)");
+        println!("  --fail-fast      Stop the batch at the first failed video");
+        println!("  --progress-file  Keep output_results/progress.json updated for monitoring");
+        println!("  --bucket-by <b>  Group video output directories by date (creation time)");
+        println!("                   or label (the most detected one)");
+        println!("  --progress-format <f> text (default), or jsonl to also write one JSON");
+        println!("                   progress event per line to stderr for tooling");
+        println!("  --palette        Write palette.json mapping each detected label to a color");
+        println!("  --audio-files    Also transcribe .wav/.mp3/.m4a/.flac/.ogg inputs");
+        println!("  --sort <order>   Processing order: name (default), size-desc, size-asc,");
+        println!("                   or mtime (oldest first)");
+        println!("  --priority <file=N> Process this video earlier (higher N first); repeatable");
+        println!("  --max-concurrent <n|auto> Videos decoded at once (default 4); auto sizes it");
+        println!("                   from available memory and the largest frame");
+        println!("  --frame-queue <n> Decode and analyze a video's frames concurrently, with");
+        println!("                   at most n decoded frames waiting");
+        println!("  --video-workers <n> Decode several videos at once into n shared frame");
+        println!("                   analysis workers");
+        println!("  --audio-workers <n> With --video-workers, transcribe on n separate workers");
+        println!("  --interpolate <s> Add interpolated track boxes every <s> seconds");
+        println!(
+            "  --sqlite <file>  Also write detections to a SQLite database (`sqlite` feature)"
+        );
+        println!("  --parquet <file> Write all detections to a Parquet file (`parquet` feature)");
+        println!("  --dump-tensors <dir> Write raw model outputs of each video's first frames");
+        println!("                   to <dir>/<video>/ for debugging (`onnx` feature)");
+        println!("  --wall-clock     Add absolute datetimes from the container creation_time");
+        println!("  --timeline       Merge every video's detection intervals into");
+        println!("                   combined_timeline.json/.csv by wall-clock time");
+        println!("  --bridge-gap <s> Keep a timeline interval going when its label is missing");
+        println!("                   for up to <s> seconds (default 0)");
+        println!("  --labels <a,b>   Only report detections with these labels");
+        println!("  --nms <iou>      Suppress same-label boxes overlapping above this IoU");
+        println!("  --soft-nms <sigma> Lower the confidence of overlapping same-label boxes");
+        println!("                   instead (Gaussian Soft-NMS, e.g. 0.5)");
+        println!("  --min-box <area> Drop boxes smaller than this (pixels, or N% of the frame)");
+        println!("  --max-box <area> Drop boxes larger than this (pixels, or N% of the frame)");
+        println!("  --max-detections <n> Keep only the n most confident detections per frame");
+        println!("  --calibration <spec> Remap confidences before thresholds: temperature:<T>");
+        println!("                   or piecewise:<raw>=<calibrated>,... (e.g. 0.3=0.5,0.7=0.9)");
+        println!("  --audit          Log detections dropped by filtering to audit.jsonl");
+        println!("  --per-video-logs Also write each video's messages to log.txt in its output");
+        println!("  --clip-labels <a,b> Save clips around detections of these labels");
+        println!("  --annotated-video <m> Also write annotated.mp4 with the boxes drawn on;");
+        println!("                   unanalyzed frames show the last boxes (hold) or none (blank)");
+        println!("  --stop-after <label=N> Stop analyzing a video once label is in N frames");
+        println!("  --stop-above <conf> Stop analyzing a video at a detection this confident");
+        println!("  --preprocess <steps> Filter frames before analysis, in order, e.g.");
+        println!("                   denoise:1.5,contrast:1,gamma:1.4");
+        println!("  --preprocess-saved Also apply --preprocess to the saved frames");
+        println!("  --cache-detections Reuse the detections of frames that look the same as");
+        println!("                   one analyzed before (e.g. shared intros)");
+        println!("  --quality        Add each frame's sharpness and brightness to results.json");
+        println!("  --min-sharpness <v> Also flag frames less sharp than this as blurry");
+        println!("                   (variance of the Laplacian, e.g. 100)");
+        println!("  --format <a,b>   Output formats per video: json (default), csv, srt, txt,");
+        println!("                   yolo (label files next to saved frames; needs --yolo-names)");
+        println!("  --confidence-decimals <n> Decimal places of confidences in results.json");
+        println!("  --timestamp-decimals <n> Decimal places of timestamps");
+        println!("                   (both default 3, at most 15)");
+        println!("  --yolo-names <file> Class names, one per line, for the yolo format");
+        println!("  --cue-duration <t> SRT cue length: next (default; until the next frame),");
+        println!("                   fixed:<s>, or clamp:<min>,<max> seconds");
+        println!("  --bbox-format <f> Boxes in results.json: xyxy (default), xywh, or the");
+        println!("                   0-1 normalized xyxyn / xywhn");
+        println!("  --speech-wav     Also extract 16 kHz mono WAV and transcribe that");
+        println!("  --transcription-retries <n> Retry failed transcription n times (default 1),");
+        println!("                   then keep the video without audio results");
+        println!("  --audio-offset <s> Shift transcript times by <s> seconds (negative is");
+        println!("                   earlier) to fix audio that is out of sync");
+        println!("  --sync <mode>    Transcript per frame: full (default) segment text, or");
+        println!("                   distributed to spread each segment's words over its frames");
+        println!("  --review-below <n> Flag videos with fewer than n detections for review");
+        println!("  --review-action <a> list (default), symlink or move flagged videos into");
+        println!("                   needs_review/ in the output directory");
+        println!("  --durable        fsync results, summaries and the manifest after each write");
+        println!("                   (safer on crash-prone machines, slower on slow disks)");
+        println!("  --low-memory     Write each frame's result as soon as it's analyzed instead");
+        println!("                   of holding the video's results (json output only)");
+        println!("  --dedupe         Process one of each set of duplicate videos and copy its");
+        println!("                   results to the others");
+        println!("  --min-activity <score> Skip videos whose peak motion score is below this");
+        println!("                   (static footage scores near 0, busy scenes near 1)");
+        println!("  --reference-keyframes (single) Also save each frame's GOP keyframe to");
+        println!("                   frames/keyframes and pair them in frames/references.json");
+        println!("  --unmatched-audio Keep transcript segments between analyzed frames as");
+        println!("                   text-only results");
+        println!("  --max-pixels <n> Reject videos/frames larger than this (default 64M)");
+        println!("  --max-decode-errors <n> Skip up to n corrupt packets per video (default 0)");
+        println!("  --no-autorotate  Keep frames as stored instead of applying rotation tags");
+        println!("  --thread-name <prefix> Name worker threads <prefix>-decode-N etc.");
+        println!("  --decoder-opt <key=value> ffmpeg decoder option, repeatable, e.g.");
+        println!("                   threads=4, ec=guess_mvs+deblock");
+        println!("  --hdr <mode>     HDR input: tonemap (default), clip, or 16bit PNG frames");
+        println!("  --bit-depth <d>  Frames of >8-bit sources: 8 (default), 16 (PNG), float (PFM)");
+        println!("  --crop <x,y,w,h> Only save and analyze this region of each frame, in pixels");
+        println!("                   or percentages of the frame (e.g. 50%,0%,50%,100%)");
+        println!("  --crop-local-boxes Report boxes relative to the crop, not the full frame");
+        println!("  --pipeline <mode> frame-first (default), or audio-aligned to transcribe");
+        println!("                   first and analyze one frame per transcript segment");
+        println!("  --max-frames <n> Analyze at most the first n frames of each video");
+        println!("  --frames-per-gop <n> Analyze up to n evenly spaced frames per keyframe");
+        println!("                   interval");
+        println!("  --frames-per-video <n> Analyze n evenly spaced frames per video");
+        println!("  --frame-names <n> Saved frame names: index (default) or time");
+        println!("                   (frame_0001_t12.480.png)");
+        println!("Bench options:");
+        println!("  --backend <name> --model <path> --frames <dir>");
+        println!("  --iterations <n> --warmup <n> --json <file>");
 
-        if args.len() > 2 && args[2] == "--config" {
+        if args.len() > 2 && args[1] == "help" && args[2] == "batch" {
             show_batch_config();
+            return Ok(());
         }
 
-        Ok(())
+        Err(ExitStatus::Usage.into())
+    }
+}
+
+// Takes the same arguments as batch and resolves them the same way, so the printed
+// config is what that batch would run with
+fn run_print_config(args: &[String]) -> Result<()> {
+    let batch_config = parse_batch_config(args).context(ExitStatus::Usage)?;
+    let base = load_config_file(args)?.unwrap_or_default();
+    let config = batch_processor::BatchProcessor::new(batch_config).effective_config(base);
+    match flag_value(args, "--output") {
+        Some(path) => config.save_to_file(Path::new(path)),
+        None => {
+            print!("{}", config.to_toml()?);
+            Ok(())
+        }
     }
 }
 
-fn run_single_video_processing() -> Result<()> {
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+// Accepts plain seconds ("7200.5") or clock time ("02:00:00", "05:30")
+fn parse_time(value: &str) -> Result<f64> {
+    value.split(':').try_fold(0.0, |total, part| {
+        part.parse::<f64>()
+            .map(|v| total * 60.0 + v)
+            .map_err(|_| anyhow::anyhow!("Invalid time value: {}", value))
+    })
+}
+
+fn parse_time_range(args: &[String]) -> Result<(Option<f64>, Option<f64>)> {
+    let start = flag_value(args, "--start").map(parse_time).transpose()?;
+    let end = flag_value(args, "--end").map(parse_time).transpose()?;
+    if let (Some(start), Some(end)) = (start, end) {
+        if end <= start {
+            return Err(anyhow::anyhow!(
+                "--end ({}) must be after --start ({})",
+                end,
+                start
+            ));
+        }
+    }
+    Ok((start, end))
+}
+
+fn run_single_video_processing(args: &[String]) -> Result<()> {
     println!("Starting single video processing...\n");
 
-    let video_path = Path::new("input.mp4");
+    let (start, end) = parse_time_range(args).context(ExitStatus::Usage)?;
+    let decoder_options = parse_decoder_options(args).context(ExitStatus::Usage)?;
+
+    // Frames and audio are decoded in separate passes, which a pipe can't provide, so
+    // stdin is first copied to a scratch file (as large as the input)
+    let input = args
+        .first()
+        .filter(|arg| !arg.starts_with("--"))
+        .map_or("input.mp4", String::as_str);
+    let spooled = if input == "-" {
+        println!("Reading video from stdin...");
+        Some(spool_stdin().context("Failed to read video from stdin")?)
+    } else {
+        None
+    };
+    let video_path = spooled.as_deref().unwrap_or(Path::new(input));
+    let processed = process_single_video(video_path, args, start, end, decoder_options);
+    if let Some(spooled) = &spooled {
+        let _ = std::fs::remove_file(spooled);
+    }
+    processed?;
+
+    println!("Processing completed successfully!");
+    Ok(())
+}
+
+// Copies stdin to a file in the temp directory and returns its path
+fn spool_stdin() -> Result<PathBuf> {
+    let path = env::temp_dir().join(format!(
+        "video-audio-processor-{}-stdin",
+        std::process::id()
+    ));
+    let mut file = std::fs::File::create(&path)?;
+    if let Err(e) = std::io::copy(&mut std::io::stdin().lock(), &mut file) {
+        let _ = std::fs::remove_file(&path);
+        return Err(e.into());
+    }
+    Ok(path)
+}
+
+fn process_single_video(
+    video_path: &Path,
+    args: &[String],
+    start: Option<f64>,
+    end: Option<f64>,
+    decoder_options: HashMap<String, String>,
+) -> Result<()> {
+    let display_aliases = match flag_value(args, "--display-aliases") {
+        Some(path) => taxonomy::load_display_aliases(Path::new(path)).context(ExitStatus::Usage)?,
+        None => HashMap::new(),
+    };
+
     let output_dir = Path::new("frames");
     let audio_path = Path::new("output.aac");
 
@@ -50,8 +395,16 @@ fn run_single_video_processing() -> Result<()> {
 
     // Step 1: Extract frames from video
     println!("1. Extracting frames from video...");
-    let timestamps = extract_frames(video_path, output_dir)
-        .map_err(|e| anyhow::anyhow!("Failed to extract frames: {}", e))?;
+    let options = ExtractionOptions {
+        start,
+        end,
+        decoder_options: decoder_options.clone(),
+        reference_keyframes: args.iter().any(|arg| arg == "--reference-keyframes"),
+        ..ExtractionOptions::default()
+    };
+    let timestamps = extract_frames_with_options(video_path, output_dir, &options)
+        .map_err(|e| anyhow::anyhow!("Failed to extract frames: {}", e))?
+        .timestamps;
     println!("   Extracted {} frames", timestamps.len());
 
     // Step 2: Load ML analyzer
@@ -72,47 +425,488 @@ fn run_single_video_processing() -> Result<()> {
             let analysis = analyzer
                 .process_frame(&frame_path, ts)
                 .map_err(|e| anyhow::anyhow!("Failed to process frame {}: {}", i, e))?;
-            frame_results.push(analysis.into());
+            frame_results.push(frame_analyzer::FrameResult::from_analysis(
+                analysis,
+                i,
+                analyzer.backend_name(),
+                options.sampling.method_name(),
+            ));
         }
     }
     println!("   Processed {} frames", frame_results.len());
 
     // Step 4: Extract audio from video
     println!("4. Extracting audio from video...");
-    extract_audio(video_path, audio_path)
+    let audio_path = extract_audio_range(video_path, audio_path, start, end, &decoder_options)
         .map_err(|e| anyhow::anyhow!("Failed to extract audio: {}", e))?;
 
     // Step 5: Transcribe audio
     println!("5. Transcribing audio...");
-    let audio_results = transcribe_audio(audio_path)?;
+    let audio_results = transcribe_audio(&audio_path)?;
     println!("   Generated {} audio segments", audio_results.len());
 
     // Step 6: Synchronize results
     println!("6. Synchronizing video and audio results...");
-    let synchronized_results = synchronize_results(frame_results, audio_results);
+    let synchronized_results =
+        synchronize_results(frame_results, audio_results, SyncStrategy::default(), false);
 
     // Step 7: Display results
-    print_results(&synchronized_results);
-
-    println!("Processing completed successfully!");
+    print_results(&synchronized_results, &display_aliases);
     Ok(())
 }
 
-fn run_batch_processing() -> Result<()> {
-    use crate::batch_processor::{BatchConfig, BatchProcessor};
+// Every "--decoder-opt key=value"; values are passed to ffmpeg unchecked
+fn parse_decoder_options(args: &[String]) -> Result<HashMap<String, String>> {
+    let mut options = HashMap::new();
+    for (i, arg) in args.iter().enumerate() {
+        if arg != "--decoder-opt" {
+            continue;
+        }
+        let entry = args.get(i + 1).map(String::as_str).unwrap_or("");
+        let (key, value) = entry.split_once('=').ok_or_else(|| {
+            anyhow::anyhow!("Invalid --decoder-opt (expected key=value): {}", entry)
+        })?;
+        options.insert(key.to_string(), value.to_string());
+    }
+    Ok(options)
+}
+
+fn load_config_file(args: &[String]) -> Result<Option<config::ProcessingConfig>> {
+    flag_value(args, "--config")
+        .map(|path| {
+            config::ProcessingConfig::load_from_file(Path::new(path))
+                .with_context(|| format!("Failed to load {}", path))
+        })
+        .transpose()
+}
+
+// Defaults, then the --config file, then the other flags
+fn parse_batch_config(args: &[String]) -> Result<batch_processor::BatchConfig> {
+    let mut config = batch_processor::BatchConfig::default();
+    if let Some(file) = load_config_file(args)? {
+        config.input_dir = file.batch.input_directory;
+        config.output_dir = file.batch.output_directory;
+        config.video_extensions = file.batch.video_extensions;
+        config.max_concurrent = match file.batch.max_concurrent_videos {
+            0 => batch_processor::Concurrency::Auto,
+            videos => batch_processor::Concurrency::Fixed(videos),
+        };
+        config.priorities = file.batch.priorities;
+        config.postprocess.confidence_threshold = file.ml_models.confidence_threshold;
+        config.postprocess.per_label_thresholds = file.ml_models.per_label_thresholds;
+        config.ml_model_path = file.ml_models.video_model_path;
+        config.class_names_path = file.ml_models.class_names_path;
+        config.transcription_model_path = file.ml_models.audio_model_path;
+        config.output_formats = file
+            .output
+            .output_format
+            .split(',')
+            .map(|format| format.trim().to_string())
+            .collect();
+        config.output = file.output;
+    }
+    if let Some(backends) = flag_value(args, "--ml-backends") {
+        config.ml_backends = backends
+            .split(',')
+            .map(|backend| backend.trim().to_string())
+            .collect();
+    }
+    if let Some(backend) = flag_value(args, "--transcriber") {
+        config.transcription_backend = backend.to_string();
+    }
+    if let Some(path) = flag_value(args, "--transcription-model") {
+        config.transcription_model_path = Some(PathBuf::from(path));
+    }
+    if let Some(source) = flag_value(args, "--transcript") {
+        config.transcript_source = match source {
+            "audio" => batch_processor::TranscriptSource::Audio,
+            "subtitles" => batch_processor::TranscriptSource::Subtitles,
+            "prefer-subtitles" => batch_processor::TranscriptSource::PreferSubtitles,
+            _ => return Err(anyhow::anyhow!("Invalid --transcript: {}", source)),
+        };
+    }
+    (config.start_time, config.end_time) = parse_time_range(args)?;
+    config.chunk_duration = flag_value(args, "--chunk").map(parse_time).transpose()?;
+    if let Some(path) = flag_value(args, "--taxonomy") {
+        config.taxonomy = Some(taxonomy::Taxonomy::load(Path::new(path))?);
+    }
+    if let Some(path) = flag_value(args, "--display-aliases") {
+        config.display_aliases = taxonomy::load_display_aliases(Path::new(path))?;
+    }
+    config.fail_fast = args.iter().any(|arg| arg == "--fail-fast");
+    config.progress_file = args.iter().any(|arg| arg == "--progress-file");
+    if let Some(bucketing) = flag_value(args, "--bucket-by") {
+        config.bucketing = match bucketing {
+            "date" => batch_processor::OutputBucketing::ByDate,
+            "label" => batch_processor::OutputBucketing::ByDominantLabel,
+            _ => return Err(anyhow::anyhow!("Invalid --bucket-by: {}", bucketing)),
+        };
+    }
+    if let Some(format) = flag_value(args, "--progress-format") {
+        config.progress_format = match format {
+            "text" => batch_processor::ProgressFormat::Text,
+            "jsonl" => batch_processor::ProgressFormat::Jsonl,
+            _ => return Err(anyhow::anyhow!("Invalid --progress-format: {}", format)),
+        };
+    }
+    config.export_palette = args.iter().any(|arg| arg == "--palette");
+    if args.iter().any(|arg| arg == "--audio-files") {
+        config.audio_extensions = ["wav", "mp3", "m4a", "flac", "ogg"]
+            .iter()
+            .map(|ext| ext.to_string())
+            .collect();
+    }
+    if let Some(order) = flag_value(args, "--sort") {
+        config.sort_order = match order {
+            "name" => batch_processor::SortOrder::Name,
+            "size-desc" => batch_processor::SortOrder::SizeDesc,
+            "size-asc" => batch_processor::SortOrder::SizeAsc,
+            "mtime" => batch_processor::SortOrder::MTime,
+            _ => return Err(anyhow::anyhow!("Invalid --sort: {}", order)),
+        };
+    }
+    for (i, arg) in args.iter().enumerate() {
+        if arg != "--priority" {
+            continue;
+        }
+        let entry = args.get(i + 1).map(String::as_str).unwrap_or("");
+        let (name, priority) = entry
+            .split_once('=')
+            .and_then(|(name, priority)| Some((name, priority.parse::<i32>().ok()?)))
+            .ok_or_else(|| anyhow::anyhow!("Invalid --priority (expected name=N): {}", entry))?;
+        config.priorities.insert(name.to_string(), priority);
+    }
+    if let Some(capacity) = flag_value(args, "--frame-queue") {
+        config.frame_queue_capacity = Some(
+            capacity
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid --frame-queue: {}", capacity))?,
+        );
+    }
+    if let Some(workers) = flag_value(args, "--video-workers") {
+        config.video_workers = Some(
+            workers
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid --video-workers: {}", workers))?,
+        );
+    }
+    if let Some(workers) = flag_value(args, "--audio-workers") {
+        config.audio_workers = Some(
+            workers
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid --audio-workers: {}", workers))?,
+        );
+    }
+    if let Some(step) = flag_value(args, "--interpolate") {
+        config.interpolate_interval = Some(
+            step.parse()
+                .map_err(|_| anyhow::anyhow!("Invalid --interpolate: {}", step))?,
+        );
+    }
+    config.sqlite_path = flag_value(args, "--sqlite").map(PathBuf::from);
+    config.parquet_path = flag_value(args, "--parquet").map(PathBuf::from);
+    config.tensor_dump_dir = flag_value(args, "--dump-tensors").map(PathBuf::from);
+    config.wall_clock = args.iter().any(|arg| arg == "--wall-clock");
+    if args.iter().any(|arg| arg == "--timeline") {
+        config.combined_timeline = true;
+        // The timeline is ordered by absolute time
+        config.wall_clock = true;
+    }
+    if let Some(gap) = flag_value(args, "--bridge-gap") {
+        config.max_bridge_gap = gap
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid --bridge-gap: {}", gap))?;
+    }
+    config.audit_log = args.iter().any(|arg| arg == "--audit");
+    config.per_video_logs = args.iter().any(|arg| arg == "--per-video-logs");
+    config.speech_wav = args.iter().any(|arg| arg == "--speech-wav");
+    if let Some(retries) = flag_value(args, "--transcription-retries") {
+        config.transcription_retries = retries
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid --transcription-retries: {}", retries))?;
+    }
+    config.ignore_rotation = args.iter().any(|arg| arg == "--no-autorotate");
+    config.decoder_options = parse_decoder_options(args)?;
+    if let Some(prefix) = flag_value(args, "--thread-name") {
+        config.thread_name_prefix = prefix.to_string();
+    }
+    if let Some(labels) = flag_value(args, "--labels") {
+        config.postprocess.label_filter = Some(
+            labels
+                .split(',')
+                .map(|label| label.trim().to_string())
+                .collect(),
+        );
+    }
+    if let Some(iou) = flag_value(args, "--nms") {
+        config.postprocess.nms = Some(postprocess::NmsMode::Hard {
+            iou: iou
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid --nms: {}", iou))?,
+        });
+    }
+    if let Some(sigma) = flag_value(args, "--soft-nms") {
+        config.postprocess.nms = Some(postprocess::NmsMode::Soft {
+            sigma: sigma
+                .parse::<f32>()
+                .ok()
+                .filter(|sigma| *sigma > 0.0)
+                .ok_or_else(|| anyhow::anyhow!("Invalid --soft-nms: {}", sigma))?,
+        });
+    }
+    if let Some(area) = flag_value(args, "--min-box") {
+        config.postprocess.min_box_area = Some(area.parse()?);
+    }
+    if let Some(area) = flag_value(args, "--max-box") {
+        config.postprocess.max_box_area = Some(area.parse()?);
+    }
+    if let Some(calibration) = flag_value(args, "--calibration") {
+        config.postprocess.calibration = calibration.parse()?;
+    }
+    if let Some(count) = flag_value(args, "--max-detections") {
+        config.postprocess.max_detections = Some(
+            count
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid --max-detections: {}", count))?,
+        );
+    }
+    if let Some(mode) = flag_value(args, "--annotated-video") {
+        config.annotated_video = Some(match mode {
+            "hold" => annotated_video::UnanalyzedFrames::HoldLast,
+            "blank" => annotated_video::UnanalyzedFrames::Blank,
+            _ => return Err(anyhow::anyhow!("Invalid --annotated-video: {}", mode)),
+        });
+    }
+    if let Some(labels) = flag_value(args, "--clip-labels") {
+        config.clips = Some(clip_extractor::ClipConfig {
+            labels: labels
+                .split(',')
+                .map(|label| label.trim().to_string())
+                .collect(),
+            ..clip_extractor::ClipConfig::default()
+        });
+    }
+    if let Some(entry) = flag_value(args, "--stop-after") {
+        let (label, frames) = entry
+            .split_once('=')
+            .and_then(|(label, frames)| Some((label, frames.parse::<usize>().ok()?)))
+            .ok_or_else(|| anyhow::anyhow!("Invalid --stop-after (expected label=N): {}", entry))?;
+        config.early_stop = Some(batch_processor::EarlyStop::LabelCount {
+            label: label.to_string(),
+            frames,
+        });
+    }
+    if let Some(confidence) = flag_value(args, "--stop-above") {
+        config.early_stop = Some(batch_processor::EarlyStop::ConfidenceAbove(
+            confidence
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid --stop-above: {}", confidence))?,
+        ));
+    }
+    if let Some(steps) = flag_value(args, "--preprocess") {
+        config.preprocessing = steps
+            .split(',')
+            .map(|step| step.trim().parse())
+            .collect::<Result<_>>()?;
+    }
+    config.preprocess_saved_frames = args.iter().any(|arg| arg == "--preprocess-saved");
+    config.cache_detections = args.iter().any(|arg| arg == "--cache-detections");
+    config.quality_metrics = args.iter().any(|arg| arg == "--quality");
+    if let Some(sharpness) = flag_value(args, "--min-sharpness") {
+        config.min_sharpness = Some(
+            sharpness
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid --min-sharpness: {}", sharpness))?,
+        );
+        // Blurry frames are flagged next to the metrics
+        config.quality_metrics = true;
+    }
+    if let Some(formats) = flag_value(args, "--format") {
+        config.output_formats = formats
+            .split(',')
+            .map(|format| format.trim().to_string())
+            .collect();
+    }
+    // Beyond 15 places f64 has no digits left to round
+    if let Some(decimals) = flag_value(args, "--confidence-decimals") {
+        config.confidence_decimals = decimals
+            .parse()
+            .ok()
+            .filter(|decimals| *decimals <= 15)
+            .ok_or_else(|| anyhow::anyhow!("Invalid --confidence-decimals: {}", decimals))?;
+    }
+    if let Some(decimals) = flag_value(args, "--timestamp-decimals") {
+        config.timestamp_decimals = decimals
+            .parse()
+            .ok()
+            .filter(|decimals| *decimals <= 15)
+            .ok_or_else(|| anyhow::anyhow!("Invalid --timestamp-decimals: {}", decimals))?;
+    }
+    if let Some(path) = flag_value(args, "--yolo-names") {
+        config.yolo_classes = Some(output_writer::YoloClasses::load(Path::new(path))?);
+        // Labels go next to the frames they describe
+        config.output.save_frames = true;
+    }
+    config.include_unmatched_audio = args.iter().any(|arg| arg == "--unmatched-audio");
+    if let Some(threshold) = flag_value(args, "--review-below") {
+        config.review_threshold = Some(
+            threshold
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid --review-below: {}", threshold))?,
+        );
+    }
+    if let Some(action) = flag_value(args, "--review-action") {
+        config.review_action = match action {
+            "list" => batch_processor::ReviewAction::List,
+            "symlink" => batch_processor::ReviewAction::Symlink,
+            "move" => batch_processor::ReviewAction::Move,
+            _ => return Err(anyhow::anyhow!("Invalid --review-action: {}", action)),
+        };
+    }
+    config.durable_writes = args.iter().any(|arg| arg == "--durable");
+    config.low_memory = args.iter().any(|arg| arg == "--low-memory");
+    config.dedupe = args.iter().any(|arg| arg == "--dedupe");
+    if let Some(score) = flag_value(args, "--min-activity") {
+        config.min_activity = Some(
+            score
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid --min-activity: {}", score))?,
+        );
+    }
+    if let Some(timing) = flag_value(args, "--cue-duration") {
+        config.subtitle_timing = timing.parse()?;
+    }
+    if let Some(format) = flag_value(args, "--bbox-format") {
+        config.bbox_format = format.parse()?;
+    }
+    if let Some(offset) = flag_value(args, "--audio-offset") {
+        config.audio_offset = offset
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid --audio-offset: {}", offset))?;
+    }
+    if let Some(strategy) = flag_value(args, "--sync") {
+        config.sync_strategy = match strategy {
+            "full" => SyncStrategy::FullSegment,
+            "distributed" => SyncStrategy::Distributed,
+            _ => return Err(anyhow::anyhow!("Invalid --sync strategy: {}", strategy)),
+        };
+    }
+    if let Some(concurrency) = flag_value(args, "--max-concurrent") {
+        config.max_concurrent = match concurrency {
+            "auto" => batch_processor::Concurrency::Auto,
+            n => batch_processor::Concurrency::Fixed(
+                n.parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid --max-concurrent: {}", n))?,
+            ),
+        };
+    }
+    if let Some(pixels) = flag_value(args, "--max-pixels") {
+        config.image_limits.max_pixels = pixels
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid --max-pixels: {}", pixels))?;
+    }
+    if let Some(errors) = flag_value(args, "--max-decode-errors") {
+        config.max_decode_errors = errors
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid --max-decode-errors: {}", errors))?;
+    }
+    if let Some(mode) = flag_value(args, "--hdr") {
+        config.hdr_mode = match mode {
+            "tonemap" => video_processor::HdrMode::ToneMap,
+            "clip" => video_processor::HdrMode::Clip,
+            "16bit" => video_processor::HdrMode::Rgb16,
+            _ => return Err(anyhow::anyhow!("Invalid --hdr mode: {}", mode)),
+        };
+    }
+    if let Some(depth) = flag_value(args, "--bit-depth") {
+        config.frame_bit_depth = match depth {
+            "8" => video_processor::FrameBitDepth::Eight,
+            "16" => video_processor::FrameBitDepth::Sixteen,
+            "float" => video_processor::FrameBitDepth::Float,
+            _ => return Err(anyhow::anyhow!("Invalid --bit-depth: {}", depth)),
+        };
+    }
+    if let Some(crop) = flag_value(args, "--crop") {
+        config.crop = Some(crop.parse()?);
+    }
+    if args.iter().any(|arg| arg == "--crop-local-boxes") {
+        config.bbox_coordinates = batch_processor::BboxCoordinates::CropLocal;
+    }
+    if let Some(mode) = flag_value(args, "--pipeline") {
+        config.pipeline_mode = match mode {
+            "frame-first" => batch_processor::PipelineMode::FrameFirst,
+            "audio-aligned" => batch_processor::PipelineMode::AudioAligned,
+            _ => return Err(anyhow::anyhow!("Invalid --pipeline: {}", mode)),
+        };
+    }
+    if let Some(count) = flag_value(args, "--max-frames") {
+        config.max_frames_per_video = Some(
+            count
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid --max-frames: {}", count))?,
+        );
+    }
+    if let Some(count) = flag_value(args, "--frames-per-gop") {
+        config.frames_per_gop = Some(
+            count
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid --frames-per-gop: {}", count))?,
+        );
+    }
+    if let Some(count) = flag_value(args, "--frames-per-video") {
+        config.frames_per_video = Some(
+            count
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid --frames-per-video: {}", count))?,
+        );
+    }
+    if let Some(naming) = flag_value(args, "--frame-names") {
+        config.frame_naming = match naming {
+            "index" => video_processor::FrameNaming::IndexOnly,
+            "time" => video_processor::FrameNaming::IndexAndTime,
+            _ => return Err(anyhow::anyhow!("Invalid --frame-names: {}", naming)),
+        };
+    }
+
+    Ok(config)
+}
+
+fn run_batch_processing(args: &[String]) -> Result<()> {
+    use crate::batch_processor::BatchProcessor;
 
     println!("Starting batch video processing...\n");
 
-    let config = BatchConfig::default();
+    let config = parse_batch_config(args).context(ExitStatus::Usage)?;
+
     println!("Batch Configuration:");
     println!("  Input directory: {:?}", config.input_dir);
     println!("  Output directory: {:?}", config.output_dir);
     println!("  Supported extensions: {:?}", config.video_extensions);
+    if !config.audio_extensions.is_empty() {
+        println!("  Audio extensions: {:?}", config.audio_extensions);
+    }
+    if config.start_time.is_some() || config.end_time.is_some() {
+        println!(
+            "  Time range: {:?} - {:?}",
+            config.start_time, config.end_time
+        );
+    }
     println!("  Max concurrent: {}\n", config.max_concurrent);
 
     let processor = BatchProcessor::new(config);
 
     match processor.process_batch() {
+        // Inputs were found but every one was filtered out; that's a result, not an error
+        Ok(batch_results)
+            if batch_results.total_videos == 0 && !batch_results.skipped.is_empty() =>
+        {
+            println!(
+                "\nNote: all {} input(s) were skipped ({} duplicate(s), {} below min_activity); nothing to process",
+                batch_results.skipped.len(),
+                batch_results.skipped.duplicates.len(),
+                batch_results.skipped.inactive.len()
+            );
+        }
         Ok(batch_results) => {
             println!("\n=== Batch Processing Complete ===");
             println!("Total videos: {}", batch_results.total_videos);
@@ -131,6 +925,18 @@ fn run_batch_processing() -> Result<()> {
 
             println!("\nResults saved to output directory.");
             println!("Check batch_summary.txt for detailed report.");
+
+            if batch_results.total_videos == 0 {
+                return Err(ExitStatus::NoInputs.into());
+            }
+            if batch_results.failed > 0 {
+                return Err(anyhow::anyhow!(
+                    "{} of {} videos failed",
+                    batch_results.failed,
+                    batch_results.total_videos
+                )
+                .context(ExitStatus::PartialFailure));
+            }
         }
         Err(e) => {
             eprintln!("Batch processing failed: {}", e);
@@ -141,14 +947,88 @@ fn run_batch_processing() -> Result<()> {
     Ok(())
 }
 
+fn run_watch(args: &[String]) -> Result<()> {
+    let mut config = batch_processor::BatchConfig::default();
+    (config.start_time, config.end_time) = parse_time_range(args).context(ExitStatus::Usage)?;
+    if let Some(path) = flag_value(args, "--taxonomy") {
+        config.taxonomy =
+            Some(taxonomy::Taxonomy::load(Path::new(path)).context(ExitStatus::Usage)?);
+    }
+
+    watcher::run_watch(config)
+}
+
+fn run_benchmark(args: &[String]) -> Result<()> {
+    let mut config = benchmark::BenchConfig::default();
+    if let Some(backend) = flag_value(args, "--backend") {
+        config.backend = backend.to_string();
+    }
+    config.model_path = flag_value(args, "--model").map(PathBuf::from);
+    config.frames_dir = flag_value(args, "--frames").map(PathBuf::from);
+    if let Some(iterations) = flag_value(args, "--iterations") {
+        config.iterations = iterations
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid --iterations: {}", iterations))
+            .context(ExitStatus::Usage)?;
+    }
+    if let Some(warmup) = flag_value(args, "--warmup") {
+        config.warmup = warmup
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid --warmup: {}", warmup))
+            .context(ExitStatus::Usage)?;
+    }
+
+    let report = benchmark::run_benchmark(&config)?;
+    println!("{}", report.summary());
+
+    if let Some(json_path) = flag_value(args, "--json") {
+        std::fs::write(json_path, serde_json::to_string_pretty(&report)?)?;
+        println!("Wrote {}", json_path);
+    }
+    Ok(())
+}
+
+fn run_results_diff(before_path: &Path, after_path: &Path) -> Result<()> {
+    let diff = results_diff::diff_results(
+        results_loader::stream_results(before_path)?,
+        results_loader::stream_results(after_path)?,
+        0.05,
+    )?;
+    println!("{}", diff.summary());
+    println!(
+        "\n{} of {} frames changed",
+        diff.frames.len(),
+        diff.before_frames
+    );
+    Ok(())
+}
+
+fn run_evaluation(results_path: &Path, ground_truth_path: &Path) -> Result<()> {
+    let results = results_loader::stream_results(results_path)?.map(|r| {
+        r.map(|r| frame_analyzer::FrameResult {
+            timestamp: r.timestamp,
+            objects: r.video_objects,
+            quality: r.quality,
+            interpolated: r.interpolated,
+        })
+    });
+    let ground_truth = ground_truth::load_ground_truth(ground_truth_path)?;
+
+    let report = evaluation::evaluate(results, &ground_truth, 0.5)?;
+    println!("{}", report.summary());
+    Ok(())
+}
+
 fn show_batch_config() {
     println!("\nBatch Processing Configuration:");
     println!("  Create 'input_videos/' directory and place your video files there");
     println!("  Supported formats: MP4, AVI, MOV, MKV, WMV, FLV");
     println!("  Results will be saved to 'output_results/' directory");
     println!("  Each video gets its own subdirectory with:");
-    println!("    - frames/ (extracted frames)");
-    println!("    - audio.aac (extracted audio)");
+    println!("    - frames/ (extracted frames, kept when save_frames is enabled)");
+    println!("    - audio.aac (extracted audio, kept when save_audio is enabled;");
+    println!("      .mp3/.opus/.ogg if ffmpeg has no AAC encoder)");
     println!("    - results.json (analysis results)");
     println!("  batch_summary.txt contains overall statistics");
+    println!("  batch_summary.json has the same summary, with failures by category");
 }
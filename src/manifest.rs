@@ -0,0 +1,61 @@
+use anyhow::Result;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+// Videos that have been processed successfully into an output directory, stored as a
+// JSON array of paths
+#[derive(Debug)]
+pub struct Manifest {
+    path: PathBuf,
+    processed: BTreeSet<PathBuf>,
+    // fsync after every update (see BatchConfig::durable_writes)
+    durable: bool,
+}
+
+impl Manifest {
+    pub fn load(output_dir: &Path) -> Result<Self> {
+        let path = output_dir.join(MANIFEST_FILE);
+        let processed = if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            serde_json::from_str(&content)
+                .map_err(|e| anyhow::anyhow!("Failed to parse manifest {:?}: {}", path, e))?
+        } else {
+            BTreeSet::new()
+        };
+        Ok(Self {
+            path,
+            processed,
+            durable: false,
+        })
+    }
+
+    pub fn set_durable(&mut self, durable: bool) {
+        self.durable = durable;
+    }
+
+    pub fn contains(&self, video_path: &Path) -> bool {
+        self.processed.contains(&Self::key(video_path))
+    }
+
+    pub fn record(&mut self, video_path: &Path) -> Result<()> {
+        if self.processed.insert(Self::key(video_path)) {
+            let mut file = fs::File::create(&self.path)?;
+            file.write_all(serde_json::to_string_pretty(&self.processed)?.as_bytes())?;
+            if self.durable {
+                file.sync_all()?;
+            }
+        }
+        Ok(())
+    }
+
+    // The same file can be reached as ./input_videos/a.mp4 or input_videos/a.mp4
+    fn key(video_path: &Path) -> PathBuf {
+        video_path
+            .canonicalize()
+            .unwrap_or_else(|_| video_path.to_path_buf())
+    }
+}
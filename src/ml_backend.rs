@@ -2,12 +2,213 @@
 #[cfg(feature = "onnx")]
 pub struct ONNXBackend {
     session: Option<ort::Session>,
+    tensor_dump: Option<TensorDumpConfig>,
+    // Frames dumped so far, per frames directory (one per video)
+    dumped_frames: std::sync::Mutex<std::collections::HashMap<std::path::PathBuf, usize>>,
+    // NCHW input tensor storage, reused across frames instead of reallocated each time.
+    // A frame takes it out for its whole run, so concurrent frames never wait on the lock;
+    // they allocate their own buffer instead.
+    input_buffer: std::sync::Mutex<Vec<f32>>,
+    // Label per output class index, e.g. from coco.names; empty means class_{index}
+    class_names: Vec<String>,
+}
+
+// Debug-only dump of raw output tensors, used while working out postprocessing. The
+// first `max_frames` frames of each video are dumped, into a subdirectory of `output_dir`
+// named after the directory holding the video's frames.
+#[cfg(feature = "onnx")]
+#[derive(Debug, Clone)]
+pub struct TensorDumpConfig {
+    pub output_dir: std::path::PathBuf,
+    pub max_frames: usize,
+    pub sample_values: usize,
+}
+
+#[cfg(feature = "onnx")]
+impl Default for TensorDumpConfig {
+    fn default() -> Self {
+        Self {
+            output_dir: std::path::PathBuf::from("tensor_dumps"),
+            max_frames: 3,
+            sample_values: 32,
+        }
+    }
+}
+
+// Aspect-preserving fit of a frame into a square model input: the frame is scaled by
+// `scale` and centered, leaving `pad_x` / `pad_y` pixels of padding on the left / top
+#[cfg(feature = "onnx")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Letterbox {
+    pub scale: f32,
+    pub pad_x: f32,
+    pub pad_y: f32,
+}
+
+#[cfg(feature = "onnx")]
+impl Letterbox {
+    pub fn new(width: u32, height: u32, input_size: u32) -> Self {
+        let scale = (input_size as f32 / width as f32).min(input_size as f32 / height as f32);
+        let (scaled_width, scaled_height) = Self::scaled_size(scale, width, height);
+        Self {
+            scale,
+            // Whole pixels, so the resized frame lands on the grid it was copied to
+            pad_x: ((input_size - scaled_width) / 2) as f32,
+            pad_y: ((input_size - scaled_height) / 2) as f32,
+        }
+    }
+
+    fn scaled_size(scale: f32, width: u32, height: u32) -> (u32, u32) {
+        (
+            ((width as f32 * scale).round() as u32).max(1),
+            ((height as f32 * scale).round() as u32).max(1),
+        )
+    }
+
+    // Frame [x1, y1, x2, y2] -> model input coordinates
+    pub fn map(&self, bbox: [f32; 4]) -> [f32; 4] {
+        [
+            bbox[0] * self.scale + self.pad_x,
+            bbox[1] * self.scale + self.pad_y,
+            bbox[2] * self.scale + self.pad_x,
+            bbox[3] * self.scale + self.pad_y,
+        ]
+    }
+
+    // Model input [x1, y1, x2, y2] -> frame coordinates; the exact inverse of `map`
+    pub fn unmap(&self, bbox: [f32; 4]) -> [f32; 4] {
+        [
+            (bbox[0] - self.pad_x) / self.scale,
+            (bbox[1] - self.pad_y) / self.scale,
+            (bbox[2] - self.pad_x) / self.scale,
+            (bbox[3] - self.pad_y) / self.scale,
+        ]
+    }
 }
 
 #[cfg(feature = "onnx")]
 impl ONNXBackend {
+    const INPUT_SIZE: u32 = 640;
+    // Padding gray used by YOLO-style letterboxing
+    const PAD_VALUE: f32 = 114.0 / 255.0;
+
     pub fn new() -> Self {
-        Self { session: None }
+        Self {
+            session: None,
+            tensor_dump: None,
+            dumped_frames: std::sync::Mutex::new(std::collections::HashMap::new()),
+            input_buffer: std::sync::Mutex::new(Vec::new()),
+            class_names: Vec::new(),
+        }
+    }
+
+    pub fn with_tensor_dump(mut self, config: TensorDumpConfig) -> Self {
+        self.tensor_dump = Some(config);
+        self
+    }
+
+    pub fn with_class_names(mut self, class_names: Vec<String>) -> Self {
+        self.class_names = class_names;
+        self
+    }
+
+    fn class_label(&self, class_index: usize) -> String {
+        self.class_names
+            .get(class_index)
+            .cloned()
+            .unwrap_or_else(|| format!("class_{}", class_index))
+    }
+
+    // Letterboxes the frame and writes the normalized CHW planes into `buffer`. The
+    // HWC -> CHW split walks plain row slices with no per-pixel indexing math, so the
+    // compiler can vectorize the normalization. The returned Letterbox maps model
+    // outputs back onto the frame.
+    pub(crate) fn preprocess_into<'a>(
+        rgb_img: &image::RgbImage,
+        buffer: &'a mut Vec<f32>,
+    ) -> Result<(ndarray::ArrayView4<'a, f32>, Letterbox)> {
+        let size = Self::INPUT_SIZE as usize;
+        let (width, height) = rgb_img.dimensions();
+        let letterbox = Letterbox::new(width, height, Self::INPUT_SIZE);
+        let (scaled_width, scaled_height) = Letterbox::scaled_size(letterbox.scale, width, height);
+        let resized = image::imageops::resize(
+            rgb_img,
+            scaled_width,
+            scaled_height,
+            image::imageops::FilterType::Triangle,
+        );
+
+        let plane = size * size;
+        buffer.clear();
+        buffer.resize(3 * plane, Self::PAD_VALUE);
+        let (red, rest) = buffer.split_at_mut(plane);
+        let (green, blue) = rest.split_at_mut(plane);
+        let (pad_x, pad_y) = (letterbox.pad_x as usize, letterbox.pad_y as usize);
+        for (y, row) in resized
+            .as_raw()
+            .chunks_exact(3 * scaled_width as usize)
+            .enumerate()
+        {
+            let offset = (y + pad_y) * size + pad_x;
+            for (x, pixel) in row.chunks_exact(3).enumerate() {
+                red[offset + x] = pixel[0] as f32 * (1.0 / 255.0);
+                green[offset + x] = pixel[1] as f32 * (1.0 / 255.0);
+                blue[offset + x] = pixel[2] as f32 * (1.0 / 255.0);
+            }
+        }
+
+        let input = ndarray::ArrayView4::from_shape((1, 3, size, size), buffer.as_slice())?;
+        Ok((input, letterbox))
+    }
+
+    // Writes the outputs of the inference process_frame already ran, so dumping doesn't
+    // run the model a second time
+    fn dump_tensors(
+        &self,
+        outputs: &ort::SessionOutputs,
+        frame_path: &Path,
+        config: &TensorDumpConfig,
+    ) -> Result<()> {
+        let mut frames_dir = frame_path.parent().unwrap_or(Path::new(""));
+        // 16-bit frames are analyzed from an 8-bit copy one level down
+        if frames_dir.ends_with(crate::video_processor::ANALYSIS_DIR) {
+            frames_dir = frames_dir.parent().unwrap_or(frames_dir);
+        }
+        {
+            let mut dumped_frames = self.dumped_frames.lock().unwrap();
+            let dumped = dumped_frames.entry(frames_dir.to_path_buf()).or_insert(0);
+            if *dumped >= config.max_frames {
+                return Ok(());
+            }
+            *dumped += 1;
+        }
+
+        let dump_dir = match frames_dir.parent().and_then(Path::file_name) {
+            Some(video_dir) => config.output_dir.join(video_dir),
+            None => config.output_dir.clone(),
+        };
+        std::fs::create_dir_all(&dump_dir)?;
+        let frame_stem = frame_path
+            .file_stem()
+            .ok_or_else(|| anyhow::anyhow!("Invalid frame path: {:?}", frame_path))?;
+        let mut dump_name = frame_stem.to_os_string();
+        dump_name.push(".tensor.json");
+
+        let mut tensors = Vec::new();
+        for (name, value) in outputs.iter() {
+            let tensor = value.try_extract_tensor::<f32>()?;
+            let sample: Vec<f32> = tensor.iter().take(config.sample_values).copied().collect();
+            tensors.push(serde_json::json!({
+                "name": name,
+                "dims": tensor.shape(),
+                "len": tensor.len(),
+                "sample": sample,
+            }));
+        }
+        let file = std::fs::File::create(dump_dir.join(dump_name))?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), &tensors)?;
+
+        Ok(())
     }
 }
 
@@ -30,7 +231,7 @@ impl MLBackend for ONNXBackend {
     }
 
     fn process_frame(&self, frame_path: &Path, timestamp: f64) -> Result<FrameAnalysis> {
-        let _session = self
+        let session = self
             .session
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("Model not loaded"))?;
@@ -42,17 +243,35 @@ impl MLBackend for ONNXBackend {
         // Convert to RGB if needed
         let rgb_img = img.to_rgb8();
 
+        let mut buffer = std::mem::take(&mut *self.input_buffer.lock().unwrap());
+        let (input, letterbox) = Self::preprocess_into(&rgb_img, &mut buffer)?;
+        let outputs = session.run(ort::inputs![input]?)?;
+
+        if let Some(config) = &self.tensor_dump {
+            self.dump_tensors(&outputs, frame_path, config)?;
+        }
+        drop(outputs);
+        *self.input_buffer.lock().unwrap() = buffer;
+
         // For now, return mock detections
-        // In a real implementation, you would:
-        // 1. Preprocess the image (resize, normalize)
-        // 2. Convert to tensor format
-        // 3. Run inference with session.run()
-        // 4. Post-process the results
+        // In a real implementation, you would decode `outputs` into boxes here
+
+        // Model outputs are in letterboxed input space; unmap them onto the frame and
+        // clip whatever falls in the padding
+        let to_frame = |bbox: [f32; 4]| {
+            let [x1, y1, x2, y2] = letterbox.unmap(bbox);
+            [
+                x1.clamp(0.0, width as f32),
+                y1.clamp(0.0, height as f32),
+                x2.clamp(0.0, width as f32),
+                y2.clamp(0.0, height as f32),
+            ]
+        };
 
         let detections = vec![DetectionResult {
-            label: format!("onnx_detection_{}x{}", width, height),
+            label: self.class_label(0),
             confidence: 0.88,
-            bbox: [90.0, 60.0, 190.0, 160.0],
+            bbox: to_frame([90.0, 60.0, 190.0, 160.0]),
         }];
 
         Ok(FrameAnalysis {
@@ -66,6 +285,105 @@ impl MLBackend for ONNXBackend {
     }
 }
 
+// Reads a names file with one class label per line, in class index order (the format of
+// YOLO's coco.names). Blank lines are kept so later indices don't shift.
+pub fn load_class_names(path: &Path) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read class names {:?}: {}", path, e))?;
+    let mut names: Vec<String> = content
+        .lines()
+        .map(|line| line.trim().to_string())
+        .collect();
+    // A trailing newline shouldn't add an unnamed class
+    while names.last().is_some_and(|name| name.is_empty()) {
+        names.pop();
+    }
+    if names.is_empty() {
+        return Err(anyhow::anyhow!("Class names file {:?} is empty", path));
+    }
+    Ok(names)
+}
+
+// A detection the templated mock returns for every frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectionTemplate {
+    pub label: String,
+    pub confidence: f32,
+    pub bbox: [f32; 4],
+}
+
+impl DetectionTemplate {
+    pub fn new(label: &str, confidence: f32, bbox: [f32; 4]) -> Self {
+        Self {
+            label: label.to_string(),
+            confidence,
+            bbox,
+        }
+    }
+}
+
+// Mock backend that returns fixed detections, so postprocessing (thresholds, NMS,
+// calibration, tracking) can be driven through specific scenarios without a model: a
+// below-threshold detection, several classes, overlapping boxes. MockMLBackend's output
+// is fixed; use this where the detections matter. Needs no model file.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateMLBackend {
+    templates: Vec<DetectionTemplate>,
+    // Confidences move by up to this much, varying smoothly with the timestamp
+    jitter: f32,
+}
+
+impl TemplateMLBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_templates(mut self, templates: Vec<DetectionTemplate>) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    pub fn with_detection(mut self, label: &str, confidence: f32, bbox: [f32; 4]) -> Self {
+        self.templates
+            .push(DetectionTemplate::new(label, confidence, bbox));
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f32) -> Self {
+        self.jitter = jitter;
+        self
+    }
+}
+
+impl MLBackend for TemplateMLBackend {
+    fn load_model(&mut self, _model_path: Option<&Path>) -> Result<()> {
+        Ok(())
+    }
+
+    // Deterministic: the same timestamp always yields the same confidences
+    fn process_frame(&self, _frame_path: &Path, timestamp: f64) -> Result<FrameAnalysis> {
+        let offset = self.jitter * timestamp.sin() as f32;
+        let detections = self
+            .templates
+            .iter()
+            .map(|template| DetectionResult {
+                label: template.label.clone(),
+                confidence: (template.confidence + offset).clamp(0.0, 1.0),
+                bbox: template.bbox,
+            })
+            .collect();
+
+        Ok(FrameAnalysis {
+            timestamp,
+            detections,
+        })
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "Template Mock Backend"
+    }
+}
+
 // Candle Backend (alternative to ONNX)
 #[cfg(feature = "candle")]
 pub struct CandleBackend {
@@ -130,20 +448,137 @@ impl MLBackend for CandleBackend {
 
 // Update the factory function to include Candle
 pub fn create_ml_backend(backend_type: &str) -> Result<Box<dyn MLBackend>> {
+    match try_create_ml_backend(backend_type, &[], None) {
+        Ok(backend) => Ok(backend),
+        Err(_) => {
+            println!(
+                "Warning: Unknown ML backend '{}', falling back to mock",
+                backend_type
+            );
+            Ok(Box::new(MockMLBackend::new()))
+        }
+    }
+}
+
+// Like create_ml_backend, but fails for unknown backends or ones not compiled in.
+// `class_names` (see load_class_names) label the classes of backends that only output
+// class indices; empty leaves them as class_{index}. With `tensor_dump_dir`, the onnx
+// backend also writes the raw output tensors of each video's first frames there (see
+// TensorDumpConfig).
+#[cfg_attr(not(feature = "onnx"), allow(unused_variables))]
+pub fn try_create_ml_backend(
+    backend_type: &str,
+    class_names: &[String],
+    tensor_dump_dir: Option<&Path>,
+) -> Result<Box<dyn MLBackend>> {
     match backend_type.to_lowercase().as_str() {
         "mock" => Ok(Box::new(MockMLBackend::new())),
         #[cfg(feature = "pytorch")]
         "pytorch" => Ok(Box::new(PyTorchBackend::new())),
         #[cfg(feature = "onnx")]
-        "onnx" => Ok(Box::new(ONNXBackend::new())),
+        "onnx" => {
+            let mut backend = ONNXBackend::new().with_class_names(class_names.to_vec());
+            if let Some(dir) = tensor_dump_dir {
+                backend = backend.with_tensor_dump(TensorDumpConfig {
+                    output_dir: dir.to_path_buf(),
+                    ..TensorDumpConfig::default()
+                });
+            }
+            Ok(Box::new(backend))
+        }
         #[cfg(feature = "candle")]
         "candle" => Ok(Box::new(CandleBackend::new())),
-        _ => {
-            println!(
-                "Warning: Unknown ML backend '{}', falling back to mock",
-                backend_type
-            );
-            Ok(Box::new(MockMLBackend::new()))
+        _ => Err(anyhow::anyhow!(
+            "ML backend '{}' is unknown or not enabled in this build",
+            backend_type
+        )),
+    }
+}
+
+#[cfg(all(test, feature = "onnx"))]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: [f32; 4], expected: [f32; 4]) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-3, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn template_backend_returns_its_detections_with_bounded_jitter() {
+        let backend = TemplateMLBackend::new()
+            .with_detection("person", 0.95, [0.0, 0.0, 10.0, 10.0])
+            .with_detection("dog", 0.2, [5.0, 5.0, 20.0, 20.0])
+            .with_jitter(0.1);
+        let frame = Path::new("unused.png");
+
+        let analysis = backend.process_frame(frame, 1.5).unwrap();
+        assert_eq!(analysis.timestamp, 1.5);
+        let labels: Vec<&str> = analysis
+            .detections
+            .iter()
+            .map(|d| d.label.as_str())
+            .collect();
+        assert_eq!(labels, ["person", "dog"]);
+        assert_eq!(analysis.detections[1].bbox, [5.0, 5.0, 20.0, 20.0]);
+        for (detection, base) in analysis.detections.iter().zip([0.95, 0.2]) {
+            assert!((detection.confidence - base).abs() <= 0.1 + 1e-6);
+            assert!((0.0..=1.0).contains(&detection.confidence));
+        }
+
+        // Same timestamp, same confidences
+        let confidences = |analysis: &FrameAnalysis| -> Vec<f32> {
+            analysis.detections.iter().map(|d| d.confidence).collect()
+        };
+        let again = backend.process_frame(frame, 1.5).unwrap();
+        assert_eq!(confidences(&analysis), confidences(&again));
+        // No jitter returns the templates verbatim
+        let fixed = TemplateMLBackend::new()
+            .with_templates(vec![DetectionTemplate::new(
+                "cat",
+                0.4,
+                [1.0, 2.0, 3.0, 4.0],
+            )])
+            .process_frame(frame, 3.0)
+            .unwrap();
+        assert_eq!(fixed.detections[0].confidence, 0.4);
+    }
+
+    #[test]
+    fn class_indices_map_to_names_in_file_order() {
+        let backend =
+            ONNXBackend::new().with_class_names(vec!["person".to_string(), "bicycle".to_string()]);
+        assert_eq!(backend.class_label(0), "person");
+        assert_eq!(backend.class_label(1), "bicycle");
+        assert_eq!(backend.class_label(7), "class_7");
+    }
+
+    #[test]
+    fn letterbox_pads_the_short_side() {
+        let landscape = Letterbox::new(1920, 1080, 640);
+        assert_eq!((landscape.pad_x, landscape.pad_y), (0.0, 140.0));
+        // The frame's corners land on the edges of the padded area
+        assert_close(
+            landscape.map([0.0, 0.0, 1920.0, 1080.0]),
+            [0.0, 140.0, 640.0, 500.0],
+        );
+
+        let portrait = Letterbox::new(480, 640, 640);
+        assert_eq!((portrait.pad_x, portrait.pad_y), (80.0, 0.0));
+    }
+
+    #[test]
+    fn letterbox_unmap_inverts_map() {
+        for (width, height) in [(1920, 1080), (1080, 1920), (640, 640), (333, 97)] {
+            let letterbox = Letterbox::new(width, height, 640);
+            let bbox = [
+                width as f32 * 0.1,
+                height as f32 * 0.25,
+                width as f32 * 0.9,
+                height as f32 * 0.75,
+            ];
+            assert_close(letterbox.unmap(letterbox.map(bbox)), bbox);
         }
     }
 }
@@ -0,0 +1,1124 @@
+use crate::frame_analyzer::DetectedObject;
+use crate::ml_backend::load_class_names;
+use crate::synchronizer::{format_results, SynchronizedResult};
+use crate::taxonomy::Taxonomy;
+use anyhow::Result;
+use chrono::SecondsFormat;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+// Writes one video's results into its output directory. Register custom writers with
+// OutputRegistry::register and select them by name through BatchConfig::output_formats.
+pub trait OutputWriter: Send + Sync {
+    fn write(&self, dir: &Path, results: &[SynchronizedResult]) -> Result<()>;
+
+    // Called by the batch with what it knows about the video; writers that can use it
+    // override this
+    fn write_video(
+        &self,
+        dir: &Path,
+        results: &[SynchronizedResult],
+        _video: &VideoInfo,
+    ) -> Result<()> {
+        self.write(dir, results)
+    }
+
+    // What the format keeps of the results (see simplified_data). Writers that don't say
+    // are assumed to keep everything.
+    fn format_capabilities(&self) -> FormatCapabilities {
+        FormatCapabilities::LOSSLESS
+    }
+
+    // What writing `results` leaves out or simplifies, as the batch warns about it;
+    // writers that drop data their capabilities can't describe add it here
+    fn simplified(&self, results: &[SynchronizedResult]) -> Vec<String> {
+        simplified_data(self.format_capabilities(), results)
+    }
+}
+
+// The parts of SynchronizedResult an output format can represent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatCapabilities {
+    pub detections: bool,
+    // Frames, and unmatched transcript entries, that have no detections
+    pub empty_frames: bool,
+    pub audio_text: bool,
+    pub quality: bool,
+    pub datetimes: bool,
+    // raw_confidence and class_id
+    pub detection_details: bool,
+    pub interpolated_flag: bool,
+    // Boxes, confidences and timestamps as stored rather than rounded
+    pub full_precision: bool,
+    // Boxes as [x1, y1, x2, y2] pixels rather than in another layout (see BboxFormat)
+    pub pixel_boxes: bool,
+}
+
+impl FormatCapabilities {
+    pub const LOSSLESS: Self = Self {
+        detections: true,
+        empty_frames: true,
+        audio_text: true,
+        quality: true,
+        datetimes: true,
+        detection_details: true,
+        interpolated_flag: true,
+        full_precision: true,
+        pixel_boxes: true,
+    };
+}
+
+// What of `results` a format with `capabilities` drops, with how many results or
+// detections it affects, e.g. "audio text (12)"; empty when the conversion loses nothing
+// for this data. Rounding and box layouts are what a format is picked for, so they're
+// described by its capabilities but not reported here.
+pub fn simplified_data(
+    capabilities: FormatCapabilities,
+    results: &[SynchronizedResult],
+) -> Vec<String> {
+    let results_with = |f: fn(&SynchronizedResult) -> bool| results.iter().filter(|r| f(r)).count();
+    let objects: Vec<&DetectedObject> = results.iter().flat_map(|r| &r.video_objects).collect();
+    let mut checks = vec![
+        (capabilities.detections, objects.len(), "detections"),
+        (
+            capabilities.empty_frames,
+            results_with(|r| r.video_objects.is_empty()),
+            "results without detections",
+        ),
+        (
+            capabilities.audio_text,
+            results_with(|r| r.audio_text.is_some()),
+            "audio text",
+        ),
+        (
+            capabilities.quality,
+            results_with(|r| r.quality.is_some()),
+            "quality metrics",
+        ),
+        (
+            capabilities.datetimes,
+            results_with(|r| r.datetime.is_some()),
+            "datetimes",
+        ),
+    ];
+    // Details of detections that aren't written at all don't need a mention of their own
+    if capabilities.detections {
+        checks.extend([
+            (
+                capabilities.detection_details,
+                objects
+                    .iter()
+                    .filter(|o| o.raw_confidence.is_some() || o.class_id.is_some())
+                    .count(),
+                "raw confidences and class ids",
+            ),
+            (
+                capabilities.interpolated_flag,
+                objects.iter().filter(|o| o.interpolated).count(),
+                "interpolated flags",
+            ),
+        ]);
+    }
+    checks
+        .into_iter()
+        .filter(|(kept, present, _)| !kept && *present > 0)
+        .map(|(_, present, what)| format!("{} ({})", what, present))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VideoInfo {
+    // Processed duration in seconds
+    pub duration: Option<f64>,
+    // Size of the frame boxes are reported on (the crop with BboxCoordinates::CropLocal)
+    pub frame_size: Option<(u32, u32)>,
+}
+
+// How boxes are written to results.json
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BboxFormat {
+    // [x1, y1, x2, y2] in pixels, as stored
+    #[default]
+    Xyxy,
+    // [x, y, width, height] in pixels
+    Xywh,
+    // Xyxy / Xywh divided by the frame width and height, so 0.0 - 1.0
+    XyxyNormalized,
+    XywhNormalized,
+}
+
+impl FromStr for BboxFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "xyxy" => Ok(Self::Xyxy),
+            "xywh" => Ok(Self::Xywh),
+            "xyxyn" => Ok(Self::XyxyNormalized),
+            "xywhn" => Ok(Self::XywhNormalized),
+            _ => Err(anyhow::anyhow!("Invalid bbox format: {}", value)),
+        }
+    }
+}
+
+impl BboxFormat {
+    // As accepted by from_str, and recorded in results.json
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Xyxy => "xyxy",
+            Self::Xywh => "xywh",
+            Self::XyxyNormalized => "xyxyn",
+            Self::XywhNormalized => "xywhn",
+        }
+    }
+
+    pub fn is_normalized(self) -> bool {
+        matches!(self, Self::XyxyNormalized | Self::XywhNormalized)
+    }
+
+    // Converts a stored [x1, y1, x2, y2] pixel box; normalized formats need the frame size
+    pub fn convert(self, bbox: [f32; 4], frame_size: Option<(u32, u32)>) -> Result<[f32; 4]> {
+        let normalized = |bbox| {
+            frame_size
+                .map(|(width, height)| normalize(bbox, width, height))
+                .ok_or_else(|| anyhow::anyhow!("Normalized bbox formats need the frame size"))
+        };
+        match self {
+            Self::Xyxy => Ok(bbox),
+            Self::Xywh => Ok(xyxy_to_xywh(bbox)),
+            Self::XyxyNormalized => normalized(bbox),
+            Self::XywhNormalized => normalized(xyxy_to_xywh(bbox)),
+        }
+    }
+
+    // The inverse of convert, back to [x1, y1, x2, y2] in pixels
+    pub fn to_xyxy(self, bbox: [f32; 4], frame_size: Option<(u32, u32)>) -> Result<[f32; 4]> {
+        let denormalized = |bbox| {
+            frame_size
+                .map(|(width, height)| denormalize(bbox, width, height))
+                .ok_or_else(|| anyhow::anyhow!("Normalized bbox formats need the frame size"))
+        };
+        match self {
+            Self::Xyxy => Ok(bbox),
+            Self::Xywh => Ok(xywh_to_xyxy(bbox)),
+            Self::XyxyNormalized => denormalized(bbox),
+            Self::XywhNormalized => denormalized(bbox).map(xywh_to_xyxy),
+        }
+    }
+}
+
+pub fn xyxy_to_xywh([x1, y1, x2, y2]: [f32; 4]) -> [f32; 4] {
+    [x1, y1, x2 - x1, y2 - y1]
+}
+
+pub fn xywh_to_xyxy([x, y, width, height]: [f32; 4]) -> [f32; 4] {
+    [x, y, x + width, y + height]
+}
+
+// Works for both layouts: x values and widths scale by the width, y values and heights
+// by the height
+pub fn normalize([a, b, c, d]: [f32; 4], width: u32, height: u32) -> [f32; 4] {
+    let (width, height) = (width.max(1) as f32, height.max(1) as f32);
+    [a / width, b / height, c / width, d / height]
+}
+
+fn denormalize([a, b, c, d]: [f32; 4], width: u32, height: u32) -> [f32; 4] {
+    let (width, height) = (width.max(1) as f32, height.max(1) as f32);
+    [a * width, b * height, c * width, d * height]
+}
+
+#[derive(Default)]
+pub struct OutputRegistry {
+    writers: HashMap<String, Box<dyn OutputWriter>>,
+}
+
+impl OutputRegistry {
+    // json, csv, srt and txt
+    pub fn with_builtins(json: JsonWriter) -> Self {
+        let mut registry = Self::default();
+        registry.register("json", json);
+        registry.register("csv", CsvWriter);
+        registry.register("srt", SrtWriter::default());
+        registry.register("txt", TxtWriter::default());
+        registry
+    }
+
+    // Replaces any writer already registered under `name`
+    pub fn register(&mut self, name: &str, writer: impl OutputWriter + 'static) {
+        self.writers.insert(name.to_string(), Box::new(writer));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn OutputWriter> {
+        self.writers.get(name).map(|writer| writer.as_ref())
+    }
+}
+
+// results.json, the format results_loader reads back
+pub struct JsonWriter {
+    pub taxonomy: Option<Taxonomy>,
+    // Decimal places written; f32 confidences otherwise print widening noise
+    pub confidence_decimals: usize,
+    pub timestamp_decimals: usize,
+    // Frames below this sharpness are flagged blurry
+    pub min_sharpness: Option<f32>,
+    // Any other than Xyxy is recorded with each result, along with the frame size for
+    // the normalized ones, so results_loader can convert the boxes back
+    pub bbox_format: BboxFormat,
+}
+
+impl OutputWriter for JsonWriter {
+    fn write(&self, output_dir: &Path, results: &[SynchronizedResult]) -> Result<()> {
+        self.write_video(output_dir, results, &VideoInfo::default())
+    }
+
+    fn write_video(
+        &self,
+        output_dir: &Path,
+        results: &[SynchronizedResult],
+        video: &VideoInfo,
+    ) -> Result<()> {
+        let mut stream = self.stream(output_dir, video)?;
+        for result in results {
+            stream.push(result)?;
+        }
+        stream.finish()
+    }
+
+    // Rounded to confidence_decimals and timestamp_decimals, boxes in bbox_format
+    fn format_capabilities(&self) -> FormatCapabilities {
+        FormatCapabilities {
+            full_precision: false,
+            pixel_boxes: self.bbox_format == BboxFormat::Xyxy,
+            ..FormatCapabilities::LOSSLESS
+        }
+    }
+}
+
+impl JsonWriter {
+    // Starts results.json in `output_dir`; results are written as they're pushed
+    pub fn stream(&self, output_dir: &Path, video: &VideoInfo) -> Result<JsonResultStream<'_>> {
+        use std::io::Write;
+
+        fs::create_dir_all(output_dir)?;
+        let mut file = std::io::BufWriter::new(fs::File::create(output_dir.join("results.json"))?);
+        writeln!(file, "[")?;
+        Ok(JsonResultStream {
+            writer: self,
+            file,
+            frame_size: video.frame_size,
+            written: 0,
+        })
+    }
+
+    // One array element, without the separator that follows it
+    fn write_result(
+        &self,
+        out: &mut impl std::io::Write,
+        result: &SynchronizedResult,
+        frame_size: Option<(u32, u32)>,
+    ) -> Result<()> {
+        // Simple JSON serialization (in production, use serde)
+        writeln!(out, "  {{")?;
+        writeln!(
+            out,
+            "    \"timestamp\": {},",
+            round_to(result.timestamp, self.timestamp_decimals)
+        )?;
+        if result.unmatched_audio {
+            writeln!(out, "    \"unmatched_audio\": true,")?;
+        }
+        if result.interpolated {
+            writeln!(out, "    \"interpolated\": true,")?;
+        }
+        if self.bbox_format != BboxFormat::Xyxy {
+            writeln!(out, "    \"bbox_format\": \"{}\",", self.bbox_format.name())?;
+            if let Some((width, height)) = frame_size.filter(|_| self.bbox_format.is_normalized()) {
+                writeln!(out, "    \"frame_size\": [{}, {}],", width, height)?;
+            }
+        }
+        if let Some(datetime) = result.datetime {
+            writeln!(
+                out,
+                "    \"datetime\": \"{}\",",
+                datetime.to_rfc3339_opts(SecondsFormat::Millis, true)
+            )?;
+        }
+        if let Some(quality) = &result.quality {
+            let blurry = matches!(self.min_sharpness, Some(min) if quality.sharpness < min);
+            writeln!(
+                out,
+                "    \"quality\": {{\"sharpness\": {}, \"brightness\": {}, \"blurry\": {}}},",
+                round_to(quality.sharpness as f64, 2),
+                round_to(quality.brightness as f64, 2),
+                blurry
+            )?;
+        }
+        writeln!(out, "    \"video_objects\": [")?;
+        for (j, object) in result.video_objects.iter().enumerate() {
+            let label = &object.label;
+            let bbox = self.bbox_format.convert(object.bbox, frame_size)?;
+            writeln!(out, "      {{")?;
+            writeln!(out, "        \"label\": {},", json_string(label))?;
+            if let Some(taxonomy) = &self.taxonomy {
+                writeln!(
+                    out,
+                    "        \"supercategory\": {},",
+                    json_string(taxonomy.supercategory(label))
+                )?;
+            }
+            writeln!(
+                out,
+                "        \"confidence\": {},",
+                round_to(object.confidence as f64, self.confidence_decimals)
+            )?;
+            if let Some(raw_confidence) = object.raw_confidence {
+                writeln!(
+                    out,
+                    "        \"raw_confidence\": {},",
+                    round_to(raw_confidence as f64, self.confidence_decimals)
+                )?;
+            }
+            writeln!(out, "        \"frame_index\": {},", object.frame_index)?;
+            writeln!(out, "        \"model\": {},", json_string(&object.model))?;
+            if !object.extraction_method.is_empty() {
+                writeln!(
+                    out,
+                    "        \"extraction_method\": {},",
+                    json_string(&object.extraction_method)
+                )?;
+            }
+            if let Some(class_id) = object.class_id {
+                writeln!(out, "        \"class_id\": {},", class_id)?;
+            }
+            if object.interpolated {
+                writeln!(out, "        \"interpolated\": true,")?;
+            }
+            writeln!(
+                out,
+                "        \"bbox\": [{}, {}, {}, {}]",
+                bbox[0], bbox[1], bbox[2], bbox[3]
+            )?;
+            writeln!(
+                out,
+                "      }}{}",
+                if j < result.video_objects.len() - 1 {
+                    ","
+                } else {
+                    ""
+                }
+            )?;
+        }
+        writeln!(out, "    ],")?;
+        if let Some(text) = &result.audio_text {
+            writeln!(out, "    \"audio_text\": {}", json_string(text))?;
+        } else {
+            writeln!(out, "    \"audio_text\": null")?;
+        }
+        write!(out, "  }}")?;
+        Ok(())
+    }
+}
+
+// results.json written incrementally, so the whole result list never has to be held
+pub struct JsonResultStream<'a> {
+    writer: &'a JsonWriter,
+    file: std::io::BufWriter<fs::File>,
+    frame_size: Option<(u32, u32)>,
+    written: usize,
+}
+
+impl JsonResultStream<'_> {
+    pub fn push(&mut self, result: &SynchronizedResult) -> Result<()> {
+        use std::io::Write;
+
+        if self.written > 0 {
+            writeln!(self.file, ",")?;
+        }
+        self.writer
+            .write_result(&mut self.file, result, self.frame_size)?;
+        self.written += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        use std::io::Write;
+
+        if self.written > 0 {
+            writeln!(self.file)?;
+        }
+        writeln!(self.file, "]")?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+// results.csv with one row per detection; frames without detections have no rows
+pub struct CsvWriter;
+
+impl OutputWriter for CsvWriter {
+    fn write(&self, dir: &Path, results: &[SynchronizedResult]) -> Result<()> {
+        use std::io::Write;
+
+        fs::create_dir_all(dir)?;
+        let mut file = std::io::BufWriter::new(fs::File::create(dir.join("results.csv"))?);
+        writeln!(
+            file,
+            "timestamp,label,confidence,x1,y1,x2,y2,frame_index,model,interpolated"
+        )?;
+        for result in results {
+            for object in &result.video_objects {
+                let bbox = object.bbox;
+                writeln!(
+                    file,
+                    "{},{},{},{},{},{},{},{},{},{}",
+                    result.timestamp,
+                    csv_field(&object.label),
+                    object.confidence,
+                    bbox[0],
+                    bbox[1],
+                    bbox[2],
+                    bbox[3],
+                    object.frame_index,
+                    csv_field(&object.model),
+                    object.interpolated
+                )?;
+            }
+        }
+        file.flush()?;
+        Ok(())
+    }
+
+    // Detections only: nothing about frames without them, nor the transcript
+    fn format_capabilities(&self) -> FormatCapabilities {
+        FormatCapabilities {
+            empty_frames: false,
+            audio_text: false,
+            quality: false,
+            datetimes: false,
+            detection_details: false,
+            ..FormatCapabilities::LOSSLESS
+        }
+    }
+}
+
+// How long a subtitle cue lasts. Whatever the choice, a cue is cut off where the next
+// one starts, so cues never overlap.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CueTiming {
+    // Until the next frame; the last cue runs to the end of the video when its duration
+    // is known
+    #[default]
+    ExtendToNext,
+    // This many seconds from the cue's first frame
+    Fixed(f64),
+    // Until the next frame (or the end of the video), but at least `min` and at most
+    // `max` seconds
+    Clamp {
+        min: f64,
+        max: f64,
+    },
+}
+
+impl FromStr for CueTiming {
+    type Err = anyhow::Error;
+
+    // "next", "fixed:<seconds>" or "clamp:<min>,<max>", with positive seconds
+    fn from_str(value: &str) -> Result<Self> {
+        let invalid = || anyhow::anyhow!("Invalid cue timing: {}", value);
+        let seconds = |s: &str| {
+            s.parse::<f64>()
+                .ok()
+                .filter(|seconds| seconds.is_finite() && *seconds > 0.0)
+                .ok_or_else(invalid)
+        };
+        match value.split_once(':') {
+            None if value == "next" => Ok(Self::ExtendToNext),
+            Some(("fixed", duration)) => Ok(Self::Fixed(seconds(duration)?)),
+            Some(("clamp", range)) => {
+                let (min, max) = range.split_once(',').ok_or_else(invalid)?;
+                let (min, max) = (seconds(min)?, seconds(max)?);
+                if min > max {
+                    return Err(invalid());
+                }
+                Ok(Self::Clamp { min, max })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+// How long the last cue lasts when no later frame or known video duration ends it
+const LAST_CUE_SECONDS: f64 = 2.0;
+
+// results.srt from the transcript text. Consecutive frames with the same text form one
+// cue, timed by `timing`.
+#[derive(Default)]
+pub struct SrtWriter {
+    pub timing: CueTiming,
+}
+
+impl SrtWriter {
+    // (start, end, text) in time order, never overlapping
+    fn cues<'a>(
+        &self,
+        results: &'a [SynchronizedResult],
+        duration: Option<f64>,
+    ) -> Vec<(f64, f64, &'a str)> {
+        // Unmatched transcript entries come after the frames
+        let mut sorted: Vec<&SynchronizedResult> = results.iter().collect();
+        sorted.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+
+        // (start, timestamp of the frame after the cue's last one, text)
+        let mut spans: Vec<(f64, Option<f64>, &str)> = Vec::new();
+        for (i, result) in sorted.iter().enumerate() {
+            let Some(text) = result.audio_text.as_deref() else {
+                continue;
+            };
+            let next = sorted.get(i + 1).map(|next| next.timestamp);
+            match spans.last_mut() {
+                Some(last)
+                    if last.2 == text && last.1.is_some_and(|end| end >= result.timestamp) =>
+                {
+                    last.1 = next
+                }
+                _ => spans.push((result.timestamp, next, text)),
+            }
+        }
+
+        let mut cues: Vec<(f64, f64, &str)> = spans
+            .into_iter()
+            .map(|(start, next, text)| {
+                let until_next = next
+                    .or(duration)
+                    .filter(|&end| end > start)
+                    .unwrap_or(start + LAST_CUE_SECONDS);
+                let end = match self.timing {
+                    CueTiming::ExtendToNext => until_next,
+                    CueTiming::Fixed(seconds) => start + seconds,
+                    CueTiming::Clamp { min, max } => until_next.max(start + min).min(start + max),
+                };
+                (start, end, text)
+            })
+            .collect();
+        for i in 1..cues.len() {
+            let next_start = cues[i].0;
+            let cue = &mut cues[i - 1];
+            cue.1 = cue.1.min(next_start).max(cue.0);
+        }
+        cues
+    }
+}
+
+impl OutputWriter for SrtWriter {
+    fn write(&self, dir: &Path, results: &[SynchronizedResult]) -> Result<()> {
+        self.write_video(dir, results, &VideoInfo::default())
+    }
+
+    fn write_video(
+        &self,
+        dir: &Path,
+        results: &[SynchronizedResult],
+        video: &VideoInfo,
+    ) -> Result<()> {
+        let cues = self.cues(results, video.duration);
+
+        let mut srt = String::new();
+        for (i, (start, end, text)) in cues.iter().enumerate() {
+            srt.push_str(&format!(
+                "{}\n{} --> {}\n{}\n\n",
+                i + 1,
+                srt_time(*start),
+                srt_time(*end),
+                text
+            ));
+        }
+
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join("results.srt"), srt)?;
+        Ok(())
+    }
+
+    // The transcript only
+    fn format_capabilities(&self) -> FormatCapabilities {
+        FormatCapabilities {
+            detections: false,
+            quality: false,
+            datetimes: false,
+            ..FormatCapabilities::LOSSLESS
+        }
+    }
+}
+
+// results.txt, the same report print_results shows for a single video
+#[derive(Default)]
+pub struct TxtWriter {
+    pub display_aliases: HashMap<String, String>,
+}
+
+impl OutputWriter for TxtWriter {
+    fn write(&self, dir: &Path, results: &[SynchronizedResult]) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        fs::write(
+            dir.join("results.txt"),
+            format_results(results, &self.display_aliases),
+        )?;
+        Ok(())
+    }
+
+    // A report for reading: boxes to one decimal, confidences to hundredths of a percent
+    fn format_capabilities(&self) -> FormatCapabilities {
+        FormatCapabilities {
+            quality: false,
+            datetimes: false,
+            detection_details: false,
+            interpolated_flag: false,
+            full_precision: false,
+            ..FormatCapabilities::LOSSLESS
+        }
+    }
+}
+
+// Label -> class id for YOLO label files
+#[derive(Debug, Clone, Default)]
+pub struct YoloClasses {
+    names: Vec<String>,
+    ids: HashMap<String, usize>,
+}
+
+impl YoloClasses {
+    pub fn from_names(names: Vec<String>) -> Self {
+        let ids = names
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| !name.is_empty())
+            .map(|(id, name)| (name.clone(), id))
+            .collect();
+        Self { names, ids }
+    }
+
+    // A names file as used by YOLO datasets: one class name per line, the line number
+    // (from 0) being its id; the same file the model's class indices are labeled from
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(Self::from_names(load_class_names(path)?))
+    }
+
+    pub fn id(&self, label: &str) -> Option<usize> {
+        self.ids.get(label).copied()
+    }
+}
+
+// frames/frame_NNNN[_tT].txt in YOLO label format next to every saved frame: one
+// `class_id cx cy w h` line per detection, normalized to the frame size. Frames without
+// detections get an empty file, as YOLO expects for negatives. Labels missing from the
+// class list and interpolated boxes are left out. Needs save_frames; with a crop, boxes
+// must be crop-local to line up with the saved frames.
+pub struct YoloWriter {
+    pub classes: YoloClasses,
+}
+
+impl OutputWriter for YoloWriter {
+    fn write(&self, dir: &Path, results: &[SynchronizedResult]) -> Result<()> {
+        let frames_dir = dir.join("frames");
+        if !frames_dir.is_dir() {
+            return Err(anyhow::anyhow!(
+                "YOLO labels are written next to saved frames; enable save_frames"
+            ));
+        }
+
+        let mut objects_by_frame: BTreeMap<usize, Vec<(usize, [f32; 4])>> = BTreeMap::new();
+        for object in results.iter().flat_map(|r| &r.video_objects) {
+            if object.interpolated {
+                continue;
+            }
+            if let Some(class_id) = self.classes.id(&object.label) {
+                objects_by_frame
+                    .entry(object.frame_index)
+                    .or_default()
+                    .push((class_id, object.bbox));
+            }
+        }
+
+        for entry in fs::read_dir(&frames_dir)? {
+            let frame_path = entry?.path();
+            if frame_path.extension().map_or(true, |ext| ext != "png") {
+                continue;
+            }
+            let Some(index) = frame_path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.strip_prefix("frame_"))
+                .and_then(|name| name.split('_').next())
+                .and_then(|index| index.parse::<usize>().ok())
+            else {
+                continue;
+            };
+
+            let (width, height) = image::image_dimensions(&frame_path)?;
+            let mut labels = String::new();
+            for (class_id, bbox) in objects_by_frame.get(&index).into_iter().flatten() {
+                labels.push_str(&yolo_line(*class_id, *bbox, width, height));
+                labels.push('\n');
+            }
+            fs::write(frame_path.with_extension("txt"), labels)?;
+        }
+
+        fs::write(
+            frames_dir.join("classes.txt"),
+            self.classes.names.join("\n") + "\n",
+        )?;
+        Ok(())
+    }
+
+    // Normalized boxes next to the frames; interpolated boxes are left out
+    fn format_capabilities(&self) -> FormatCapabilities {
+        FormatCapabilities {
+            audio_text: false,
+            quality: false,
+            datetimes: false,
+            detection_details: false,
+            interpolated_flag: false,
+            full_precision: false,
+            pixel_boxes: false,
+            ..FormatCapabilities::LOSSLESS
+        }
+    }
+
+    // Detections of classes missing from the names file have no class id to write
+    fn simplified(&self, results: &[SynchronizedResult]) -> Vec<String> {
+        let mut simplified = simplified_data(self.format_capabilities(), results);
+        let unlisted: Vec<&DetectedObject> = results
+            .iter()
+            .flat_map(|r| &r.video_objects)
+            .filter(|o| !o.interpolated && self.classes.id(&o.label).is_none())
+            .collect();
+        if !unlisted.is_empty() {
+            let mut labels: Vec<&str> = unlisted.iter().map(|o| o.label.as_str()).collect();
+            labels.sort_unstable();
+            labels.dedup();
+            simplified.push(format!(
+                "detections of classes not in the names file: {} ({})",
+                labels.join(", "),
+                unlisted.len()
+            ));
+        }
+        simplified
+    }
+}
+
+// `class_id cx cy w h` with the box clamped to the frame and normalized to 0.0 - 1.0
+pub fn yolo_line(class_id: usize, bbox: [f32; 4], width: u32, height: u32) -> String {
+    let (width, height) = (width as f32, height as f32);
+    let x1 = bbox[0].clamp(0.0, width);
+    let y1 = bbox[1].clamp(0.0, height);
+    let x2 = bbox[2].clamp(0.0, width);
+    let y2 = bbox[3].clamp(0.0, height);
+    format!(
+        "{} {:.6} {:.6} {:.6} {:.6}",
+        class_id,
+        (x1 + x2) / 2.0 / width,
+        (y1 + y2) / 2.0 / height,
+        (x2 - x1).max(0.0) / width,
+        (y2 - y1).max(0.0) / height
+    )
+}
+
+pub fn round_to(value: f64, decimals: usize) -> f64 {
+    let scale = 10f64.powi(decimals as i32);
+    (value * scale).round() / scale
+}
+
+// `value` as a quoted JSON string, escaped
+fn json_string(value: &str) -> String {
+    serde_json::to_string(value).expect("strings always serialize")
+}
+
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// HH:MM:SS,mmm
+fn srt_time(seconds: f64) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(timestamp: f64, objects: Vec<DetectedObject>) -> SynchronizedResult {
+        SynchronizedResult {
+            timestamp,
+            video_objects: objects,
+            audio_text: None,
+            quality: None,
+            datetime: None,
+            unmatched_audio: false,
+            interpolated: false,
+        }
+    }
+
+    fn json_writer() -> JsonWriter {
+        JsonWriter {
+            taxonomy: None,
+            confidence_decimals: 3,
+            timestamp_decimals: 3,
+            min_sharpness: None,
+            bbox_format: BboxFormat::Xyxy,
+        }
+    }
+
+    fn decimals(value: &serde_json::Value) -> usize {
+        let text = value.to_string();
+        text.split_once('.')
+            .map_or(0, |(_, fraction)| fraction.len())
+    }
+
+    #[test]
+    fn json_confidence_and_timestamp_are_rounded() {
+        let object = DetectedObject::from(("person".to_string(), 0.88f32, [1.0, 2.0, 3.0, 4.0]));
+        let mut out = Vec::new();
+        json_writer()
+            .write_result(&mut out, &result(1.0 / 3.0, vec![object]), None)
+            .unwrap();
+
+        let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        let confidence = &value["video_objects"][0]["confidence"];
+        assert_eq!(confidence.as_f64(), Some(0.88));
+        assert!(decimals(confidence) <= 3, "{}", confidence);
+        assert!(decimals(&value["timestamp"]) <= 3, "{}", value["timestamp"]);
+    }
+
+    #[test]
+    fn json_strings_are_escaped() {
+        let label = "say \"hi\"\\n\ttab\nline";
+        let object = DetectedObject::from((label.to_string(), 0.5f32, [1.0, 2.0, 3.0, 4.0]));
+        let mut with_text = result(0.0, vec![object]);
+        with_text.audio_text = Some("quote \" backslash \\ newline\n".to_string());
+        let mut out = Vec::new();
+        json_writer()
+            .write_result(&mut out, &with_text, None)
+            .unwrap();
+
+        let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(value["video_objects"][0]["label"], label);
+        assert_eq!(
+            value["audio_text"].as_str(),
+            with_text.audio_text.as_deref()
+        );
+    }
+
+    #[test]
+    fn last_srt_cue_has_a_length() {
+        let mut first = result(0.0, Vec::new());
+        first.audio_text = Some("hello".to_string());
+        let mut last = result(1.0, Vec::new());
+        last.audio_text = Some("bye".to_string());
+
+        let cues = SrtWriter::default().cues(&[first, last], None);
+
+        assert_eq!(
+            cues,
+            [(0.0, 1.0, "hello"), (1.0, 1.0 + LAST_CUE_SECONDS, "bye")]
+        );
+    }
+
+    #[test]
+    fn json_records_detection_provenance() {
+        let object = DetectedObject {
+            frame_index: 7,
+            model: "mock".to_string(),
+            extraction_method: "interval".to_string(),
+            ..DetectedObject::from(("person".to_string(), 0.5f32, [1.0, 2.0, 3.0, 4.0]))
+        };
+        let mut out = Vec::new();
+        json_writer()
+            .write_result(&mut out, &result(0.0, vec![object]), None)
+            .unwrap();
+
+        let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        let object = &value["video_objects"][0];
+        assert_eq!(object["frame_index"], 7);
+        assert_eq!(object["model"], "mock");
+        assert_eq!(object["extraction_method"], "interval");
+    }
+
+    #[test]
+    fn yolo_line_is_normalized_to_the_frame() {
+        assert_eq!(
+            yolo_line(2, [160.0, 120.0, 480.0, 360.0], 640, 480),
+            "2 0.500000 0.500000 0.500000 0.500000"
+        );
+        assert_eq!(
+            yolo_line(0, [0.0, 0.0, 64.0, 12.0], 640, 480),
+            "0 0.050000 0.012500 0.100000 0.025000"
+        );
+        // Parts of the box outside the frame are cut off before normalizing
+        assert_eq!(
+            yolo_line(1, [-10.0, 0.0, 110.0, 48.0], 100, 50),
+            "1 0.500000 0.480000 1.000000 0.960000"
+        );
+    }
+
+    #[test]
+    fn yolo_class_ids_follow_the_names_order() {
+        let classes = YoloClasses::from_names(vec!["person".to_string(), "car".to_string()]);
+        assert_eq!(classes.id("person"), Some(0));
+        assert_eq!(classes.id("car"), Some(1));
+        assert_eq!(classes.id("dog"), None);
+    }
+
+    #[test]
+    fn yolo_reports_detections_of_classes_it_has_no_id_for() {
+        let writer = YoloWriter {
+            classes: YoloClasses::from_names(vec!["person".to_string()]),
+        };
+        let detection = |label: &str| DetectedObject::from((label.to_string(), 0.9f32, [0.0; 4]));
+        let results = [result(
+            0.0,
+            vec![detection("person"), detection("dog"), detection("dog")],
+        )];
+        assert_eq!(
+            writer.simplified(&results),
+            ["detections of classes not in the names file: dog (2)"]
+        );
+    }
+
+    #[test]
+    fn rounding_and_box_layouts_are_declared_but_not_reported() {
+        let object = DetectedObject::from(("person".to_string(), 0.123456f32, [1.25; 4]));
+        let results = [result(0.123456, vec![object])];
+        assert!(TxtWriter::default().simplified(&results).is_empty());
+        assert!(json_writer().simplified(&results).is_empty());
+
+        let capabilities = json_writer().format_capabilities();
+        assert!(!capabilities.full_precision);
+        assert!(capabilities.pixel_boxes);
+        let xywh = JsonWriter {
+            bbox_format: BboxFormat::Xywh,
+            ..json_writer()
+        };
+        assert!(!xywh.format_capabilities().pixel_boxes);
+    }
+
+    #[test]
+    fn names_file_lines_are_class_indices() {
+        let dir = crate::test_fixtures::scratch_dir("class-names");
+        let path = dir.join("coco.names");
+        fs::write(&path, "person\n\ncar\n").unwrap();
+
+        assert_eq!(load_class_names(&path).unwrap(), ["person", "", "car"]);
+        // The blank line keeps car at index 2
+        let classes = YoloClasses::load(&path).unwrap();
+        assert_eq!(classes.id("person"), Some(0));
+        assert_eq!(classes.id("car"), Some(2));
+        assert_eq!(classes.id(""), None);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    fn srt_results(entries: &[(f64, &str)]) -> Vec<SynchronizedResult> {
+        entries
+            .iter()
+            .map(|(timestamp, text)| SynchronizedResult {
+                audio_text: Some(text.to_string()),
+                ..result(*timestamp, Vec::new())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cues_extend_to_the_next_one_and_the_last_to_the_video_end() {
+        let results = srt_results(&[(0.0, "a"), (3.0, "b")]);
+        let cues = SrtWriter::default().cues(&results, Some(5.0));
+        assert_eq!(cues, [(0.0, 3.0, "a"), (3.0, 5.0, "b")]);
+    }
+
+    #[test]
+    fn clamped_cues_stay_between_min_and_max_without_overlapping() {
+        let results = srt_results(&[(0.0, "a"), (0.5, "b"), (10.0, "c")]);
+        let writer = SrtWriter {
+            timing: CueTiming::Clamp { min: 1.0, max: 4.0 },
+        };
+        let cues = writer.cues(&results, Some(20.0));
+        // "a" would be lengthened to a second, but is cut off where "b" starts; "b" and
+        // "c" are cut to four seconds
+        assert_eq!(cues, [(0.0, 0.5, "a"), (0.5, 4.5, "b"), (10.0, 14.0, "c")]);
+    }
+
+    #[test]
+    fn fixed_cues_last_their_length() {
+        let results = srt_results(&[(0.0, "a"), (5.0, "b")]);
+        let writer = SrtWriter {
+            timing: CueTiming::Fixed(2.0),
+        };
+        assert_eq!(
+            writer.cues(&results, None),
+            [(0.0, 2.0, "a"), (5.0, 7.0, "b")]
+        );
+    }
+
+    #[test]
+    fn cue_timing_needs_positive_finite_seconds() {
+        assert_eq!(
+            "next".parse::<CueTiming>().unwrap(),
+            CueTiming::ExtendToNext
+        );
+        assert_eq!(
+            "fixed:1.5".parse::<CueTiming>().unwrap(),
+            CueTiming::Fixed(1.5)
+        );
+        assert_eq!(
+            "clamp:0.5,3".parse::<CueTiming>().unwrap(),
+            CueTiming::Clamp { min: 0.5, max: 3.0 }
+        );
+        for invalid in [
+            "fixed:-1",
+            "fixed:0",
+            "fixed:NaN",
+            "fixed:inf",
+            "clamp:-1,2",
+            "clamp:1,NaN",
+            "clamp:3,1",
+            "clamp:1",
+            "last",
+        ] {
+            assert!(invalid.parse::<CueTiming>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn every_bbox_format_converts_back_to_xyxy() {
+        let bbox = [64.0, 48.0, 320.0, 240.0];
+        let frame_size = Some((640, 480));
+        for (format, expected) in [
+            (BboxFormat::Xyxy, [64.0, 48.0, 320.0, 240.0]),
+            (BboxFormat::Xywh, [64.0, 48.0, 256.0, 192.0]),
+            (BboxFormat::XyxyNormalized, [0.1, 0.1, 0.5, 0.5]),
+            (BboxFormat::XywhNormalized, [0.1, 0.1, 0.4, 0.4]),
+        ] {
+            let converted = format.convert(bbox, frame_size).unwrap();
+            for (a, e) in converted.iter().zip(expected) {
+                assert!((a - e).abs() < 1e-5, "{:?}: {:?}", format, converted);
+            }
+            let back = format.to_xyxy(converted, frame_size).unwrap();
+            for (a, e) in back.iter().zip(bbox) {
+                assert!((a - e).abs() < 1e-3, "{:?}: {:?}", format, back);
+            }
+            assert_eq!(format.name().parse::<BboxFormat>().unwrap(), format);
+        }
+        assert!(BboxFormat::XyxyNormalized.convert(bbox, None).is_err());
+        assert!(BboxFormat::XywhNormalized.to_xyxy(bbox, None).is_err());
+    }
+}
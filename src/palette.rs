@@ -0,0 +1,46 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+pub const PALETTE_FILE: &str = "palette.json";
+
+// Stable color for a label, for drawing boxes and for external visualizers. Uses
+// FNV-1a rather than std's hasher, whose output may change between Rust releases, so
+// the same label gets the same color on every run and machine.
+pub fn label_color(label: &str) -> [u8; 3] {
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in label.bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+
+    // Hash picks the hue; fixed saturation/value keeps every color readable on video
+    let hue = (hash % 360) as f32;
+    hsv_to_rgb(hue, 0.75, 0.95)
+}
+
+// label -> color for every label given, as a JSON object of [r, g, b] arrays
+pub fn write_palette<'a>(path: &Path, labels: impl IntoIterator<Item = &'a str>) -> Result<()> {
+    let palette: BTreeMap<&str, [u8; 3]> = labels
+        .into_iter()
+        .map(|label| (label, label_color(label)))
+        .collect();
+    std::fs::write(path, serde_json::to_string_pretty(&palette)?)?;
+    Ok(())
+}
+
+fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> [u8; 3] {
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let m = value - chroma;
+    let (r, g, b) = match (hue / 60.0) as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let channel = |c: f32| ((c + m) * 255.0).round() as u8;
+    [channel(r), channel(g), channel(b)]
+}
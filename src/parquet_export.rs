@@ -0,0 +1,71 @@
+use crate::batch_processor::VideoProcessingResult;
+use anyhow::Result;
+use arrow::array::{ArrayRef, Float32Array, Float64Array, StringArray};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use std::path::Path;
+use std::sync::Arc;
+
+// One row per detection; new columns are only ever appended so readers stay valid.
+//   video       utf8     input file path
+//   timestamp   float64  seconds from the first video frame
+//   label       utf8
+//   confidence  float32
+//   x1, y1, x2, y2  float32  box corners in full-frame pixels
+// Interpolated detections are not real model output and are left out.
+fn schema() -> Schema {
+    Schema::new(vec![
+        Field::new("video", DataType::Utf8, false),
+        Field::new("timestamp", DataType::Float64, false),
+        Field::new("label", DataType::Utf8, false),
+        Field::new("confidence", DataType::Float32, false),
+        Field::new("x1", DataType::Float32, false),
+        Field::new("y1", DataType::Float32, false),
+        Field::new("x2", DataType::Float32, false),
+        Field::new("y2", DataType::Float32, false),
+    ])
+}
+
+pub fn export_batch(path: &Path, results: &[VideoProcessingResult]) -> Result<()> {
+    let mut videos = Vec::new();
+    let mut timestamps = Vec::new();
+    let mut labels = Vec::new();
+    let mut confidences = Vec::new();
+    let mut corners: [Vec<f32>; 4] = Default::default();
+
+    for result in results.iter().filter(|r| r.success) {
+        let video = result.video_path.to_string_lossy();
+        for frame in &result.synchronized_results {
+            for object in frame.video_objects.iter().filter(|o| !o.interpolated) {
+                videos.push(video.to_string());
+                timestamps.push(frame.timestamp);
+                labels.push(object.label.clone());
+                confidences.push(object.confidence);
+                for (column, value) in corners.iter_mut().zip(object.bbox) {
+                    column.push(value);
+                }
+            }
+        }
+    }
+
+    let [x1, y1, x2, y2] = corners;
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from(videos)),
+        Arc::new(Float64Array::from(timestamps)),
+        Arc::new(StringArray::from(labels)),
+        Arc::new(Float32Array::from(confidences)),
+        Arc::new(Float32Array::from(x1)),
+        Arc::new(Float32Array::from(y1)),
+        Arc::new(Float32Array::from(x2)),
+        Arc::new(Float32Array::from(y2)),
+    ];
+    let schema = Arc::new(schema());
+    let batch = RecordBatch::try_new(schema.clone(), columns)?;
+
+    let file = std::fs::File::create(path)?;
+    let mut writer = ArrowWriter::try_new(file, schema, None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
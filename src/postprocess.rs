@@ -0,0 +1,743 @@
+use crate::ml_backend::{DetectionResult, FrameAnalysis};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+#[derive(Debug, Clone)]
+pub struct PostprocessConfig {
+    // Applied to model confidences before smoothing and every threshold below
+    pub calibration: ConfidenceCalibration,
+    pub confidence_threshold: f32,
+    // Overrides the global threshold for specific labels
+    pub per_label_thresholds: HashMap<String, f32>,
+    // EMA factor for smoothing confidences along tracks; None disables smoothing
+    pub smoothing_alpha: Option<f32>,
+    pub track_iou_threshold: f32,
+    // Only these labels are reported; None keeps every label
+    pub label_filter: Option<HashSet<String>>,
+    // Suppression of same-label boxes overlapping a more confident one; None disables NMS
+    pub nms: Option<NmsMode>,
+    // Boxes outside these areas are dropped as noise (specks, frame-filling blobs)
+    pub min_box_area: Option<BoxArea>,
+    pub max_box_area: Option<BoxArea>,
+    // Keeps only this many of the most confident detections per frame; None keeps all
+    pub max_detections: Option<usize>,
+}
+
+impl Default for PostprocessConfig {
+    fn default() -> Self {
+        Self {
+            calibration: ConfidenceCalibration::Identity,
+            confidence_threshold: 0.5,
+            per_label_thresholds: HashMap::new(),
+            smoothing_alpha: None,
+            track_iou_threshold: 0.3,
+            label_filter: None,
+            nms: None,
+            min_box_area: None,
+            max_box_area: None,
+            max_detections: None,
+        }
+    }
+}
+
+impl PostprocessConfig {
+    pub fn threshold_for(&self, label: &str) -> f32 {
+        self.per_label_thresholds
+            .get(label)
+            .copied()
+            .unwrap_or(self.confidence_threshold)
+    }
+}
+
+// Maps a model's confidences onto a common scale, so one threshold means the same
+// thing across backends. Every variant is strictly increasing, so calibration never
+// reorders detections.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ConfidenceCalibration {
+    #[default]
+    Identity,
+    // sigmoid(logit(p) / T); T > 1 tempers an overconfident model
+    Temperature(f32),
+    // Linear interpolation through (raw, calibrated) points, with (0, 0) and (1, 1)
+    // implied at the ends; both coordinates strictly increasing
+    Piecewise(Vec<(f32, f32)>),
+}
+
+impl ConfidenceCalibration {
+    pub fn is_identity(&self) -> bool {
+        *self == ConfidenceCalibration::Identity
+    }
+
+    pub fn apply(&self, confidence: f32) -> f32 {
+        match self {
+            ConfidenceCalibration::Identity => confidence,
+            ConfidenceCalibration::Temperature(t) => sigmoid(logit(confidence) / t),
+            ConfidenceCalibration::Piecewise(points) => {
+                interpolate(points.iter().copied(), confidence)
+            }
+        }
+    }
+}
+
+impl FromStr for ConfidenceCalibration {
+    type Err = anyhow::Error;
+
+    // "temperature:<T>" or "piecewise:<raw>=<calibrated>,..."
+    fn from_str(value: &str) -> anyhow::Result<Self> {
+        let invalid = || anyhow::anyhow!("Invalid calibration: {}", value);
+        match value.split_once(':') {
+            Some(("temperature", t)) => {
+                let t: f32 = t.trim().parse().map_err(|_| invalid())?;
+                if !(t > 0.0 && t.is_finite()) {
+                    return Err(anyhow::anyhow!("Temperature must be positive: {}", value));
+                }
+                Ok(ConfidenceCalibration::Temperature(t))
+            }
+            Some(("piecewise", points)) => {
+                let points = points
+                    .split(',')
+                    .map(|point| {
+                        let (raw, cal) = point.split_once('=')?;
+                        Some((raw.trim().parse().ok()?, cal.trim().parse().ok()?))
+                    })
+                    .collect::<Option<Vec<(f32, f32)>>>()
+                    .ok_or_else(invalid)?;
+                let increasing = std::iter::once((0.0, 0.0))
+                    .chain(points.iter().copied())
+                    .chain(std::iter::once((1.0, 1.0)))
+                    .collect::<Vec<_>>()
+                    .windows(2)
+                    .all(|w| w[1].0 > w[0].0 && w[1].1 > w[0].1);
+                if !increasing {
+                    return Err(anyhow::anyhow!(
+                        "Calibration points must increase strictly within (0, 1): {}",
+                        value
+                    ));
+                }
+                Ok(ConfidenceCalibration::Piecewise(points))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+// Confidences are clamped away from 0 and 1 so the logit stays finite
+fn logit(p: f32) -> f32 {
+    let p = p.clamp(1e-6, 1.0 - 1e-6);
+    (p / (1.0 - p)).ln()
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+// Piecewise-linear through `points`, with (0, 0) and (1, 1) added at the ends
+fn interpolate(points: impl Iterator<Item = (f32, f32)>, x: f32) -> f32 {
+    let x = x.clamp(0.0, 1.0);
+    let mut previous = (0.0, 0.0);
+    for point in points.chain(std::iter::once((1.0, 1.0))) {
+        if x <= point.0 {
+            let t = (x - previous.0) / (point.0 - previous.0);
+            return previous.1 + t * (point.1 - previous.1);
+        }
+        previous = point;
+    }
+    1.0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NmsMode {
+    // Boxes overlapping a more confident one above this IoU are removed
+    Hard { iou: f32 },
+    // Gaussian Soft-NMS (Bodla et al., 2017): overlapping boxes keep their place but have
+    // their confidence scaled by exp(-iou^2 / sigma), and are dropped only if that takes
+    // them below their confidence threshold. Keeps people standing close together.
+    Soft { sigma: f32 },
+}
+
+// Bound on a box's area, absolute or relative to the frame it was detected in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BoxArea {
+    Pixels(f32),
+    // 0.0 - 1.0 of the frame area
+    FrameFraction(f32),
+}
+
+impl BoxArea {
+    // None for a fraction when the frame size isn't known
+    fn pixels(&self, frame_size: Option<(u32, u32)>) -> Option<f32> {
+        match *self {
+            BoxArea::Pixels(area) => Some(area),
+            BoxArea::FrameFraction(fraction) => {
+                frame_size.map(|(width, height)| fraction * width as f32 * height as f32)
+            }
+        }
+    }
+}
+
+impl FromStr for BoxArea {
+    type Err = anyhow::Error;
+
+    // "<pixels>" or "<percent>%" of the frame area
+    fn from_str(value: &str) -> anyhow::Result<Self> {
+        let invalid = || anyhow::anyhow!("Invalid box area: {}", value);
+        match value.strip_suffix('%') {
+            Some(percent) => Ok(BoxArea::FrameFraction(
+                percent.trim().parse::<f32>().map_err(|_| invalid())? / 100.0,
+            )),
+            None => Ok(BoxArea::Pixels(
+                value.trim().parse().map_err(|_| invalid())?,
+            )),
+        }
+    }
+}
+
+// Intersection over union of two [x1, y1, x2, y2] boxes
+pub fn iou(a: &[f32; 4], b: &[f32; 4]) -> f32 {
+    let ix = (a[2].min(b[2]) - a[0].max(b[0])).max(0.0);
+    let iy = (a[3].min(b[3]) - a[1].max(b[1])).max(0.0);
+    let intersection = ix * iy;
+    let union = (a[2] - a[0]) * (a[3] - a[1]) + (b[2] - b[0]) * (b[3] - b[1]) - intersection;
+    if union <= 0.0 {
+        0.0
+    } else {
+        intersection / union
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DropStage {
+    Confidence,
+    Label,
+    Size,
+    Nms,
+    Cap,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DroppedDetection {
+    pub stage: DropStage,
+    pub label: String,
+    pub confidence: f32,
+    pub bbox: [f32; 4],
+}
+
+// What postprocessing removed from one frame; one line of audit.jsonl
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FrameAudit {
+    pub frame_index: usize,
+    pub timestamp: f64,
+    pub kept: usize,
+    pub dropped_by_confidence: usize,
+    pub dropped_by_label: usize,
+    pub dropped_by_size: usize,
+    pub dropped_by_nms: usize,
+    pub dropped_by_cap: usize,
+    pub dropped: Vec<DroppedDetection>,
+}
+
+impl FrameAudit {
+    fn record<T: AsDetection>(&mut self, stage: DropStage, dropped: Vec<T>) {
+        let count = match stage {
+            DropStage::Confidence => &mut self.dropped_by_confidence,
+            DropStage::Label => &mut self.dropped_by_label,
+            DropStage::Size => &mut self.dropped_by_size,
+            DropStage::Nms => &mut self.dropped_by_nms,
+            DropStage::Cap => &mut self.dropped_by_cap,
+        };
+        *count += dropped.len();
+        self.dropped.extend(dropped.into_iter().map(|d| {
+            let d = d.into_detection();
+            DroppedDetection {
+                stage,
+                label: d.label,
+                confidence: d.confidence,
+                bbox: d.bbox,
+            }
+        }));
+    }
+}
+
+// A detection as the stages below see it. postprocess_frame runs them on Calibrated
+// detections, which keep the model's own confidence alongside the calibrated one.
+pub trait AsDetection {
+    fn detection(&self) -> &DetectionResult;
+    fn detection_mut(&mut self) -> &mut DetectionResult;
+    fn into_detection(self) -> DetectionResult;
+}
+
+impl AsDetection for DetectionResult {
+    fn detection(&self) -> &DetectionResult {
+        self
+    }
+
+    fn detection_mut(&mut self) -> &mut DetectionResult {
+        self
+    }
+
+    fn into_detection(self) -> DetectionResult {
+        self
+    }
+}
+
+struct Calibrated {
+    detection: DetectionResult,
+    raw_confidence: f32,
+}
+
+impl AsDetection for Calibrated {
+    fn detection(&self) -> &DetectionResult {
+        &self.detection
+    }
+
+    fn detection_mut(&mut self) -> &mut DetectionResult {
+        &mut self.detection
+    }
+
+    fn into_detection(self) -> DetectionResult {
+        self.detection
+    }
+}
+
+// Returns (kept, dropped)
+pub fn filter_by_confidence<T: AsDetection>(
+    detections: Vec<T>,
+    config: &PostprocessConfig,
+) -> (Vec<T>, Vec<T>) {
+    detections.into_iter().partition(|d| {
+        let d = d.detection();
+        d.confidence >= config.threshold_for(&d.label)
+    })
+}
+
+pub fn filter_by_label<T: AsDetection>(
+    detections: Vec<T>,
+    config: &PostprocessConfig,
+) -> (Vec<T>, Vec<T>) {
+    match &config.label_filter {
+        Some(labels) => detections
+            .into_iter()
+            .partition(|d| labels.contains(&d.detection().label)),
+        None => (detections, Vec::new()),
+    }
+}
+
+// Clamps boxes to the frame, when its size is known, then drops those outside
+// min_box_area / max_box_area. Boxes are clamped even when no size filter is set.
+pub fn filter_by_size<T: AsDetection>(
+    detections: Vec<T>,
+    config: &PostprocessConfig,
+    frame_size: Option<(u32, u32)>,
+) -> (Vec<T>, Vec<T>) {
+    let min_area = config.min_box_area.and_then(|area| area.pixels(frame_size));
+    let max_area = config.max_box_area.and_then(|area| area.pixels(frame_size));
+
+    detections
+        .into_iter()
+        .map(|mut detection| {
+            if let Some((width, height)) = frame_size {
+                let bbox = &mut detection.detection_mut().bbox;
+                let [x1, y1, x2, y2] = *bbox;
+                let (width, height) = (width as f32, height as f32);
+                *bbox = [
+                    x1.clamp(0.0, width),
+                    y1.clamp(0.0, height),
+                    x2.clamp(0.0, width),
+                    y2.clamp(0.0, height),
+                ];
+            }
+            detection
+        })
+        .partition(|d| {
+            let bbox = d.detection().bbox;
+            let area = (bbox[2] - bbox[0]).max(0.0) * (bbox[3] - bbox[1]).max(0.0);
+            min_area.map_or(true, |min| area >= min) && max_area.map_or(true, |max| area <= max)
+        })
+}
+
+// Greedy per-label NMS; kept detections stay in their original order
+pub fn non_max_suppression<T: AsDetection>(
+    detections: Vec<T>,
+    iou_threshold: f32,
+) -> (Vec<T>, Vec<T>) {
+    let boxes: Vec<&DetectionResult> = detections.iter().map(T::detection).collect();
+    let mut order: Vec<usize> = (0..boxes.len()).collect();
+    order.sort_by(|a, b| boxes[*b].confidence.total_cmp(&boxes[*a].confidence));
+
+    let mut keep = vec![false; boxes.len()];
+    let mut kept_so_far: Vec<usize> = Vec::new();
+    for i in order {
+        let suppressed = kept_so_far.iter().any(|&k| {
+            boxes[k].label == boxes[i].label && iou(&boxes[k].bbox, &boxes[i].bbox) > iou_threshold
+        });
+        if !suppressed {
+            keep[i] = true;
+            kept_so_far.push(i);
+        }
+    }
+
+    let mut kept = Vec::new();
+    let mut dropped = Vec::new();
+    for (detection, keep) in detections.into_iter().zip(keep) {
+        if keep {
+            kept.push(detection);
+        } else {
+            dropped.push(detection);
+        }
+    }
+    (kept, dropped)
+}
+
+// Keeps the `max_detections` most confident, sorted by descending confidence
+pub fn cap_detections<T: AsDetection>(
+    mut detections: Vec<T>,
+    max_detections: usize,
+) -> (Vec<T>, Vec<T>) {
+    if detections.len() <= max_detections {
+        return (detections, Vec::new());
+    }
+    detections.sort_by(|a, b| {
+        b.detection()
+            .confidence
+            .total_cmp(&a.detection().confidence)
+    });
+    let dropped = detections.split_off(max_detections);
+    (detections, dropped)
+}
+
+// Decays confidences as described for NmsMode::Soft, then re-applies the confidence
+// thresholds; kept detections stay in their original order
+pub fn soft_non_max_suppression<T: AsDetection>(
+    mut detections: Vec<T>,
+    sigma: f32,
+    config: &PostprocessConfig,
+) -> (Vec<T>, Vec<T>) {
+    let mut remaining: Vec<usize> = (0..detections.len()).collect();
+    while !remaining.is_empty() {
+        let (position, _) = remaining
+            .iter()
+            .enumerate()
+            .max_by(|a, b| {
+                detections[*a.1]
+                    .detection()
+                    .confidence
+                    .total_cmp(&detections[*b.1].detection().confidence)
+            })
+            .unwrap();
+        let best = detections[remaining.remove(position)].detection().clone();
+        for &i in &remaining {
+            let detection = detections[i].detection_mut();
+            if detection.label == best.label {
+                let overlap = iou(&best.bbox, &detection.bbox);
+                detection.confidence *= (-(overlap * overlap) / sigma).exp();
+            }
+        }
+    }
+    filter_by_confidence(detections, config)
+}
+
+// Calibration, then confidence threshold, then label filter, then box size, then NMS,
+// then the per-frame cap. `analysis` holds the model's confidences; `frame_size` is the
+// size of the frame the boxes are in, if known. Dropped detections are recorded in
+// `audit` when one is given. Also returns the model's confidence for each kept
+// detection when the calibration changed it.
+pub fn postprocess_frame(
+    mut analysis: FrameAnalysis,
+    config: &PostprocessConfig,
+    frame_size: Option<(u32, u32)>,
+    mut audit: Option<&mut FrameAudit>,
+) -> (FrameAnalysis, Vec<Option<f32>>) {
+    let mut record = |stage, dropped: Vec<Calibrated>| {
+        if let Some(audit) = audit.as_deref_mut() {
+            audit.record(stage, dropped);
+        }
+    };
+
+    let calibrated = analysis
+        .detections
+        .into_iter()
+        .map(|detection| Calibrated {
+            raw_confidence: detection.confidence,
+            detection: DetectionResult {
+                confidence: config.calibration.apply(detection.confidence),
+                ..detection
+            },
+        })
+        .collect();
+    let (kept, dropped) = filter_by_confidence(calibrated, config);
+    record(DropStage::Confidence, dropped);
+    let (kept, dropped) = filter_by_label(kept, config);
+    record(DropStage::Label, dropped);
+    let (mut kept, dropped) = filter_by_size(kept, config, frame_size);
+    record(DropStage::Size, dropped);
+    if let Some(mode) = config.nms {
+        let (after_nms, dropped) = match mode {
+            NmsMode::Hard { iou } => non_max_suppression(kept, iou),
+            NmsMode::Soft { sigma } => soft_non_max_suppression(kept, sigma, config),
+        };
+        record(DropStage::Nms, dropped);
+        kept = after_nms;
+    }
+    if let Some(max_detections) = config.max_detections {
+        let (capped, dropped) = cap_detections(kept, max_detections);
+        record(DropStage::Cap, dropped);
+        kept = capped;
+    }
+
+    if let Some(audit) = audit {
+        audit.timestamp = analysis.timestamp;
+        audit.kept = kept.len();
+    }
+    let calibrated = !config.calibration.is_identity();
+    let (detections, raw_confidences) = kept
+        .into_iter()
+        .map(|d| (d.detection, calibrated.then_some(d.raw_confidence)))
+        .unzip();
+    analysis.detections = detections;
+    (analysis, raw_confidences)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detection(bbox: [f32; 4]) -> DetectionResult {
+        DetectionResult {
+            label: "person".to_string(),
+            confidence: 0.9,
+            bbox,
+        }
+    }
+
+    #[test]
+    fn template_backend_drives_thresholds_and_nms() {
+        use crate::ml_backend::{MLBackend, TemplateMLBackend};
+
+        let backend = TemplateMLBackend::new()
+            .with_detection("person", 0.9, [0.0, 0.0, 40.0, 40.0])
+            .with_detection("person", 0.7, [2.0, 2.0, 42.0, 42.0])
+            .with_detection("person", 0.3, [60.0, 0.0, 90.0, 30.0])
+            .with_detection("dog", 0.6, [1.0, 1.0, 41.0, 41.0]);
+        let analysis = backend
+            .process_frame(std::path::Path::new("unused.png"), 0.0)
+            .unwrap();
+        let config = PostprocessConfig {
+            confidence_threshold: 0.5,
+            nms: Some(NmsMode::Hard { iou: 0.5 }),
+            ..Default::default()
+        };
+        let mut audit = FrameAudit::default();
+        let (analysis, _) = postprocess_frame(analysis, &config, None, Some(&mut audit));
+
+        // The faint person falls under the threshold, the overlapping one to NMS; the
+        // dog overlaps too but NMS only compares boxes of the same label
+        let kept: Vec<(&str, f32)> = analysis
+            .detections
+            .iter()
+            .map(|d| (d.label.as_str(), d.confidence))
+            .collect();
+        assert_eq!(kept, [("person", 0.9), ("dog", 0.6)]);
+        assert_eq!(audit.kept, 2);
+        assert_eq!(audit.dropped_by_confidence, 1);
+        assert_eq!(audit.dropped_by_nms, 1);
+    }
+
+    fn labeled(label: &str, confidence: f32) -> DetectionResult {
+        DetectionResult {
+            label: label.to_string(),
+            confidence,
+            ..detection([0.0, 0.0, 10.0, 10.0])
+        }
+    }
+
+    #[test]
+    fn per_label_thresholds_override_the_global_one() {
+        let config = PostprocessConfig {
+            confidence_threshold: 0.5,
+            per_label_thresholds: HashMap::from([
+                ("person".to_string(), 0.3),
+                ("handbag".to_string(), 0.7),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(config.threshold_for("person"), 0.3);
+        assert_eq!(config.threshold_for("handbag"), 0.7);
+
+        let (kept, dropped) = filter_by_confidence(
+            vec![labeled("handbag", 0.6), labeled("person", 0.4)],
+            &config,
+        );
+        assert_eq!(kept, [labeled("person", 0.4)]);
+        assert_eq!(dropped, [labeled("handbag", 0.6)]);
+    }
+
+    #[test]
+    fn a_label_without_an_override_uses_the_global_threshold() {
+        let config = PostprocessConfig {
+            per_label_thresholds: HashMap::from([("handbag".to_string(), 0.7)]),
+            ..Default::default()
+        };
+        assert_eq!(config.threshold_for("dog"), 0.5);
+
+        let (kept, dropped) =
+            filter_by_confidence(vec![labeled("dog", 0.6), labeled("dog", 0.4)], &config);
+        assert_eq!(kept, [labeled("dog", 0.6)]);
+        assert_eq!(dropped, [labeled("dog", 0.4)]);
+    }
+
+    #[test]
+    fn boxes_are_clamped_without_a_size_filter() {
+        let config = PostprocessConfig::default();
+        let (kept, dropped) = filter_by_size(
+            vec![detection([-5.0, 10.0, 120.0, 60.0])],
+            &config,
+            Some((100, 50)),
+        );
+        assert!(dropped.is_empty());
+        assert_eq!(kept[0].bbox, [0.0, 10.0, 100.0, 50.0]);
+    }
+
+    #[test]
+    fn too_small_box_is_dropped_and_in_range_box_survives() {
+        let config = PostprocessConfig {
+            min_box_area: Some(BoxArea::Pixels(100.0)),
+            max_box_area: Some(BoxArea::FrameFraction(0.5)),
+            ..Default::default()
+        };
+        let speck = detection([10.0, 10.0, 14.0, 14.0]);
+        let person = detection([20.0, 5.0, 40.0, 45.0]);
+        let blob = detection([0.0, 0.0, 100.0, 50.0]);
+        let (kept, dropped) = filter_by_size(
+            vec![speck.clone(), person.clone(), blob.clone()],
+            &config,
+            Some((100, 50)),
+        );
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].bbox, person.bbox);
+        assert_eq!(dropped.len(), 2);
+    }
+
+    #[test]
+    fn boxes_are_measured_after_clamping() {
+        // 20x20 box of which only 5x20 is inside the frame
+        let config = PostprocessConfig {
+            min_box_area: Some(BoxArea::Pixels(200.0)),
+            ..Default::default()
+        };
+        let (kept, _) = filter_by_size(
+            vec![detection([95.0, 0.0, 115.0, 20.0])],
+            &config,
+            Some((100, 50)),
+        );
+        assert!(kept.is_empty());
+    }
+
+    #[test]
+    fn crowded_frame_is_capped_to_the_most_confident() {
+        // 500 boxes with distinct confidences, in no particular order
+        let detections = (0..500)
+            .map(|i| DetectionResult {
+                confidence: ((i * 7919) % 500) as f32 / 500.0,
+                ..detection([i as f32, 0.0, i as f32 + 10.0, 10.0])
+            })
+            .collect();
+        let config = PostprocessConfig {
+            max_detections: Some(20),
+            ..Default::default()
+        };
+        let analysis = FrameAnalysis {
+            timestamp: 0.0,
+            detections,
+        };
+
+        let kept = postprocess_frame(analysis, &config, None, None)
+            .0
+            .detections;
+        assert_eq!(kept.len(), 20);
+        let confidences: Vec<f32> = kept.iter().map(|d| d.confidence).collect();
+        let expected: Vec<f32> = (480..500).rev().map(|i| i as f32 / 500.0).collect();
+        assert_eq!(confidences, expected);
+    }
+
+    #[test]
+    fn calibration_curves_are_monotonic() {
+        for calibration in [
+            "temperature:2.5".parse::<ConfidenceCalibration>().unwrap(),
+            "temperature:0.5".parse().unwrap(),
+            "piecewise:0.2=0.5,0.6=0.7".parse().unwrap(),
+        ] {
+            let calibrated: Vec<f32> = (0..=100)
+                .map(|i| calibration.apply(i as f32 / 100.0))
+                .collect();
+            assert!(
+                calibrated.windows(2).all(|w| w[1] > w[0]),
+                "{:?}: {:?}",
+                calibration,
+                calibrated
+            );
+            assert!(calibrated.iter().all(|c| (0.0..=1.0).contains(c)));
+        }
+    }
+
+    #[test]
+    fn thresholds_see_calibrated_confidences_and_raw_ones_are_kept() {
+        let config = PostprocessConfig {
+            calibration: "piecewise:0.2=0.5".parse().unwrap(),
+            confidence_threshold: 0.4,
+            ..Default::default()
+        };
+        let analysis = FrameAnalysis {
+            timestamp: 0.0,
+            detections: vec![
+                DetectionResult {
+                    confidence: 0.2,
+                    ..detection([0.0, 0.0, 10.0, 10.0])
+                },
+                DetectionResult {
+                    confidence: 0.1,
+                    ..detection([20.0, 0.0, 30.0, 10.0])
+                },
+            ],
+        };
+
+        let (analysis, raw) = postprocess_frame(analysis, &config, None, None);
+        assert_eq!(analysis.detections.len(), 1);
+        assert_eq!(analysis.detections[0].confidence, 0.5);
+        assert_eq!(raw, vec![Some(0.2)]);
+    }
+
+    #[test]
+    fn soft_nms_keeps_overlapping_boxes_that_hard_nms_drops() {
+        let config = PostprocessConfig {
+            confidence_threshold: 0.3,
+            ..Default::default()
+        };
+        // Two people standing close: IoU 0.8
+        let front = DetectionResult {
+            confidence: 0.9,
+            ..detection([0.0, 0.0, 100.0, 100.0])
+        };
+        let behind = DetectionResult {
+            confidence: 0.8,
+            ..detection([0.0, 0.0, 100.0, 80.0])
+        };
+
+        let (kept, dropped) = non_max_suppression(vec![front.clone(), behind.clone()], 0.5);
+        assert_eq!((kept.len(), dropped.len()), (1, 1));
+        assert_eq!(kept[0].confidence, 0.9);
+
+        let (kept, dropped) = soft_non_max_suppression(vec![front, behind], 1.0, &config);
+        assert!(dropped.is_empty());
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].confidence, 0.9);
+        let decayed = 0.8 * (-0.8f32 * 0.8).exp();
+        assert!(
+            (kept[1].confidence - decayed).abs() < 1e-5,
+            "{}",
+            kept[1].confidence
+        );
+        assert!(kept[1].confidence >= config.confidence_threshold);
+    }
+}
@@ -0,0 +1,153 @@
+use image::RgbImage;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PreprocessStep {
+    // Gaussian blur with this sigma; knocks down sensor noise in low-light footage
+    Denoise { sigma: f32 },
+    // Stretches luma so this percentage of pixels clips at each end
+    ContrastStretch { clip_percent: f32 },
+    // Output = input^(1/gamma); values above 1 brighten shadows
+    Gamma(f32),
+}
+
+impl FromStr for PreprocessStep {
+    type Err = anyhow::Error;
+
+    // "denoise[:sigma]", "contrast[:clip_percent]" or "gamma:<value>"
+    fn from_str(value: &str) -> anyhow::Result<Self> {
+        let (name, parameter) = match value.split_once(':') {
+            Some((name, parameter)) => (name, Some(parameter)),
+            None => (value, None),
+        };
+        let parameter = |default: Option<f32>| -> anyhow::Result<f32> {
+            match parameter {
+                Some(p) => p
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid preprocessing parameter: {}", value)),
+                None => default
+                    .ok_or_else(|| anyhow::anyhow!("Preprocessing step needs a value: {}", value)),
+            }
+        };
+
+        match name {
+            "denoise" => Ok(Self::Denoise {
+                sigma: parameter(Some(1.0))?,
+            }),
+            "contrast" => Ok(Self::ContrastStretch {
+                clip_percent: parameter(Some(1.0))?,
+            }),
+            "gamma" => Ok(Self::Gamma(parameter(None)?)),
+            _ => Err(anyhow::anyhow!("Unknown preprocessing step: {}", name)),
+        }
+    }
+}
+
+// Runs the steps in the order given
+pub fn apply_preprocessing(mut image: RgbImage, steps: &[PreprocessStep]) -> RgbImage {
+    for step in steps {
+        image = match *step {
+            PreprocessStep::Denoise { sigma } => image::imageops::blur(&image, sigma),
+            PreprocessStep::ContrastStretch { clip_percent } => {
+                stretch_contrast(image, clip_percent)
+            }
+            PreprocessStep::Gamma(gamma) => adjust_gamma(image, gamma),
+        };
+    }
+    image
+}
+
+// One mapping for all three channels, taken from the luma histogram, so hues don't shift
+fn stretch_contrast(mut image: RgbImage, clip_percent: f32) -> RgbImage {
+    let mut histogram = [0usize; 256];
+    for pixel in image.pixels() {
+        let [r, g, b] = pixel.0;
+        let luma = (0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32) as usize;
+        histogram[luma.min(255)] += 1;
+    }
+
+    let total = (image.width() * image.height()) as usize;
+    let clipped = (total as f32 * clip_percent.clamp(0.0, 49.0) / 100.0) as usize;
+    let percentile = |levels: &mut dyn Iterator<Item = usize>| {
+        let mut seen = 0;
+        for level in levels {
+            seen += histogram[level];
+            if seen > clipped {
+                return level;
+            }
+        }
+        0
+    };
+    let low = percentile(&mut (0..256));
+    let high = percentile(&mut (0..256).rev());
+    // Flat images have nothing to stretch
+    if high <= low {
+        return image;
+    }
+
+    let scale = 255.0 / (high - low) as f32;
+    let lut: Vec<u8> = (0..256)
+        .map(|v| ((v as f32 - low as f32) * scale).clamp(0.0, 255.0).round() as u8)
+        .collect();
+    for value in image.iter_mut() {
+        *value = lut[*value as usize];
+    }
+    image
+}
+
+fn adjust_gamma(mut image: RgbImage, gamma: f32) -> RgbImage {
+    if gamma <= 0.0 {
+        return image;
+    }
+    let lut: Vec<u8> = (0..256)
+        .map(|v| ((v as f32 / 255.0).powf(1.0 / gamma) * 255.0).round() as u8)
+        .collect();
+    for value in image.iter_mut() {
+        *value = lut[*value as usize];
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A dim ramp, so contrast stretching and gamma both have something to change
+    fn ramp() -> RgbImage {
+        RgbImage::from_fn(64, 1, |x, _| {
+            let v = 40 + x as u8;
+            image::Rgb([v, v, v])
+        })
+    }
+
+    #[test]
+    fn steps_run_in_the_order_given() {
+        let gamma = PreprocessStep::Gamma(2.0);
+        let contrast = PreprocessStep::ContrastStretch { clip_percent: 0.0 };
+
+        let combined = apply_preprocessing(ramp(), &[gamma, contrast]);
+        let one_by_one = apply_preprocessing(apply_preprocessing(ramp(), &[gamma]), &[contrast]);
+        let reversed = apply_preprocessing(ramp(), &[contrast, gamma]);
+
+        assert_eq!(combined, one_by_one);
+        assert_ne!(combined, reversed);
+    }
+
+    #[test]
+    fn steps_are_parsed_with_defaults() {
+        assert_eq!(
+            "denoise".parse::<PreprocessStep>().unwrap(),
+            PreprocessStep::Denoise { sigma: 1.0 }
+        );
+        assert_eq!(
+            "contrast:2.5".parse::<PreprocessStep>().unwrap(),
+            PreprocessStep::ContrastStretch { clip_percent: 2.5 }
+        );
+        assert_eq!(
+            "gamma:1.8".parse::<PreprocessStep>().unwrap(),
+            PreprocessStep::Gamma(1.8)
+        );
+        assert!("gamma".parse::<PreprocessStep>().is_err());
+        assert!("sharpen".parse::<PreprocessStep>().is_err());
+    }
+}
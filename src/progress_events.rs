@@ -0,0 +1,44 @@
+use serde::Serialize;
+use std::io::Write;
+
+// One line of the JSON-lines progress stream on stderr (ProgressFormat::Jsonl), e.g.
+// {"event":"video_started","video":"a.mp4","index":1,"total":3}
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent<'a> {
+    BatchStarted {
+        total: usize,
+    },
+    VideoStarted {
+        video: &'a str,
+        // 1-based position in the batch
+        index: usize,
+        total: usize,
+    },
+    // `stage` is one of "transcription", "analysis", "synchronization" or "output"
+    StageCompleted {
+        video: &'a str,
+        stage: &'static str,
+    },
+    VideoFinished {
+        video: &'a str,
+        success: bool,
+        frames: usize,
+        processing_secs: f64,
+        error: Option<&'a str>,
+    },
+    BatchFinished {
+        successful: usize,
+        failed: usize,
+        elapsed_secs: f64,
+    },
+}
+
+// Writes the event as a single line; stderr is locked so lines from parallel workers
+// never interleave. Progress reporting shouldn't stop the batch, so errors are ignored.
+pub fn emit(event: &ProgressEvent) {
+    let Ok(line) = serde_json::to_string(event) else {
+        return;
+    };
+    let _ = writeln!(std::io::stderr().lock(), "{}", line);
+}
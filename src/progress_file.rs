@@ -0,0 +1,105 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+pub const PROGRESS_FILE: &str = "progress.json";
+
+#[derive(Debug, Clone, Serialize)]
+struct ProgressSnapshot<'a> {
+    current_video: Option<&'a str>,
+    // 1-based position of the current video
+    index: usize,
+    total: usize,
+    completed: usize,
+    failed: usize,
+    percent: f64,
+    elapsed_secs: f64,
+    // Extrapolated from the average time per finished video; None until one finishes
+    eta_secs: Option<f64>,
+    done: bool,
+}
+
+// Headless counterpart of BatchProgress: the same start/finish points, written to
+// progress.json in the output directory so another process can poll it
+pub struct ProgressFile {
+    path: PathBuf,
+    total: usize,
+    started: Instant,
+    current_video: Option<String>,
+    index: usize,
+    completed: usize,
+    failed: usize,
+}
+
+impl ProgressFile {
+    pub fn new(output_dir: &Path, total_videos: usize, started: Instant) -> Self {
+        let progress = Self {
+            path: output_dir.join(PROGRESS_FILE),
+            total: total_videos,
+            started,
+            current_video: None,
+            index: 0,
+            completed: 0,
+            failed: 0,
+        };
+        progress.update(false);
+        progress
+    }
+
+    pub fn start_video(&mut self, video_name: &str) {
+        self.current_video = Some(video_name.to_string());
+        self.index += 1;
+        self.update(false);
+    }
+
+    pub fn finish_video(&mut self, success: bool) {
+        self.completed += 1;
+        if !success {
+            self.failed += 1;
+        }
+        self.update(false);
+    }
+
+    pub fn finish(&mut self) {
+        self.current_video = None;
+        self.update(true);
+    }
+
+    // A monitoring hiccup shouldn't stop the batch, so failed writes only warn
+    fn update(&self, done: bool) {
+        if let Err(e) = self.write(done) {
+            eprintln!("Warning: Failed to write {:?}: {}", self.path, e);
+        }
+    }
+
+    // Written to a temp file and renamed so readers never see partial JSON
+    fn write(&self, done: bool) -> Result<()> {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let percent = if self.total == 0 {
+            100.0
+        } else {
+            self.completed as f64 / self.total as f64 * 100.0
+        };
+        let eta_secs = (self.completed > 0 && !done)
+            .then(|| elapsed / self.completed as f64 * (self.total - self.completed) as f64);
+
+        let snapshot = ProgressSnapshot {
+            current_video: self.current_video.as_deref(),
+            index: self.index,
+            total: self.total,
+            completed: self.completed,
+            failed: self.failed,
+            percent,
+            elapsed_secs: elapsed,
+            eta_secs,
+            done,
+        };
+
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(&snapshot)?)?;
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+}
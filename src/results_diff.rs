@@ -0,0 +1,244 @@
+use crate::frame_analyzer::DetectedObject;
+use crate::postprocess::iou;
+use crate::synchronizer::SynchronizedResult;
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+// Timestamps closer than this are treated as the same frame
+const TIMESTAMP_EPSILON: f64 = 1e-3;
+const MATCH_IOU: f32 = 0.5;
+
+#[derive(Debug, Clone)]
+pub struct ConfidenceDelta {
+    pub label: String,
+    pub before: f32,
+    pub after: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FrameDiff {
+    pub timestamp: f64,
+    pub added: Vec<DetectedObject>,
+    pub removed: Vec<DetectedObject>,
+    pub confidence_deltas: Vec<ConfidenceDelta>,
+    // (before, after) when the attached audio text changed
+    pub audio_change: Option<(Option<String>, Option<String>)>,
+}
+
+impl FrameDiff {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.confidence_deltas.is_empty()
+            && self.audio_change.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ResultsDiff {
+    // Only frames with at least one change
+    pub frames: Vec<FrameDiff>,
+    // Frames in the `before` results
+    pub before_frames: usize,
+}
+
+impl ResultsDiff {
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        if self.frames.is_empty() {
+            return "No differences".to_string();
+        }
+
+        let mut lines = Vec::new();
+        for frame in &self.frames {
+            let mut parts = Vec::new();
+            for (label, count) in count_labels(&frame.added) {
+                parts.push(format!("+{} {}", count, label));
+            }
+            for (label, count) in count_labels(&frame.removed) {
+                parts.push(format!("-{} {}", count, label));
+            }
+            for delta in &frame.confidence_deltas {
+                parts.push(format!(
+                    "~{} {:.2}->{:.2}",
+                    delta.label, delta.before, delta.after
+                ));
+            }
+            if frame.audio_change.is_some() {
+                parts.push("audio text changed".to_string());
+            }
+            lines.push(format!(
+                "frame {:.2}s: {}",
+                frame.timestamp,
+                parts.join(", ")
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+fn count_labels(objects: &[DetectedObject]) -> BTreeMap<&str, usize> {
+    let mut counts = BTreeMap::new();
+    for object in objects {
+        *counts.entry(object.label.as_str()).or_insert(0) += 1;
+    }
+    counts
+}
+
+// Walks both result sequences side by side, pairing frames with the same timestamp.
+// Both must be in timestamp order, as results.json is; only the current frame of each
+// is held at a time.
+pub fn diff_results(
+    before: impl IntoIterator<Item = Result<SynchronizedResult>>,
+    after: impl IntoIterator<Item = Result<SynchronizedResult>>,
+    min_confidence_delta: f32,
+) -> Result<ResultsDiff> {
+    let (mut before, mut after) = (before.into_iter(), after.into_iter());
+    let mut diff = ResultsDiff::default();
+    let mut old = before.next().transpose()?;
+    let mut new = after.next().transpose()?;
+
+    loop {
+        let frame = match (old.take(), new.take()) {
+            (None, None) => break,
+            (Some(o), Some(n)) if (n.timestamp - o.timestamp).abs() < TIMESTAMP_EPSILON => {
+                diff.before_frames += 1;
+                old = before.next().transpose()?;
+                new = after.next().transpose()?;
+                diff_frame(&o, &n, min_confidence_delta)
+            }
+            (Some(o), Some(n)) if n.timestamp < o.timestamp => {
+                old = Some(o);
+                new = after.next().transpose()?;
+                added_frame(n)
+            }
+            (Some(o), n) => {
+                diff.before_frames += 1;
+                new = n;
+                old = before.next().transpose()?;
+                FrameDiff {
+                    timestamp: o.timestamp,
+                    audio_change: o.audio_text.map(|text| (Some(text), None)),
+                    removed: o.video_objects,
+                    ..FrameDiff::default()
+                }
+            }
+            (None, Some(n)) => {
+                new = after.next().transpose()?;
+                added_frame(n)
+            }
+        };
+        if !frame.is_empty() {
+            diff.frames.push(frame);
+        }
+    }
+    Ok(diff)
+}
+
+fn added_frame(new: SynchronizedResult) -> FrameDiff {
+    FrameDiff {
+        timestamp: new.timestamp,
+        audio_change: new.audio_text.map(|text| (None, Some(text))),
+        added: new.video_objects,
+        ..FrameDiff::default()
+    }
+}
+
+// Detections are paired greedily by label and IoU; leftovers count as added/removed
+fn diff_frame(
+    old: &SynchronizedResult,
+    new: &SynchronizedResult,
+    min_confidence_delta: f32,
+) -> FrameDiff {
+    let mut diff = FrameDiff {
+        timestamp: old.timestamp,
+        ..FrameDiff::default()
+    };
+    let mut new_matched = vec![false; new.video_objects.len()];
+
+    for object in &old.video_objects {
+        let best = new
+            .video_objects
+            .iter()
+            .enumerate()
+            .filter(|(j, candidate)| !new_matched[*j] && candidate.label == object.label)
+            .map(|(j, candidate)| (j, iou(&object.bbox, &candidate.bbox)))
+            .filter(|(_, overlap)| *overlap >= MATCH_IOU)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        match best {
+            Some((j, _)) => {
+                new_matched[j] = true;
+                let new_confidence = new.video_objects[j].confidence;
+                if (new_confidence - object.confidence).abs() >= min_confidence_delta {
+                    diff.confidence_deltas.push(ConfidenceDelta {
+                        label: object.label.clone(),
+                        before: object.confidence,
+                        after: new_confidence,
+                    });
+                }
+            }
+            None => diff.removed.push(object.clone()),
+        }
+    }
+
+    diff.added = new
+        .video_objects
+        .iter()
+        .zip(&new_matched)
+        .filter(|(_, matched)| !**matched)
+        .map(|(object, _)| object.clone())
+        .collect();
+
+    if old.audio_text != new.audio_text {
+        diff.audio_change = Some((old.audio_text.clone(), new.audio_text.clone()));
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(timestamp: f64, labels: &[&str]) -> Result<SynchronizedResult> {
+        Ok(SynchronizedResult {
+            timestamp,
+            video_objects: labels
+                .iter()
+                .map(|label| (label.to_string(), 0.9, [10.0, 10.0, 50.0, 90.0]).into())
+                .collect(),
+            audio_text: None,
+            quality: None,
+            datetime: None,
+            unmatched_audio: false,
+            interpolated: false,
+        })
+    }
+
+    #[test]
+    fn streams_are_paired_by_timestamp() {
+        let before = vec![
+            result(0.0, &["person"]),
+            result(0.5, &["car"]),
+            result(1.0, &["dog"]),
+        ];
+        let after = vec![
+            result(0.0, &["person"]),
+            result(0.75, &["car"]),
+            result(1.0, &["dog", "cat"]),
+        ];
+
+        let diff = diff_results(before, after, 0.05).unwrap();
+        assert_eq!(diff.before_frames, 3);
+        let changes: Vec<(f64, usize, usize)> = diff
+            .frames
+            .iter()
+            .map(|f| (f.timestamp, f.added.len(), f.removed.len()))
+            .collect();
+        assert_eq!(changes, vec![(0.5, 0, 1), (0.75, 1, 0), (1.0, 1, 0)]);
+    }
+}
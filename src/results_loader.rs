@@ -0,0 +1,275 @@
+use crate::frame_analyzer::DetectedObject;
+use crate::output_writer::BboxFormat;
+use crate::synchronizer::SynchronizedResult;
+use crate::video_processor::FrameQuality;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+// Mirrors the layout written by BatchProcessor::save_results
+#[derive(Debug, Deserialize)]
+struct StoredResult {
+    timestamp: f64,
+    video_objects: Vec<StoredObject>,
+    audio_text: Option<String>,
+    #[serde(default)]
+    quality: Option<StoredQuality>,
+    // RFC 3339, present when the batch ran with wall_clock
+    #[serde(default)]
+    datetime: Option<String>,
+    #[serde(default)]
+    unmatched_audio: bool,
+    // Absent from files written before interpolated results were marked
+    #[serde(default)]
+    interpolated: Option<bool>,
+    // Written unless boxes are xyxy pixels (see JsonWriter::bbox_format); frame_size
+    // comes with the normalized formats
+    #[serde(default)]
+    bbox_format: Option<String>,
+    #[serde(default)]
+    frame_size: Option<(u32, u32)>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StoredQuality {
+    sharpness: f32,
+    brightness: f32,
+}
+
+#[derive(Debug, Deserialize)]
+struct StoredObject {
+    label: String,
+    confidence: f32,
+    #[serde(default)]
+    raw_confidence: Option<f32>,
+    bbox: [f32; 4],
+    // Absent from files written before detections carried provenance
+    #[serde(default)]
+    frame_index: usize,
+    #[serde(default)]
+    model: String,
+    #[serde(default)]
+    extraction_method: String,
+    #[serde(default)]
+    class_id: Option<u32>,
+    #[serde(default)]
+    interpolated: bool,
+}
+
+// Boxes come back as xyxy pixels, whatever format they were written in
+impl TryFrom<StoredResult> for SynchronizedResult {
+    type Error = anyhow::Error;
+
+    fn try_from(stored: StoredResult) -> Result<Self> {
+        // Older files: a result whose detections are all interpolated was added by
+        // interpolation
+        let interpolated = stored.interpolated.unwrap_or_else(|| {
+            !stored.video_objects.is_empty() && stored.video_objects.iter().all(|o| o.interpolated)
+        });
+        let bbox_format: BboxFormat = match &stored.bbox_format {
+            Some(format) => format.parse()?,
+            None => BboxFormat::Xyxy,
+        };
+        let video_objects = stored
+            .video_objects
+            .into_iter()
+            .map(|o| {
+                Ok(DetectedObject {
+                    label: o.label,
+                    confidence: o.confidence,
+                    raw_confidence: o.raw_confidence,
+                    bbox: bbox_format.to_xyxy(o.bbox, stored.frame_size)?,
+                    frame_index: o.frame_index,
+                    model: o.model,
+                    extraction_method: o.extraction_method,
+                    class_id: o.class_id,
+                    interpolated: o.interpolated,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            timestamp: stored.timestamp,
+            video_objects,
+            audio_text: stored.audio_text,
+            quality: stored.quality.map(|q| FrameQuality {
+                sharpness: q.sharpness,
+                brightness: q.brightness,
+            }),
+            datetime: stored
+                .datetime
+                .and_then(|d| DateTime::parse_from_rfc3339(&d).ok())
+                .map(|d| d.with_timezone(&Utc)),
+            unmatched_audio: stored.unmatched_audio,
+            interpolated,
+        })
+    }
+}
+
+// Reads results.json one element at a time, so only the current result is in memory.
+// Files from long videos run to gigabytes.
+pub fn stream_results(path: &Path) -> Result<ResultStream> {
+    let file = File::open(path).map_err(|e| anyhow::anyhow!("Failed to read {:?}: {}", path, e))?;
+    let mut stream = ResultStream {
+        reader: BufReader::new(file),
+        path: path.to_path_buf(),
+        started: false,
+        done: false,
+    };
+    match stream.next_byte()? {
+        Some(b'[') => Ok(stream),
+        _ => Err(anyhow::anyhow!(
+            "Failed to parse {:?}: expected a JSON array",
+            path
+        )),
+    }
+}
+
+pub struct ResultStream {
+    reader: BufReader<File>,
+    path: PathBuf,
+    // Past the first element, so a separator comes before the next one
+    started: bool,
+    done: bool,
+}
+
+impl ResultStream {
+    // Next byte that isn't JSON whitespace, without consuming it
+    fn peek_byte(&mut self) -> Result<Option<u8>> {
+        loop {
+            let buffer = self.reader.fill_buf()?;
+            match buffer.first() {
+                None => return Ok(None),
+                Some(b' ' | b'\t' | b'\n' | b'\r') => self.reader.consume(1),
+                Some(&byte) => return Ok(Some(byte)),
+            }
+        }
+    }
+
+    fn next_byte(&mut self) -> Result<Option<u8>> {
+        let byte = self.peek_byte()?;
+        if byte.is_some() {
+            self.reader.consume(1);
+        }
+        Ok(byte)
+    }
+
+    // Moves past the separator before the next element; false at the closing bracket
+    fn advance(&mut self) -> Result<bool> {
+        let has_next = match self.peek_byte()? {
+            Some(b']') => false,
+            Some(b',') if self.started => true,
+            Some(_) if !self.started => return Ok(true),
+            _ => return Err(anyhow::anyhow!("expected ',' or ']'")),
+        };
+        self.reader.consume(1);
+        Ok(has_next)
+    }
+
+    fn read_next(&mut self) -> Result<Option<SynchronizedResult>> {
+        if !self.advance()? {
+            return Ok(None);
+        }
+        self.started = true;
+        // The deserializer stops at the element's closing brace and reads nothing past it
+        let mut deserializer = serde_json::Deserializer::from_reader(&mut self.reader);
+        let stored = StoredResult::deserialize(&mut deserializer)?;
+        SynchronizedResult::try_from(stored).map(Some)
+    }
+}
+
+impl Iterator for ResultStream {
+    type Item = Result<SynchronizedResult>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.read_next() {
+            Ok(Some(result)) => Some(Ok(result)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(anyhow::anyhow!(
+                    "Failed to parse {:?}: {}",
+                    self.path,
+                    e
+                )))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output_writer::{JsonWriter, OutputWriter, VideoInfo};
+
+    fn write_and_load(bbox_format: BboxFormat, bbox: [f32; 4]) -> Result<Vec<SynchronizedResult>> {
+        let dir = crate::test_fixtures::scratch_dir(&format!("load-{}", bbox_format.name()));
+        let writer = JsonWriter {
+            taxonomy: None,
+            confidence_decimals: 3,
+            timestamp_decimals: 3,
+            min_sharpness: None,
+            bbox_format,
+        };
+        let result = SynchronizedResult {
+            timestamp: 1.0,
+            video_objects: vec![DetectedObject::from(("person".to_string(), 0.9f32, bbox))],
+            audio_text: None,
+            quality: None,
+            datetime: None,
+            unmatched_audio: false,
+            interpolated: false,
+        };
+        let video = VideoInfo {
+            duration: None,
+            frame_size: Some((640, 480)),
+        };
+        writer.write_video(&dir, &[result], &video).unwrap();
+
+        let loaded = stream_results(&dir.join("results.json"))?.collect();
+        let _ = std::fs::remove_dir_all(dir);
+        loaded
+    }
+
+    #[test]
+    fn boxes_are_read_back_as_xyxy_pixels_in_every_format() {
+        let bbox = [64.0, 48.0, 320.0, 240.0];
+        for format in [
+            BboxFormat::Xyxy,
+            BboxFormat::Xywh,
+            BboxFormat::XyxyNormalized,
+            BboxFormat::XywhNormalized,
+        ] {
+            let loaded = write_and_load(format, bbox).unwrap();
+            let loaded = loaded[0].video_objects[0].bbox;
+            for (a, e) in loaded.iter().zip(bbox) {
+                assert!((a - e).abs() < 1e-3, "{:?}: {:?}", format, loaded);
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_bbox_format_is_an_error() {
+        let dir = crate::test_fixtures::scratch_dir("load-unknown-format");
+        let path = dir.join("results.json");
+        std::fs::write(
+            &path,
+            r#"[{"timestamp": 0.0, "bbox_format": "cxcywh", "audio_text": null,
+                "video_objects": [{"label": "a", "confidence": 0.5, "bbox": [1, 2, 3, 4]}]}]"#,
+        )
+        .unwrap();
+
+        let loaded: Result<Vec<SynchronizedResult>> = stream_results(&path).unwrap().collect();
+        let message = format!("{:#}", loaded.unwrap_err());
+        assert!(message.contains("cxcywh"), "{}", message);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::audio_processor::AudioResult;
+use crate::synchronizer::SynchronizedResult;
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use std::path::Path;
+
+const SCHEMA: &str = "
+PRAGMA foreign_keys = ON;
+CREATE TABLE IF NOT EXISTS videos (
+    id INTEGER PRIMARY KEY,
+    path TEXT NOT NULL UNIQUE,
+    frame_count INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS frames (
+    id INTEGER PRIMARY KEY,
+    video_id INTEGER NOT NULL REFERENCES videos(id) ON DELETE CASCADE,
+    frame_index INTEGER NOT NULL,
+    timestamp REAL NOT NULL,
+    audio_text TEXT
+);
+CREATE TABLE IF NOT EXISTS detections (
+    id INTEGER PRIMARY KEY,
+    frame_id INTEGER NOT NULL REFERENCES frames(id) ON DELETE CASCADE,
+    label TEXT NOT NULL,
+    confidence REAL NOT NULL,
+    x1 REAL NOT NULL,
+    y1 REAL NOT NULL,
+    x2 REAL NOT NULL,
+    y2 REAL NOT NULL
+);
+CREATE TABLE IF NOT EXISTS audio_segments (
+    id INTEGER PRIMARY KEY,
+    video_id INTEGER NOT NULL REFERENCES videos(id) ON DELETE CASCADE,
+    start_time REAL NOT NULL,
+    end_time REAL NOT NULL,
+    text TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_frames_video ON frames(video_id, timestamp);
+CREATE INDEX IF NOT EXISTS idx_detections_frame ON detections(frame_id);
+CREATE INDEX IF NOT EXISTS idx_detections_label ON detections(label, confidence);
+";
+
+// Writes one video's rows in a single transaction; the database accumulates across
+// the batch and re-processing a video replaces its previous rows.
+pub fn export_video(
+    db_path: &Path,
+    video_path: &Path,
+    results: &[SynchronizedResult],
+    audio_results: &[AudioResult],
+) -> Result<()> {
+    let mut conn = Connection::open(db_path)?;
+    conn.execute_batch(SCHEMA)?;
+
+    let tx = conn.transaction()?;
+    let video_key = video_path.to_string_lossy();
+    tx.execute("DELETE FROM videos WHERE path = ?1", params![video_key])?;
+    tx.execute(
+        "INSERT INTO videos (path, frame_count) VALUES (?1, ?2)",
+        params![video_key, results.len() as i64],
+    )?;
+    let video_id = tx.last_insert_rowid();
+
+    {
+        let mut insert_frame = tx.prepare(
+            "INSERT INTO frames (video_id, frame_index, timestamp, audio_text)
+             VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        let mut insert_detection = tx.prepare(
+            "INSERT INTO detections (frame_id, label, confidence, x1, y1, x2, y2)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?;
+        let mut insert_segment = tx.prepare(
+            "INSERT INTO audio_segments (video_id, start_time, end_time, text)
+             VALUES (?1, ?2, ?3, ?4)",
+        )?;
+
+        for (frame_index, result) in results.iter().enumerate() {
+            let frame_id = insert_frame.insert(params![
+                video_id,
+                frame_index as i64,
+                result.timestamp,
+                result.audio_text
+            ])?;
+
+            for object in result.video_objects.iter().filter(|o| !o.interpolated) {
+                let bbox = object.bbox;
+                insert_detection.execute(params![
+                    frame_id,
+                    object.label,
+                    object.confidence as f64,
+                    bbox[0] as f64,
+                    bbox[1] as f64,
+                    bbox[2] as f64,
+                    bbox[3] as f64
+                ])?;
+            }
+        }
+
+        for segment in audio_results {
+            insert_segment.execute(params![
+                video_id,
+                segment.start_time,
+                segment.end_time,
+                segment.text
+            ])?;
+        }
+    }
+
+    tx.commit()?;
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use crate::audio_processor::AudioResult;
+use ffmpeg_next::{codec::subtitle::Rect, format, media, Error};
+use std::path::Path;
+
+// Reads the first subtitle stream (SRT, ASS, mov_text, DVB teletext, ...) as timed text.
+// CEA-608/708 captions carried inside the video bitstream are not a separate stream and
+// are not picked up here; bitmap subtitles (PGS, DVD) are skipped since they have no text.
+pub fn extract_subtitles(video_path: &Path) -> Result<Vec<AudioResult>, Error> {
+    ffmpeg_next::init()?;
+
+    let mut ictx = format::input(&video_path)?;
+    let subtitle_stream = ictx
+        .streams()
+        .best(media::Type::Subtitle)
+        .ok_or(Error::StreamNotFound)?;
+
+    let subtitle_stream_index = subtitle_stream.index();
+    let context_decoder =
+        ffmpeg_next::codec::context::Context::from_parameters(subtitle_stream.parameters())?;
+    let mut decoder = context_decoder.decoder().subtitle()?;
+
+    let mut segments = Vec::new();
+
+    for (stream, packet) in ictx.packets() {
+        if stream.index() != subtitle_stream_index {
+            continue;
+        }
+
+        let mut subtitle = ffmpeg_next::Subtitle::new();
+        if !decoder.decode(&packet, &mut subtitle)? {
+            continue;
+        }
+
+        // Subtitle pts is in AV_TIME_BASE units; display times are milliseconds relative to it
+        let base = match subtitle.pts() {
+            Some(pts) => pts as f64 / ffmpeg_next::ffi::AV_TIME_BASE as f64,
+            None => packet.pts().unwrap_or(0) as f64 * f64::from(stream.time_base()),
+        };
+        let start_time = base + subtitle.start() as f64 / 1000.0;
+        let end_time = if subtitle.end() > subtitle.start() {
+            base + subtitle.end() as f64 / 1000.0
+        } else {
+            base + packet.duration() as f64 * f64::from(stream.time_base())
+        };
+
+        let text: Vec<String> = subtitle
+            .rects()
+            .filter_map(|rect| match rect {
+                Rect::Text(text) => Some(text.get().trim().to_string()),
+                Rect::Ass(ass) => Some(ass_dialogue_text(ass.get())),
+                _ => None,
+            })
+            .filter(|text| !text.is_empty())
+            .collect();
+
+        if !text.is_empty() {
+            segments.push(AudioResult {
+                start_time,
+                end_time,
+                text: text.join(" "),
+            });
+        }
+    }
+
+    Ok(segments)
+}
+
+// ASS events look like "ReadOrder,Layer,Style,Name,MarginL,MarginR,MarginV,Effect,Text";
+// keep the text field, drop {\override} tags and turn \N line breaks into spaces
+fn ass_dialogue_text(event: &str) -> String {
+    let text = event.splitn(9, ',').last().unwrap_or(event);
+
+    let mut plain = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '{' => in_tag = true,
+            '}' => in_tag = false,
+            _ if !in_tag => plain.push(c),
+            _ => {}
+        }
+    }
+
+    plain
+        .replace("\\N", " ")
+        .replace("\\n", " ")
+        .trim()
+        .to_string()
+}
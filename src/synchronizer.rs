@@ -1,63 +1,354 @@
-use crate::audio_processor::AudioResult;
-use crate::frame_analyzer::FrameResult;
+use crate::audio_processor::{AudioResult, StreamDurations};
+use crate::frame_analyzer::{DetectedObject, FrameResult};
+use crate::taxonomy::display_label;
+use crate::video_processor::FrameQuality;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+// How much the video and audio streams may differ in length before it's worth a warning
+const DURATION_MISMATCH_SECONDS: f64 = 2.0;
+
+// How a transcript segment's text is attached to the frames it spans
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyncStrategy {
+    // Every frame inside a segment gets the segment's full text
+    #[default]
+    FullSegment,
+    // Each word goes to the frame nearest the time it was spoken, so a long sentence is
+    // spread across its frames instead of repeated. Backends give no word timings, so
+    // speech is assumed to run at a constant rate per character over the segment. Frames
+    // that end up with no words get no text.
+    Distributed,
+}
 
 #[derive(Debug)]
 pub struct SynchronizedResult {
     pub timestamp: f64,
-    pub video_objects: Vec<(String, f32, [f32; 4])>,
+    pub video_objects: Vec<DetectedObject>,
     pub audio_text: Option<String>,
+    pub quality: Option<FrameQuality>,
+    // Absolute time of the frame, when the container records its creation time
+    pub datetime: Option<DateTime<Utc>>,
+    // A transcript segment no analyzed frame fell inside, kept at its start time so the
+    // text isn't lost; not a frame
+    pub unmatched_audio: bool,
+    // Only interpolated detections, at a time no frame was analyzed at (see
+    // FrameResult::interpolated); not a frame either
+    pub interpolated: bool,
+}
+
+impl SynchronizedResult {
+    // Whether the result stands for a frame the model saw, as opposed to unmatched audio
+    // or interpolated detections
+    pub fn is_analyzed_frame(&self) -> bool {
+        !self.unmatched_audio && !self.interpolated
+    }
+}
+
+// Moves transcript segments by `offset` seconds
+pub fn shift_audio_results(audio_results: &mut [AudioResult], offset: f64) {
+    for segment in audio_results {
+        segment.start_time += offset;
+        segment.end_time += offset;
+    }
+}
+
+// `creation_time` is when the first video frame was recorded
+pub fn attach_datetimes(results: &mut [SynchronizedResult], creation_time: DateTime<Utc>) {
+    for result in results {
+        let offset = chrono::Duration::microseconds((result.timestamp * 1e6) as i64);
+        result.datetime = Some(creation_time + offset);
+    }
 }
 
+// Re-bases frames and segments that share the container clock so that `zero` becomes 0.0
+pub fn rebase_timestamps(
+    frame_results: &mut [FrameResult],
+    audio_results: &mut [AudioResult],
+    zero: f64,
+) {
+    for frame_result in frame_results {
+        frame_result.timestamp -= zero;
+    }
+    shift_audio_results(audio_results, -zero);
+}
+
+// Matches a single frame the way SyncStrategy::FullSegment does, for callers that
+// synchronize frames as they're analyzed
+pub fn synchronize_frame(
+    frame_result: FrameResult,
+    audio_results: &[AudioResult],
+) -> SynchronizedResult {
+    let audio_text = audio_results
+        .iter()
+        .find(|audio| {
+            audio.start_time <= frame_result.timestamp && frame_result.timestamp <= audio.end_time
+        })
+        .map(|audio| audio.text.clone());
+    SynchronizedResult {
+        timestamp: frame_result.timestamp,
+        video_objects: frame_result.objects,
+        audio_text,
+        quality: frame_result.quality,
+        datetime: None,
+        unmatched_audio: false,
+        interpolated: frame_result.interpolated,
+    }
+}
+
+// With `include_unmatched_audio`, segments without a frame get an entry of their own
 pub fn synchronize_results(
     frame_results: Vec<FrameResult>,
     audio_results: Vec<AudioResult>,
+    strategy: SyncStrategy,
+    include_unmatched_audio: bool,
 ) -> Vec<SynchronizedResult> {
-    let mut synchronized = Vec::new();
+    // Find corresponding audio segment
+    let segments: Vec<Option<usize>> = frame_results
+        .iter()
+        .map(|frame_result| {
+            audio_results.iter().position(|audio| {
+                audio.start_time <= frame_result.timestamp
+                    && frame_result.timestamp <= audio.end_time
+            })
+        })
+        .collect();
 
-    for frame_result in frame_results {
-        let timestamp = frame_result.timestamp;
-
-        // Find corresponding audio segment
-        let audio_text = audio_results
+    let texts = match strategy {
+        SyncStrategy::FullSegment => segments
             .iter()
-            .find(|audio| audio.start_time <= timestamp && timestamp <= audio.end_time)
-            .map(|audio| audio.text.clone());
+            .map(|segment| segment.map(|s| audio_results[s].text.clone()))
+            .collect(),
+        SyncStrategy::Distributed => distribute_words(&frame_results, &audio_results, &segments),
+    };
 
-        synchronized.push(SynchronizedResult {
-            timestamp,
+    let mut results: Vec<SynchronizedResult> = frame_results
+        .into_iter()
+        .zip(texts)
+        .map(|(frame_result, audio_text)| SynchronizedResult {
+            timestamp: frame_result.timestamp,
             video_objects: frame_result.objects,
             audio_text,
-        });
+            quality: frame_result.quality,
+            datetime: None,
+            unmatched_audio: false,
+            interpolated: frame_result.interpolated,
+        })
+        .collect();
+
+    if include_unmatched_audio {
+        let mut matched = vec![false; audio_results.len()];
+        for &s in segments.iter().flatten() {
+            matched[s] = true;
+        }
+        results.extend(
+            audio_results
+                .into_iter()
+                .zip(matched)
+                .filter(|(_, matched)| !matched)
+                .map(|(segment, _)| SynchronizedResult {
+                    timestamp: segment.start_time,
+                    video_objects: Vec::new(),
+                    audio_text: Some(segment.text),
+                    quality: None,
+                    datetime: None,
+                    unmatched_audio: true,
+                    interpolated: false,
+                }),
+        );
+        results.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
     }
+    results
+}
 
-    synchronized
+// (video length, audio length) when the streams differ in length by enough that
+// the end of one will have nothing to synchronize with
+pub fn duration_mismatch(durations: StreamDurations) -> Option<(f64, f64)> {
+    let (video, audio) = (durations.video?, durations.audio?);
+    ((video - audio).abs() > DURATION_MISMATCH_SECONDS).then_some((video, audio))
 }
 
-pub fn print_results(results: &[SynchronizedResult]) {
+// `segments` holds the segment index of each frame
+fn distribute_words(
+    frame_results: &[FrameResult],
+    audio_results: &[AudioResult],
+    segments: &[Option<usize>],
+) -> Vec<Option<String>> {
+    let mut words_per_frame: Vec<Vec<&str>> = vec![Vec::new(); frame_results.len()];
+
+    for (s, segment) in audio_results.iter().enumerate() {
+        let frames: Vec<usize> = (0..frame_results.len())
+            .filter(|&f| segments[f] == Some(s))
+            .collect();
+        if frames.is_empty() {
+            continue;
+        }
+
+        let words: Vec<&str> = segment.text.split_whitespace().collect();
+        let total_chars = words
+            .iter()
+            .map(|w| w.chars().count())
+            .sum::<usize>()
+            .max(1);
+        let duration = segment.end_time - segment.start_time;
+        let mut spoken_chars = 0;
+        for word in words {
+            let length = word.chars().count();
+            let spoken_at = segment.start_time
+                + duration * (spoken_chars as f64 + length as f64 / 2.0) / total_chars as f64;
+            spoken_chars += length;
+
+            let nearest = frames
+                .iter()
+                .copied()
+                .min_by(|&a, &b| {
+                    let distance_a = (frame_results[a].timestamp - spoken_at).abs();
+                    let distance_b = (frame_results[b].timestamp - spoken_at).abs();
+                    distance_a.total_cmp(&distance_b)
+                })
+                .unwrap();
+            words_per_frame[nearest].push(word);
+        }
+    }
+
+    words_per_frame
+        .into_iter()
+        .map(|words| (!words.is_empty()).then(|| words.join(" ")))
+        .collect()
+}
+
+pub fn print_results(results: &[SynchronizedResult], aliases: &HashMap<String, String>) {
     println!("\n=== Synchronized Video and Audio Analysis Results ===\n");
+    print!("{}", format_results(results, aliases));
+}
 
+// Human-readable report of detections and transcript per frame, with labels shown under
+// their display alias
+pub fn format_results(results: &[SynchronizedResult], aliases: &HashMap<String, String>) -> String {
+    use std::fmt::Write;
+
+    let mut out = String::new();
     for result in results {
-        println!("Timestamp: {:.2}s", result.timestamp);
+        let _ = writeln!(out, "Timestamp: {:.2}s", result.timestamp);
 
         if !result.video_objects.is_empty() {
-            println!("  Video Objects:");
-            for (label, confidence, bbox) in &result.video_objects {
-                println!(
+            let _ = writeln!(out, "  Video Objects:");
+            for object in &result.video_objects {
+                let bbox = object.bbox;
+                let _ = writeln!(
+                    out,
                     "    - {}: {:.2}% confidence at [{:.1}, {:.1}, {:.1}, {:.1}]",
-                    label,
-                    confidence * 100.0,
+                    display_label(aliases, &object.label),
+                    object.confidence * 100.0,
                     bbox[0],
                     bbox[1],
                     bbox[2],
                     bbox[3]
                 );
+                if !object.model.is_empty() {
+                    let _ = writeln!(
+                        out,
+                        "      (frame {}, model: {}, extraction: {})",
+                        object.frame_index, object.model, object.extraction_method
+                    );
+                }
             }
         }
 
         if let Some(text) = &result.audio_text {
-            println!("  Audio: \"{}\"", text);
+            let _ = writeln!(out, "  Audio: \"{}\"", text);
+        }
+
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(timestamp: f64) -> FrameResult {
+        FrameResult {
+            timestamp,
+            objects: Vec::new(),
+            quality: None,
+            interpolated: false,
+        }
+    }
+
+    fn segment(start_time: f64, end_time: f64, text: &str) -> AudioResult {
+        AudioResult {
+            start_time,
+            end_time,
+            text: text.to_string(),
         }
+    }
+
+    #[test]
+    fn audio_stream_offset_is_corrected_before_matching() {
+        // Video starts at 0.0 on the container clock, audio at 0.5; the transcript counts
+        // from the start of the audio stream
+        let (video_start, audio_start) = (0.0, 0.5);
+        let mut frames: Vec<FrameResult> = [0.25, 0.75, 1.25, 1.75].map(frame).into();
+        let mut segments = vec![segment(0.0, 0.5, "first"), segment(0.5, 1.0, "second")];
+
+        shift_audio_results(&mut segments, audio_start);
+        rebase_timestamps(&mut frames, &mut segments, video_start);
+        let results = synchronize_results(frames, segments, SyncStrategy::FullSegment, false);
+
+        let texts: Vec<Option<&str>> = results.iter().map(|r| r.audio_text.as_deref()).collect();
+        assert_eq!(texts, [None, Some("first"), Some("second"), None]);
+    }
+
+    #[test]
+    fn analyzed_frames_with_only_interpolated_boxes_still_count() {
+        let interpolated_box = DetectedObject {
+            interpolated: true,
+            ..DetectedObject::from(("person".to_string(), 0.8f32, [0.0, 0.0, 1.0, 1.0]))
+        };
+        let analyzed = FrameResult {
+            objects: vec![interpolated_box.clone()],
+            ..frame(1.0)
+        };
+        let added = FrameResult {
+            objects: vec![interpolated_box],
+            interpolated: true,
+            ..frame(1.5)
+        };
+
+        let results = synchronize_results(
+            vec![analyzed, added],
+            Vec::new(),
+            SyncStrategy::FullSegment,
+            false,
+        );
+
+        assert!(results[0].is_analyzed_frame());
+        assert!(!results[1].is_analyzed_frame());
+    }
+
+    #[test]
+    fn manual_audio_offset_moves_text_later() {
+        let frames: Vec<FrameResult> = [0.25, 0.75].map(frame).into();
+        let mut segments = vec![segment(0.0, 0.5, "hello")];
+
+        shift_audio_results(&mut segments, 0.5);
+        let results = synchronize_results(frames, segments, SyncStrategy::FullSegment, false);
+
+        assert_eq!(results[0].audio_text, None);
+        assert_eq!(results[1].audio_text.as_deref(), Some("hello"));
+    }
 
-        println!();
+    #[test]
+    fn duration_mismatch_compares_the_streams() {
+        let durations = |video, audio| StreamDurations { video, audio };
+        assert_eq!(
+            duration_mismatch(durations(Some(60.0), Some(45.0))),
+            Some((60.0, 45.0))
+        );
+        assert_eq!(duration_mismatch(durations(Some(60.0), Some(59.0))), None);
+        // Nothing to compare without both lengths
+        assert_eq!(duration_mismatch(durations(Some(60.0), None)), None);
     }
 }
@@ -0,0 +1,101 @@
+use crate::synchronizer::SynchronizedResult;
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+pub const DEFAULT_SUPERCATEGORY: &str = "other";
+
+// Maps fine-grained labels to supercategories, e.g. car/truck/bus -> vehicle
+#[derive(Debug, Clone, Default)]
+pub struct Taxonomy {
+    categories: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CategoryRollup {
+    pub supercategory: String,
+    pub total: usize,
+    // Per-label counts, sorted by label
+    pub labels: BTreeMap<String, usize>,
+}
+
+impl Taxonomy {
+    pub fn new(categories: HashMap<String, String>) -> Self {
+        Self { categories }
+    }
+
+    // Expects a JSON object of label -> supercategory
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read taxonomy {:?}: {}", path, e))?;
+        let categories: HashMap<String, String> = serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse taxonomy {:?}: {}", path, e))?;
+        Ok(Self { categories })
+    }
+
+    pub fn supercategory(&self, label: &str) -> &str {
+        self.categories
+            .get(label)
+            .map(String::as_str)
+            .unwrap_or(DEFAULT_SUPERCATEGORY)
+    }
+
+    // Largest supercategories first
+    pub fn rollup(&self, results: &[SynchronizedResult]) -> Vec<CategoryRollup> {
+        let mut rollups: HashMap<&str, CategoryRollup> = HashMap::new();
+        for result in results {
+            for object in result.video_objects.iter().filter(|o| !o.interpolated) {
+                let label = &object.label;
+                let supercategory = self.supercategory(label);
+                let rollup = rollups
+                    .entry(supercategory)
+                    .or_insert_with(|| CategoryRollup {
+                        supercategory: supercategory.to_string(),
+                        total: 0,
+                        labels: BTreeMap::new(),
+                    });
+                rollup.total += 1;
+                *rollup.labels.entry(label.clone()).or_insert(0) += 1;
+            }
+        }
+
+        let mut rollups: Vec<CategoryRollup> = rollups.into_values().collect();
+        rollups.sort_by(|a, b| {
+            b.total
+                .cmp(&a.total)
+                .then_with(|| a.supercategory.cmp(&b.supercategory))
+        });
+        rollups
+    }
+}
+
+// Friendly names for labels in human-readable reports (printed results, results.txt
+// and the batch summary); results.json, filtering and tracking keep the model's label.
+// Expects a JSON object of label -> display name.
+pub fn load_display_aliases(path: &Path) -> Result<HashMap<String, String>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read display aliases {:?}: {}", path, e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| anyhow::anyhow!("Failed to parse display aliases {:?}: {}", path, e))
+}
+
+pub fn display_label<'a>(aliases: &'a HashMap<String, String>, label: &'a str) -> &'a str {
+    aliases.get(label).map_or(label, String::as_str)
+}
+
+impl std::fmt::Display for CategoryRollup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let labels: Vec<String> = self
+            .labels
+            .iter()
+            .map(|(label, count)| format!("{} {}", count, label))
+            .collect();
+        write!(
+            f,
+            "{} {} detections ({})",
+            self.total,
+            self.supercategory,
+            labels.join(", ")
+        )
+    }
+}
@@ -0,0 +1,120 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+// Generates a small media file with the ffmpeg CLI (lavfi sources, so nothing is
+// checked in) and caches it in the temp dir. Returns None when ffmpeg isn't installed,
+// in which case the caller skips its test.
+pub fn generate(name: &str, args: &[&str]) -> Option<PathBuf> {
+    let dir = std::env::temp_dir().join("video-audio-processor-fixtures");
+    let path = dir.join(name);
+    if path.exists() {
+        return Some(path);
+    }
+    std::fs::create_dir_all(&dir).ok()?;
+
+    // Written under a process-unique name and renamed, so parallel tests never read a
+    // half-written file
+    let partial = dir.join(format!(
+        "{}.{}.partial.{}",
+        name,
+        std::process::id(),
+        extension(name)
+    ));
+    let status = Command::new("ffmpeg")
+        .args(["-y", "-loglevel", "error"])
+        .args(args)
+        .arg(&partial)
+        .status()
+        .ok()?;
+    if !status.success() {
+        eprintln!("skipping: ffmpeg couldn't generate fixture {}", name);
+        return None;
+    }
+    std::fs::rename(&partial, &path).ok()?;
+    Some(path)
+}
+
+// Two seconds of 64x48 test pattern at 10 fps with a 440 Hz tone, in codecs every
+// ffmpeg build has
+pub fn av_clip() -> Option<PathBuf> {
+    generate(
+        "av_clip.mkv",
+        &[
+            "-f",
+            "lavfi",
+            "-i",
+            "testsrc=size=64x48:rate=10:duration=2",
+            "-f",
+            "lavfi",
+            "-i",
+            "sine=frequency=440:sample_rate=16000:duration=2",
+            "-c:v",
+            "mpeg4",
+            "-c:a",
+            "pcm_s16le",
+        ],
+    )
+}
+
+// One second of 64x48 test pattern, tagged to be shown rotated by 90 degrees (as phones
+// record portrait)
+pub fn rotated_clip() -> Option<PathBuf> {
+    generate(
+        "rotated.mp4",
+        &[
+            "-f",
+            "lavfi",
+            "-i",
+            "testsrc=size=64x48:rate=10:duration=1",
+            "-c:v",
+            "mpeg4",
+            "-metadata:s:v:0",
+            "rotate=90",
+        ],
+    )
+}
+
+// One second of a 64x48 gray ramp from black (left) to 70% signal (right), 10-bit
+// BT.2020 tagged with the given transfer, e.g. smpte2084 (PQ) or arib-std-b67 (HLG).
+// 70% is about 620 nits in PQ, a highlight well above SDR white. FFV1 is used as every
+// ffmpeg build can encode it at 10 bits.
+pub fn hdr_ramp(name: &str, transfer: &str) -> Option<PathBuf> {
+    generate(
+        name,
+        &[
+            "-f",
+            "lavfi",
+            "-i",
+            "nullsrc=size=64x48:rate=10:duration=1",
+            "-vf",
+            // Limited range: 10-bit luma 64 is black, 64 + 0.7 * 876 is 70%
+            "format=yuv420p10le,geq=lum='64+613*X/W':cb=512:cr=512",
+            "-c:v",
+            "ffv1",
+            "-color_primaries",
+            "bt2020",
+            "-color_trc",
+            transfer,
+            "-colorspace",
+            "bt2020nc",
+            "-color_range",
+            "tv",
+        ],
+    )
+}
+
+// Empty directory for one test's output, unique to this process
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "video-audio-processor-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn extension(name: &str) -> &str {
+    name.rsplit_once('.').map_or("", |(_, extension)| extension)
+}
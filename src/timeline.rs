@@ -0,0 +1,235 @@
+use crate::output_writer::csv_field;
+use crate::synchronizer::SynchronizedResult;
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+pub const TIMELINE_JSON: &str = "combined_timeline.json";
+pub const TIMELINE_CSV: &str = "combined_timeline.csv";
+
+// A run of consecutive analyzed frames in which a label was detected. Times are the
+// first and last of those frames, so a single-frame interval has start == end.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectInterval {
+    pub label: String,
+    pub start: f64,
+    pub end: f64,
+    pub start_datetime: Option<DateTime<Utc>>,
+    pub end_datetime: Option<DateTime<Utc>>,
+    pub max_confidence: f32,
+    // The frame the label was detected with max_confidence in (the first of them on a
+    // tie), which shows it most clearly
+    pub peak_datetime: Option<DateTime<Utc>>,
+    pub peak_frame_index: usize,
+    pub frames: usize,
+}
+
+// One camera's interval on the combined timeline
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineEntry<'a> {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub video: &'a str,
+    pub label: String,
+    pub max_confidence: f32,
+    // When max_confidence was reached, and the index of that frame in the video's
+    // results, e.g. for picking a thumbnail
+    pub peak: DateTime<Utc>,
+    pub peak_frame: usize,
+    pub frames: usize,
+}
+
+// Intervals per label, in order of their start. Interpolated boxes and unmatched
+// transcript entries aren't analyzed frames and neither extend nor break a run. A label
+// missing from some frames is bridged over when it's detected again at most
+// `max_bridge_gap` seconds after it was last seen, so brief occlusions or dips below
+// the threshold don't split the interval; 0 ends the run at the first frame without it.
+pub fn object_intervals(
+    results: &[SynchronizedResult],
+    max_bridge_gap: f64,
+) -> Vec<ObjectInterval> {
+    let mut frames: Vec<&SynchronizedResult> =
+        results.iter().filter(|r| r.is_analyzed_frame()).collect();
+    frames.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+
+    let mut open: BTreeMap<&str, ObjectInterval> = BTreeMap::new();
+    // Open labels missing from at least one frame since they were last seen
+    let mut missing: BTreeSet<&str> = BTreeSet::new();
+    let mut intervals = Vec::new();
+    for frame in frames {
+        // Best confidence per label in this frame, and its frame index
+        let mut seen: BTreeMap<&str, (f32, usize)> = BTreeMap::new();
+        for object in frame.video_objects.iter().filter(|o| !o.interpolated) {
+            let best = seen
+                .entry(object.label.as_str())
+                .or_insert((object.confidence, object.frame_index));
+            if object.confidence > best.0 {
+                *best = (object.confidence, object.frame_index);
+            }
+        }
+
+        // A run ends once its label has been gone for longer than can be bridged, found
+        // either while it's still missing or when it's seen again
+        let ended: Vec<&str> = open
+            .iter()
+            .filter(|(label, interval)| {
+                let gone = !seen.contains_key(*label) || missing.contains(*label);
+                gone && frame.timestamp - interval.end > max_bridge_gap
+            })
+            .map(|(label, _)| *label)
+            .collect();
+        for label in ended {
+            intervals.extend(open.remove(label));
+            missing.remove(label);
+        }
+        for label in open.keys() {
+            if !seen.contains_key(label) {
+                missing.insert(*label);
+            }
+        }
+
+        for (label, (confidence, frame_index)) in seen {
+            let interval = open.entry(label).or_insert_with(|| ObjectInterval {
+                label: label.to_string(),
+                start: frame.timestamp,
+                end: frame.timestamp,
+                start_datetime: frame.datetime,
+                end_datetime: frame.datetime,
+                max_confidence: confidence,
+                peak_datetime: frame.datetime,
+                peak_frame_index: frame_index,
+                frames: 0,
+            });
+            interval.end = frame.timestamp;
+            interval.end_datetime = frame.datetime;
+            if confidence > interval.max_confidence {
+                interval.max_confidence = confidence;
+                interval.peak_datetime = frame.datetime;
+                interval.peak_frame_index = frame_index;
+            }
+            interval.frames += 1;
+            missing.remove(label);
+        }
+    }
+    intervals.extend(open.into_values());
+    intervals.sort_by(|a, b| a.start.total_cmp(&b.start).then(a.label.cmp(&b.label)));
+    intervals
+}
+
+// Merges every video's intervals (see object_intervals) into one timeline ordered by
+// wall-clock start, tagged with the video they came from. Intervals without a datetime
+// (videos with no creation_time) can't be placed and are left out.
+pub fn combined_timeline<'a>(
+    videos: impl IntoIterator<Item = (&'a str, &'a [SynchronizedResult])>,
+    max_bridge_gap: f64,
+) -> Vec<TimelineEntry<'a>> {
+    let mut entries: Vec<TimelineEntry> = videos
+        .into_iter()
+        .flat_map(|(video, results)| {
+            object_intervals(results, max_bridge_gap)
+                .into_iter()
+                .filter_map(move |interval| {
+                    Some(TimelineEntry {
+                        start: interval.start_datetime?,
+                        end: interval.end_datetime?,
+                        video,
+                        label: interval.label,
+                        max_confidence: interval.max_confidence,
+                        peak: interval.peak_datetime?,
+                        peak_frame: interval.peak_frame_index,
+                        frames: interval.frames,
+                    })
+                })
+        })
+        .collect();
+    entries.sort_by(|a, b| {
+        a.start
+            .cmp(&b.start)
+            .then(a.video.cmp(b.video))
+            .then(a.label.cmp(&b.label))
+    });
+    entries
+}
+
+pub fn timeline_csv(entries: &[TimelineEntry]) -> String {
+    let mut csv = String::from("start,end,video,label,max_confidence,peak,peak_frame,frames\n");
+    for entry in entries {
+        csv.push_str(&format!(
+            "{},{},{},{},{:.3},{},{},{}\n",
+            entry.start.to_rfc3339_opts(SecondsFormat::Millis, true),
+            entry.end.to_rfc3339_opts(SecondsFormat::Millis, true),
+            csv_field(entry.video),
+            csv_field(&entry.label),
+            entry.max_confidence,
+            entry.peak.to_rfc3339_opts(SecondsFormat::Millis, true),
+            entry.peak_frame,
+            entry.frames
+        ));
+    }
+    csv
+}
+
+pub fn timeline_json(entries: &[TimelineEntry]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(entries)?)
+}
+
+// Videos with detections but no datetimes, which the combined timeline leaves out
+pub fn undated_videos<'a>(
+    videos: impl IntoIterator<Item = (&'a str, &'a [SynchronizedResult])>,
+) -> Vec<&'a str> {
+    videos
+        .into_iter()
+        .filter(|(_, results)| {
+            results.iter().any(|r| !r.video_objects.is_empty())
+                && results.iter().all(|r| r.datetime.is_none())
+        })
+        .map(|(video, _)| video)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame_analyzer::DetectedObject;
+
+    // A frame a second, with "person" detected in the frames marked true
+    fn frames(person: &[bool]) -> Vec<SynchronizedResult> {
+        person
+            .iter()
+            .enumerate()
+            .map(|(i, &present)| SynchronizedResult {
+                timestamp: i as f64,
+                video_objects: present
+                    .then(|| DetectedObject::from(("person".to_string(), 0.9f32, [0.0; 4])))
+                    .into_iter()
+                    .collect(),
+                audio_text: None,
+                quality: None,
+                datetime: None,
+                unmatched_audio: false,
+                interpolated: false,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_one_frame_gap_is_bridged_and_a_long_gap_is_not() {
+        let results = frames(&[true, true, false, true, false, false, false, false, true]);
+        let spans: Vec<(f64, f64, usize)> = object_intervals(&results, 2.5)
+            .iter()
+            .map(|interval| (interval.start, interval.end, interval.frames))
+            .collect();
+        assert_eq!(spans, [(0.0, 3.0, 3), (8.0, 8.0, 1)]);
+    }
+
+    #[test]
+    fn no_bridge_gap_ends_the_run_at_the_first_frame_without_the_label() {
+        let results = frames(&[true, false, true]);
+        let spans: Vec<(f64, f64)> = object_intervals(&results, 0.0)
+            .iter()
+            .map(|interval| (interval.start, interval.end))
+            .collect();
+        assert_eq!(spans, [(0.0, 0.0), (2.0, 2.0)]);
+    }
+}
@@ -0,0 +1,254 @@
+use ffmpeg_next::color::{Primaries, Range, TransferCharacteristic};
+use ffmpeg_next::decoder;
+use ffmpeg_next::ffi;
+use ffmpeg_next::software::scaling;
+
+// SDR reference white (ITU-R BT.2408); HDR content at this level maps to 1.0
+const REFERENCE_WHITE_NITS: f32 = 203.0;
+// Assumed mastering peak when the stream doesn't say otherwise
+const PEAK_NITS: f32 = 1000.0;
+// Resolution of the output gamma lookup table
+const ENCODE_LUT_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HdrTransfer {
+    // SMPTE ST 2084, used by HDR10 / Dolby Vision
+    Pq,
+    // ARIB STD-B67 hybrid log-gamma, used in broadcast
+    Hlg,
+}
+
+// HDR streams are recognised by their transfer function; 10-bit SDR is not HDR
+pub fn hdr_transfer(decoder: &decoder::Video) -> Option<HdrTransfer> {
+    match decoder.color_transfer_characteristic() {
+        TransferCharacteristic::SMPTE2084 => Some(HdrTransfer::Pq),
+        TransferCharacteristic::ARIB_STD_B67 => Some(HdrTransfer::Hlg),
+        _ => None,
+    }
+}
+
+pub fn is_bt2020(decoder: &decoder::Video) -> bool {
+    decoder.color_primaries() == Primaries::BT2020
+}
+
+// swscale assumes BT.601 for YUV -> RGB unless told otherwise, which shifts BT.2020
+// colors before tone mapping even starts
+pub fn use_bt2020_matrix(scaler: &mut scaling::Context, decoder: &decoder::Video) {
+    let full_range = i32::from(decoder.color_range() == Range::JPEG);
+    unsafe {
+        let coefficients = ffi::sws_getCoefficients(ffi::SWS_CS_BT2020 as i32);
+        ffi::sws_setColorspaceDetails(
+            scaler.as_mut_ptr(),
+            coefficients,
+            full_range,
+            coefficients,
+            1,
+            0,
+            1 << 16,
+            1 << 16,
+        );
+    }
+}
+
+// Maps 16-bit HDR RGB (as produced by swscale in RGB48) to 8-bit SDR: linearize with
+// the stream's transfer function, convert BT.2020 primaries to BT.709, compress
+// highlights with an extended Reinhard curve, then apply BT.709/sRGB gamma
+pub struct ToneMapper {
+    // Linear light per 16-bit code value, relative to reference white
+    linearize: Vec<f32>,
+    // Gamma-encoded 8-bit value per linear level in [0, 1]
+    encode: Vec<u8>,
+    convert_primaries: bool,
+    white_point: f32,
+}
+
+impl ToneMapper {
+    pub fn new(transfer: HdrTransfer, bt2020: bool) -> Self {
+        let linearize = (0..=u16::MAX)
+            .map(|code| {
+                let signal = code as f32 / u16::MAX as f32;
+                let nits = match transfer {
+                    HdrTransfer::Pq => pq_to_nits(signal),
+                    HdrTransfer::Hlg => hlg_to_nits(signal),
+                };
+                nits / REFERENCE_WHITE_NITS
+            })
+            .collect();
+        let encode = (0..ENCODE_LUT_SIZE)
+            .map(|i| {
+                let linear = i as f32 / (ENCODE_LUT_SIZE - 1) as f32;
+                (srgb_encode(linear) * 255.0).round() as u8
+            })
+            .collect();
+
+        Self {
+            linearize,
+            encode,
+            convert_primaries: bt2020,
+            white_point: PEAK_NITS / REFERENCE_WHITE_NITS,
+        }
+    }
+
+    pub fn map(&self, rgb16: &[u16]) -> Vec<u8> {
+        let white_sq = self.white_point * self.white_point;
+        let mut rgb = Vec::with_capacity(rgb16.len());
+
+        for pixel in rgb16.chunks_exact(3) {
+            let mut linear = [
+                self.linearize[pixel[0] as usize],
+                self.linearize[pixel[1] as usize],
+                self.linearize[pixel[2] as usize],
+            ];
+            if self.convert_primaries {
+                linear = bt2020_to_bt709(linear);
+            }
+            for channel in linear {
+                let c = channel.max(0.0);
+                let mapped = (c * (1.0 + c / white_sq) / (1.0 + c)).min(1.0);
+                rgb.push(self.encode[(mapped * (ENCODE_LUT_SIZE - 1) as f32) as usize]);
+            }
+        }
+        rgb
+    }
+}
+
+fn pq_to_nits(signal: f32) -> f32 {
+    const M1: f32 = 2610.0 / 16384.0;
+    const M2: f32 = 2523.0 / 4096.0 * 128.0;
+    const C1: f32 = 3424.0 / 4096.0;
+    const C2: f32 = 2413.0 / 4096.0 * 32.0;
+    const C3: f32 = 2392.0 / 4096.0 * 32.0;
+
+    let p = signal.powf(1.0 / M2);
+    let linear = ((p - C1).max(0.0) / (C2 - C3 * p)).powf(1.0 / M1);
+    linear * 10000.0
+}
+
+// Inverse HLG OETF followed by the reference OOTF (system gamma 1.2) for a
+// PEAK_NITS display; the OOTF is applied per channel rather than on luminance
+fn hlg_to_nits(signal: f32) -> f32 {
+    const A: f32 = 0.178_832_77;
+    const B: f32 = 0.284_668_92;
+    const C: f32 = 0.559_910_7;
+
+    let scene = if signal <= 0.5 {
+        signal * signal / 3.0
+    } else {
+        (((signal - C) / A).exp() + B) / 12.0
+    };
+    scene.powf(1.2) * PEAK_NITS
+}
+
+// Linear-light BT.2020 -> BT.709 (ITU-R BT.2087); out-of-gamut values clamp later
+fn bt2020_to_bt709([r, g, b]: [f32; 3]) -> [f32; 3] {
+    [
+        1.6605 * r - 0.5876 * g - 0.0728 * b,
+        -0.1246 * r + 1.1329 * g - 0.0083 * b,
+        -0.0182 * r - 0.1006 * g + 1.1187 * b,
+    ]
+}
+
+fn srgb_encode(linear: f32) -> f32 {
+    if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(signal: f32) -> u16 {
+        (signal * u16::MAX as f32).round() as u16
+    }
+
+    fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn pq_curve_matches_st_2084_reference_levels() {
+        assert_eq!(pq_to_nits(0.0), 0.0);
+        assert_close(pq_to_nits(0.5081), 100.0, 0.5);
+        // SDR reference white is 58% PQ (ITU-R BT.2408)
+        assert_close(pq_to_nits(0.5807), REFERENCE_WHITE_NITS, 0.5);
+        assert_close(pq_to_nits(0.7518), 1000.0, 2.0);
+        assert_close(pq_to_nits(1.0), 10000.0, 1.0);
+    }
+
+    #[test]
+    fn hlg_curve_is_continuous_and_peaks_at_the_display_peak() {
+        assert_eq!(hlg_to_nits(0.0), 0.0);
+        // The square-root and log segments meet at 50%
+        assert_close(hlg_to_nits(0.4999), hlg_to_nits(0.5001), 0.1);
+        // Reference white is 75% HLG on a 1000-nit display (ITU-R BT.2408)
+        assert_close(hlg_to_nits(0.75), REFERENCE_WHITE_NITS, 0.5);
+        assert_close(hlg_to_nits(1.0), PEAK_NITS, 0.5);
+    }
+
+    #[test]
+    fn pq_highlights_roll_off_below_white_up_to_the_mastering_peak() {
+        let tone_mapper = ToneMapper::new(HdrTransfer::Pq, true);
+        let gray = |signal: f32| {
+            let code = code(signal);
+            tone_mapper.map(&[code, code, code])
+        };
+
+        assert_eq!(gray(0.0), [0, 0, 0]);
+        // Reference white keeps headroom for highlights
+        assert_eq!(gray(0.5807), [191, 191, 191]);
+        let highlights: Vec<u8> = [0.6, 0.65, 0.7, 0.74].iter().map(|&s| gray(s)[0]).collect();
+        assert!(
+            highlights.windows(2).all(|pair| pair[0] < pair[1]),
+            "{:?}",
+            highlights
+        );
+        assert!(
+            highlights.iter().all(|&value| value < 255),
+            "{:?}",
+            highlights
+        );
+        // Only the mastering peak and above reach white
+        assert_eq!(gray(0.7518), [255, 255, 255]);
+        assert_eq!(gray(1.0), [255, 255, 255]);
+    }
+
+    #[test]
+    fn hlg_reference_white_matches_pq_and_its_peak_maps_to_white() {
+        let tone_mapper = ToneMapper::new(HdrTransfer::Hlg, true);
+        let gray = |signal: f32| {
+            let code = code(signal);
+            tone_mapper.map(&[code, code, code])
+        };
+
+        assert_eq!(gray(0.0), [0, 0, 0]);
+        assert_eq!(gray(0.75), [191, 191, 191]);
+        let highlights: Vec<u8> = [0.8, 0.85, 0.9, 0.95].iter().map(|&s| gray(s)[0]).collect();
+        assert!(
+            highlights.windows(2).all(|pair| pair[0] < pair[1]),
+            "{:?}",
+            highlights
+        );
+        assert!(
+            highlights.iter().all(|&value| value < 255),
+            "{:?}",
+            highlights
+        );
+        assert_eq!(gray(1.0), [255, 255, 255]);
+    }
+
+    #[test]
+    fn bt2020_primaries_leave_gray_unchanged() {
+        let [r, g, b] = bt2020_to_bt709([0.5, 0.5, 0.5]);
+        for channel in [r, g, b] {
+            assert_close(channel, 0.5, 1e-3);
+        }
+    }
+}
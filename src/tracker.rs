@@ -0,0 +1,217 @@
+use crate::frame_analyzer::{DetectedObject, FrameResult};
+use crate::ml_backend::FrameAnalysis;
+use crate::postprocess::iou;
+
+// Timestamps closer than this are treated as the same frame
+const TIMESTAMP_EPSILON: f64 = 1e-6;
+
+#[derive(Debug, Clone)]
+pub struct TrackObservation {
+    pub frame_index: usize,
+    pub detection_index: usize,
+    pub timestamp: f64,
+    pub bbox: [f32; 4],
+    // Confidence as reported by the model, before any smoothing
+    pub raw_confidence: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Track {
+    pub id: usize,
+    pub label: String,
+    pub observations: Vec<TrackObservation>,
+}
+
+impl Track {
+    fn last(&self) -> &TrackObservation {
+        self.observations.last().unwrap()
+    }
+}
+
+// Greedy frame-to-frame association: a detection continues the same-label track
+// from the previous frame it overlaps most, otherwise it starts a new track.
+pub fn track_detections(frames: &[FrameAnalysis], iou_threshold: f32) -> Vec<Track> {
+    let mut tracks: Vec<Track> = Vec::new();
+
+    for (frame_index, frame) in frames.iter().enumerate() {
+        let mut claimed = vec![false; tracks.len()];
+
+        for (detection_index, detection) in frame.detections.iter().enumerate() {
+            let best = tracks
+                .iter()
+                .enumerate()
+                .filter(|(t, track)| {
+                    !claimed[*t]
+                        && track.label == detection.label
+                        && track.last().frame_index + 1 == frame_index
+                })
+                .map(|(t, track)| (t, iou(&track.last().bbox, &detection.bbox)))
+                .filter(|(_, overlap)| *overlap >= iou_threshold)
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+
+            let observation = TrackObservation {
+                frame_index,
+                detection_index,
+                timestamp: frame.timestamp,
+                bbox: detection.bbox,
+                raw_confidence: detection.confidence,
+            };
+
+            match best {
+                Some((t, _)) => {
+                    claimed[t] = true;
+                    tracks[t].observations.push(observation);
+                }
+                None => {
+                    tracks.push(Track {
+                        id: tracks.len(),
+                        label: detection.label.clone(),
+                        observations: vec![observation],
+                    });
+                    claimed.push(true);
+                }
+            }
+        }
+    }
+
+    tracks
+}
+
+// Replaces each tracked detection's confidence with an exponential moving average
+// along its track; the raw values stay on the track observations.
+pub fn smooth_confidences(frames: &mut [FrameAnalysis], tracks: &[Track], alpha: f32) {
+    for track in tracks {
+        let mut smoothed: Option<f32> = None;
+        for observation in &track.observations {
+            let value = match smoothed {
+                Some(previous) => alpha * observation.raw_confidence + (1.0 - alpha) * previous,
+                None => observation.raw_confidence,
+            };
+            smoothed = Some(value);
+            frames[observation.frame_index].detections[observation.detection_index].confidence =
+                value;
+        }
+    }
+}
+
+// Fills in each track at `target_timestamps` that fall strictly between two of its
+// observations, linearly interpolating the box and raw confidence. Synthesized
+// detections are flagged `interpolated` and added to the result at that timestamp,
+// or to a new result (no quality, no real detections) if none exists.
+pub fn interpolate_tracks(
+    results: &mut Vec<FrameResult>,
+    tracks: &[Track],
+    target_timestamps: &[f64],
+    model: &str,
+) {
+    for &target in target_timestamps {
+        let mut interpolated = Vec::new();
+        for track in tracks {
+            let Some(after) = track.observations.iter().position(|o| o.timestamp > target) else {
+                continue;
+            };
+            if after == 0 {
+                continue;
+            }
+            let (previous, next) = (&track.observations[after - 1], &track.observations[after]);
+            if (previous.timestamp - target).abs() < TIMESTAMP_EPSILON {
+                continue;
+            }
+
+            let t = ((target - previous.timestamp) / (next.timestamp - previous.timestamp)) as f32;
+            let lerp = |a: f32, b: f32| a + (b - a) * t;
+            interpolated.push(DetectedObject {
+                label: track.label.clone(),
+                confidence: lerp(previous.raw_confidence, next.raw_confidence),
+                raw_confidence: None,
+                bbox: std::array::from_fn(|i| lerp(previous.bbox[i], next.bbox[i])),
+                frame_index: previous.frame_index,
+                model: model.to_string(),
+                extraction_method: "interpolated".to_string(),
+                class_id: None,
+                interpolated: true,
+            });
+        }
+        if interpolated.is_empty() {
+            continue;
+        }
+
+        match results
+            .iter_mut()
+            .find(|r| (r.timestamp - target).abs() < TIMESTAMP_EPSILON)
+        {
+            Some(result) => result.objects.extend(interpolated),
+            None => results.push(FrameResult {
+                timestamp: target,
+                objects: interpolated,
+                quality: None,
+                interpolated: true,
+            }),
+        }
+    }
+
+    results.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ml_backend::DetectionResult;
+
+    fn frame(timestamp: f64, confidence: f32) -> FrameAnalysis {
+        FrameAnalysis {
+            timestamp,
+            detections: vec![DetectionResult {
+                label: "person".to_string(),
+                confidence,
+                bbox: [10.0, 10.0, 50.0, 90.0],
+            }],
+        }
+    }
+
+    #[test]
+    fn smoothing_bridges_a_single_low_confidence_frame() {
+        let mut frames: Vec<FrameAnalysis> = [0.9, 0.9, 0.2, 0.9, 0.9]
+            .iter()
+            .enumerate()
+            .map(|(i, &confidence)| frame(i as f64, confidence))
+            .collect();
+        let tracks = track_detections(&frames, 0.3);
+        assert_eq!(tracks.len(), 1);
+
+        smooth_confidences(&mut frames, &tracks, 0.3);
+
+        let threshold = 0.5;
+        assert!(frames
+            .iter()
+            .all(|frame| frame.detections[0].confidence >= threshold));
+        // The dip still pulls the smoothed value down, and the raw value is kept
+        assert!(frames[2].detections[0].confidence < 0.9);
+        assert_eq!(tracks[0].observations[2].raw_confidence, 0.2);
+    }
+
+    #[test]
+    fn smoothing_leaves_the_first_observation_unchanged() {
+        let mut frames = vec![frame(0.0, 0.4), frame(1.0, 0.8)];
+        let tracks = track_detections(&frames, 0.3);
+
+        smooth_confidences(&mut frames, &tracks, 0.5);
+
+        assert_eq!(frames[0].detections[0].confidence, 0.4);
+        assert!((frames[1].detections[0].confidence - 0.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn interpolated_results_are_marked() {
+        let frames = vec![frame(0.0, 0.9), frame(1.0, 0.9)];
+        let tracks = track_detections(&frames, 0.3);
+        let mut results: Vec<FrameResult> = frames.into_iter().map(FrameResult::from).collect();
+
+        interpolate_tracks(&mut results, &tracks, &[0.5], "mock");
+
+        let added = results.iter().find(|r| r.timestamp == 0.5).unwrap();
+        assert!(added.interpolated);
+        assert!(added.objects.iter().all(|o| o.interpolated));
+        assert_eq!(results.iter().filter(|r| !r.interpolated).count(), 2);
+    }
+}
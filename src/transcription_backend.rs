@@ -0,0 +1,243 @@
+use crate::audio_processor::AudioResult;
+use crate::video_log::video_println;
+use anyhow::Result;
+use std::path::Path;
+
+pub trait TranscriptionBackend: Send + Sync {
+    fn load_model(&mut self, _model_path: Option<&Path>) -> Result<()> {
+        Ok(())
+    }
+
+    fn transcribe(&self, audio_path: &Path) -> Result<Vec<AudioResult>>;
+
+    // Sample rate and channel count the model wants; when set, the batch transcribes a
+    // PCM WAV in that format instead of the archival copy of the audio
+    fn audio_format(&self) -> Option<(u32, u16)> {
+        None
+    }
+
+    fn backend_name(&self) -> &'static str;
+}
+
+// Mock Backend (default)
+#[derive(Default)]
+pub struct MockTranscriptionBackend;
+
+impl MockTranscriptionBackend {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl TranscriptionBackend for MockTranscriptionBackend {
+    fn transcribe(&self, audio_path: &Path) -> Result<Vec<AudioResult>> {
+        // Pseudo-code for speech recognition (e.g., Whisper integration)
+        // In real implementation, you would call an external service or library
+        video_println!("Transcribing audio from: {:?}", audio_path);
+
+        let transcription = vec![
+            AudioResult {
+                start_time: 0.0,
+                end_time: 5.0,
+                text: "Hello, this is a sample transcription".to_string(),
+            },
+            AudioResult {
+                start_time: 5.0,
+                end_time: 10.0,
+                text: "This demonstrates audio processing capabilities".to_string(),
+            },
+        ];
+
+        Ok(transcription)
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "Mock Transcription Backend"
+    }
+}
+
+// Whisper Backend (optional)
+#[cfg(feature = "whisper")]
+pub struct WhisperBackend {
+    context: Option<whisper_rs::WhisperContext>,
+}
+
+#[cfg(feature = "whisper")]
+impl WhisperBackend {
+    pub fn new() -> Self {
+        Self { context: None }
+    }
+}
+
+#[cfg(feature = "whisper")]
+impl TranscriptionBackend for WhisperBackend {
+    fn load_model(&mut self, model_path: Option<&Path>) -> Result<()> {
+        let model_path = model_path.ok_or_else(|| {
+            anyhow::anyhow!("Whisper model path required (--transcription-model <ggml file>)")
+        })?;
+        let model_str = model_path
+            .to_str()
+            .ok_or_else(|| anyhow::anyhow!("Invalid Whisper model path: {:?}", model_path))?;
+
+        let context = whisper_rs::WhisperContext::new_with_params(
+            model_str,
+            whisper_rs::WhisperContextParameters::default(),
+        )?;
+
+        self.context = Some(context);
+        println!("Loaded Whisper model from {:?}", model_path);
+        Ok(())
+    }
+
+    fn transcribe(&self, audio_path: &Path) -> Result<Vec<AudioResult>> {
+        let context = self
+            .context
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Model not loaded"))?;
+
+        video_println!("Transcribing audio with Whisper from: {:?}", audio_path);
+
+        // The batch hands over a 16 kHz mono PCM WAV (see audio_format)
+        let pcm = read_pcm_wav(audio_path, 16_000)?;
+        let mut samples = vec![0.0f32; pcm.len()];
+        whisper_rs::convert_integer_to_float_audio(&pcm, &mut samples)?;
+
+        let mut params =
+            whisper_rs::FullParams::new(whisper_rs::SamplingStrategy::Greedy { best_of: 1 });
+        params.set_print_special(false);
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        params.set_print_timestamps(false);
+
+        let mut state = context.create_state()?;
+        state.full(params, &samples)?;
+
+        // Segment times are in centiseconds
+        let mut segments = Vec::new();
+        for segment in 0..state.full_n_segments()? {
+            segments.push(AudioResult {
+                start_time: state.full_get_segment_t0(segment)? as f64 / 100.0,
+                end_time: state.full_get_segment_t1(segment)? as f64 / 100.0,
+                text: state.full_get_segment_text(segment)?.trim().to_string(),
+            });
+        }
+        Ok(segments)
+    }
+
+    fn audio_format(&self) -> Option<(u32, u16)> {
+        Some((16_000, 1))
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "Whisper Transcription Backend"
+    }
+}
+
+// Samples of a mono 16-bit PCM WAV at `sample_rate`, as written by
+// AudioOutputSpec::pcm_wav
+#[cfg(feature = "whisper")]
+fn read_pcm_wav(path: &Path, sample_rate: u32) -> Result<Vec<i16>> {
+    let bytes =
+        std::fs::read(path).map_err(|e| anyhow::anyhow!("Failed to read {:?}: {}", path, e))?;
+    parse_pcm_wav(&bytes, sample_rate).map_err(|e| e.context(format!("Invalid WAV {:?}", path)))
+}
+
+#[cfg(feature = "whisper")]
+fn parse_pcm_wav(bytes: &[u8], sample_rate: u32) -> Result<Vec<i16>> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(anyhow::anyhow!("not a RIFF/WAVE file"));
+    }
+    let u16_at = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+    let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+
+    let mut format_ok = false;
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let size = u32_at(offset + 4) as usize;
+        let body = offset + 8;
+        let end = body.saturating_add(size).min(bytes.len());
+        match id {
+            b"fmt " if size >= 16 && body + 16 <= bytes.len() => {
+                let (format, channels) = (u16_at(body), u16_at(body + 2));
+                let (rate, bits) = (u32_at(body + 4), u16_at(body + 14));
+                if (format, channels, rate, bits) != (1, 1, sample_rate, 16) {
+                    return Err(anyhow::anyhow!(
+                        "expected {} Hz mono 16-bit PCM, got format {} with {} channels at {} Hz, {} bits",
+                        sample_rate,
+                        format,
+                        channels,
+                        rate,
+                        bits
+                    ));
+                }
+                format_ok = true;
+            }
+            b"data" if format_ok => {
+                return Ok(bytes[body..end]
+                    .chunks_exact(2)
+                    .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+                    .collect());
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even size
+        offset = body.saturating_add(size + size % 2);
+    }
+    Err(anyhow::anyhow!("no fmt chunk before the data chunk"))
+}
+
+pub fn create_transcription_backend(backend_type: &str) -> Result<Box<dyn TranscriptionBackend>> {
+    match backend_type.to_lowercase().as_str() {
+        "mock" => Ok(Box::new(MockTranscriptionBackend::new())),
+        #[cfg(feature = "whisper")]
+        "whisper" => Ok(Box::new(WhisperBackend::new())),
+        _ => {
+            println!(
+                "Warning: Unknown transcription backend '{}', falling back to mock",
+                backend_type
+            );
+            Ok(Box::new(MockTranscriptionBackend::new()))
+        }
+    }
+}
+
+#[cfg(all(test, feature = "whisper"))]
+mod tests {
+    use super::*;
+
+    fn wav(sample_rate: u32, channels: u16, samples: &[i16]) -> Vec<u8> {
+        let data: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&channels.to_le_bytes());
+        bytes.extend_from_slice(&sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&(sample_rate * 2 * channels as u32).to_le_bytes());
+        bytes.extend_from_slice(&(2 * channels).to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&data);
+        bytes
+    }
+
+    #[test]
+    fn parses_mono_pcm_samples() {
+        let bytes = wav(16_000, 1, &[0, 1, -1, i16::MAX, i16::MIN]);
+        assert_eq!(
+            parse_pcm_wav(&bytes, 16_000).unwrap(),
+            vec![0, 1, -1, i16::MAX, i16::MIN]
+        );
+    }
+
+    #[test]
+    fn rejects_other_formats() {
+        assert!(parse_pcm_wav(&wav(44_100, 1, &[0]), 16_000).is_err());
+        assert!(parse_pcm_wav(&wav(16_000, 2, &[0, 0]), 16_000).is_err());
+        assert!(parse_pcm_wav(b"not a wav file", 16_000).is_err());
+    }
+}
@@ -0,0 +1,79 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::cell::RefCell;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+pub const VIDEO_LOG: &str = "log.txt";
+
+thread_local! {
+    // Output directory of the video this thread is working on, if its log is captured
+    static CURRENT: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+// Restores the previous video (usually none) when with_video_log returns or unwinds
+struct Restore(Option<PathBuf>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        CURRENT.with(|current| *current.borrow_mut() = self.0.take());
+    }
+}
+
+// Runs `f` with this thread's video_println!/video_eprintln! lines also appended to
+// `output_dir`/log.txt (BatchConfig::per_video_logs). Every thread that works on a video
+// wraps its share of the work, so lines from parallel workers land in the right file.
+pub fn with_video_log<T>(output_dir: Option<&Path>, f: impl FnOnce() -> T) -> T {
+    let Some(output_dir) = output_dir else {
+        return f();
+    };
+    let previous = CURRENT.with(|current| current.replace(Some(output_dir.to_path_buf())));
+    let _restore = Restore(previous);
+    f()
+}
+
+// Appends `line` to the current video's log, if any. The file is opened per line, in
+// append mode, so threads never share a handle and the directory can be moved (see
+// OutputBucketing::ByDominantLabel) once the video is done. Logging shouldn't fail the
+// video, so errors are ignored.
+pub fn record(line: &str) {
+    CURRENT.with(|current| {
+        let Some(output_dir) = current.borrow().clone() else {
+            return;
+        };
+        let _ = fs::create_dir_all(&output_dir).and_then(|()| {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(output_dir.join(VIDEO_LOG))?;
+            writeln!(
+                file,
+                "{} {}",
+                DateTime::<Utc>::from(SystemTime::now())
+                    .to_rfc3339_opts(SecondsFormat::Millis, true),
+                line.trim()
+            )
+        });
+    });
+}
+
+// println! that is also recorded in the current video's log
+macro_rules! video_println {
+    ($($arg:tt)*) => {{
+        let line = format!($($arg)*);
+        println!("{}", line);
+        $crate::video_log::record(&line);
+    }};
+}
+
+// eprintln! that is also recorded in the current video's log
+macro_rules! video_eprintln {
+    ($($arg:tt)*) => {{
+        let line = format!($($arg)*);
+        eprintln!("{}", line);
+        $crate::video_log::record(&line);
+    }};
+}
+
+pub(crate) use {video_eprintln, video_println};
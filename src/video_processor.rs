@@ -1,67 +1,2053 @@
+use crate::tonemap::{hdr_transfer, is_bt2020, use_bt2020_matrix, ToneMapper};
+use crate::video_log::video_eprintln;
+use chrono::{DateTime, Utc};
 use ffmpeg_next::{
     format::{self, Pixel},
     frame, media,
     software::scaling::{self, Flags},
+    util::error::EAGAIN,
     Error,
 };
-use std::path::Path;
+use image::codecs::png::PngEncoder;
+use image::{ColorType, ImageEncoder};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::SyncSender;
+use std::sync::Arc;
 
-pub fn extract_frames(video_path: &Path, output_dir: &Path) -> Result<Vec<f64>, Error> {
+// Frames extract_frames lets the decoder get ahead of the PNG writer
+const STREAMING_QUEUE: usize = 8;
+// Subdirectory of the frames directory for reference keyframes, and the file pairing
+// them with the sampled frames
+pub const KEYFRAME_DIR: &str = "keyframes";
+pub const REFERENCES_FILE: &str = "references.json";
+// Subdirectory of the frames directory for the 8-bit copies 16-bit frames are analyzed
+// from (see FrameWriter::write)
+pub const ANALYSIS_DIR: &str = "analysis";
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum FrameSampling {
+    #[default]
+    All,
+    // One frame per target: the first decoded frame at or after each timestamp
+    Timestamps(Vec<f64>),
+    // One frame per this many seconds of presentation time. Slots are measured on frame
+    // PTS, not frame index, so the rate holds for variable-frame-rate video too
+    Interval(f64),
+    // Up to this many evenly spaced frames from each keyframe interval (GOP); shorter
+    // GOPs emit every frame
+    PerGop(usize),
+    // This many frames spread evenly from the first to the last frame (of the start/end
+    // window, if set), each target snapped to the nearest frame. Videos with fewer
+    // frames emit each of them once.
+    FixedCount(usize),
+}
+
+impl FrameSampling {
+    // Short name recorded with each detection (DetectedObject::extraction_method)
+    pub fn method_name(&self) -> &'static str {
+        match self {
+            FrameSampling::All => "all",
+            FrameSampling::Timestamps(_) => "timestamps",
+            FrameSampling::Interval(_) => "interval",
+            FrameSampling::PerGop(_) => "per_gop",
+            FrameSampling::FixedCount(_) => "fixed_count",
+        }
+    }
+}
+
+// How HDR (PQ / HLG) input is turned into frames. SDR input is unaffected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HdrMode {
+    // Tone-map to 8-bit BT.709 so frames look like an SDR grade
+    #[default]
+    ToneMap,
+    // Convert straight to 8-bit RGB; highlights clip and colors look washed out
+    Clip,
+    // Save 16-bit PNGs with the original signal; analysis reads a tone-mapped 8-bit copy
+    // saved under frames/analysis
+    Rgb16,
+}
+
+// Sample depth of saved frames for sources with more than 8 bits per component (10-bit
+// SDR as well as HDR); 8-bit sources are always saved as 8-bit PNGs. Frames are still
+// analyzed at 8 bits, from a copy under frames/analysis when the saved frame is 16-bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrameBitDepth {
+    #[default]
+    Eight,
+    // 16-bit PNGs of the unmodified signal
+    Sixteen,
+    // 8-bit PNGs for analysis plus a 32-bit float PFM of the signal (0.0 - 1.0) next to
+    // each one
+    Float,
+}
+
+// File names of saved frames. The zero-padded index always leads, so names sort in
+// frame order either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrameNaming {
+    // frame_0001.png
+    #[default]
+    IndexOnly,
+    // frame_0001_t12.480.png, with the timestamp in seconds
+    IndexAndTime,
+}
+
+// Upper bounds on frame size, so a corrupt or hostile input can't make decoding or
+// image loading allocate gigabytes. Frames beyond any limit are rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageLimits {
+    pub max_width: u32,
+    pub max_height: u32,
+    pub max_pixels: u64,
+}
+
+impl Default for ImageLimits {
+    fn default() -> Self {
+        Self {
+            max_width: 16384,
+            max_height: 16384,
+            // 8K UHD is ~33M pixels
+            max_pixels: 64_000_000,
+        }
+    }
+}
+
+impl ImageLimits {
+    pub fn check(&self, width: u32, height: u32) -> anyhow::Result<()> {
+        if width > self.max_width
+            || height > self.max_height
+            || width as u64 * height as u64 > self.max_pixels
+        {
+            return Err(anyhow::anyhow!(
+                "Frame size {}x{} exceeds the limit of {}x{} / {} pixels",
+                width,
+                height,
+                self.max_width,
+                self.max_height,
+                self.max_pixels
+            ));
+        }
+        Ok(())
+    }
+
+    // Reads only the image header, so an oversized file is rejected before it is decoded
+    pub fn check_file(&self, path: &Path) -> anyhow::Result<()> {
+        let (width, height) = image::image_dimensions(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {:?}: {}", path, e))?;
+        self.check(width, height)
+    }
+}
+
+// Region of interest cut out of every frame before it is saved or analyzed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CropRegion {
+    Pixels {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    // Fractions of the frame size, e.g. x: 0.5, width: 0.5 for the right half
+    Normalized {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl CropRegion {
+    // Rejects empty regions and normalized ones that don't start inside the frame
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match *self {
+            CropRegion::Pixels { width, height, .. } => {
+                if width == 0 || height == 0 {
+                    return Err(anyhow::anyhow!("Crop width and height must be positive"));
+                }
+            }
+            CropRegion::Normalized {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                if !(0.0..1.0).contains(&x) || !(0.0..1.0).contains(&y) {
+                    return Err(anyhow::anyhow!("Normalized crop x and y must be in [0, 1)"));
+                }
+                if !(width > 0.0 && width <= 1.0 && height > 0.0 && height <= 1.0) {
+                    return Err(anyhow::anyhow!(
+                        "Normalized crop width and height must be in (0, 1]"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Pixel rectangle for a frame of the given size, clamped to lie inside it. At least
+    // one pixel wide and high, even for regions validate() would reject.
+    pub fn resolve(&self, frame_width: u32, frame_height: u32) -> PixelRect {
+        let (x, y, width, height) = match *self {
+            CropRegion::Pixels {
+                x,
+                y,
+                width,
+                height,
+            } => (x, y, width, height),
+            CropRegion::Normalized {
+                x,
+                y,
+                width,
+                height,
+            } => (
+                (x * frame_width as f32).round() as u32,
+                (y * frame_height as f32).round() as u32,
+                (width * frame_width as f32).round() as u32,
+                (height * frame_height as f32).round() as u32,
+            ),
+        };
+
+        let x = x.min(frame_width.saturating_sub(1));
+        let y = y.min(frame_height.saturating_sub(1));
+        PixelRect {
+            x,
+            y,
+            width: width.min(frame_width - x).max(1),
+            height: height.min(frame_height - y).max(1),
+        }
+    }
+}
+
+impl std::str::FromStr for CropRegion {
+    type Err = anyhow::Error;
+
+    // "x,y,width,height" in pixels, or with every value a percent of the frame size,
+    // e.g. "50%,0%,50%,100%" for the right half
+    fn from_str(value: &str) -> anyhow::Result<Self> {
+        let invalid = || anyhow::anyhow!("Invalid crop (expected x,y,width,height): {}", value);
+        let parts: Vec<&str> = value.split(',').map(str::trim).collect();
+        if parts.len() != 4 {
+            return Err(invalid());
+        }
+        if parts.iter().all(|part| part.ends_with('%')) {
+            let mut fractions = [0.0f32; 4];
+            for (fraction, part) in fractions.iter_mut().zip(&parts) {
+                let percent = part.trim_end_matches('%').trim();
+                *fraction = percent.parse::<f32>().map_err(|_| invalid())? / 100.0;
+            }
+            let [x, y, width, height] = fractions;
+            return Ok(CropRegion::Normalized {
+                x,
+                y,
+                width,
+                height,
+            });
+        }
+        let mut pixels = [0u32; 4];
+        for (pixel, part) in pixels.iter_mut().zip(&parts) {
+            *pixel = part.parse().map_err(|_| invalid())?;
+        }
+        let [x, y, width, height] = pixels;
+        Ok(CropRegion::Pixels {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+}
+
+impl PixelRect {
+    // Maps a crop-local [x1, y1, x2, y2] box back to full-frame coordinates
+    pub fn to_full_frame(&self, bbox: [f32; 4]) -> [f32; 4] {
+        let (dx, dy) = (self.x as f32, self.y as f32);
+        [bbox[0] + dx, bbox[1] + dy, bbox[2] + dx, bbox[3] + dy]
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExtractionOptions {
+    pub sampling: FrameSampling,
+    // Hard safety cap on emitted frames, independent of sampling
+    pub max_frames: Option<usize>,
+    // Time window in seconds; emitted timestamps stay relative to the video start
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+    // Frames are cropped before being emitted, so saved frames and detections are
+    // crop-local
+    pub crop: Option<CropRegion>,
+    // Compute FrameQuality for every emitted frame
+    pub quality_metrics: bool,
+    pub hdr: HdrMode,
+    pub bit_depth: FrameBitDepth,
+    pub image_limits: ImageLimits,
+    // Corrupt packets (rejected by the decoder) that are logged and skipped before
+    // decoding gives up; 0 fails on the first one. Frames that fail to decode from an
+    // accepted packet are always skipped.
+    pub max_decode_errors: usize,
+    // Keep frames in stored orientation instead of applying the stream's display matrix
+    // (phone videos are often stored sideways with a 90/270 degree rotation tag)
+    pub ignore_rotation: bool,
+    // Passed to the decoder when it is opened; see open_decoder
+    pub decoder_options: HashMap<String, String>,
+    pub frame_naming: FrameNaming,
+    // Pair every emitted frame with the keyframe that starts its GOP, as a stable
+    // reference for change-from-reference analysis
+    pub reference_keyframes: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct ExtractedFrames {
+    pub timestamps: Vec<f64>,
+    // Parallel to timestamps; None unless quality_metrics was requested
+    pub qualities: Vec<Option<FrameQuality>>,
+    // Parallel to timestamps; None unless reference_keyframes was requested
+    pub references: Vec<Option<FrameMeta>>,
+    pub truncated: bool,
+    pub skipped_packets: usize,
+    pub resolution_changes: usize,
+}
+
+// The keyframe a sampled frame was paired with, as decoded. Frames from the same GOP
+// share one.
+#[derive(Debug)]
+pub struct ReferenceKeyframe {
+    pub timestamp: f64,
+    pub width: u32,
+    pub height: u32,
+    pub rgb: Vec<u8>,
+}
+
+// A saved frame and the reference keyframe saved for it, as written to references.json.
+// Paths are relative to the frames directory.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FrameMeta {
+    pub frame: PathBuf,
+    pub timestamp: f64,
+    pub keyframe: PathBuf,
+    pub keyframe_timestamp: f64,
+}
+
+// How decoding went, apart from the frames themselves
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DecodeSummary {
+    // max_frames cut the output short
+    pub truncated: bool,
+    // Corrupt packets skipped under max_decode_errors
+    pub skipped_packets: usize,
+    // Times the decoded frame size or pixel format changed mid-stream (concatenated or
+    // adaptive streams); the scaler is rebuilt for each
+    pub resolution_changes: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameQuality {
+    // Variance of the Laplacian of the luma plane; low values mean a blurry frame
+    pub sharpness: f32,
+    // Mean luma, 0-255; very low or high values mean under/overexposure
+    pub brightness: f32,
+}
+
+// Phones and screen recorders often produce variable-frame-rate video, where the
+// nominal rate (r_frame_rate) differs from the average rate. Index-based assumptions
+// like "every 30th frame is one second" break there; use FrameSampling::Interval.
+pub fn is_variable_frame_rate(video_path: &Path) -> Result<bool, Error> {
+    ffmpeg_next::init()?;
+
+    let ictx = format::input(&video_path)?;
+    let stream = ictx
+        .streams()
+        .best(media::Type::Video)
+        .ok_or(Error::StreamNotFound)?;
+
+    let nominal = f64::from(stream.rate());
+    let average = f64::from(stream.avg_frame_rate());
+    if nominal <= 0.0 || average <= 0.0 {
+        return Ok(false);
+    }
+    // Allow for rounding in containers that store e.g. 29.97 as 30000/1001 vs 2997/100
+    Ok((nominal - average).abs() / nominal > 0.01)
+}
+
+// Cheap per-second motion estimate that never decodes pixels: inter-coded packets
+// are small when little changes between frames, so each second's mean non-keyframe
+// packet size is divided by the mean keyframe size. Static footage scores near 0;
+// busy scenes approach or exceed 1. Seconds containing only keyframes score 0.
+pub fn motion_profile(video_path: &Path) -> Result<Vec<(f64, f32)>, Error> {
     ffmpeg_next::init()?;
 
     let mut ictx = format::input(&video_path)?;
-    let video_stream = ictx
+    let video_stream_index = ictx
+        .streams()
+        .best(media::Type::Video)
+        .ok_or(Error::StreamNotFound)?
+        .index();
+
+    let mut key_bytes = 0usize;
+    let mut key_count = 0usize;
+    // second -> (inter-coded bytes, inter-coded packets)
+    let mut seconds: std::collections::BTreeMap<i64, (usize, usize)> = Default::default();
+
+    for (stream, packet) in ictx.packets() {
+        if stream.index() != video_stream_index {
+            continue;
+        }
+        let Some(ts) = packet.pts().or(packet.dts()) else {
+            continue;
+        };
+        let second = (ts as f64 * f64::from(stream.time_base())).floor() as i64;
+        let entry = seconds.entry(second).or_default();
+
+        if packet.is_key() {
+            key_bytes += packet.size();
+            key_count += 1;
+        } else {
+            entry.0 += packet.size();
+            entry.1 += 1;
+        }
+    }
+
+    let mean_key = if key_count > 0 {
+        key_bytes as f64 / key_count as f64
+    } else {
+        1.0
+    };
+    Ok(seconds
+        .into_iter()
+        .map(|(second, (bytes, count))| {
+            let score = if count > 0 {
+                (bytes as f64 / count as f64 / mean_key) as f32
+            } else {
+                0.0
+            };
+            (second as f64, score)
+        })
+        .collect())
+}
+
+// Frames in each GOP, keyed by the PTS of the keyframe that starts it. Counted from
+// packets, so nothing is decoded.
+fn gop_sizes(video_path: &Path) -> Result<HashMap<i64, usize>, Error> {
+    let mut ictx = format::input(&video_path)?;
+    let video_stream_index = ictx
+        .streams()
+        .best(media::Type::Video)
+        .ok_or(Error::StreamNotFound)?
+        .index();
+
+    let mut sizes = HashMap::new();
+    let mut current: Option<i64> = None;
+    for (stream, packet) in ictx.packets() {
+        if stream.index() != video_stream_index {
+            continue;
+        }
+        if packet.is_key() {
+            current = packet.pts();
+        }
+        if let Some(key_pts) = current {
+            *sizes.entry(key_pts).or_insert(0) += 1;
+        }
+    }
+    Ok(sizes)
+}
+
+// End of the video stream's last packet, for files that record no duration. Reads
+// every packet but decodes none.
+fn scanned_duration(video_path: &Path) -> Result<Option<f64>, Error> {
+    let mut ictx = format::input(&video_path)?;
+    let (video_stream_index, time_base) = ictx
+        .streams()
+        .best(media::Type::Video)
+        .map(|stream| (stream.index(), stream.time_base()))
+        .ok_or(Error::StreamNotFound)?;
+
+    let end = ictx
+        .packets()
+        .filter(|(stream, _)| stream.index() == video_stream_index)
+        .filter_map(|(_, packet)| Some(packet.pts()? + packet.duration()))
+        .max();
+    Ok(end
+        .map(|end| end as f64 * f64::from(time_base))
+        .filter(|duration| *duration > 0.0))
+}
+
+// Cover art in audio files shows up as a one-frame video stream; it doesn't count
+pub fn has_video_stream(path: &Path) -> Result<bool, Error> {
+    ffmpeg_next::init()?;
+
+    let ictx = format::input(&path)?;
+    let has_video = ictx.streams().any(|stream| {
+        stream.parameters().medium() == media::Type::Video
+            && !stream
+                .disposition()
+                .contains(format::stream::Disposition::ATTACHED_PIC)
+    });
+    Ok(has_video)
+}
+
+// The container's `creation_time` tag (ISO 8601, usually UTC), if present and parseable
+pub fn creation_time(path: &Path) -> Result<Option<DateTime<Utc>>, Error> {
+    ffmpeg_next::init()?;
+
+    let ictx = format::input(&path)?;
+    let creation_time = ictx
+        .metadata()
+        .get("creation_time")
+        .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+        .map(|time| time.with_timezone(&Utc));
+    Ok(creation_time)
+}
+
+// Container duration in seconds; None when the container doesn't say
+pub fn video_duration(video_path: &Path) -> Result<Option<f64>, Error> {
+    ffmpeg_next::init()?;
+
+    let ictx = format::input(&video_path)?;
+    let duration = ictx.duration();
+    Ok((duration > 0).then(|| duration as f64 / ffmpeg_next::ffi::AV_TIME_BASE as f64))
+}
+
+// Clockwise rotation (0, 90, 180 or 270) that shows the video stream upright, from its
+// display matrix
+fn stream_rotation(stream: &format::stream::Stream) -> u32 {
+    use ffmpeg_next::codec::packet::side_data::Type;
+
+    let Some(matrix) = stream
+        .side_data()
+        .find(|side_data| side_data.kind() == Type::DisplayMatrix)
+    else {
+        return 0;
+    };
+    // 3x3 matrix of i32
+    if matrix.data().len() < 36 {
+        return 0;
+    }
+    let counterclockwise =
+        unsafe { ffmpeg_next::ffi::av_display_rotation_get(matrix.data().as_ptr() as *const i32) };
+    if counterclockwise.is_nan() {
+        return 0;
+    }
+    ((-counterclockwise).rem_euclid(360.0) / 90.0).round() as u32 % 4 * 90
+}
+
+// Opens a decoder for a stream with `options` as its AVDictionary, e.g. "threads"
+// ("auto" or a count), "ec" (error concealment, "guess_mvs+deblock") or "skip_frame"
+// ("nokey" decodes keyframes only). Options the codec doesn't know are ignored.
+pub fn open_decoder(
+    parameters: ffmpeg_next::codec::Parameters,
+    options: &HashMap<String, String>,
+) -> Result<ffmpeg_next::decoder::Opened, Error> {
+    let decoder = ffmpeg_next::codec::context::Context::from_parameters(parameters)?.decoder();
+    let codec = ffmpeg_next::decoder::find(decoder.id()).ok_or(Error::DecoderNotFound)?;
+    let mut dictionary = ffmpeg_next::Dictionary::new();
+    for (key, value) in options {
+        dictionary.set(key, value);
+    }
+    decoder.open_as_with(codec, dictionary)
+}
+
+// Size of the frames extracted from the video stream: its coded size, turned by the
+// display matrix unless `ignore_rotation` is set (see ExtractionOptions)
+pub fn display_dimensions(video_path: &Path, ignore_rotation: bool) -> Result<(u32, u32), Error> {
+    ffmpeg_next::init()?;
+
+    let ictx = format::input(&video_path)?;
+    let stream = ictx
         .streams()
         .best(media::Type::Video)
         .ok_or(Error::StreamNotFound)?;
+    let decoder = ffmpeg_next::codec::context::Context::from_parameters(stream.parameters())?
+        .decoder()
+        .video()?;
+    let (width, height) = (decoder.width(), decoder.height());
+    if !ignore_rotation && stream_rotation(&stream) % 180 == 90 {
+        Ok((height, width))
+    } else {
+        Ok((width, height))
+    }
+}
+
+// Decodes on a separate thread through extract_frames_streaming while this one writes
+// the PNGs, so decoding and encoding overlap
+pub fn extract_frames(video_path: &Path, output_dir: &Path) -> Result<Vec<f64>, Error> {
+    let (sender, receiver) = std::sync::mpsc::sync_channel(STREAMING_QUEUE);
+    std::thread::scope(|scope| {
+        let decoder =
+            scope.spawn(move || extract_frames_streaming(video_path, FrameSampling::All, sender));
 
-    let video_stream_index = video_stream.index();
-    let context_decoder =
-        ffmpeg_next::codec::context::Context::from_parameters(video_stream.parameters())?;
-    let mut decoder = context_decoder.decoder().video()?;
-
-    let mut scaler = scaling::Context::get(
-        decoder.format(),
-        decoder.width(),
-        decoder.height(),
-        Pixel::RGB24,
-        decoder.width(),
-        decoder.height(),
-        Flags::BILINEAR,
-    )?;
+        let mut writer = FrameWriter::new(FrameBitDepth::Eight);
+        let mut timestamps = Vec::new();
+        let written =
+            receiver
+                .iter()
+                .enumerate()
+                .try_for_each(|(index, (timestamp, rgb, width, height))| {
+                    timestamps.push(timestamp);
+                    let frame = DecodedFrame {
+                        index,
+                        timestamp,
+                        width,
+                        height,
+                        rgb,
+                        quality: None,
+                        rgb16: None,
+                        reference: None,
+                    };
+                    let path = frame_path(output_dir, index, timestamp, FrameNaming::IndexOnly);
+                    writer.write(&frame, &path).map(|_| ())
+                });
 
+        // Dropping the receiver unblocks the decoder if a write failed
+        drop(receiver);
+        let decoded = decoder.join().map_err(|_| Error::Bug)?;
+        written?;
+        decoded?;
+        Ok(timestamps)
+    })
+}
+
+// Sends each sampled frame as (timestamp, packed RGB24, width, height) as soon as it is
+// decoded, for a consumer on another thread. A bounded channel keeps decoding from
+// running ahead of it; decoding stops once the receiver is dropped.
+pub fn extract_frames_streaming(
+    video_path: &Path,
+    sampling: FrameSampling,
+    sender: SyncSender<(f64, Vec<u8>, u32, u32)>,
+) -> Result<DecodeSummary, Error> {
+    let options = ExtractionOptions {
+        sampling,
+        ..ExtractionOptions::default()
+    };
+    decode_frames(video_path, &options, |frame| {
+        Ok(sender
+            .send((frame.timestamp, frame.rgb, frame.width, frame.height))
+            .is_ok())
+    })
+}
+
+// A decoded frame as packed RGB24 rows (no stride padding)
+#[derive(Debug, Clone)]
+pub struct DecodedFrame {
+    pub index: usize,
+    pub timestamp: f64,
+    pub width: u32,
+    pub height: u32,
+    pub rgb: Vec<u8>,
+    pub quality: Option<FrameQuality>,
+    // Untouched 16-bit samples, kept for HDR input in HdrMode::Rgb16 and for high bit
+    // depth input when a FrameBitDepth above Eight is requested
+    pub rgb16: Option<Vec<u16>>,
+    // Set when reference_keyframes was requested and a keyframe has been decoded
+    pub reference: Option<Arc<ReferenceKeyframe>>,
+}
+
+pub fn extract_frames_with_options(
+    video_path: &Path,
+    output_dir: &Path,
+    options: &ExtractionOptions,
+) -> Result<ExtractedFrames, Error> {
     let mut timestamps = Vec::new();
-    let mut frame_index = 0;
+    let mut qualities = Vec::new();
+    let mut references = Vec::new();
 
-    for (stream, packet) in ictx.packets() {
-        if stream.index() == video_stream_index {
-            decoder.send_packet(&packet)?;
-            let mut decoded = frame::Video::empty();
+    let mut writer = FrameWriter::new(options.bit_depth);
+    let summary = decode_frames(video_path, options, |frame| {
+        timestamps.push(frame.timestamp);
+        qualities.push(frame.quality);
+        let path = frame_path(
+            output_dir,
+            frame.index,
+            frame.timestamp,
+            options.frame_naming,
+        );
+        writer.write(&frame, &path)?;
+        references.push(writer.write_reference(&frame, &path, output_dir)?);
+        Ok(true)
+    })?;
+
+    if options.reference_keyframes {
+        let pairs: Vec<&FrameMeta> = references.iter().flatten().collect();
+        let json = serde_json::to_string_pretty(&pairs)
+            .map_err(|e| Error::Other { error: Box::new(e) })?;
+        std::fs::write(output_dir.join(REFERENCES_FILE), json)
+            .map_err(|e| Error::Other { error: Box::new(e) })?;
+    }
+
+    Ok(ExtractedFrames {
+        timestamps,
+        qualities,
+        references,
+        truncated: summary.truncated,
+        skipped_packets: summary.skipped_packets,
+        resolution_changes: summary.resolution_changes,
+    })
+}
+
+pub fn keyframe_path(output_dir: &Path, timestamp: f64) -> PathBuf {
+    output_dir
+        .join(KEYFRAME_DIR)
+        .join(format!("keyframe_t{:.3}.png", timestamp))
+}
+
+// Where FrameWriter saves the 8-bit copy of a 16-bit frame
+pub fn analysis_copy_path(frame_path: &Path) -> PathBuf {
+    let (dir, name) = (frame_path.parent(), frame_path.file_name());
+    dir.unwrap_or(Path::new(""))
+        .join(ANALYSIS_DIR)
+        .join(name.unwrap_or_default())
+}
+
+// The file analysis should read for a saved frame: its 8-bit copy, if it has one
+pub fn analysis_input(frame_path: &Path) -> PathBuf {
+    let copy = analysis_copy_path(frame_path);
+    if copy.exists() {
+        copy
+    } else {
+        frame_path.to_path_buf()
+    }
+}
+
+pub fn frame_path(output_dir: &Path, index: usize, timestamp: f64, naming: FrameNaming) -> PathBuf {
+    match naming {
+        FrameNaming::IndexOnly => output_dir.join(format!("frame_{:04}.png", index)),
+        FrameNaming::IndexAndTime => {
+            output_dir.join(format!("frame_{:04}_t{:.3}.png", index, timestamp))
+        }
+    }
+}
+
+// Writes frames as PNG, reusing its buffers across frames. Each PNG is encoded in
+// memory and written with a single write call instead of through an 8 KiB BufWriter,
+// which matters on network filesystems. The encoder settings are the ones
+// image::save_buffer uses, so the files are byte-identical.
+#[derive(Default)]
+pub struct FrameWriter {
+    bit_depth: FrameBitDepth,
+    encoded: Vec<u8>,
+    // Native-endian bytes of 16-bit frames, as the PNG encoder expects them
+    samples16: Vec<u8>,
+    // Timestamp of the reference keyframe saved last, so each is written once
+    last_reference: Option<f64>,
+}
+
+impl FrameWriter {
+    pub fn new(bit_depth: FrameBitDepth) -> Self {
+        Self {
+            bit_depth,
+            ..Self::default()
+        }
+    }
+
+    // Returns the file analysis should read: `path` itself, or for a 16-bit frame an
+    // 8-bit copy of its analysis pixels (tone-mapped for HDR) under frames/analysis, so
+    // the model never sees a naively truncated signal
+    pub fn write(&mut self, frame: &DecodedFrame, path: &Path) -> Result<PathBuf, Error> {
+        if let (FrameBitDepth::Float, Some(rgb16)) = (self.bit_depth, &frame.rgb16) {
+            self.encode_pfm(rgb16, frame.width, frame.height);
+            std::fs::write(path.with_extension("pfm"), &self.encoded)
+                .map_err(|e| Error::Other { error: Box::new(e) })?;
+        }
+
+        let (samples, color_type) = match &frame.rgb16 {
+            Some(rgb16) if self.bit_depth != FrameBitDepth::Float => {
+                self.samples16.clear();
+                self.samples16
+                    .extend(rgb16.iter().flat_map(|sample| sample.to_ne_bytes()));
+                (self.samples16.as_slice(), ColorType::Rgb16)
+            }
+            _ => (frame.rgb.as_slice(), ColorType::Rgb8),
+        };
+
+        self.encoded.clear();
+        PngEncoder::new(&mut self.encoded)
+            .write_image(samples, frame.width, frame.height, color_type)
+            .map_err(|e| Error::Other { error: Box::new(e) })?;
+        std::fs::write(path, &self.encoded).map_err(|e| Error::Other { error: Box::new(e) })?;
+        if color_type == ColorType::Rgb8 {
+            return Ok(path.to_path_buf());
+        }
+
+        let copy = analysis_copy_path(path);
+        if let Some(dir) = copy.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| Error::Other { error: Box::new(e) })?;
+        }
+        self.encoded.clear();
+        PngEncoder::new(&mut self.encoded)
+            .write_image(&frame.rgb, frame.width, frame.height, ColorType::Rgb8)
+            .map_err(|e| Error::Other { error: Box::new(e) })?;
+        std::fs::write(&copy, &self.encoded).map_err(|e| Error::Other { error: Box::new(e) })?;
+        Ok(copy)
+    }
 
-            while decoder.receive_frame(&mut decoded).is_ok() {
-                let timestamp = packet.pts().unwrap_or(0) as f64
-                    * stream.time_base().numerator() as f64
-                    / stream.time_base().denominator() as f64;
-                timestamps.push(timestamp);
-
-                let mut rgb_frame = frame::Video::empty();
-                scaler.run(&decoded, &mut rgb_frame)?;
-
-                let frame_path = output_dir.join(format!("frame_{:04}.png", frame_index));
-                image::save_buffer(
-                    &frame_path,
-                    rgb_frame.data(0),
-                    rgb_frame.width(),
-                    rgb_frame.height(),
-                    image::ColorType::Rgb8,
+    // Saves the frame's reference keyframe under output_dir/keyframes unless it was
+    // saved for an earlier frame, and returns the pairing for the frame saved at
+    // `path`. Reference keyframes are always 8-bit.
+    pub fn write_reference(
+        &mut self,
+        frame: &DecodedFrame,
+        path: &Path,
+        output_dir: &Path,
+    ) -> Result<Option<FrameMeta>, Error> {
+        let Some(reference) = &frame.reference else {
+            return Ok(None);
+        };
+        let keyframe = keyframe_path(output_dir, reference.timestamp);
+        if self.last_reference != Some(reference.timestamp) {
+            std::fs::create_dir_all(output_dir.join(KEYFRAME_DIR))
+                .map_err(|e| Error::Other { error: Box::new(e) })?;
+            self.encoded.clear();
+            PngEncoder::new(&mut self.encoded)
+                .write_image(
+                    &reference.rgb,
+                    reference.width,
+                    reference.height,
+                    ColorType::Rgb8,
                 )
                 .map_err(|e| Error::Other { error: Box::new(e) })?;
+            std::fs::write(&keyframe, &self.encoded)
+                .map_err(|e| Error::Other { error: Box::new(e) })?;
+            self.last_reference = Some(reference.timestamp);
+        }
+
+        let relative = |p: &Path| p.strip_prefix(output_dir).unwrap_or(p).to_path_buf();
+        Ok(Some(FrameMeta {
+            frame: relative(path),
+            timestamp: frame.timestamp,
+            keyframe: relative(&keyframe),
+            keyframe_timestamp: reference.timestamp,
+        }))
+    }
 
-                frame_index += 1;
+    // Color PFM: a text header, then little-endian f32 RGB rows from the bottom up (the
+    // negative scale marks little-endian)
+    fn encode_pfm(&mut self, rgb16: &[u16], width: u32, height: u32) {
+        self.encoded.clear();
+        self.encoded
+            .extend_from_slice(format!("PF\n{} {}\n-1.0\n", width, height).as_bytes());
+        for row in rgb16.chunks_exact(width as usize * 3).rev() {
+            for sample in row {
+                let value = *sample as f32 / u16::MAX as f32;
+                self.encoded.extend_from_slice(&value.to_le_bytes());
             }
         }
     }
+}
+
+// Push-style wrapper around FrameReader shared by the extraction paths. `emit` returns
+// Ok(false) to stop early.
+pub fn decode_frames<F>(
+    video_path: &Path,
+    options: &ExtractionOptions,
+    mut emit: F,
+) -> Result<DecodeSummary, Error>
+where
+    F: FnMut(DecodedFrame) -> Result<bool, Error>,
+{
+    let mut reader = FrameReader::with_options(video_path, options)?;
+    for frame in reader.by_ref() {
+        if !emit(frame?)? {
+            break;
+        }
+    }
+    Ok(DecodeSummary {
+        truncated: reader.truncated(),
+        skipped_packets: reader.skipped_packets(),
+        resolution_changes: reader.resolution_changes(),
+    })
+}
+
+// Lazily decodes a video one sampled frame at a time, applying the same sampling, time
+// window, crop, HDR handling and limits as extract_frames. Memory stays bounded by one
+// decoded frame (plus duplicates for coinciding timestamp targets), and dropping the
+// reader stops decoding.
+pub struct FrameReader {
+    options: ExtractionOptions,
+    ictx: format::context::Input,
+    decoder: ffmpeg_next::decoder::Video,
+    scaler: scaling::Context,
+    tone_mapper: Option<ToneMapper>,
+    // Source has more than 8 bits per component and bit_depth asks to keep them
+    keep_high_depth: bool,
+    // Clockwise rotation applied to every frame (see video_rotation)
+    rotation: u32,
+    video_stream_index: usize,
+    time_base: ffmpeg_next::Rational,
+    // Timestamp targets, consumed from the back as frames are emitted
+    targets: Vec<f64>,
+    // For timestamp sampling: the last frame inside the window, which serves any targets
+    // past the end of the stream
+    last_decoded: Option<(frame::Video, f64)>,
+    // How far past a frame a target may lie and still pick it (FixedCount only)
+    target_tolerance: f64,
+    gop_sizes: HashMap<i64, usize>,
+    // Frames decoded but not yet handed out
+    pending: VecDeque<DecodedFrame>,
+    frame_index: usize,
+    truncated: bool,
+    skipped_packets: usize,
+    resolution_changes: usize,
+    finished: bool,
+    // For interval sampling: time of the first frame, and the last slot emitted
+    interval_origin: Option<f64>,
+    last_slot: Option<i64>,
+    // For per-GOP sampling: frames in the current GOP (None if unknown), position in it
+    gop_size: Option<usize>,
+    gop_position: usize,
+    last_gop_slot: Option<usize>,
+    // For reference keyframes: the latest keyframe, kept decoded until a frame of its
+    // GOP is emitted, then converted once and shared
+    last_keyframe: Option<(frame::Video, f64)>,
+    reference: Option<Arc<ReferenceKeyframe>>,
+}
+
+impl FrameReader {
+    pub fn open(video_path: &Path, sampling: FrameSampling) -> Result<Self, Error> {
+        Self::with_options(
+            video_path,
+            &ExtractionOptions {
+                sampling,
+                ..ExtractionOptions::default()
+            },
+        )
+    }
+
+    pub fn with_options(video_path: &Path, options: &ExtractionOptions) -> Result<Self, Error> {
+        ffmpeg_next::init()?;
+
+        let mut targets = match &options.sampling {
+            FrameSampling::Timestamps(targets) => {
+                let mut targets = targets.clone();
+                targets.sort_by(|a, b| a.total_cmp(b));
+                targets
+            }
+            _ => Vec::new(),
+        };
+        targets.reverse();
+
+        let gop_sizes = match options.sampling {
+            FrameSampling::PerGop(_) => gop_sizes(video_path)?,
+            _ => HashMap::new(),
+        };
+
+        let mut ictx = format::input(&video_path)?;
+        let video_stream = ictx
+            .streams()
+            .best(media::Type::Video)
+            .ok_or(Error::StreamNotFound)?;
 
-    decoder.send_eof()?;
-    Ok(timestamps)
+        let video_stream_index = video_stream.index();
+        let time_base = video_stream.time_base();
+        let mut target_tolerance = 0.0;
+        if let FrameSampling::FixedCount(count) = options.sampling {
+            // Very short or single-frame videos often have no average rate yet
+            let frame_rate = [video_stream.avg_frame_rate(), video_stream.rate()]
+                .into_iter()
+                .map(f64::from)
+                .find(|rate| *rate > 0.0)
+                .unwrap_or(0.0);
+            let frame_interval = if frame_rate > 0.0 {
+                1.0 / frame_rate
+            } else {
+                0.0
+            };
+            // Containers written without an index (raw streams, interrupted recordings)
+            // may not know their duration; the stream, or failing that its packets, does
+            let container_duration = ictx.duration() as f64 / ffmpeg_next::ffi::AV_TIME_BASE as f64;
+            let stream_duration = video_stream.duration() as f64 * f64::from(time_base);
+            let duration = [container_duration, stream_duration]
+                .into_iter()
+                .find(|duration| *duration > 0.0)
+                .or_else(|| scanned_duration(video_path).ok().flatten());
+            let first = options.start.unwrap_or(0.0).max(0.0);
+            match duration {
+                Some(duration) => {
+                    // The last frame starts one frame interval before the end of the video
+                    let end = options.end.map_or(duration, |end| end.min(duration));
+                    let last = (end - frame_interval).max(first);
+                    targets = fixed_count_targets(count, first, last);
+                }
+                None => {
+                    video_eprintln!(
+                        "Warning: {:?} has no known duration to spread {} frames over; \
+                         sampling its first frame",
+                        video_path,
+                        count
+                    );
+                    targets = vec![first];
+                }
+            }
+            targets.reverse();
+            target_tolerance = frame_interval / 2.0;
+        }
+        let rotation = if options.ignore_rotation {
+            0
+        } else {
+            stream_rotation(&video_stream)
+        };
+        let decoder = open_decoder(video_stream.parameters(), &options.decoder_options)?.video()?;
+
+        // Frames are allocated at the coded size, so refuse before decoding the first one
+        if let Err(e) = options
+            .image_limits
+            .check(decoder.width(), decoder.height())
+        {
+            video_eprintln!("Warning: Rejecting {:?}: {}", video_path, e);
+            return Err(Error::InvalidData);
+        }
+
+        // HDR frames are converted at 16 bits and tone-mapped here instead of by swscale
+        let tone_mapper = match options.hdr {
+            HdrMode::Clip => None,
+            HdrMode::ToneMap | HdrMode::Rgb16 => hdr_transfer(&decoder)
+                .map(|transfer| ToneMapper::new(transfer, is_bt2020(&decoder))),
+        };
+
+        let keep_high_depth =
+            options.bit_depth != FrameBitDepth::Eight && component_depth(decoder.format()) > 8;
+
+        let mut scaler = scaling::Context::get(
+            decoder.format(),
+            decoder.width(),
+            decoder.height(),
+            if tone_mapper.is_some() || keep_high_depth {
+                Pixel::RGB48LE
+            } else {
+                Pixel::RGB24
+            },
+            decoder.width(),
+            decoder.height(),
+            Flags::BILINEAR,
+        )?;
+        if tone_mapper.is_some() && is_bt2020(&decoder) {
+            use_bt2020_matrix(&mut scaler, &decoder);
+        }
+
+        // Seek to the keyframe before the window instead of decoding from zero
+        if let Some(start) = options.start {
+            let position = (start * ffmpeg_next::ffi::AV_TIME_BASE as f64) as i64;
+            ictx.seek(position, ..position)?;
+        }
+
+        Ok(Self {
+            options: options.clone(),
+            ictx,
+            decoder,
+            scaler,
+            tone_mapper,
+            keep_high_depth,
+            rotation,
+            video_stream_index,
+            time_base,
+            targets,
+            target_tolerance,
+            gop_sizes,
+            pending: VecDeque::new(),
+            frame_index: 0,
+            truncated: false,
+            skipped_packets: 0,
+            resolution_changes: 0,
+            finished: false,
+            interval_origin: None,
+            last_slot: None,
+            gop_size: None,
+            gop_position: 0,
+            last_gop_slot: None,
+            last_keyframe: None,
+            reference: None,
+            last_decoded: None,
+        })
+    }
+
+    // Whether max_frames cut the output short
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    // Corrupt packets skipped so far (see max_decode_errors)
+    pub fn skipped_packets(&self) -> usize {
+        self.skipped_packets
+    }
+
+    // Mid-stream frame size or format changes seen so far
+    pub fn resolution_changes(&self) -> usize {
+        self.resolution_changes
+    }
+
+    // The scaler is built for one input size and format and refuses frames that differ,
+    // so it is rebuilt when the stream switches. Limits are checked again for the new size.
+    fn rebuild_scaler(&mut self, decoded: &frame::Video) -> Result<(), Error> {
+        let previous = self.scaler.input();
+        video_eprintln!(
+            "Note: Frame size changed mid-stream from {}x{} ({:?}) to {}x{} ({:?})",
+            previous.width,
+            previous.height,
+            previous.format,
+            decoded.width(),
+            decoded.height(),
+            decoded.format()
+        );
+        if let Err(e) = self
+            .options
+            .image_limits
+            .check(decoded.width(), decoded.height())
+        {
+            video_eprintln!("Warning: Rejecting frame: {}", e);
+            return Err(Error::InvalidData);
+        }
+
+        self.scaler = scaling::Context::get(
+            decoded.format(),
+            decoded.width(),
+            decoded.height(),
+            self.scaler.output().format,
+            decoded.width(),
+            decoded.height(),
+            Flags::BILINEAR,
+        )?;
+        if self.tone_mapper.is_some() && is_bt2020(&self.decoder) {
+            use_bt2020_matrix(&mut self.scaler, &self.decoder);
+        }
+        self.resolution_changes += 1;
+        Ok(())
+    }
+
+    // Skips a packet the decoder rejected, or gives up once max_decode_errors is used up
+    fn skip_corrupt_packet(&mut self, error: Error) -> Result<(), Error> {
+        self.skipped_packets += 1;
+        if self.skipped_packets > self.options.max_decode_errors {
+            return Err(error);
+        }
+        video_eprintln!(
+            "Warning: Skipping corrupt packet ({} of at most {}): {}",
+            self.skipped_packets,
+            self.options.max_decode_errors,
+            error
+        );
+        Ok(())
+    }
+
+    // Reads packets until a frame is pending or the input (or window) is exhausted
+    fn fill(&mut self) -> Result<(), Error> {
+        let mut packet = ffmpeg_next::Packet::empty();
+        while self.pending.is_empty() && !self.finished {
+            match packet.read(&mut self.ictx) {
+                Ok(()) => {}
+                Err(Error::Eof) => {
+                    self.drain()?;
+                    self.finished = true;
+                    break;
+                }
+                // Corrupt packets are skipped, as ictx.packets() does
+                Err(_) => continue,
+            }
+            if packet.stream() != self.video_stream_index {
+                continue;
+            }
+
+            if let Err(e) = self.decoder.send_packet(&packet) {
+                self.skip_corrupt_packet(e)?;
+                continue;
+            }
+            let mut decoded = frame::Video::empty();
+            while !self.finished {
+                match self.decoder.receive_frame(&mut decoded) {
+                    Ok(()) => {
+                        let timestamp = self.frame_timestamp(&decoded);
+                        self.sample(&decoded, timestamp)?;
+                        self.keep_last(&mut decoded, timestamp);
+                    }
+                    // Needs more input, or fully drained
+                    Err(Error::Other { errno: EAGAIN }) | Err(Error::Eof) => break,
+                    // A frame the decoder couldn't finish; as before max_decode_errors
+                    // existed, it's dropped without counting toward the limit
+                    Err(e) => {
+                        video_eprintln!("Warning: Skipping undecodable frame: {}", e);
+                        break;
+                    }
+                }
+            }
+
+            if matches!(
+                self.options.sampling,
+                FrameSampling::Timestamps(_) | FrameSampling::FixedCount(_)
+            ) && self.targets.is_empty()
+            {
+                self.finished = true;
+            }
+        }
+        if self.finished {
+            self.sample_remaining_targets()?;
+        }
+        Ok(())
+    }
+
+    // Frame-threaded decoders hold back their last few frames until flushed, which for a
+    // video of only a few frames can be every one of them
+    fn drain(&mut self) -> Result<(), Error> {
+        if self.decoder.send_eof().is_err() {
+            return Ok(());
+        }
+        let mut decoded = frame::Video::empty();
+        while !self.finished && self.decoder.receive_frame(&mut decoded).is_ok() {
+            let timestamp = self.frame_timestamp(&decoded);
+            self.sample(&decoded, timestamp)?;
+            self.keep_last(&mut decoded, timestamp);
+        }
+        Ok(())
+    }
+
+    // Seconds on the container clock. Taken from the frame itself: with reordering or
+    // frame threading, the packet just sent belongs to a different frame.
+    fn frame_timestamp(&self, decoded: &frame::Video) -> f64 {
+        let pts = decoded.timestamp().or(decoded.pts()).unwrap_or(0);
+        pts as f64 * f64::from(self.time_base)
+    }
+
+    // Holds on to a frame inside the window while timestamp targets remain. The frame is
+    // moved out, not copied; the decoder fills a fresh one next.
+    fn keep_last(&mut self, decoded: &mut frame::Video, timestamp: f64) {
+        if !matches!(self.options.sampling, FrameSampling::Timestamps(_))
+            || self.targets.is_empty()
+            || self.finished
+            || self.options.start.is_some_and(|start| timestamp < start)
+        {
+            return;
+        }
+        let frame = std::mem::replace(decoded, frame::Video::empty());
+        self.last_decoded = Some((frame, timestamp));
+    }
+
+    // Targets past the last frame (e.g. a transcript segment starting after the video's
+    // last frame) get that frame, so every target still yields exactly one frame
+    fn sample_remaining_targets(&mut self) -> Result<(), Error> {
+        if self.targets.is_empty() {
+            return Ok(());
+        }
+        let Some((decoded, timestamp)) = self.last_decoded.take() else {
+            return Ok(());
+        };
+        video_eprintln!(
+            "Note: {} target(s) past the last frame at {:.3}s; using that frame",
+            self.targets.len(),
+            timestamp
+        );
+        self.targets
+            .iter_mut()
+            .for_each(|target| *target = timestamp);
+        self.sample(&decoded, timestamp)
+    }
+
+    // Queues `decoded` as many times as the sampling mode wants it (usually 0 or 1)
+    fn sample(&mut self, decoded: &frame::Video, timestamp: f64) -> Result<(), Error> {
+        // Track GOP position for every decoded frame, including skipped ones
+        if decoded.is_key() {
+            self.gop_size = decoded
+                .pts()
+                .and_then(|pts| self.gop_sizes.get(&pts).copied());
+            self.gop_position = 0;
+            self.last_gop_slot = None;
+            if self.options.reference_keyframes {
+                self.last_keyframe = Some((decoded.clone(), timestamp));
+                self.reference = None;
+            }
+        }
+        let position_in_gop = self.gop_position;
+        self.gop_position += 1;
+
+        if self.options.start.is_some_and(|start| timestamp < start) {
+            return Ok(());
+        }
+        if self.options.end.is_some_and(|end| timestamp > end) {
+            self.finished = true;
+            return Ok(());
+        }
+
+        // Several targets can land before the same frame; emit it once for each
+        let copies = match self.options.sampling {
+            FrameSampling::All => 1,
+            FrameSampling::Interval(step) if step <= 0.0 => 1,
+            FrameSampling::Interval(step) => {
+                let origin = *self.interval_origin.get_or_insert(timestamp);
+                let slot = ((timestamp - origin) / step).floor() as i64;
+                if self.last_slot.is_some_and(|last| slot <= last) {
+                    0
+                } else {
+                    self.last_slot = Some(slot);
+                    1
+                }
+            }
+            FrameSampling::PerGop(per_gop) => {
+                let per_gop = per_gop.max(1);
+                // Unknown GOP length: take its first frames
+                let slot = match self.gop_size {
+                    Some(size) if size > per_gop => position_in_gop * per_gop / size,
+                    _ => position_in_gop,
+                };
+                if slot >= per_gop || self.last_gop_slot == Some(slot) {
+                    0
+                } else {
+                    self.last_gop_slot = Some(slot);
+                    1
+                }
+            }
+            FrameSampling::Timestamps(_) => {
+                let mut copies = 0;
+                while self.targets.last().is_some_and(|&t| t <= timestamp) {
+                    self.targets.pop();
+                    copies += 1;
+                }
+                copies
+            }
+            FrameSampling::FixedCount(_) => {
+                let mut hit = false;
+                while self
+                    .targets
+                    .last()
+                    .is_some_and(|&t| t <= timestamp + self.target_tolerance)
+                {
+                    self.targets.pop();
+                    hit = true;
+                }
+                usize::from(hit)
+            }
+        };
+        if copies == 0 {
+            return Ok(());
+        }
+
+        let (mut rgb, mut rgb16, width, height) = self.convert(decoded)?;
+        let quality = self
+            .options
+            .quality_metrics
+            .then(|| frame_quality(&rgb, width, height));
+        if let Some((keyframe, keyframe_timestamp)) = self.last_keyframe.take() {
+            // A sampled keyframe is its own reference
+            let (rgb, width, height) = if keyframe_timestamp == timestamp {
+                (rgb.clone(), width, height)
+            } else {
+                let (rgb, _, width, height) = self.convert(&keyframe)?;
+                (rgb, width, height)
+            };
+            self.reference = Some(Arc::new(ReferenceKeyframe {
+                timestamp: keyframe_timestamp,
+                width,
+                height,
+                rgb,
+            }));
+        }
+
+        for copy in 0..copies {
+            if self
+                .options
+                .max_frames
+                .is_some_and(|max| self.frame_index >= max)
+            {
+                self.truncated = true;
+                self.finished = true;
+                break;
+            }
+
+            let last_copy = copy + 1 == copies;
+            self.pending.push_back(DecodedFrame {
+                index: self.frame_index,
+                timestamp,
+                width,
+                height,
+                quality,
+                rgb: if last_copy {
+                    std::mem::take(&mut rgb)
+                } else {
+                    rgb.clone()
+                },
+                rgb16: if last_copy {
+                    rgb16.take()
+                } else {
+                    rgb16.clone()
+                },
+                reference: self.reference.clone(),
+            });
+            self.frame_index += 1;
+        }
+        Ok(())
+    }
+
+    // Scales, turns upright and crops a decoded frame; returns the 8-bit RGB, the 16-bit
+    // samples when they are kept, and the final size
+    fn convert(
+        &mut self,
+        decoded: &frame::Video,
+    ) -> Result<(Vec<u8>, Option<Vec<u16>>, u32, u32), Error> {
+        let input = self.scaler.input();
+        if (input.width, input.height, input.format)
+            != (decoded.width(), decoded.height(), decoded.format())
+        {
+            self.rebuild_scaler(decoded)?;
+        }
+        let mut rgb_frame = frame::Video::empty();
+        self.scaler.run(decoded, &mut rgb_frame)?;
+        let (coded_width, coded_height) = (rgb_frame.width(), rgb_frame.height());
+        let (mut width, mut height) = if self.rotation % 180 == 90 {
+            (coded_height, coded_width)
+        } else {
+            (coded_width, coded_height)
+        };
+        // The crop is given in display orientation, so frames are turned upright first
+        let rect = self.options.crop.map(|crop| crop.resolve(width, height));
+        let (rgb, rgb16) = if self.tone_mapper.is_some() || self.keep_high_depth {
+            let rgb16 = upright_rgb(
+                packed_rgb48(&rgb_frame),
+                coded_width,
+                coded_height,
+                self.rotation,
+                rect.as_ref(),
+            );
+            // Analysis and quality metrics work on 8 bits either way
+            let rgb = match &self.tone_mapper {
+                Some(tone_mapper) => tone_mapper.map(&rgb16),
+                None => rgb16.iter().map(|sample| (sample >> 8) as u8).collect(),
+            };
+            let keep = self.keep_high_depth
+                || (self.tone_mapper.is_some() && self.options.hdr == HdrMode::Rgb16);
+            (rgb, keep.then_some(rgb16))
+        } else {
+            let rgb = upright_rgb(
+                packed_rgb(&rgb_frame),
+                coded_width,
+                coded_height,
+                self.rotation,
+                rect.as_ref(),
+            );
+            (rgb, None)
+        };
+        if let Some(rect) = rect {
+            (width, height) = (rect.width, rect.height);
+        }
+        Ok((rgb, rgb16, width, height))
+    }
+}
+
+impl Iterator for FrameReader {
+    type Item = Result<DecodedFrame, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pending.is_empty() {
+            if let Err(e) = self.fill() {
+                self.finished = true;
+                return Some(Err(e));
+            }
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+// Computed on the packed RGB buffer already in hand, so no extra decode or file read
+pub fn frame_quality(rgb: &[u8], width: u32, height: u32) -> FrameQuality {
+    let (width, height) = (width as usize, height as usize);
+    let luma: Vec<f32> = rgb
+        .chunks_exact(3)
+        .map(|p| 0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32)
+        .collect();
+    let brightness = if luma.is_empty() {
+        0.0
+    } else {
+        luma.iter().sum::<f32>() / luma.len() as f32
+    };
+
+    // 4-neighbour Laplacian over interior pixels
+    let mut sum = 0.0f64;
+    let mut sum_sq = 0.0f64;
+    let mut count = 0usize;
+    for y in 1..height.saturating_sub(1) {
+        for x in 1..width.saturating_sub(1) {
+            let i = y * width + x;
+            let laplacian =
+                4.0 * luma[i] - luma[i - 1] - luma[i + 1] - luma[i - width] - luma[i + width];
+            sum += laplacian as f64;
+            sum_sq += (laplacian as f64).powi(2);
+            count += 1;
+        }
+    }
+    let sharpness = if count == 0 {
+        0.0
+    } else {
+        let mean = sum / count as f64;
+        (sum_sq / count as f64 - mean * mean) as f32
+    };
+
+    FrameQuality {
+        sharpness,
+        brightness,
+    }
+}
+
+// `count` timestamps from `first` to `last` inclusive: first + (last - first) * i / (count - 1)
+fn fixed_count_targets(count: usize, first: f64, last: f64) -> Vec<f64> {
+    match count {
+        0 => Vec::new(),
+        1 => vec![first],
+        _ => (0..count)
+            .map(|i| first + (last - first) * i as f64 / (count - 1) as f64)
+            .collect(),
+    }
+}
+
+// Rotates a packed RGB buffer of the coded size clockwise by `rotation` degrees, then
+// cuts out `rect` (given in the rotated frame)
+fn upright_rgb<T: Copy>(
+    rgb: Vec<T>,
+    coded_width: u32,
+    coded_height: u32,
+    rotation: u32,
+    rect: Option<&PixelRect>,
+) -> Vec<T> {
+    let rgb = rotate_rgb(rgb, coded_width, coded_height, rotation);
+    let width = if rotation % 180 == 90 {
+        coded_height
+    } else {
+        coded_width
+    };
+    match rect {
+        Some(rect) => crop_rgb(&rgb, width, rect),
+        None => rgb,
+    }
+}
+
+// Clockwise rotation by 90, 180 or 270 degrees; anything else leaves the buffer as is
+fn rotate_rgb<T: Copy>(rgb: Vec<T>, width: u32, height: u32, rotation: u32) -> Vec<T> {
+    if !matches!(rotation, 90 | 180 | 270) {
+        return rgb;
+    }
+    let (width, height) = (width as usize, height as usize);
+    let mut rotated = rgb.clone();
+    for y in 0..height {
+        for x in 0..width {
+            let target = match rotation {
+                90 => x * height + (height - 1 - y),
+                180 => (height - 1 - y) * width + (width - 1 - x),
+                _ => (width - 1 - x) * height + y,
+            };
+            rotated[target * 3..target * 3 + 3]
+                .copy_from_slice(&rgb[(y * width + x) * 3..(y * width + x) * 3 + 3]);
+        }
+    }
+    rotated
+}
+
+// Cuts `rect` out of a packed RGB buffer `frame_width` pixels wide
+fn crop_rgb<T: Copy>(rgb: &[T], frame_width: u32, rect: &PixelRect) -> Vec<T> {
+    let row_len = frame_width as usize * 3;
+    let start = rect.x as usize * 3;
+    let end = start + rect.width as usize * 3;
+
+    let mut cropped = Vec::with_capacity(rect.width as usize * rect.height as usize * 3);
+    for row in rect.y as usize..(rect.y + rect.height) as usize {
+        cropped.extend_from_slice(&rgb[row * row_len + start..row * row_len + end]);
+    }
+    cropped
+}
+
+// Copies plane 0 row by row, dropping any per-row alignment padding
+fn packed_rgb(rgb_frame: &frame::Video) -> Vec<u8> {
+    let row_len = rgb_frame.width() as usize * 3;
+    let stride = rgb_frame.stride(0);
+    let data = rgb_frame.data(0);
+
+    let mut rgb = Vec::with_capacity(row_len * rgb_frame.height() as usize);
+    for row in 0..rgb_frame.height() as usize {
+        rgb.extend_from_slice(&data[row * stride..row * stride + row_len]);
+    }
+    rgb
+}
+
+// Bits per component of the source pixel format (8 for yuv420p, 10 for yuv420p10le)
+fn component_depth(format: Pixel) -> i32 {
+    match format.descriptor() {
+        Some(descriptor) => unsafe { (*descriptor.as_ptr()).comp[0].depth },
+        None => 8,
+    }
+}
+
+// RGB48LE variant of packed_rgb: three little-endian u16 samples per pixel
+fn packed_rgb48(rgb_frame: &frame::Video) -> Vec<u16> {
+    let row_len = rgb_frame.width() as usize * 6;
+    let stride = rgb_frame.stride(0);
+    let data = rgb_frame.data(0);
+
+    let mut rgb = Vec::with_capacity(row_len / 2 * rgb_frame.height() as usize);
+    for row in 0..rgb_frame.height() as usize {
+        rgb.extend(
+            data[row * stride..row * stride + row_len]
+                .chunks_exact(2)
+                .map(|sample| u16::from_le_bytes([sample[0], sample[1]])),
+        );
+    }
+    rgb
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures;
+
+    fn timestamps(video_path: &Path, options: &ExtractionOptions) -> Vec<f64> {
+        let mut timestamps = Vec::new();
+        decode_frames(video_path, options, |frame| {
+            timestamps.push(frame.timestamp);
+            Ok(true)
+        })
+        .unwrap();
+        timestamps
+    }
+
+    // 10 fps for the first two seconds, 30 fps for the next two
+    fn vfr_fixture() -> Option<PathBuf> {
+        test_fixtures::generate(
+            "vfr.mp4",
+            &[
+                "-f",
+                "lavfi",
+                "-i",
+                "testsrc=size=64x48:rate=30:duration=4",
+                "-vf",
+                "select='gt(t\\,2)+not(mod(n\\,3))'",
+                "-vsync",
+                "vfr",
+                "-pix_fmt",
+                "yuv420p",
+            ],
+        )
+    }
+
+    #[test]
+    fn rotation_tag_turns_frames_and_dimensions_upright() {
+        let Some(video) = test_fixtures::rotated_clip() else {
+            return;
+        };
+        let ictx = format::input(&video).unwrap();
+        if stream_rotation(&ictx.streams().best(media::Type::Video).unwrap()) == 0 {
+            eprintln!("skipping: this ffmpeg doesn't write rotation tags");
+            return;
+        }
+
+        assert_eq!(display_dimensions(&video, false).unwrap(), (48, 64));
+        assert_eq!(display_dimensions(&video, true).unwrap(), (64, 48));
+        for (ignore_rotation, size) in [(false, (48, 64)), (true, (64, 48))] {
+            let options = ExtractionOptions {
+                ignore_rotation,
+                ..ExtractionOptions::default()
+            };
+            decode_frames(&video, &options, |frame| {
+                assert_eq!((frame.width, frame.height), size);
+                Ok(true)
+            })
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn a_single_frame_video_yields_its_frame() {
+        let Some(video) = test_fixtures::generate(
+            "single_frame.mp4",
+            &[
+                "-f",
+                "lavfi",
+                "-i",
+                "testsrc=size=64x48:rate=10",
+                "-frames:v",
+                "1",
+                "-c:v",
+                "mpeg4",
+            ],
+        ) else {
+            return;
+        };
+        for sampling in [FrameSampling::All, FrameSampling::Interval(1.0)] {
+            let options = ExtractionOptions {
+                sampling: sampling.clone(),
+                ..ExtractionOptions::default()
+            };
+            assert_eq!(timestamps(&video, &options), [0.0], "{:?}", sampling);
+        }
+    }
+
+    #[test]
+    fn reordered_frames_are_stamped_in_presentation_order() {
+        // B-frames are decoded out of order, so packet and frame timestamps differ
+        let Some(video) = test_fixtures::generate(
+            "b_frames.mkv",
+            &[
+                "-f",
+                "lavfi",
+                "-i",
+                "testsrc=size=64x48:rate=10:duration=1",
+                "-c:v",
+                "mpeg4",
+                "-bf",
+                "2",
+            ],
+        ) else {
+            return;
+        };
+        let timestamps = timestamps(&video, &ExtractionOptions::default());
+        assert_eq!(timestamps.len(), 10);
+        for (i, timestamp) in timestamps.iter().enumerate() {
+            let expected = timestamps[0] + i as f64 * 0.1;
+            assert!((timestamp - expected).abs() < 0.002, "{:?}", timestamps);
+        }
+    }
+
+    #[test]
+    fn scanned_duration_matches_the_stream_length() {
+        let Some(clip) = test_fixtures::av_clip() else {
+            return;
+        };
+        let duration = scanned_duration(&clip).unwrap().unwrap();
+        assert!((duration - 2.0).abs() < 0.2, "{}", duration);
+    }
+
+    // One second at 64x48 followed by one at 96x64 in a single MPEG-2 stream, as a
+    // concatenated broadcast recording would be
+    fn resolution_switch_fixture() -> Option<PathBuf> {
+        let part = |name: &str, size: &str| {
+            let source = format!("testsrc=size={}:rate=25:duration=1", size);
+            test_fixtures::generate(
+                name,
+                &[
+                    "-f",
+                    "lavfi",
+                    "-i",
+                    &source,
+                    "-c:v",
+                    "mpeg2video",
+                    "-f",
+                    "mpegts",
+                ],
+            )
+        };
+        let small = part("switch_small.ts", "64x48")?;
+        let large = part("switch_large.ts", "96x64")?;
+        let concat = format!("concat:{}|{}", small.display(), large.display());
+        test_fixtures::generate(
+            "resolution_switch.ts",
+            &["-i", &concat, "-c", "copy", "-f", "mpegts"],
+        )
+    }
+
+    #[test]
+    fn frames_after_a_resolution_switch_are_extracted_at_the_new_size() {
+        let Some(video) = resolution_switch_fixture() else {
+            return;
+        };
+        let mut sizes = Vec::new();
+        let summary = decode_frames(&video, &ExtractionOptions::default(), |frame| {
+            assert_eq!(frame.rgb.len(), (frame.width * frame.height * 3) as usize);
+            sizes.push((frame.width, frame.height));
+            Ok(true)
+        })
+        .unwrap();
+
+        assert_eq!(sizes.first(), Some(&(64, 48)));
+        assert_eq!(sizes.last(), Some(&(96, 64)));
+        assert!(sizes.len() >= 45, "{} frames", sizes.len());
+        assert_eq!(summary.resolution_changes, 1);
+    }
+
+    // The first frame of `video` in 8-bit RGB, or None when the transfer function wasn't
+    // carried through to the decoder (then the fixture isn't HDR to us)
+    fn first_hdr_frame(video: &Path, hdr: HdrMode) -> Option<Vec<u8>> {
+        let ictx = format::input(&video).unwrap();
+        let stream = ictx.streams().best(media::Type::Video).unwrap();
+        let decoder = open_decoder(stream.parameters(), &HashMap::new())
+            .unwrap()
+            .video()
+            .unwrap();
+        if hdr_transfer(&decoder).is_none() {
+            eprintln!("skipping: {:?} isn't tagged as HDR", video);
+            return None;
+        }
+
+        let options = ExtractionOptions {
+            hdr,
+            ..ExtractionOptions::default()
+        };
+        let mut rgb = Vec::new();
+        decode_frames(video, &options, |frame| {
+            rgb = frame.rgb;
+            Ok(false)
+        })
+        .unwrap();
+        Some(rgb)
+    }
+
+    // Highlights of the ramp stay below white and keep their gradations instead of
+    // clipping to one value
+    fn assert_highlights_not_clipped(rgb: &[u8]) {
+        let max = *rgb.iter().max().unwrap();
+        assert!(max < 255, "highlights clipped to {}", max);
+        // The right half of the top row, from about 35% to 70% signal
+        let highlights: std::collections::BTreeSet<u8> =
+            rgb[32 * 3..64 * 3].iter().copied().collect();
+        assert!(highlights.len() >= 8, "{:?}", highlights);
+    }
+
+    #[test]
+    fn tone_mapped_pq_highlights_are_not_clipped() {
+        let Some(video) = test_fixtures::hdr_ramp("hdr_pq.mkv", "smpte2084") else {
+            return;
+        };
+        let Some(tone_mapped) = first_hdr_frame(&video, HdrMode::ToneMap) else {
+            return;
+        };
+        let clipped = first_hdr_frame(&video, HdrMode::Clip).unwrap();
+
+        assert_eq!(tone_mapped.len(), 64 * 48 * 3);
+        assert_highlights_not_clipped(&tone_mapped);
+        // A 620-nit highlight is far brighter than SDR white; read as SDR it's dull gray
+        let (mapped_max, clipped_max) = (tone_mapped.iter().max(), clipped.iter().max());
+        assert!(
+            mapped_max > clipped_max,
+            "{:?} {:?}",
+            mapped_max,
+            clipped_max
+        );
+    }
+
+    #[test]
+    fn tone_mapped_hlg_highlights_are_not_clipped() {
+        let Some(video) = test_fixtures::hdr_ramp("hdr_hlg.mkv", "arib-std-b67") else {
+            return;
+        };
+        let Some(tone_mapped) = first_hdr_frame(&video, HdrMode::ToneMap) else {
+            return;
+        };
+
+        assert_eq!(tone_mapped.len(), 64 * 48 * 3);
+        assert_highlights_not_clipped(&tone_mapped);
+    }
+
+    fn high_depth_frame() -> DecodedFrame {
+        DecodedFrame {
+            index: 0,
+            timestamp: 0.0,
+            width: 2,
+            height: 1,
+            rgb: vec![10, 20, 30, 200, 210, 220],
+            quality: None,
+            rgb16: Some(vec![2560, 5120, 7680, 51200, 53760, 56320]),
+            reference: None,
+        }
+    }
+
+    #[test]
+    fn sixteen_bit_frames_are_saved_at_sixteen_bits_with_an_analysis_copy() {
+        let dir = test_fixtures::scratch_dir("sixteen-bit-frames");
+        let path = frame_path(&dir, 0, 0.0, FrameNaming::IndexOnly);
+
+        let analysis_path = FrameWriter::new(FrameBitDepth::Sixteen)
+            .write(&high_depth_frame(), &path)
+            .unwrap();
+
+        let saved = image::open(&path).unwrap();
+        assert_eq!(saved.color(), image::ColorType::Rgb16);
+        assert_eq!(analysis_path, analysis_copy_path(&path));
+        assert_eq!(analysis_input(&path), analysis_path);
+        let copy = image::open(&analysis_path).unwrap();
+        assert_eq!(copy.color(), image::ColorType::Rgb8);
+        assert_eq!(copy.to_rgb8().into_raw(), high_depth_frame().rgb);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn eight_bit_frames_are_analyzed_directly() {
+        let dir = test_fixtures::scratch_dir("eight-bit-frames");
+        let path = frame_path(&dir, 0, 0.0, FrameNaming::IndexOnly);
+        let frame = DecodedFrame {
+            rgb16: None,
+            ..high_depth_frame()
+        };
+
+        let analysis_path = FrameWriter::new(FrameBitDepth::Sixteen)
+            .write(&frame, &path)
+            .unwrap();
+
+        assert_eq!(analysis_path, path);
+        assert!(!dir.join(ANALYSIS_DIR).exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    // A PNG signature, an IHDR chunk claiming `width` x `height` RGB pixels and an empty
+    // IDAT; decoding it would allocate the full frame
+    fn png_header(width: u32, height: u32) -> Vec<u8> {
+        let chunk = |kind: &[u8], data: &[u8]| {
+            let body = [kind, data].concat();
+            let crc = body.iter().fold(!0u32, |crc, &byte| {
+                (0..8).fold(crc ^ byte as u32, |crc, _| {
+                    (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg())
+                })
+            });
+            [
+                &(data.len() as u32).to_be_bytes()[..],
+                &body,
+                &(!crc).to_be_bytes(),
+            ]
+            .concat()
+        };
+        let ihdr = [
+            &width.to_be_bytes()[..],
+            &height.to_be_bytes(),
+            &[8, 2, 0, 0, 0],
+        ]
+        .concat();
+        [
+            &b"\x89PNG\r\n\x1a\n"[..],
+            &chunk(b"IHDR", &ihdr),
+            &chunk(b"IDAT", &[]),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn oversized_frames_are_rejected_from_the_header() {
+        let dir = test_fixtures::scratch_dir("oversized-frame");
+        let path = dir.join("huge.png");
+        std::fs::write(&path, png_header(100_000, 100_000)).unwrap();
+
+        let error = ImageLimits::default().check_file(&path).unwrap_err();
+        assert!(error.to_string().contains("exceeds the limit"), "{}", error);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn image_limits_apply_to_each_dimension_and_the_pixel_count() {
+        let limits = ImageLimits {
+            max_width: 100,
+            max_height: 50,
+            max_pixels: 2000,
+        };
+        assert!(limits.check(40, 50).is_ok());
+        assert!(limits.check(101, 10).is_err());
+        assert!(limits.check(10, 51).is_err());
+        assert!(limits.check(100, 21).is_err());
+    }
+
+    #[test]
+    fn crop_maps_boxes_back_to_full_frame() {
+        let crop = CropRegion::Normalized {
+            x: 0.5,
+            y: 0.25,
+            width: 0.5,
+            height: 0.5,
+        };
+        let rect = crop.resolve(640, 480);
+        assert_eq!(
+            rect,
+            PixelRect {
+                x: 320,
+                y: 120,
+                width: 320,
+                height: 240
+            }
+        );
+        assert_eq!(
+            rect.to_full_frame([10.0, 20.0, 30.0, 40.0]),
+            [330.0, 140.0, 350.0, 160.0]
+        );
+    }
+
+    #[test]
+    fn crop_is_parsed_from_pixels_or_percentages() {
+        assert_eq!(
+            "10, 20,640,360".parse::<CropRegion>().unwrap(),
+            CropRegion::Pixels {
+                x: 10,
+                y: 20,
+                width: 640,
+                height: 360
+            }
+        );
+        assert_eq!(
+            "50%,0%,50%,100%".parse::<CropRegion>().unwrap(),
+            CropRegion::Normalized {
+                x: 0.5,
+                y: 0.0,
+                width: 0.5,
+                height: 1.0
+            }
+        );
+        assert!("50%,0,50%,100%".parse::<CropRegion>().is_err());
+        assert!("10,20,640".parse::<CropRegion>().is_err());
+    }
+
+    #[test]
+    fn crop_is_clamped_to_the_frame() {
+        let crop = CropRegion::Pixels {
+            x: 600,
+            y: 0,
+            width: 100,
+            height: 1000,
+        };
+        let rect = crop.resolve(640, 480);
+        assert_eq!((rect.width, rect.height), (40, 480));
+        // Degenerate frame sizes don't panic
+        assert_eq!(crop.resolve(0, 0).width, 1);
+    }
+
+    #[test]
+    fn empty_or_out_of_frame_crops_are_rejected() {
+        let empty = CropRegion::Pixels {
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 10,
+        };
+        assert!(empty.validate().is_err());
+        let outside = CropRegion::Normalized {
+            x: 1.5,
+            y: 0.0,
+            width: 0.5,
+            height: 0.5,
+        };
+        assert!(outside.validate().is_err());
+        let nan = CropRegion::Normalized {
+            x: 0.0,
+            y: 0.0,
+            width: f32::NAN,
+            height: 0.5,
+        };
+        assert!(nan.validate().is_err());
+    }
+
+    #[test]
+    fn variable_frame_rate_is_detected() {
+        let Some(video) = vfr_fixture() else { return };
+        assert!(is_variable_frame_rate(&video).unwrap());
+    }
+
+    #[test]
+    fn interval_sampling_follows_real_time_in_vfr_video() {
+        let Some(video) = vfr_fixture() else { return };
+        let options = ExtractionOptions {
+            sampling: FrameSampling::Interval(0.5),
+            ..ExtractionOptions::default()
+        };
+
+        let timestamps = timestamps(&video, &options);
+
+        // One frame per half second in both the 10 fps and the 30 fps part
+        assert_eq!(timestamps.len(), 8, "{:?}", timestamps);
+        for pair in timestamps.windows(2) {
+            assert!((pair[1] - pair[0] - 0.5).abs() < 0.05, "{:?}", timestamps);
+        }
+    }
 }
@@ -0,0 +1,138 @@
+use crate::batch_processor::{BatchConfig, BatchProcessor, SkippedVideos, VideoProcessingResult};
+use crate::manifest::Manifest;
+use anyhow::Result;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+// How often pending files are re-checked while no events arrive
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+// A file counts as fully copied once its size hasn't changed for this long
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+struct PendingFile {
+    size: u64,
+    changed_at: Instant,
+}
+
+// Processes videos already waiting in the input directory, then keeps watching it and
+// processes new files once they stop growing. Runs until Ctrl-C. Each round decodes up
+// to max_concurrent videos at once (fan-in, unless video_workers or a setting that
+// doesn't fan in says otherwise), and the batch summary covers every video since the
+// watcher started.
+pub fn run_watch(mut config: BatchConfig) -> Result<()> {
+    let running = Arc::new(AtomicBool::new(true));
+    {
+        let running = running.clone();
+        ctrlc::set_handler(move || {
+            println!("\nStopping watcher after the current videos finish...");
+            running.store(false, Ordering::SeqCst);
+        })?;
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(&config.input_dir, RecursiveMode::NonRecursive)?;
+
+    if config.video_workers.is_none() && config.chunk_duration.is_none() && !config.low_memory {
+        config.video_workers = Some(1);
+    }
+    let processor = BatchProcessor::new(config);
+    processor.check_output_writable()?;
+    processor.validate_config()?;
+    let (analyzer, transcriber) = processor.load_backends()?;
+    let started = Instant::now();
+    let mut processed: Vec<VideoProcessingResult> = Vec::new();
+    let mut skipped = SkippedVideos::default();
+
+    println!("Watching {:?} for new videos", processor.config().input_dir);
+
+    let mut pending: HashMap<PathBuf, PendingFile> = HashMap::new();
+    for path in processor.find_video_files()? {
+        track(&mut pending, path);
+    }
+
+    while running.load(Ordering::SeqCst) {
+        match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    for path in event.paths {
+                        if path.is_file() && processor.is_video_file(&path) {
+                            track(&mut pending, path);
+                        }
+                    }
+                }
+            }
+            Ok(Err(e)) => eprintln!("Warning: Watch error: {}", e),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+
+        // Re-read the manifest each round so files processed elsewhere are skipped too
+        let manifest = Manifest::load(&processor.config().output_dir)?;
+        pending.retain(|path, _| path.exists() && !manifest.contains(path));
+
+        let mut ready = settled_files(&mut pending);
+        ready.sort();
+        if ready.is_empty() {
+            continue;
+        }
+        for path in &ready {
+            pending.remove(path);
+        }
+
+        let known: Vec<PathBuf> = processed.iter().map(|r| r.video_path.clone()).collect();
+        let (ready, round_skipped) = processor.filter_inputs(ready, &known)?;
+        skipped.duplicates.extend(round_skipped.duplicates);
+        skipped.inactive.extend(round_skipped.inactive);
+        if ready.is_empty() {
+            continue;
+        }
+
+        let batch = processor.process_videos(
+            &ready,
+            std::mem::take(&mut skipped),
+            std::mem::take(&mut processed),
+            &analyzer,
+            transcriber.as_ref(),
+            started,
+        )?;
+        println!(
+            "Processed {} new video(s); {} so far: {} successful, {} failed",
+            ready.len(),
+            batch.total_videos,
+            batch.successful,
+            batch.failed
+        );
+        processed = batch.results;
+        skipped = batch.skipped;
+    }
+
+    Ok(())
+}
+
+fn track(pending: &mut HashMap<PathBuf, PendingFile>, path: PathBuf) {
+    pending.entry(path).or_insert(PendingFile {
+        size: 0,
+        changed_at: Instant::now(),
+    });
+}
+
+// Files whose size has been stable for SETTLE_TIME; others get their size refreshed
+fn settled_files(pending: &mut HashMap<PathBuf, PendingFile>) -> Vec<PathBuf> {
+    let mut ready = Vec::new();
+    for (path, file) in pending.iter_mut() {
+        let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        if size != file.size {
+            file.size = size;
+            file.changed_at = Instant::now();
+        } else if size > 0 && file.changed_at.elapsed() >= SETTLE_TIME {
+            ready.push(path.clone());
+        }
+    }
+    ready
+}